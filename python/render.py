@@ -3,9 +3,6 @@ import argparse
 import os
 import sys
 
-import numpy as np
-import pyexr
-
 try:
     import dacquoise
 except Exception as exc:
@@ -25,15 +22,53 @@ def parse_args() -> argparse.Namespace:
     )
     parser.add_argument("--spp", type=int, default=8, help="Samples per pixel.")
     parser.add_argument("--max-depth", type=int, default=4, help="Max path depth.")
+    parser.add_argument("--integrator", help="Integrator type overriding the scene's.")
+    parser.add_argument("--seed", type=int, default=0, help="Random seed.")
+    parser.add_argument("--camera", type=int, default=0, help="Sensor index.")
+    parser.add_argument(
+        "--aov",
+        dest="aovs",
+        action="append",
+        default=[],
+        metavar="NAME",
+        help="Also write an AOV (albedo, normal, depth, position, uv) as <out>_<NAME>.exr; may be repeated.",
+    )
     parser.add_argument(
         "--progress",
         action="store_true",
         help="Force a progress bar even when stdout/stderr is not a TTY.",
     )
+    parser.add_argument(
+        "-D",
+        dest="defines",
+        action="append",
+        default=[],
+        metavar="NAME=VALUE",
+        help="Override a scene <default> value; may be repeated.",
+    )
     parser.add_argument(
         "--out",
         default=os.path.join("output", "cbox.exr"),
-        help="Output EXR path.",
+        help="Output path; .exr, .hdr and .pfm are linear, .png and .jpg are tonemapped.",
+    )
+    parser.add_argument("--exposure", type=float, default=0.0, help="Exposure in stops for PNG/JPEG output.")
+    parser.add_argument(
+        "--tonemap",
+        default="clamp",
+        choices=["clamp", "reinhard", "aces", "agx"],
+        help="Tonemapper for PNG/JPEG output.",
+    )
+    parser.add_argument(
+        "--transfer",
+        default="srgb",
+        choices=["srgb", "rec709", "linear"],
+        help="Transfer function for PNG/JPEG output.",
+    )
+    parser.add_argument("--dither", action="store_true", help="Dither PNG/JPEG output.")
+    parser.add_argument(
+        "--denoise",
+        action="store_true",
+        help="Denoise the image; the raw render is written as <out>_noisy.",
     )
     return parser.parse_args()
 
@@ -44,25 +79,56 @@ def main() -> int:
     if args.progress:
         os.environ["DACQUOISE_PROGRESS"] = "1"
 
-    scene = dacquoise.load_scene(args.scene)
-    matrix = dacquoise.render(scene, args.spp, args.max_depth)
-    matrix = np.asarray(matrix)
-    height, width_times_three = matrix.shape
-
-    if width_times_three % 3 != 0:
-        print(
-            f"Unexpected matrix shape {matrix.shape}; expected width * 3 columns.",
-            file=sys.stderr,
-        )
-        return 1
+    defines = {}
+    for define in args.defines:
+        name, sep, value = define.partition("=")
+        if not name or not sep:
+            print(f"Malformed define '{define}', expected NAME=VALUE.", file=sys.stderr)
+            return 1
+        defines[name] = value
 
-    image = matrix.reshape(height, width_times_three // 3, 3)
+    scene = dacquoise.load_scene(args.scene, defines)
+    result = dacquoise.render(
+        scene,
+        args.spp,
+        args.max_depth,
+        integrator=args.integrator,
+        seed=args.seed,
+        camera=args.camera,
+        aovs=args.aovs or None,
+        denoise=args.denoise,
+    )
+    image, aovs = result if args.aovs or args.denoise else (result, {})
+    noisy = aovs.pop("noisy", None)
 
     out_dir = os.path.dirname(args.out)
     if out_dir:
         os.makedirs(out_dir, exist_ok=True)
-    pyexr.write(args.out, image.astype(np.float32, copy=False))
+    dacquoise.write_image(
+        args.out,
+        image,
+        exposure=args.exposure,
+        tonemapper=args.tonemap,
+        transfer=args.transfer,
+        dither=args.dither,
+    )
     print(f"Wrote {args.out} with shape {image.shape}")
+    stem, ext = os.path.splitext(args.out)
+    if noisy is not None:
+        path = f"{stem}_noisy{ext}"
+        dacquoise.write_image(
+            path,
+            noisy,
+            exposure=args.exposure,
+            tonemapper=args.tonemap,
+            transfer=args.transfer,
+            dither=args.dither,
+        )
+        print(f"Wrote {path} with shape {noisy.shape}")
+    for name, aov in aovs.items():
+        path = f"{stem}_{name}.exr"
+        dacquoise.write_image(path, aov)
+        print(f"Wrote {path} with shape {aov.shape}")
     return 0
 
 
@@ -0,0 +1,137 @@
+use dacquoise::core::emitter::EmitterFlag;
+use dacquoise::core::scene_loader::load_scene_with_settings;
+use dacquoise::emitters::validation::{
+    area_light_histogram, emitter_direction_histogram, emitter_disk_histogram, environment_histogram,
+    position_pdf_mismatches, sample_emitter_pdf_mismatches,
+};
+use dacquoise::io::image_io::write_image;
+use dacquoise::materials::validation::{ChiSquareSettings, Histogram};
+use dacquoise::math::constants::{Float, Vector2f, Vector3f};
+use std::path::{Path, PathBuf};
+
+fn parse_vector(text: &str) -> Option<Vector3f> {
+    let values: Vec<Float> = text.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+    if values.len() == 3 {
+        Some(Vector3f::new(values[0], values[1], values[2]))
+    } else {
+        None
+    }
+}
+
+fn report(out_dir: &Path, name: &str, histogram: &Histogram, settings: &ChiSquareSettings) {
+    let result = histogram.chi_square(settings.min_expected);
+    let path = out_dir.join(format!("{}.exr", name));
+    if let Err(e) = write_image(&path, &histogram.to_image()) {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+    println!(
+        "{:<28} chi2 {:>10.2}  dof {:>4}  p {:>8.2e}  unexpected {:>5}  -> {}",
+        name,
+        result.statistic,
+        result.degrees_of_freedom,
+        result.p_value,
+        result.unexpected_samples,
+        path.display()
+    );
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: {} <scene.xml> [--out DIR] [--from x,y,z] [--samples N] [--bins THETAxPHI] [--seed N]",
+            args[0]
+        );
+        std::process::exit(1);
+    }
+
+    let mut out_dir = PathBuf::from(".");
+    let mut reference: Option<Vector3f> = None;
+    let mut settings = ChiSquareSettings { theta_bins: 32, phi_bins: 64, integration_resolution: 8, ..ChiSquareSettings::default() };
+    let mut i = 2;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--out" => {
+                i += 1;
+                if let Some(dir) = args.get(i) {
+                    out_dir = PathBuf::from(dir);
+                }
+            }
+            "--from" => {
+                i += 1;
+                reference = args.get(i).and_then(|v| parse_vector(v));
+                if reference.is_none() {
+                    eprintln!("Malformed point, expected x,y,z.");
+                    std::process::exit(1);
+                }
+            }
+            "--samples" => {
+                i += 1;
+                settings.sample_count = args.get(i).and_then(|v| v.parse().ok()).unwrap_or(settings.sample_count);
+            }
+            "--bins" => {
+                i += 1;
+                let bins = args.get(i).and_then(|v| v.split_once('x'));
+                match bins.and_then(|(t, p)| Some((t.parse().ok()?, p.parse().ok()?))) {
+                    Some((theta_bins, phi_bins)) if theta_bins > 0 && phi_bins > 0 => {
+                        settings.theta_bins = theta_bins;
+                        settings.phi_bins = phi_bins;
+                    }
+                    _ => {
+                        eprintln!("Malformed bins, expected THETAxPHI.");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--seed" => {
+                i += 1;
+                settings.seed = args.get(i).and_then(|v| v.parse().ok()).unwrap_or(settings.seed);
+            }
+            other => eprintln!("Ignoring unknown argument '{}'.", other),
+        }
+        i += 1;
+    }
+
+    let mut scene = load_scene_with_settings(&args[1])
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {:?}", args[1], e);
+            std::process::exit(1);
+        })
+        .scene;
+    scene.build_bvh();
+    if let Err(e) = std::fs::create_dir_all(&out_dir) {
+        eprintln!("Failed to create {}: {}", out_dir.display(), e);
+        std::process::exit(1);
+    }
+
+    // Area lights are seen from the first camera unless told otherwise.
+    let reference = reference
+        .or_else(|| scene.camera(0).map(|camera| camera.sample_ray(&Vector2f::new(0.5, 0.5)).origin()))
+        .unwrap_or_else(|| scene.scene_bounds().center());
+    println!("reference point {:?}", reference);
+
+    let flags: Vec<EmitterFlag> = scene.emitters().iter().map(|e| e.get_flag()).collect();
+    if flags.iter().any(|f| f.contains(EmitterFlag::SURFACE)) {
+        report(&out_dir, "scene_area_lights", &area_light_histogram(&scene, reference, &settings), &settings);
+    }
+    if flags.iter().any(|f| f.contains(EmitterFlag::DIRECTION) && !f.contains(EmitterFlag::DELTA)) {
+        report(&out_dir, "scene_environment", &environment_histogram(&scene, &settings), &settings);
+    }
+    for (index, emitter) in scene.emitters().iter().enumerate() {
+        let flag = emitter.get_flag();
+        let name = format!("emitter{}_{}", index, emitter.id());
+        if !flag.contains(EmitterFlag::DELTA) {
+            let histogram = emitter_direction_histogram(emitter.as_ref(), Vector2f::new(0.5, 0.5), &settings);
+            report(&out_dir, &format!("{}_direction", name), &histogram, &settings);
+        }
+        if flag.contains(EmitterFlag::DIRECTION) {
+            let histogram = emitter_disk_histogram(emitter.as_ref(), scene.scene_bounds(), &settings);
+            report(&out_dir, &format!("{}_position", name), &histogram, &settings);
+        }
+        let mismatches = position_pdf_mismatches(emitter.as_ref(), 10_000, 1e-3, settings.seed);
+        println!("{:<28} position pdf mismatches {} / 10000", name, mismatches);
+    }
+    let mismatches = sample_emitter_pdf_mismatches(&scene, reference, 10_000, 1e-3, settings.seed);
+    println!("sample_emitter pdf mismatches {} / 10000", mismatches);
+}
@@ -1,33 +1,10 @@
-use exr::prelude::*;
+use dacquoise::io::image_io::{read_image, Image};
 
-#[derive(Debug)]
-struct Pixels {
-    width: usize,
-    height: usize,
-    data: Vec<(f32, f32, f32, f32)>,
-}
-
-fn read_rgba(path: &str) -> Pixels {
-    let image = read()
-        .no_deep_data()
-        .largest_resolution_level()
-        .rgba_channels(
-            |resolution, _| Pixels {
-                width: resolution.width(),
-                height: resolution.height(),
-                data: vec![(0.0, 0.0, 0.0, 0.0); resolution.width() * resolution.height()],
-            },
-            |image, position, (r, g, b, a): (f32, f32, f32, f32)| {
-                let idx = position.y() * image.width + position.x();
-                image.data[idx] = (r, g, b, a);
-            },
-        )
-        .first_valid_layer()
-        .all_attributes()
-        .from_file(path)
-        .unwrap_or_else(|e| panic!("failed to read {}: {}", path, e));
-
-    image.layer_data.channel_data.pixels
+fn read_or_exit(path: &str) -> Image {
+    read_image(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    })
 }
 
 fn main() {
@@ -40,8 +17,8 @@ fn main() {
     let x: usize = args[3].parse().unwrap_or(0);
     let y: usize = args[4].parse().unwrap_or(0);
 
-    let a = read_rgba(&args[1]);
-    let b = read_rgba(&args[2]);
+    let a = read_or_exit(&args[1]);
+    let b = read_or_exit(&args[2]);
 
     if a.width != b.width || a.height != b.height {
         eprintln!("Size mismatch: {}x{} vs {}x{}", a.width, a.height, b.width, b.height);
@@ -52,9 +29,8 @@ fn main() {
         std::process::exit(3);
     }
 
-    let idx = y * a.width + x;
-    let (ar, ag, ab, aa) = a.data[idx];
-    let (br, bg, bb, ba) = b.data[idx];
+    let [ar, ag, ab, aa] = a.rgba_at(x, y);
+    let [br, bg, bb, ba] = b.rgba_at(x, y);
 
     println!("Pixel ({}, {})", x, y);
     println!("A: R {:.6}, G {:.6}, B {:.6}, A {:.6}", ar, ag, ab, aa);
@@ -1,33 +1,10 @@
-use exr::prelude::*;
+use dacquoise::io::image_io::{read_image, Image};
 
-#[derive(Debug)]
-struct Pixels {
-    width: usize,
-    height: usize,
-    data: Vec<(f32, f32, f32, f32)>,
-}
-
-fn read_rgba(path: &str) -> Pixels {
-    let image = read()
-        .no_deep_data()
-        .largest_resolution_level()
-        .rgba_channels(
-            |resolution, _| Pixels {
-                width: resolution.width(),
-                height: resolution.height(),
-                data: vec![(0.0, 0.0, 0.0, 0.0); resolution.width() * resolution.height()],
-            },
-            |image, position, (r, g, b, a): (f32, f32, f32, f32)| {
-                let idx = position.y() * image.width + position.x();
-                image.data[idx] = (r, g, b, a);
-            },
-        )
-        .first_valid_layer()
-        .all_attributes()
-        .from_file(path)
-        .unwrap_or_else(|e| panic!("failed to read {}: {}", path, e));
-
-    image.layer_data.channel_data.pixels
+fn read_or_exit(path: &str) -> Image {
+    read_image(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    })
 }
 
 fn main() {
@@ -37,35 +14,39 @@ fn main() {
         std::process::exit(1);
     }
 
-    let img = read_rgba(&args[1]);
+    let img = read_or_exit(&args[1]);
     let mut min = (f32::INFINITY, f32::INFINITY, f32::INFINITY);
     let mut max = (f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
     let mut sum = (0.0f64, 0.0f64, 0.0f64);
     let mut nan_count = 0usize;
     let mut inf_count = 0usize;
 
-    for (r, g, b, _) in &img.data {
-        if !r.is_finite() || !g.is_finite() || !b.is_finite() {
-            if r.is_nan() || g.is_nan() || b.is_nan() {
-                nan_count += 1;
-            } else {
-                inf_count += 1;
+    for y in 0..img.height {
+        for x in 0..img.width {
+            let [r, g, b, _] = img.rgba_at(x, y);
+            if !r.is_finite() || !g.is_finite() || !b.is_finite() {
+                if r.is_nan() || g.is_nan() || b.is_nan() {
+                    nan_count += 1;
+                } else {
+                    inf_count += 1;
+                }
+                continue;
             }
-            continue;
+            if r < min.0 { min.0 = r; }
+            if g < min.1 { min.1 = g; }
+            if b < min.2 { min.2 = b; }
+            if r > max.0 { max.0 = r; }
+            if g > max.1 { max.1 = g; }
+            if b > max.2 { max.2 = b; }
+            sum.0 += r as f64;
+            sum.1 += g as f64;
+            sum.2 += b as f64;
         }
-        if *r < min.0 { min.0 = *r; }
-        if *g < min.1 { min.1 = *g; }
-        if *b < min.2 { min.2 = *b; }
-        if *r > max.0 { max.0 = *r; }
-        if *g > max.1 { max.1 = *g; }
-        if *b > max.2 { max.2 = *b; }
-        sum.0 += *r as f64;
-        sum.1 += *g as f64;
-        sum.2 += *b as f64;
     }
 
-    let n = img.data.len() as f64;
-    println!("Size: {}x{} ({} pixels)", img.width, img.height, img.data.len());
+    let pixel_count = img.width * img.height;
+    let n = pixel_count as f64;
+    println!("Size: {}x{} ({} pixels)", img.width, img.height, pixel_count);
     println!("Min RGB: {:.6}, {:.6}, {:.6}", min.0, min.1, min.2);
     println!("Max RGB: {:.6}, {:.6}, {:.6}", max.0, max.1, max.2);
     println!("Mean RGB: {:.6}, {:.6}, {:.6}", sum.0 / n, sum.1 / n, sum.2 / n);
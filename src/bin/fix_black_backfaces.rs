@@ -1,44 +1,14 @@
 use dacquoise::core::interaction::SurfaceIntersection;
 use dacquoise::core::scene::Scene;
 use dacquoise::core::scene_loader::load_scene_with_settings;
+use dacquoise::io::image_io::read_image;
 use dacquoise::math::constants::{Float, Vector2f};
 use dacquoise::math::ray::Ray3f;
-use exr::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-struct Pixels {
-    width: usize,
-    height: usize,
-    data: Vec<(f32, f32, f32, f32)>,
-}
-
-fn read_rgba(path: &str) -> Pixels {
-    let image = read()
-        .no_deep_data()
-        .largest_resolution_level()
-        .rgba_channels(
-            |resolution, _| Pixels {
-                width: resolution.width(),
-                height: resolution.height(),
-                data: vec![(0.0, 0.0, 0.0, 0.0); resolution.width() * resolution.height()],
-            },
-            |image, position, (r, g, b, a): (f32, f32, f32, f32)| {
-                let idx = position.y() * image.width + position.x();
-                image.data[idx] = (r, g, b, a);
-            },
-        )
-        .first_valid_layer()
-        .all_attributes()
-        .from_file(path)
-        .unwrap_or_else(|e| panic!("failed to read {}: {}", path, e));
-
-    image.layer_data.channel_data.pixels
-}
-
 fn primary_hit(scene: &Scene, ray: &Ray3f) -> Option<(usize, SurfaceIntersection)> {
     let mut best_t = std::f32::MAX;
     let mut best_idx = None;
@@ -376,7 +346,8 @@ fn main() {
     let base_dir = scene.base_dir().to_path_buf();
     let sensor = scene.camera(0).expect("camera not found");
 
-    let img = read_rgba(exr_path);
+    let img = read_image(exr_path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", exr_path, e));
     let width = img.width;
     let height = img.height;
 
@@ -386,7 +357,7 @@ fn main() {
 
     for y in 0..height {
         for x in 0..width {
-            let (r, g, b, _) = img.data[y * width + x];
+            let [r, g, b, _] = img.rgba_at(x, y);
             if !r.is_finite() || !g.is_finite() || !b.is_finite() {
                 continue;
             }
@@ -0,0 +1,126 @@
+use dacquoise::io::image_compare::{heatmap, CompareSettings, ErrorMaps, ErrorMetric, Region};
+use dacquoise::io::image_io::{read_image, write_image, write_ldr, Image, ImageFormat};
+use dacquoise::io::tonemap::TonemapSettings;
+
+fn read_or_exit(path: &str) -> Image {
+    read_image(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", path, e);
+        std::process::exit(1);
+    })
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: {} <test> <reference> [--region name=x0,y0,x1,y1]... [--heatmap out.exr|.png] [--heatmap-metric flip|mse|relmse|smape|ssim] [--heatmap-max V] [--ppd N]",
+            args[0]
+        );
+        std::process::exit(1);
+    }
+
+    let mut regions: Vec<(String, Region)> = Vec::new();
+    let mut heatmap_path: Option<String> = None;
+    let mut heatmap_metric = ErrorMetric::Flip;
+    let mut heatmap_max: Option<f32> = None;
+    let mut settings = CompareSettings::default();
+    let mut i = 3;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--region" => {
+                i += 1;
+                let spec = args.get(i).map(String::as_str).unwrap_or("");
+                let (name, rect) = spec.split_once('=').unwrap_or(("", spec));
+                match Region::parse(rect) {
+                    Some(region) => {
+                        let name = if name.is_empty() { rect.to_string() } else { name.to_string() };
+                        regions.push((name, region));
+                    }
+                    None => {
+                        eprintln!("Malformed region '{}', expected name=x0,y0,x1,y1.", spec);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--heatmap" => {
+                i += 1;
+                heatmap_path = args.get(i).cloned();
+            }
+            "--heatmap-metric" => {
+                i += 1;
+                let name = args.get(i).map(String::as_str).unwrap_or("");
+                heatmap_metric = ErrorMetric::from_name(name).unwrap_or_else(|| {
+                    eprintln!("Unknown metric '{}'.", name);
+                    std::process::exit(1);
+                });
+            }
+            "--heatmap-max" => {
+                i += 1;
+                heatmap_max = args.get(i).and_then(|v| v.parse().ok());
+            }
+            "--ppd" => {
+                i += 1;
+                if let Some(ppd) = args.get(i).and_then(|v| v.parse().ok()) {
+                    settings.pixels_per_degree = ppd;
+                }
+            }
+            other => eprintln!("Ignoring unknown argument '{}'.", other),
+        }
+        i += 1;
+    }
+
+    let test = read_or_exit(&args[1]);
+    let reference = read_or_exit(&args[2]);
+    let maps = ErrorMaps::new(&test, &reference, &settings).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    if let Some(path) = &heatmap_path {
+        let errors = maps.error(heatmap_metric);
+        // FLIP and 1 - SSIM are already in [0, 1]; other metrics are scaled
+        // to their largest value.
+        let max = heatmap_max.unwrap_or(match heatmap_metric {
+            ErrorMetric::Flip | ErrorMetric::Ssim => 1.0,
+            _ => errors.iter().cloned().filter(|v| v.is_finite()).fold(0.0, f32::max),
+        });
+        let image = heatmap(&errors, maps.width, maps.height, max);
+        let result = match ImageFormat::from_path(path) {
+            Some(format) if format.is_ldr() => write_ldr(path, &image, &TonemapSettings::default()),
+            _ => write_image(path, &image),
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to write {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+
+    let full = maps.metrics(Region::full(maps.width, maps.height));
+    let region_reports: Vec<String> = regions
+        .iter()
+        .map(|(name, region)| format!("{}: {}", json_string(name), maps.metrics(*region).to_json()))
+        .collect();
+    println!(
+        "{{\"test\": {}, \"reference\": {}, \"width\": {}, \"height\": {}, \"metrics\": {}, \"regions\": {{{}}}}}",
+        json_string(&args[1]),
+        json_string(&args[2]),
+        maps.width,
+        maps.height,
+        full.to_json(),
+        region_reports.join(", ")
+    );
+}
@@ -1,9 +1,9 @@
 use dacquoise::core::integrator::Integrator;
-use dacquoise::core::rng::LcgRng;
 use dacquoise::core::scene_loader::load_scene_with_settings;
 use dacquoise::integrators::path::PathIntegrator;
 use dacquoise::integrators::raymarching::RaymarchingIntegrator;
 use dacquoise::math::constants::{Float, Vector2f, Vector3f};
+use dacquoise::samplers::create_sampler;
 use std::env;
 
 fn main() {
@@ -70,12 +70,13 @@ fn main() {
         }
     };
     let pixel = Vector2f::new(x as Float, y as Float);
-    let pixel_seed = ((seed & 0xFFF) << 32) | (((y as u64) & 0xFFFF) << 16) | ((x as u64) & 0xFFFF);
-    let mut rng = LcgRng::new(pixel_seed);
+    let sampler_name = load_result.sampler_type.as_deref().unwrap_or("independent");
+    let mut sampler = create_sampler(sampler_name, spp, seed).expect("sampler type validated by the loader");
 
     let mut accum = Vector3f::zeros();
-    for _ in 0..spp {
-        let rgb = integrator.trace_ray_forward(&scene, sensor, pixel, &mut rng);
+    for sample_index in 0..spp {
+        sampler.start_pixel_sample(x, y, sample_index);
+        let rgb = integrator.trace_ray_forward(&scene, sensor, pixel, sampler.as_mut());
         accum += Vector3f::new(rgb[0], rgb[1], rgb[2]);
     }
 
@@ -1,9 +1,9 @@
-use dacquoise::core::rng::LcgRng;
+use dacquoise::core::path_record::{paths_to_json, paths_to_mesh, record_pixels, OverlaySettings, RecordedPath};
 use dacquoise::core::scene_loader::load_scene_with_settings;
-use dacquoise::core::tangent_frame::{build_tangent_frame, local_to_world, world_to_local};
-use dacquoise::math::constants::{Float, Vector2f, Vector3f};
+use dacquoise::integrators::create_integrator;
+use dacquoise::math::constants::{Float, Vector3f};
 use dacquoise::math::ray::Ray3f;
-use dacquoise::math::spectrum::Spectrum;
+use dacquoise::samplers::create_sampler;
 use std::env;
 
 fn brute_force_primary_hit(
@@ -29,10 +29,90 @@ fn brute_force_primary_hit(
     }
 }
 
+fn parse_region(text: &str) -> Option<[usize; 4]> {
+    let values: Vec<usize> = text.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+    if values.len() == 4 && values[0] < values[2] && values[1] < values[3] {
+        Some([values[0], values[1], values[2], values[3]])
+    } else {
+        None
+    }
+}
+
+fn fmt_vector(v: &Vector3f) -> String {
+    format!("({:.5}, {:.5}, {:.5})", v.x, v.y, v.z)
+}
+
+fn print_path(path: &RecordedPath) {
+    println!(
+        "pixel ({}, {}) sample {}: L=({:.6}, {:.6}, {:.6}) origin={}",
+        path.pixel.0,
+        path.pixel.1,
+        path.sample_index,
+        path.radiance[0],
+        path.radiance[1],
+        path.radiance[2],
+        fmt_vector(&path.origin)
+    );
+    for (bounce, vertex) in path.vertices.iter().enumerate() {
+        let bsdf = vertex.bsdf.as_ref().map(|(kind, id)| format!("{} '{}'", kind, id)).unwrap_or_else(|| "<none>".to_string());
+        println!(
+            "  bounce {}: bsdf={} p={} n_geo={} n_sh={} throughput={}",
+            bounce,
+            bsdf,
+            fmt_vector(&vertex.position),
+            fmt_vector(&vertex.geo_normal),
+            fmt_vector(&vertex.sh_normal),
+            fmt_vector(&vertex.throughput)
+        );
+        if vertex.emitted != Vector3f::zeros() {
+            println!(
+                "    emitted={} light_pdf={:.6} mis={:.4}",
+                fmt_vector(&vertex.emitted),
+                vertex.light_pdf,
+                vertex.emission_mis_weight
+            );
+        }
+        if let Some(nee) = &vertex.nee {
+            println!(
+                "    nee: dir={} light_pdf={:.6} bsdf_pdf={:.6} mis={:.4} {} contribution={}",
+                fmt_vector(&nee.direction),
+                nee.light_pdf,
+                nee.bsdf_pdf,
+                nee.mis_weight,
+                if nee.occluded { "occluded" } else { "visible" },
+                fmt_vector(&nee.contribution)
+            );
+        }
+        match &vertex.sampled_direction {
+            Some(wo) => println!(
+                "    sampled wo={} pdf={:.6} weight={} survival={:.3}",
+                fmt_vector(wo),
+                vertex.bsdf_pdf,
+                fmt_vector(&vertex.bsdf_weight),
+                vertex.survival
+            ),
+            None => println!("    path terminated"),
+        }
+    }
+    if let Some(escaped) = &path.escaped {
+        println!(
+            "  escaped: dir={} throughput={} env_pdf={:.6} mis={:.4} emitted={}",
+            fmt_vector(&escaped.direction),
+            fmt_vector(&escaped.throughput),
+            escaped.environment_pdf,
+            escaped.mis_weight,
+            fmt_vector(&escaped.emitted)
+        );
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 4 {
-        eprintln!("Usage: {} <scene.xml> <x> <y> [--max-depth N] [--seed N] [--camera N]", args[0]);
+        eprintln!(
+            "Usage: {} <scene.xml> <x> <y> [--region x0,y0,x1,y1] [--spp N] [--max-depth N] [--seed N] [--camera N] [--json FILE] [--ply FILE] [--radius R] [--escape-length L] [--no-shadow-rays] [--quiet]",
+            args[0]
+        );
         std::process::exit(1);
     }
 
@@ -40,16 +120,34 @@ fn main() {
     let x: usize = args[2].parse().unwrap_or(0);
     let y: usize = args[3].parse().unwrap_or(0);
 
-    let mut max_depth: u32 = 8;
+    let mut region: Option<[usize; 4]> = None;
+    let mut spp: u32 = 1;
+    let mut max_depth: Option<u32> = None;
     let mut seed: u64 = 0;
     let mut camera_id: usize = 0;
+    let mut json_path: Option<String> = None;
+    let mut ply_path: Option<String> = None;
+    let mut overlay = OverlaySettings::default();
+    let mut quiet = false;
 
     let mut i = 4;
     while i < args.len() {
         match args[i].as_str() {
+            "--region" => {
+                i += 1;
+                region = args.get(i).and_then(|v| parse_region(v));
+                if region.is_none() {
+                    eprintln!("Malformed region, expected x0,y0,x1,y1 with x0 < x1 and y0 < y1.");
+                    std::process::exit(1);
+                }
+            }
+            "--spp" => {
+                i += 1;
+                spp = args.get(i).and_then(|v| v.parse::<u32>().ok()).unwrap_or(spp);
+            }
             "--max-depth" => {
                 i += 1;
-                max_depth = args.get(i).and_then(|v| v.parse::<u32>().ok()).unwrap_or(max_depth);
+                max_depth = args.get(i).and_then(|v| v.parse::<u32>().ok()).or(max_depth);
             }
             "--seed" => {
                 i += 1;
@@ -59,134 +157,110 @@ fn main() {
                 i += 1;
                 camera_id = args.get(i).and_then(|v| v.parse::<usize>().ok()).unwrap_or(camera_id);
             }
-            _ => {}
+            "--json" => {
+                i += 1;
+                json_path = args.get(i).cloned();
+            }
+            "--ply" => {
+                i += 1;
+                ply_path = args.get(i).cloned();
+            }
+            "--radius" => {
+                i += 1;
+                overlay.radius = args.get(i).and_then(|v| v.parse::<Float>().ok()).unwrap_or(overlay.radius);
+            }
+            "--escape-length" => {
+                i += 1;
+                overlay.escape_length = args.get(i).and_then(|v| v.parse::<Float>().ok()).unwrap_or(overlay.escape_length);
+            }
+            "--no-shadow-rays" => overlay.shadow_rays = false,
+            "--quiet" => quiet = true,
+            other => eprintln!("Ignoring unknown argument '{}'.", other),
         }
         i += 1;
     }
 
     let load_result = load_scene_with_settings(scene_path)
         .unwrap_or_else(|e| panic!("failed to load scene: {:?}", e));
-    let scene = load_result.scene;
+    let max_depth = max_depth.or(load_result.max_depth).unwrap_or(8);
+    let integrator_name = load_result.integrator_type.as_deref().unwrap_or("path");
+    let integrator = create_integrator(integrator_name, max_depth, spp, load_result.step_size, load_result.firefly).unwrap_or_else(|| {
+        eprintln!("Unsupported integrator '{}', falling back to path.", integrator_name);
+        create_integrator("path", max_depth, spp, None, load_result.firefly).unwrap()
+    });
+    let sampler_name = load_result.sampler_type.as_deref().unwrap_or("independent");
+    let mut sampler = create_sampler(sampler_name, spp, seed).expect("sampler type validated by the loader");
+    let mut scene = load_result.scene;
+    scene.build_bvh();
     let sensor = scene.camera(camera_id).expect("camera not found");
     let (width, height) = {
         let bmp = sensor.bitmap();
         (bmp.width(), bmp.height())
     };
 
-    if x >= width || y >= height {
-        eprintln!("Pixel out of bounds: ({}, {}) for size {}x{}", x, y, width, height);
+    let pixels: Vec<(usize, usize)> = match region {
+        Some([x0, y0, x1, y1]) => (y0..y1.min(height)).flat_map(|py| (x0..x1.min(width)).map(move |px| (px, py))).collect(),
+        None => vec![(x, y)],
+    };
+    if pixels.is_empty() || pixels.iter().any(|&(px, py)| px >= width || py >= height) {
+        eprintln!("Pixel out of bounds for size {}x{}", width, height);
         std::process::exit(2);
     }
 
-    let pixel_seed = ((seed & 0xFFF) << 32) | (((y as u64) & 0xFFFF) << 16) | ((x as u64) & 0xFFFF);
-    let mut rng = LcgRng::new(pixel_seed);
-    let u = (x as Float + rng.next_f32()) / (width as Float);
-    let v = (y as Float + rng.next_f32()) / (height as Float);
-    let mut ray = sensor.sample_ray(&Vector2f::new(u, v));
-    let mut throughput = Vector3f::new(1.0, 1.0, 1.0);
-
-    println!("trace_pixel_path: scene={} pixel=({}, {}) u={:.6} v={:.6}", scene_path, x, y, u, v);
-
-    if let Some((idx, hit)) = brute_force_primary_hit(&scene, &ray) {
-        let object = &scene.objects()[idx];
-        let name = object.name.as_deref().unwrap_or("<unnamed>");
-        println!(
-            "primary object: {} bsdf={} t={:.6} p=({:.5}, {:.5}, {:.5})",
-            name,
-            object.material.name(),
-            hit.t(),
-            hit.p().x, hit.p().y, hit.p().z
-        );
-    } else {
-        println!("primary object: miss");
-    }
-
-    for bounce in 0..max_depth {
-        let intersection = match scene.ray_intersection(&ray) {
-            Some(hit) => hit,
-            None => {
-                let mut env = Vector3f::zeros();
-                for emitter in scene.emitters() {
-                    let le = emitter.eval_direction(&ray.dir());
-                    env += Vector3f::new(le[0], le[1], le[2]);
-                }
+    println!("trace_pixel_path: scene={} integrator={} pixels={} spp={}", scene_path, integrator.describe(), pixels.len(), spp);
+    if region.is_none() {
+        // Compare the BVH against a linear scan through the pixel center.
+        let u = (x as Float + 0.5) / (width as Float);
+        let v = (y as Float + 0.5) / (height as Float);
+        let ray = sensor.sample_ray(&dacquoise::math::constants::Vector2f::new(u, v));
+        let bvh_t = scene.ray_intersection(&ray).map(|hit| hit.t());
+        match brute_force_primary_hit(&scene, &ray) {
+            Some((idx, hit)) => {
+                let object = &scene.objects()[idx];
                 println!(
-                    "bounce {}: miss, env=({:.6}, {:.6}, {:.6}) throughput=({:.6}, {:.6}, {:.6})",
-                    bounce, env.x, env.y, env.z, throughput.x, throughput.y, throughput.z
+                    "primary object: {} bsdf={} t={:.6} (bvh t={:?}) p={}",
+                    object.name.as_deref().unwrap_or("<unnamed>"),
+                    object.material.name(),
+                    hit.t(),
+                    bvh_t,
+                    fmt_vector(&hit.p())
                 );
-                break;
-            }
-        };
-
-        let material = match intersection.material() {
-            Some(m) => m,
-            None => {
-                println!("bounce {}: hit with no material", bounce);
-                break;
             }
-        };
-
-        let le = intersection.le();
-        if !le.is_black() {
-            println!(
-                "bounce {}: hit emissive le=({:.6}, {:.6}, {:.6})",
-                bounce, le[0], le[1], le[2]
-            );
+            None => println!("primary object: miss (bvh t={:?})", bvh_t),
         }
+    }
 
-        println!(
-            "bounce {}: bsdf={} p=({:.5}, {:.5}, {:.5}) n=({:.5}, {:.5}, {:.5}) uv=({:.5}, {:.5})",
-            bounce,
-            material.name(),
-            intersection.p().x, intersection.p().y, intersection.p().z,
-            intersection.geo_normal().x, intersection.geo_normal().y, intersection.geo_normal().z,
-            intersection.uv().x, intersection.uv().y
-        );
-
-        let n_geo = intersection.geo_normal();
-        let n_sh = intersection.sh_normal();
-        let wi_world = -ray.dir();
-        let (tangent, bitangent) = build_tangent_frame(&n_sh);
-        let wi_local = world_to_local(&wi_world, &tangent, &bitangent, &n_sh);
-        let wi_dot_geo = wi_world.dot(&n_geo);
-        println!(
-            "bounce {}: wi_local.z={:.6} wi_dot_geo={:.6} (sign={})",
-            bounce,
-            wi_local.z,
-            wi_dot_geo,
-            if wi_local.z * wi_dot_geo > 0.0 { "ok" } else { "mismatch" }
-        );
-
-        let u1 = Vector2f::new(rng.next_f32(), rng.next_f32());
-        let u2 = Vector2f::new(rng.next_f32(), rng.next_f32());
-        let mut sample = material.sample(u1, u2, wi_local);
-        sample.uv = intersection.uv();
-        let pdf = sample.pdf;
-        let wo_local = sample.wo;
-        if pdf <= 0.0 {
-            println!("bounce {}: bsdf pdf <= 0, terminate", bounce);
-            break;
+    let paths = record_pixels(integrator.as_ref(), &scene, sensor, &pixels, spp, sampler.as_mut());
+    if paths.iter().all(|p| p.vertices.is_empty() && p.escaped.is_none()) {
+        eprintln!("{} does not record paths; only radiance is reported.", integrator.describe());
+    }
+    if !quiet {
+        for path in &paths {
+            print_path(path);
         }
-
-        let cos_theta = wo_local.z.abs();
-        let eval = material.eval(sample);
-        let f = Vector3f::new(eval.value[0], eval.value[1], eval.value[2]);
-        let bsdf_weight = f * (cos_theta / pdf);
-        throughput = throughput.component_mul(&bsdf_weight);
-
+    }
+    let total = |p: &RecordedPath| p.radiance[0] + p.radiance[1] + p.radiance[2];
+    let brightest = paths.iter().max_by(|a, b| total(a).partial_cmp(&total(b)).unwrap_or(std::cmp::Ordering::Equal));
+    if let Some(path) = brightest {
         println!(
-            "bounce {}: wo_local=({:.5}, {:.5}, {:.5}) pdf={:.6} f=({:.6}, {:.6}, {:.6}) weight=({:.6}, {:.6}, {:.6}) throughput=({:.6}, {:.6}, {:.6})",
-            bounce,
-            wo_local.x, wo_local.y, wo_local.z,
-            pdf,
-            f.x, f.y, f.z,
-            bsdf_weight.x, bsdf_weight.y, bsdf_weight.z,
-            throughput.x, throughput.y, throughput.z
+            "brightest sample: pixel ({}, {}) sample {} L=({:.6}, {:.6}, {:.6})",
+            path.pixel.0, path.pixel.1, path.sample_index, path.radiance[0], path.radiance[1], path.radiance[2]
         );
+    }
 
-        let wo_world = local_to_world(&wo_local, &tangent, &bitangent, &n_sh);
-        let offset_dir = if wo_world.dot(&n_geo) >= 0.0 { n_geo } else { -n_geo };
-        let origin = intersection.p() + offset_dir * 1e-6;
-        ray = Ray3f::new(origin, wo_world, Some(1e-4), None);
+    if let Some(path) = json_path {
+        if let Err(e) = std::fs::write(&path, paths_to_json(&paths)) {
+            eprintln!("Failed to write {}: {}", path, e);
+            std::process::exit(1);
+        }
+        println!("wrote {}", path);
+    }
+    if let Some(path) = ply_path {
+        let mesh = paths_to_mesh(&paths, &overlay);
+        if let Err(e) = mesh.write_ply(&path) {
+            eprintln!("Failed to write {}: {}", path, e);
+            std::process::exit(1);
+        }
+        println!("wrote {} ({} triangles)", path, mesh.triangle_count());
     }
 }
@@ -1,6 +1,8 @@
 // Copyright @yucwang 2023
 
 use crate::core::computation_node::ComputationNode;
+use crate::core::gradient::GradientBuffers;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{ Float, Vector2f, Vector3f };
 use crate::math::spectrum::RGBSpectrum;
 
@@ -14,6 +16,7 @@ pub struct BSDFSampleRecord {
     pub wo: Vector3f,
     pub pdf: Float,
     pub uv: Vector2f,
+    pub color: Option<RGBSpectrum>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -29,6 +32,15 @@ pub trait BSDF: ComputationNode + Send + Sync {
     fn is_null(&self) -> bool {
         false
     }
+    /// Mirror-like or nearly so; feature buffers look through such surfaces.
+    fn is_specular(&self) -> bool {
+        false
+    }
+    /// Whether the BSDF reads the vertex color of the hit. Only the diffuse
+    /// reflectance does; every other BSDF ignores mesh colors.
+    fn uses_vertex_colors(&self) -> bool {
+        false
+    }
     fn eval(&self, sample_record: BSDFSampleRecord) -> BSDFEvalResult;
     fn sample(&self, u1: Vector2f, 
                      u2: Vector2f,
@@ -36,6 +48,28 @@ pub trait BSDF: ComputationNode + Send + Sync {
     fn sample_and_eval(&self, u1: Vector2f, 
                               u2: Vector2f,
                               wi: Vector3f) -> BSDFEvalResult;
+
+    /// Reflectance the BSDF value is proportional to, for BSDFs that are
+    /// differentiable with respect to it; `None` otherwise.
+    fn diffuse_reflectance(&self, _uv: Vector2f, _color: Option<RGBSpectrum>) -> Option<RGBSpectrum> {
+        None
+    }
+
+    /// Propagates `grad`, the gradient with respect to
+    /// `diffuse_reflectance(uv, color)`, to the parameters behind it.
+    fn backward_reflectance(&self, _uv: Vector2f, _color: Option<RGBSpectrum>, _grad: &RGBSpectrum, _grads: &mut GradientBuffers) {}
+
+    /// Copy with its microfacet roughness raised to at least `min_alpha`,
+    /// for path regularization. `None` if the BSDF has no roughness to
+    /// raise or is already that rough.
+    fn regularized(&self, _min_alpha: Float) -> Option<Box<dyn BSDF>> {
+        None
+    }
+
+    /// Element describing this BSDF in a written scene.
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Err(SceneWriteError::Unsupported(format!("bsdf {}", self.id())))
+    }
 }
 
 impl Default for BSDFSampleRecord {
@@ -45,6 +79,7 @@ impl Default for BSDFSampleRecord {
             wo: Vector3f::zeros(),
             pdf: 0.0,
             uv: Vector2f::new(0.0, 0.0),
+            color: None,
         }
     }
 }
@@ -56,8 +91,14 @@ impl BSDFSampleRecord {
             wo: _wo,
             pdf: _pdf,
             uv: _uv,
+            color: None,
         }
     }
+
+    pub fn with_color(mut self, color: Option<RGBSpectrum>) -> Self {
+        self.color = color;
+        self
+    }
 }
 
 impl Default for BSDFEvalResult {
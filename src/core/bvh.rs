@@ -61,6 +61,9 @@ impl BVH {
             bvh.build(0, bvh.indices.len(), bounds, centroid_bounds);
         }
 
+        // Bounds and centroids are only needed while building.
+        bvh.prim_bounds = Vec::new();
+        bvh.prim_centroids = Vec::new();
         bvh
     }
 
@@ -2,6 +2,7 @@
 
 use crate::core::computation_node::ComputationNode;
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{Float, Vector2f, Vector3f};
 use crate::math::aabb::AABB;
 use crate::math::spectrum::RGBSpectrum;
@@ -55,4 +56,10 @@ pub trait Emitter: ComputationNode + Send + Sync {
     fn sample_direction(&self, u: &Vector2f, position: &SurfaceIntersection) -> Vector3f;
     fn pdf_position(&self, position: &SurfaceIntersection) -> Float;
     fn pdf_direction(&self, position: &SurfaceIntersection, direction: &Vector3f) -> Float;
+
+    /// Element for this emitter in a written scene, or `None` when it is
+    /// written as part of something else (area lights go on their shape).
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<Option<XmlElement>, SceneWriteError> {
+        Err(SceneWriteError::Unsupported(format!("emitter {}", self.id())))
+    }
 }
@@ -0,0 +1,515 @@
+// Copyright @yucwang 2026
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use base64::Engine;
+use gltf::camera::Projection;
+use gltf::khr_lights_punctual::Kind;
+use gltf::texture::{MagFilter, WrappingMode};
+
+use crate::core::bsdf::BSDF;
+use crate::core::emitter::Emitter;
+use crate::core::scene::{Scene, SceneObject};
+use crate::core::shape::Shape;
+use crate::core::texture::Texture;
+use crate::emitters::directional::DirectionalEmitter;
+use crate::materials::blend::BlendBSDF;
+use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+use crate::materials::microfacet::MicrofacetType;
+use crate::materials::roughconductor::RoughConductorBSDF;
+use crate::math::aabb::AABB;
+use crate::math::constants::{Float, Matrix4f, Vector2f, Vector3f};
+use crate::math::spectrum::{RGBSpectrum, Spectrum};
+use crate::math::transform::Transform;
+use crate::sensors::perspective::PerspectiveCamera;
+use crate::shapes::cube::Cube;
+use crate::shapes::triangle_mesh::TriangleMesh;
+use crate::textures::constant::ConstantTexture;
+use crate::textures::image::{FilterMode, ImageTexture, WrapMode};
+use crate::textures::vertex_color::VertexColorTexture;
+
+// glTF cameras carry no resolution; films get this height and the
+// camera's aspect ratio (4:3 when unspecified).
+const DEFAULT_FILM_HEIGHT: usize = 576;
+const DEFAULT_ASPECT: Float = 4.0 / 3.0;
+// Half-size of the emissive cube standing in for a punctual light,
+// relative to the diagonal of the imported geometry.
+const PUNCTUAL_LIGHT_SIZE: Float = 1e-3;
+
+#[derive(Debug)]
+pub enum GltfLoadError {
+    Io(std::io::Error),
+    Gltf(gltf::Error),
+    Parse(String),
+}
+
+impl From<std::io::Error> for GltfLoadError {
+    fn from(err: std::io::Error) -> Self {
+        GltfLoadError::Io(err)
+    }
+}
+
+impl From<gltf::Error> for GltfLoadError {
+    fn from(err: gltf::Error) -> Self {
+        GltfLoadError::Gltf(err)
+    }
+}
+
+impl fmt::Display for GltfLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfLoadError::Io(err) => write!(f, "io error: {}", err),
+            GltfLoadError::Gltf(err) => write!(f, "gltf error: {}", err),
+            GltfLoadError::Parse(err) => write!(f, "parse error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for GltfLoadError {}
+
+/// Contents of a glTF asset, converted to dacquoise objects in world space.
+///
+/// Materials map onto the closest BSDFs: the metallic-roughness model
+/// becomes a GGX conductor tinted by the base color blended over a
+/// Lambertian base by `metallic`, with `alpha = roughness^2`. Emissive
+/// materials become area lights. Point and spot lights become small
+/// emissive cubes of matching intensity (spot cones are ignored), and
+/// directional lights map to `DirectionalEmitter`.
+pub struct GltfImport {
+    pub objects: Vec<SceneObject>,
+    pub cameras: Vec<PerspectiveCamera>,
+    pub emitters: Vec<Box<dyn Emitter>>,
+}
+
+/// Imports the default scene of a `.gltf` or `.glb` file, placing it with
+/// `to_world` on top of the node hierarchy.
+pub fn import_gltf<P: AsRef<Path>>(path: P, to_world: &Matrix4f) -> Result<GltfImport, GltfLoadError> {
+    let path = path.as_ref();
+    let gltf = gltf::Gltf::open(path)?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf();
+    let buffers = load_buffers(&gltf, &base_dir)?;
+
+    let mut importer = Importer {
+        base_dir,
+        buffers,
+        materials: HashMap::new(),
+        output: GltfImport { objects: Vec::new(), cameras: Vec::new(), emitters: Vec::new() },
+        lights: Vec::new(),
+    };
+
+    let scene = gltf
+        .default_scene()
+        .or_else(|| gltf.scenes().next())
+        .ok_or_else(|| GltfLoadError::Parse("asset has no scene".to_string()))?;
+    for node in scene.nodes() {
+        importer.visit_node(&node, to_world)?;
+    }
+    importer.add_punctual_lights();
+    Ok(importer.output)
+}
+
+/// Builds a complete scene from a glTF file. Only the first camera renders;
+/// its film gets the default height and the camera's aspect ratio.
+pub fn load_gltf_scene<P: AsRef<Path>>(path: P) -> Result<Scene, GltfLoadError> {
+    let path = path.as_ref();
+    let import = import_gltf(path, &Matrix4f::identity())?;
+
+    let mut scene = Scene::new();
+    scene.set_base_dir(path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf());
+    for object in import.objects {
+        scene.add_object(object);
+    }
+    for emitter in import.emitters {
+        scene.add_emitter(emitter);
+    }
+    for camera in import.cameras {
+        scene.add_sensor(Box::new(camera));
+    }
+    scene.build_bvh();
+    Ok(scene)
+}
+
+struct Importer {
+    base_dir: PathBuf,
+    buffers: Vec<Vec<u8>>,
+    materials: HashMap<(Option<usize>, bool), Arc<dyn BSDF>>,
+    output: GltfImport,
+    lights: Vec<(Kind, RGBSpectrum, Matrix4f, Option<String>)>,
+}
+
+impl Importer {
+    fn visit_node(&mut self, node: &gltf::Node, parent: &Matrix4f) -> Result<(), GltfLoadError> {
+        let world = parent * Matrix4f::from(node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            let name = node.name().or_else(|| mesh.name()).map(str::to_string);
+            for (i, primitive) in mesh.primitives().enumerate() {
+                let object_name = name.as_ref().map(|n| {
+                    if mesh.primitives().len() > 1 { format!("{}_{}", n, i) } else { n.clone() }
+                });
+                if let Some(object) = self.build_primitive(&primitive, &world, object_name)? {
+                    self.output.objects.push(object);
+                }
+            }
+        }
+        if let Some(camera) = node.camera() {
+            if let Some(camera) = build_camera(&camera, &world) {
+                self.output.cameras.push(camera);
+            } else {
+                log::warn!("Skipping orthographic glTF camera {:?}.", camera.name());
+            }
+        }
+        if let Some(light) = node.light() {
+            let color = light.color();
+            let intensity = light.intensity();
+            let power = RGBSpectrum::new(color[0], color[1], color[2]) * intensity;
+            if let Kind::Spot { .. } = light.kind() {
+                log::warn!("glTF spot light {:?} is imported as a point light.", light.name());
+            }
+            self.lights.push((light.kind(), power, world, light.name().map(str::to_string)));
+        }
+
+        for child in node.children() {
+            self.visit_node(&child, &world)?;
+        }
+        Ok(())
+    }
+
+    fn build_primitive(&mut self,
+                       primitive: &gltf::Primitive,
+                       world: &Matrix4f,
+                       name: Option<String>) -> Result<Option<SceneObject>, GltfLoadError> {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            log::warn!("Skipping glTF primitive with unsupported mode {:?}.", primitive.mode());
+            return Ok(None);
+        }
+
+        let buffers = &self.buffers;
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| data.as_slice()));
+        let positions: Vec<Vector3f> = match reader.read_positions() {
+            Some(iter) => iter.map(|p| Vector3f::new(p[0], p[1], p[2])).collect(),
+            None => return Ok(None),
+        };
+        let flat: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        let indices = flat.chunks_exact(3).map(|f| [f[0], f[1], f[2]]).collect();
+
+        let mut mesh = TriangleMesh::from_indexed(positions, indices, name.clone());
+        if let Some(normals) = reader.read_normals() {
+            mesh = mesh.with_normals(normals.map(|n| Vector3f::new(n[0], n[1], n[2])).collect());
+        }
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            // glTF puts the texture origin at the top left.
+            mesh = mesh.with_uvs(uvs.into_f32().map(|uv| Vector2f::new(uv[0], 1.0 - uv[1])).collect());
+        }
+        if let Some(tangents) = reader.read_tangents() {
+            mesh = mesh.with_tangents(tangents.map(|t| Vector3f::new(t[0], t[1], t[2])).collect());
+        }
+        let has_colors = match reader.read_colors(0) {
+            Some(colors) => {
+                let colors = colors.into_rgb_f32().map(|c| RGBSpectrum::new(c[0], c[1], c[2])).collect();
+                mesh = mesh.with_colors(colors);
+                true
+            }
+            None => false,
+        };
+        mesh.apply_transform_matrix(&Transform::new(*world));
+
+        let material = primitive.material();
+        let bsdf = self.material(&material, has_colors)?;
+        let mut object = SceneObject::new(Arc::new(mesh), bsdf);
+        let emissive = material.emissive_factor();
+        object.emission = RGBSpectrum::new(emissive[0], emissive[1], emissive[2])
+            * material.emissive_strength().unwrap_or(1.0);
+        if let Some(name) = name {
+            object = object.with_name(name);
+        }
+        Ok(Some(object))
+    }
+
+    fn material(&mut self, material: &gltf::Material, has_colors: bool) -> Result<Arc<dyn BSDF>, GltfLoadError> {
+        let key = (material.index(), has_colors);
+        if let Some(bsdf) = self.materials.get(&key) {
+            return Ok(bsdf.clone());
+        }
+
+        let id = material.name().map(str::to_string);
+        let pbr = material.pbr_metallic_roughness();
+        // Roughness, metalness and emission are constant per material, and
+        // shading normals come from the mesh.
+        let ignored = [
+            ("metallicRoughnessTexture", pbr.metallic_roughness_texture().is_some()),
+            ("normalTexture", material.normal_texture().is_some()),
+            ("emissiveTexture", material.emissive_texture().is_some()),
+        ];
+        for (texture, _) in ignored.iter().filter(|(_, present)| *present) {
+            log::warn!(
+                "glTF material {:?} has a {}, which is not supported and is ignored.",
+                material.name(),
+                texture
+            );
+        }
+        let factor = pbr.base_color_factor();
+        let base_color = RGBSpectrum::new(factor[0], factor[1], factor[2]);
+
+        let texture: Arc<dyn Texture> = if let Some(info) = pbr.base_color_texture() {
+            let mut image = self.load_texture(&info.texture(), true)?;
+            image.scale(base_color);
+            Arc::new(image)
+        } else if has_colors {
+            Arc::new(VertexColorTexture::new(base_color))
+        } else {
+            Arc::new(ConstantTexture::new(base_color))
+        };
+        let diffuse: Arc<dyn BSDF> = Arc::new(LambertianDiffuseBSDF::new(texture, id.clone()));
+
+        let metallic = pbr.metallic_factor().clamp(0.0, 1.0);
+        let alpha = pbr.roughness_factor().clamp(0.0, 1.0).powi(2);
+        let bsdf = if metallic <= 0.0 {
+            diffuse
+        } else {
+            // eta = 0, k = 1 gives a perfect mirror tinted by the base color.
+            let conductor: Arc<dyn BSDF> = Arc::new(RoughConductorBSDF::new(
+                MicrofacetType::GGX,
+                alpha,
+                alpha,
+                true,
+                RGBSpectrum::new(0.0, 0.0, 0.0),
+                RGBSpectrum::new(1.0, 1.0, 1.0),
+                base_color,
+                id.clone(),
+            ));
+            if metallic >= 1.0 {
+                conductor
+            } else {
+                Arc::new(BlendBSDF::new(conductor, diffuse, metallic, id))
+            }
+        };
+        self.materials.insert(key, bsdf.clone());
+        Ok(bsdf)
+    }
+
+    fn load_texture(&self, texture: &gltf::Texture, srgb: bool) -> Result<ImageTexture, GltfLoadError> {
+        let mut image = match texture.source().source() {
+            gltf::image::Source::View { view, .. } => {
+                let data = self.buffers.get(view.buffer().index())
+                    .and_then(|buffer| buffer.get(view.offset()..view.offset() + view.length()))
+                    .ok_or_else(|| GltfLoadError::Parse("image buffer view out of range".to_string()))?;
+                ImageTexture::from_memory(data, srgb)
+            }
+            gltf::image::Source::Uri { uri, .. } => {
+                if uri.starts_with("data:") {
+                    ImageTexture::from_memory(&decode_data_uri(uri)?, srgb)
+                } else {
+                    let path = self.base_dir.join(decode_uri_path(uri));
+                    ImageTexture::from_file_with_srgb(&path.to_string_lossy(), srgb)
+                }
+            }
+        }
+        .map_err(GltfLoadError::Parse)?;
+
+        let sampler = texture.sampler();
+        let wrap_mode = |mode| match mode {
+            WrappingMode::ClampToEdge => WrapMode::Clamp,
+            WrappingMode::MirroredRepeat => WrapMode::Mirror,
+            WrappingMode::Repeat => WrapMode::Repeat,
+        };
+        image.set_wrap_modes(wrap_mode(sampler.wrap_s()), wrap_mode(sampler.wrap_t()));
+        if sampler.mag_filter() == Some(MagFilter::Nearest) {
+            image.set_filter_mode(FilterMode::Nearest);
+        }
+        Ok(image)
+    }
+
+    // Punctual lights are added last so their proxy size can follow the
+    // extent of the imported geometry.
+    fn add_punctual_lights(&mut self) {
+        let mut bounds = AABB::default();
+        for object in &self.output.objects {
+            bounds.expand_by_aabb(&object.shape.bounding_box());
+        }
+        let half_size = if bounds.is_valid() {
+            (PUNCTUAL_LIGHT_SIZE * bounds.diagnal().norm()).max(1e-4)
+        } else {
+            PUNCTUAL_LIGHT_SIZE
+        };
+
+        for (kind, power, world, name) in self.lights.drain(..) {
+            let transform = Transform::new(world);
+            match kind {
+                Kind::Directional => {
+                    let direction = transform.apply_vector(Vector3f::new(0.0, 0.0, -1.0)).normalize();
+                    self.output.emitters.push(Box::new(DirectionalEmitter::new_with(direction, power, name)));
+                }
+                Kind::Point | Kind::Spot { .. } => {
+                    if power.is_black() {
+                        continue;
+                    }
+                    // A convex emitter of area A seen from any direction has
+                    // mean projected area A / 4, i.e. 6 s^2 for this cube.
+                    let position = transform.apply_point(Vector3f::zeros());
+                    let mut matrix = Matrix4f::identity() * half_size;
+                    matrix[(3, 3)] = 1.0;
+                    matrix.fixed_slice_mut::<3, 1>(0, 3).copy_from(&position);
+                    let shape: Arc<dyn Shape> = Arc::new(Cube::new(Transform::new(matrix), name.clone()));
+                    let black: Arc<dyn Texture> = Arc::new(ConstantTexture::new(RGBSpectrum::default()));
+                    let bsdf: Arc<dyn BSDF> = Arc::new(LambertianDiffuseBSDF::new(black, None));
+                    let radiance = power * (1.0 / (6.0 * half_size * half_size));
+                    let mut object = SceneObject::with_emission(shape, bsdf, radiance);
+                    if let Some(name) = name {
+                        object = object.with_name(name);
+                    }
+                    self.output.objects.push(object);
+                }
+            }
+        }
+    }
+}
+
+// glTF cameras look down -Z with +Y up in their node frame.
+fn build_camera(camera: &gltf::Camera, world: &Matrix4f) -> Option<PerspectiveCamera> {
+    let perspective = match camera.projection() {
+        Projection::Perspective(perspective) => perspective,
+        Projection::Orthographic(_) => return None,
+    };
+    let transform = Transform::new(*world);
+    let origin = transform.apply_point(Vector3f::zeros());
+    let forward = transform.apply_vector(Vector3f::new(0.0, 0.0, -1.0)).normalize();
+    let up = transform.apply_vector(Vector3f::new(0.0, 1.0, 0.0)).normalize();
+
+    let aspect = perspective.aspect_ratio().filter(|a| *a > 0.0).unwrap_or(DEFAULT_ASPECT);
+    let height = DEFAULT_FILM_HEIGHT;
+    let width = ((height as Float) * aspect).round().max(1.0) as usize;
+    Some(PerspectiveCamera::new(
+        origin,
+        origin + forward,
+        up,
+        perspective.yfov(),
+        width as Float / height as Float,
+        width,
+        height,
+        perspective.znear(),
+        perspective.zfar().unwrap_or(1e4),
+    ))
+}
+
+fn load_buffers(gltf: &gltf::Gltf, base_dir: &Path) -> Result<Vec<Vec<u8>>, GltfLoadError> {
+    let mut buffers = Vec::new();
+    for buffer in gltf.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => gltf.blob.clone()
+                .ok_or_else(|| GltfLoadError::Parse("missing binary chunk".to_string()))?,
+            gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => decode_data_uri(uri)?,
+            gltf::buffer::Source::Uri(uri) => fs::read(base_dir.join(decode_uri_path(uri)))?,
+        };
+        if data.len() < buffer.length() {
+            return Err(GltfLoadError::Parse(format!(
+                "buffer {} holds {} bytes, expected {}", buffer.index(), data.len(), buffer.length())));
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, GltfLoadError> {
+    let payload = uri
+        .split_once(";base64,")
+        .map(|(_, payload)| payload)
+        .ok_or_else(|| GltfLoadError::Parse("only base64 data URIs are supported".to_string()))?;
+    base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .map_err(|e| GltfLoadError::Parse(format!("invalid base64 data: {}", e)))
+}
+
+// Relative URIs may percent-encode characters such as spaces.
+fn decode_uri_path(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(value) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{import_gltf, load_gltf_scene};
+    use crate::math::constants::{Matrix4f, Vector3f};
+    use crate::math::ray::Ray3f;
+
+    // One triangle under a translated node, a camera and a point light,
+    // with the vertex data embedded as a base64 buffer.
+    fn write_asset() -> std::path::PathBuf {
+        let mut data = Vec::new();
+        for v in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            for c in v {
+                data.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        use base64::Engine;
+        let payload = base64::engine::general_purpose::STANDARD.encode(&data);
+        let json = format!(r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0, 1, 2]}}],
+  "nodes": [
+    {{"name": "tri", "mesh": 0, "translation": [0.0, 0.0, -2.0]}},
+    {{"camera": 0, "translation": [0.0, 0.0, 3.0]}},
+    {{"extensions": {{"KHR_lights_punctual": {{"light": 0}}}}, "translation": [0.0, 2.0, 0.0]}}
+  ],
+  "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}, "material": 0}}]}}],
+  "materials": [{{"pbrMetallicRoughness": {{"baseColorFactor": [0.2, 0.4, 0.6, 1.0], "metallicFactor": 0.0}}}}],
+  "cameras": [{{"type": "perspective", "perspective": {{"yfov": 0.8, "aspectRatio": 2.0, "znear": 0.01}}}}],
+  "extensionsUsed": ["KHR_lights_punctual"],
+  "extensions": {{"KHR_lights_punctual": {{"lights": [{{"type": "point", "intensity": 5.0}}]}}}},
+  "accessors": [{{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                  "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]}}],
+  "bufferViews": [{{"buffer": 0, "byteLength": 36}}],
+  "buffers": [{{"byteLength": 36, "uri": "data:application/octet-stream;base64,{}"}}]
+}}"#, payload);
+        let path = std::env::temp_dir().join(format!("dacquoise_gltf_{}.gltf", std::process::id()));
+        std::fs::write(&path, json).expect("write gltf");
+        path
+    }
+
+    #[test]
+    fn test_import_gltf_nodes_cameras_and_lights() {
+        let path = write_asset();
+        let mut to_world = Matrix4f::identity();
+        to_world[(0, 3)] = 10.0;
+        let import = import_gltf(&path, &to_world).expect("import gltf");
+        assert_eq!(import.cameras.len(), 1);
+        assert_eq!(import.cameras[0].width(), 1152);
+        // The triangle plus the cube standing in for the point light.
+        assert_eq!(import.objects.len(), 2);
+        assert_eq!(import.objects[0].name.as_deref(), Some("tri"));
+        let bbox = import.objects[0].shape.bounding_box();
+        assert!((bbox.p_min - Vector3f::new(10.0, 0.0, -2.0)).norm() < 1e-5);
+        assert!(import.objects[1].emission[0] > 0.0);
+
+        let scene = load_gltf_scene(&path).expect("load gltf scene");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(scene.emitters().len(), 1);
+        let camera = scene.camera(0).expect("camera");
+        let ray = camera.sample_ray(&crate::math::constants::Vector2f::new(0.5, 0.5));
+        assert!((ray.dir() - Vector3f::new(0.0, 0.0, -1.0)).norm() < 1e-5);
+        let hit = scene.ray_intersection(&Ray3f::new(Vector3f::new(0.2, 0.2, 1.0), Vector3f::new(0.0, 0.0, -1.0), None, None));
+        assert!((hit.expect("hit triangle").t() - 3.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright @yucwang 2026
+
+use crate::core::scene::{RawDataView, Scene};
+use crate::math::constants::{Float, MatrixXF};
+use std::collections::HashMap;
+
+/// Gradients with respect to the scene's raw data buffers (textures and
+/// grid volumes). While rendering they are keyed by buffer address, so a
+/// parameter only needs its `RawDataView` to receive a gradient.
+#[derive(Default)]
+pub struct GradientBuffers {
+    buffers: HashMap<usize, MatrixXF>,
+}
+
+impl GradientBuffers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `value` to entry (`row`, `col`) of the gradient of `view`'s
+    /// buffer.
+    pub fn add(&mut self, view: &RawDataView, row: usize, col: usize, value: Float) {
+        if value == 0.0 || !value.is_finite() {
+            return;
+        }
+        let gradient = self
+            .buffers
+            .entry(view.ptr as usize)
+            .or_insert_with(|| MatrixXF::zeros(view.rows, view.cols));
+        gradient[(row, col)] += value;
+    }
+
+    pub fn merge(&mut self, other: GradientBuffers) {
+        for (key, gradient) in other.buffers {
+            match self.buffers.get_mut(&key) {
+                Some(existing) => *existing += gradient,
+                None => {
+                    self.buffers.insert(key, gradient);
+                }
+            }
+        }
+    }
+
+    pub fn scale(&mut self, factor: Float) {
+        for gradient in self.buffers.values_mut() {
+            gradient.scale_mut(factor);
+        }
+    }
+
+    /// One gradient per entry of `scene.raw_data()`, shaped like its buffer
+    /// and zero where nothing contributed.
+    pub fn into_scene_gradients(self, scene: &Scene) -> HashMap<String, MatrixXF> {
+        scene
+            .raw_data()
+            .iter()
+            .map(|(key, view)| {
+                let gradient = self
+                    .buffers
+                    .get(&(view.ptr as usize))
+                    .cloned()
+                    .unwrap_or_else(|| MatrixXF::zeros(view.rows, view.cols));
+                (key.clone(), gradient)
+            })
+            .collect()
+    }
+}
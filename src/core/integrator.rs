@@ -1,15 +1,56 @@
 // Copyright @yucwang 2026
 
+use crate::core::gradient::GradientBuffers;
+use crate::core::path_record::RecordedPath;
 use crate::core::scene::Scene;
-use crate::core::rng::LcgRng;
+use crate::core::sampler::Sampler;
 use crate::core::sensor::Sensor;
 use crate::math::constants::Vector2f;
 use crate::math::spectrum::RGBSpectrum;
 
 pub trait Integrator: Sync {
-    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, rng: &mut LcgRng) -> RGBSpectrum;
+    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, sampler: &mut dyn Sampler) -> RGBSpectrum;
     fn samples_per_pixel(&self) -> u32;
+    /// Type of the integrator; a checkpoint only resumes with the same one.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+    /// Longest path traced, or 0 for integrators without a depth limit.
+    fn max_depth(&self) -> u32 {
+        0
+    }
+    /// Traces the same sample as `trace_ray_forward` and records its
+    /// vertices into `path`. Integrators without recording support leave
+    /// `path` untouched.
+    fn trace_ray_recorded(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        path: &mut RecordedPath,
+    ) -> RGBSpectrum {
+        let _ = path;
+        self.trace_ray_forward(scene, sensor, pixel, sampler)
+    }
     fn describe(&self) -> String {
         String::from("Integrator")
     }
 }
+
+/// Integrators that can propagate an image-space adjoint back to the
+/// scene's raw data buffers. Visibility discontinuities are ignored, so
+/// gradients only account for how shading changes with the parameters.
+pub trait DifferentiableIntegrator: Integrator {
+    /// Traces the same sample as `trace_ray_forward` (given the same sampler
+    /// state), adds the gradient of `adjoint · L` to `grads` and returns L.
+    fn trace_ray_backward(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        adjoint: &RGBSpectrum,
+        grads: &mut GradientBuffers,
+    ) -> RGBSpectrum;
+}
@@ -15,6 +15,8 @@ pub struct SurfaceIntersection {
     material: Option<Arc<dyn BSDF>>,
     tri_index: Option<usize>,
     object_index: Option<usize>,
+    tangent: Option<Vector3f>,
+    color: Option<RGBSpectrum>,
 }
 
 pub struct SurfaceSampleRecord {
@@ -33,7 +35,7 @@ impl SurfaceIntersection {
                _new_light_pdf_area: Option<Float>) -> Self {
         Self { p: new_p, geo_normal: new_geo_normal, sh_normal: new_sh_normal,
                uv: new_uv, t: new_t, le: new_le, material: new_material,
-               tri_index: None, object_index: None }
+               tri_index: None, object_index: None, tangent: None, color: None }
     }
 
     pub fn t(&self) -> Float {
@@ -75,6 +77,50 @@ impl SurfaceIntersection {
             material: self.material.clone(),
             tri_index,
             object_index: self.object_index,
+            tangent: self.tangent,
+            color: self.color,
+        }
+    }
+
+    /// Shading tangent (dp/du) if the shape provides one.
+    pub fn tangent(&self) -> Option<Vector3f> {
+        self.tangent
+    }
+
+    pub fn with_tangent(&self, tangent: Option<Vector3f>) -> Self {
+        Self {
+            p: self.p,
+            geo_normal: self.geo_normal,
+            sh_normal: self.sh_normal,
+            uv: self.uv,
+            t: self.t,
+            le: self.le,
+            material: self.material.clone(),
+            tri_index: self.tri_index,
+            object_index: self.object_index,
+            tangent,
+            color: self.color,
+        }
+    }
+
+    /// Interpolated vertex color, for meshes that carry one.
+    pub fn color(&self) -> Option<RGBSpectrum> {
+        self.color
+    }
+
+    pub fn with_color(&self, color: Option<RGBSpectrum>) -> Self {
+        Self {
+            p: self.p,
+            geo_normal: self.geo_normal,
+            sh_normal: self.sh_normal,
+            uv: self.uv,
+            t: self.t,
+            le: self.le,
+            material: self.material.clone(),
+            tri_index: self.tri_index,
+            object_index: self.object_index,
+            tangent: self.tangent,
+            color,
         }
     }
 
@@ -93,6 +139,8 @@ impl SurfaceIntersection {
             material: self.material.clone(),
             tri_index: self.tri_index,
             object_index,
+            tangent: self.tangent,
+            color: self.color,
         }
     }
 
@@ -107,6 +155,8 @@ impl SurfaceIntersection {
             material: self.material.clone(),
             tri_index: self.tri_index,
             object_index: self.object_index,
+            tangent: self.tangent,
+            color: self.color,
         }
     }
 
@@ -114,6 +164,11 @@ impl SurfaceIntersection {
         self.material.as_deref()
     }
 
+    /// The material's shared handle, for callers that keep it.
+    pub fn material_arc(&self) -> Option<&Arc<dyn BSDF>> {
+        self.material.as_ref()
+    }
+
     pub fn with_material(&self, new_material: Arc<dyn BSDF>) -> Self {
         Self {
             p: self.p.clone(),
@@ -125,6 +180,8 @@ impl SurfaceIntersection {
             material: Some(new_material),
             tri_index: self.tri_index,
             object_index: self.object_index,
+            tangent: self.tangent,
+            color: self.color,
         }
     }
 }
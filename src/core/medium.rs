@@ -1,5 +1,7 @@
 // Copyright @yucwang 2026
 
+use crate::core::gradient::GradientBuffers;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants::Vector3f;
 use crate::math::spectrum::RGBSpectrum;
@@ -20,4 +22,15 @@ pub trait Medium: Send + Sync {
     fn bbox(&self) -> Option<AABB> {
         None
     }
+
+    /// Propagates `grad`, the gradient with respect to `sigma_t(p_world)`,
+    /// to the medium's raw data. Media without any have nothing to add.
+    fn backward_sigma_t(&self, _p_world: Vector3f, _grad: &RGBSpectrum, _grads: &mut GradientBuffers) {}
+
+    /// Same as `backward_sigma_t`, for `albedo(p_world)`.
+    fn backward_albedo(&self, _p_world: Vector3f, _grad: &RGBSpectrum, _grads: &mut GradientBuffers) {}
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Err(SceneWriteError::Unsupported("medium".to_string()))
+    }
 }
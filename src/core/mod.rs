@@ -4,14 +4,21 @@ pub mod bsdf;
 pub mod bvh;
 pub mod computation_node;
 pub mod emitter;
+pub mod gltf_loader;
+pub mod gradient;
 pub mod integrator;
 pub mod interaction;
 pub mod medium;
+pub mod path_record;
 pub mod sensor;
 pub mod scene;
 pub mod scene_loader;
+pub mod scene_writer;
 pub mod rng;
+pub mod sampler;
 pub mod shape;
 pub mod tangent_frame;
+#[cfg(test)]
+pub(crate) mod test_scene;
 pub mod texture;
 pub mod volume;
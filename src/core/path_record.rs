@@ -0,0 +1,390 @@
+// Copyright @yucwang 2026
+
+//! Per-vertex records of the light paths traced for a few pixels, for
+//! debugging fireflies and other single-pixel artifacts. Paths are filled in
+//! by `Integrator::trace_ray_recorded` and can be written out as JSON, or as
+//! a mesh of thin tubes that loads back into a scene as a `ply` shape.
+
+use crate::core::integrator::Integrator;
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::core::sensor::Sensor;
+use crate::core::tangent_frame::build_tangent_frame;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::math::spectrum::RGBSpectrum;
+use crate::shapes::triangle_mesh::TriangleMesh;
+use std::fmt::Write;
+
+/// Next event estimation performed at a vertex.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NeeRecord {
+    /// Sampled point on an area light; `None` for directional and
+    /// environment emitters.
+    pub light_position: Option<Vector3f>,
+    pub direction: Vector3f,
+    /// Solid angle pdf of the light sample.
+    pub light_pdf: Float,
+    /// Solid angle pdf of the BSDF sampling the same direction.
+    pub bsdf_pdf: Float,
+    pub mis_weight: Float,
+    pub occluded: bool,
+    /// Radiance added to the pixel, zero when occluded.
+    pub contribution: Vector3f,
+}
+
+/// One surface interaction of a recorded path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathVertex {
+    pub position: Vector3f,
+    pub geo_normal: Vector3f,
+    pub sh_normal: Vector3f,
+    /// BSDF type and id, `None` for surfaces without a material.
+    pub bsdf: Option<(String, String)>,
+    /// Path throughput arriving at the vertex.
+    pub throughput: Vector3f,
+    /// MIS-weighted emission added at the vertex.
+    pub emitted: Vector3f,
+    /// Solid angle pdf of light sampling this vertex, for emissive hits.
+    pub light_pdf: Float,
+    pub emission_mis_weight: Float,
+    pub nee: Option<NeeRecord>,
+    /// World-space direction sampled from the BSDF, `None` if the path ended.
+    pub sampled_direction: Option<Vector3f>,
+    pub bsdf_pdf: Float,
+    /// f·|cos|/pdf of the sampled direction.
+    pub bsdf_weight: Vector3f,
+    /// Russian roulette survival probability, 1 if roulette was not played.
+    pub survival: Float,
+}
+
+impl PathVertex {
+    pub fn new(position: Vector3f, geo_normal: Vector3f, sh_normal: Vector3f, throughput: Vector3f) -> Self {
+        Self {
+            position,
+            geo_normal,
+            sh_normal,
+            bsdf: None,
+            throughput,
+            emitted: Vector3f::zeros(),
+            light_pdf: 0.0,
+            emission_mis_weight: 0.0,
+            nee: None,
+            sampled_direction: None,
+            bsdf_pdf: 0.0,
+            bsdf_weight: Vector3f::zeros(),
+            survival: 1.0,
+        }
+    }
+}
+
+/// The ray that left the scene and what the environment added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscapeRecord {
+    pub direction: Vector3f,
+    pub throughput: Vector3f,
+    pub environment_pdf: Float,
+    pub mis_weight: Float,
+    pub emitted: Vector3f,
+}
+
+/// A single camera sample and everything the integrator did with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedPath {
+    pub pixel: (usize, usize),
+    pub sample_index: u32,
+    pub origin: Vector3f,
+    pub vertices: Vec<PathVertex>,
+    pub escaped: Option<EscapeRecord>,
+    pub radiance: RGBSpectrum,
+}
+
+impl RecordedPath {
+    pub fn new(pixel: (usize, usize), sample_index: u32) -> Self {
+        Self {
+            pixel,
+            sample_index,
+            origin: Vector3f::zeros(),
+            vertices: Vec::new(),
+            escaped: None,
+            radiance: RGBSpectrum::default(),
+        }
+    }
+
+    /// Sum of the emission and NEE contributions along the path, which
+    /// equals `radiance` unless the integrator clamped it.
+    pub fn recorded_radiance(&self) -> Vector3f {
+        let mut total = self.escaped.as_ref().map(|e| e.emitted).unwrap_or_else(Vector3f::zeros);
+        for vertex in &self.vertices {
+            total += vertex.emitted;
+            if let Some(nee) = &vertex.nee {
+                total += nee.contribution;
+            }
+        }
+        total
+    }
+}
+
+/// Traces `samples_per_pixel` recorded samples for each pixel, driving the
+/// sampler the same way the renderers do.
+pub fn record_pixels(
+    integrator: &dyn Integrator,
+    scene: &Scene,
+    sensor: &dyn Sensor,
+    pixels: &[(usize, usize)],
+    samples_per_pixel: u32,
+    sampler: &mut dyn Sampler,
+) -> Vec<RecordedPath> {
+    let mut paths = Vec::with_capacity(pixels.len() * samples_per_pixel as usize);
+    for &(x, y) in pixels {
+        for sample_index in 0..samples_per_pixel {
+            sampler.start_pixel_sample(x, y, sample_index);
+            let mut path = RecordedPath::new((x, y), sample_index);
+            let pixel = Vector2f::new(x as Float, y as Float);
+            path.radiance = integrator.trace_ray_recorded(scene, sensor, pixel, sampler, &mut path);
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Writes the paths as a JSON array, with `null` for non-finite values.
+pub fn paths_to_json(paths: &[RecordedPath]) -> String {
+    let mut out = String::from("[\n");
+    for (index, path) in paths.iter().enumerate() {
+        let radiance = Vector3f::new(path.radiance[0], path.radiance[1], path.radiance[2]);
+        let _ = write!(
+            out,
+            "  {{\"pixel\": [{}, {}], \"sample\": {}, \"radiance\": {}, \"origin\": {},\n   \"vertices\": [",
+            path.pixel.0,
+            path.pixel.1,
+            path.sample_index,
+            json_vector(&radiance),
+            json_vector(&path.origin)
+        );
+        for (i, vertex) in path.vertices.iter().enumerate() {
+            out.push_str(if i == 0 { "\n    " } else { ",\n    " });
+            write_vertex(&mut out, vertex);
+        }
+        out.push_str(if path.vertices.is_empty() { "],\n" } else { "\n   ],\n" });
+        match &path.escaped {
+            Some(e) => {
+                let _ = write!(
+                    out,
+                    "   \"escaped\": {{\"direction\": {}, \"throughput\": {}, \"environment_pdf\": {}, \"mis_weight\": {}, \"emitted\": {}}}}}",
+                    json_vector(&e.direction),
+                    json_vector(&e.throughput),
+                    json_number(e.environment_pdf),
+                    json_number(e.mis_weight),
+                    json_vector(&e.emitted)
+                );
+            }
+            None => out.push_str("   \"escaped\": null}"),
+        }
+        out.push_str(if index + 1 < paths.len() { ",\n" } else { "\n" });
+    }
+    out.push_str("]\n");
+    out
+}
+
+fn write_vertex(out: &mut String, v: &PathVertex) {
+    let bsdf = match &v.bsdf {
+        Some((kind, id)) => format!("{{\"type\": {}, \"id\": {}}}", json_string(kind), json_string(id)),
+        None => "null".to_string(),
+    };
+    let nee = match &v.nee {
+        Some(n) => format!(
+            "{{\"light_position\": {}, \"direction\": {}, \"light_pdf\": {}, \"bsdf_pdf\": {}, \"mis_weight\": {}, \"occluded\": {}, \"contribution\": {}}}",
+            n.light_position.as_ref().map(json_vector).unwrap_or_else(|| "null".to_string()),
+            json_vector(&n.direction),
+            json_number(n.light_pdf),
+            json_number(n.bsdf_pdf),
+            json_number(n.mis_weight),
+            n.occluded,
+            json_vector(&n.contribution)
+        ),
+        None => "null".to_string(),
+    };
+    let _ = write!(
+        out,
+        "{{\"position\": {}, \"geo_normal\": {}, \"sh_normal\": {}, \"bsdf\": {}, \"throughput\": {}, \"emitted\": {}, \"light_pdf\": {}, \"emission_mis_weight\": {}, \"nee\": {}, \"sampled_direction\": {}, \"bsdf_pdf\": {}, \"bsdf_weight\": {}, \"survival\": {}}}",
+        json_vector(&v.position),
+        json_vector(&v.geo_normal),
+        json_vector(&v.sh_normal),
+        bsdf,
+        json_vector(&v.throughput),
+        json_vector(&v.emitted),
+        json_number(v.light_pdf),
+        json_number(v.emission_mis_weight),
+        nee,
+        v.sampled_direction.as_ref().map(json_vector).unwrap_or_else(|| "null".to_string()),
+        json_number(v.bsdf_pdf),
+        json_vector(&v.bsdf_weight),
+        json_number(v.survival)
+    );
+}
+
+fn json_number(value: Float) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_vector(v: &Vector3f) -> String {
+    format!("[{}, {}, {}]", json_number(v.x), json_number(v.y), json_number(v.z))
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Settings for `paths_to_mesh`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OverlaySettings {
+    /// Tube radius in scene units.
+    pub radius: Float,
+    /// Length of the segment drawn for rays that left the scene.
+    pub escape_length: Float,
+    /// Also draw the shadow rays of next event estimation.
+    pub shadow_rays: bool,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self { radius: 0.005, escape_length: 1.0, shadow_rays: true }
+    }
+}
+
+/// Turns the paths into triangular tubes, one per segment. Vertex colors
+/// hold the throughput carried along a segment, or the NEE contribution for
+/// shadow rays, so a `mesh_attribute` texture can show where energy blows
+/// up. `TriangleMesh::write_ply` saves the result for loading back.
+pub fn paths_to_mesh(paths: &[RecordedPath], settings: &OverlaySettings) -> TriangleMesh {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    let mut add_tube = |a: Vector3f, b: Vector3f, color: Vector3f| {
+        let axis = b - a;
+        if axis.norm() <= 0.0 {
+            return;
+        }
+        let (t, s) = build_tangent_frame(&axis.normalize());
+        let base = positions.len() as u32;
+        for end in [a, b] {
+            for k in 0..3 {
+                let angle = k as Float * 2.0 * std::f32::consts::PI / 3.0;
+                positions.push(end + (t * angle.cos() + s * angle.sin()) * settings.radius);
+                colors.push(RGBSpectrum::new(color.x, color.y, color.z));
+            }
+        }
+        for k in 0..3 {
+            let next = (k + 1) % 3;
+            indices.push([base + k, base + next, base + 3 + k]);
+            indices.push([base + next, base + 3 + next, base + 3 + k]);
+        }
+    };
+
+    for path in paths {
+        let mut from = path.origin;
+        for vertex in &path.vertices {
+            add_tube(from, vertex.position, vertex.throughput);
+            if let (true, Some(nee)) = (settings.shadow_rays, &vertex.nee) {
+                let to = nee.light_position.unwrap_or(vertex.position + nee.direction * settings.escape_length);
+                add_tube(vertex.position, to, nee.contribution);
+            }
+            from = vertex.position;
+        }
+        if let Some(escaped) = &path.escaped {
+            add_tube(from, from + escaped.direction * settings.escape_length, escaped.throughput);
+        }
+    }
+    TriangleMesh::from_indexed(positions, indices, Some("recorded_paths".to_string())).with_colors(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_scene::TestScene;
+    use crate::integrators::path::PathIntegrator;
+    use crate::samplers::independent::IndependentSampler;
+
+    // A diffuse floor lit by a small downward-facing area light.
+    fn scene() -> Scene {
+        TestScene::default()
+            .with_albedo(RGBSpectrum::new(0.5, 0.5, 0.5))
+            .with_floor(4.0)
+            .with_light(0.5, 2.0, 5.0)
+            .with_camera(Vector3f::new(0.0, -5.0, 3.0), 0.6, 8)
+            .build()
+    }
+
+    #[test]
+    fn test_recorded_contributions_sum_to_radiance() {
+        let scene = scene();
+        let integrator = PathIntegrator::new(6, 1);
+        let mut sampler = IndependentSampler::new(1, 5);
+        let sensor = scene.camera(0).unwrap();
+        let paths = record_pixels(&integrator, &scene, sensor, &[(4, 4), (1, 6)], 16, &mut sampler);
+        assert_eq!(paths.len(), 32);
+
+        let mut lit = 0;
+        for path in &paths {
+            assert!(!path.vertices.is_empty());
+            assert_eq!(path.vertices[0].throughput, Vector3f::new(1.0, 1.0, 1.0));
+            let radiance = Vector3f::new(path.radiance[0], path.radiance[1], path.radiance[2]);
+            let recorded = path.recorded_radiance();
+            assert!((radiance - recorded).norm() <= 1e-4 * (1.0 + radiance.norm()), "{:?} vs {:?}", radiance, recorded);
+            if radiance.x > 0.0 {
+                lit += 1;
+            }
+            for vertex in &path.vertices {
+                assert!(vertex.bsdf.as_ref().is_some_and(|(kind, _)| kind.contains("LambertianDiffuseBSDF")));
+            }
+        }
+        assert!(lit > 0);
+
+        // The same sampler state gives the same radiance as a plain trace.
+        let mut sampler = IndependentSampler::new(1, 5);
+        sampler.start_pixel_sample(4, 4, 0);
+        let plain = integrator.trace_ray_forward(&scene, sensor, Vector2f::new(4.0, 4.0), &mut sampler);
+        assert_eq!(plain, paths[0].radiance);
+    }
+
+    #[test]
+    fn test_export_json_and_overlay_mesh() {
+        let scene = scene();
+        let integrator = PathIntegrator::new(3, 1);
+        let mut sampler = IndependentSampler::new(1, 1);
+        let paths = record_pixels(&integrator, &scene, scene.camera(0).unwrap(), &[(4, 4)], 2, &mut sampler);
+
+        let json = paths_to_json(&paths);
+        assert!(json.starts_with('[') && json.trim_end().ends_with(']'));
+        assert_eq!(json.matches("\"pixel\": [4, 4]").count(), 2);
+        assert_eq!(json.matches("\"position\"").count(), paths.iter().map(|p| p.vertices.len()).sum::<usize>());
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+
+        let settings = OverlaySettings { shadow_rays: false, ..OverlaySettings::default() };
+        let segments: usize = paths.iter().map(|p| p.vertices.len() + p.escaped.is_some() as usize).sum();
+        let mesh = paths_to_mesh(&paths, &settings);
+        assert_eq!(mesh.triangle_count(), 6 * segments);
+        assert_eq!(mesh.colors().len(), mesh.vertex_count());
+
+        let file = std::env::temp_dir().join(format!("dacquoise_paths_{}.ply", std::process::id()));
+        mesh.write_ply(file.to_str().unwrap()).unwrap();
+        let loaded = TriangleMesh::from_ply(file.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&file);
+        assert_eq!(loaded.triangle_count(), mesh.triangle_count());
+        assert_eq!(loaded.colors().len(), loaded.vertex_count());
+    }
+}
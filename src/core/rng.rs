@@ -11,6 +11,12 @@ impl LcgRng {
         Self { state: seed }
     }
 
+    /// Current generator state; `LcgRng::new(rng.state())` continues the
+    /// same sequence.
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
     pub fn next_u32(&mut self) -> u32 {
         self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1);
         (self.state >> 32) as u32
@@ -0,0 +1,38 @@
+// Copyright @yucwang 2026
+
+use crate::math::constants::{Float, Vector2f};
+
+/// Source of sample values for integrators. A sampler is positioned on one
+/// pixel sample with `start_pixel_sample` and then hands out consecutive
+/// dimensions; the values depend only on the pixel, the sample index, the
+/// dimension and the seed, so any pixel sample can be regenerated.
+pub trait Sampler: Send + Sync {
+    fn samples_per_pixel(&self) -> u32;
+
+    /// Moves to sample `sample_index` of pixel (`x`, `y`) and resets the
+    /// dimension counter. Dimensions 0 and 1 are reserved for
+    /// `get_pixel_2d`.
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32);
+
+    fn get_1d(&mut self) -> Float;
+    fn get_2d(&mut self) -> Vector2f;
+
+    /// Sub-pixel position of the current sample, in [0, 1)^2.
+    fn get_pixel_2d(&mut self) -> Vector2f;
+
+    /// Index of the next dimension `get_1d`/`get_2d` will consume.
+    fn dimension(&self) -> u32;
+
+    /// Copy with the same settings and position, so it continues the
+    /// current pixel sample's stream; render threads each take one.
+    fn clone_box(&self) -> Box<dyn Sampler>;
+
+    /// Type of the sampler; a checkpoint only resumes with the same one.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn describe(&self) -> String {
+        String::from("Sampler")
+    }
+}
@@ -14,6 +14,7 @@ use crate::math::aabb::AABB;
 use crate::math::constants::{ Float, MatrixXF, Vector2f, Vector3f };
 use crate::math::ray::Ray3f;
 use crate::math::spectrum::{RGBSpectrum, Spectrum};
+use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -53,9 +54,11 @@ impl SceneObject {
     }
 }
 
+/// Address and shape of a `RawData` buffer, e.g. a texture's texels, for
+/// reading it, writing it or keying its gradient.
 #[derive(Clone, Copy)]
 pub struct RawDataView {
-    pub ptr: *const Float,
+    pub ptr: *mut Float,
     pub rows: usize,
     pub cols: usize,
 }
@@ -64,12 +67,52 @@ unsafe impl Send for RawDataView {}
 unsafe impl Sync for RawDataView {}
 
 impl RawDataView {
-    pub fn from_matrix(matrix: &MatrixXF) -> Self {
-        Self {
-            ptr: matrix.as_slice().as_ptr(),
-            rows: matrix.nrows(),
-            cols: matrix.ncols(),
-        }
+    /// Overwrites the buffer with `values`, in column-major order.
+    ///
+    /// # Safety
+    /// The `RawData` behind the view must be alive, and nothing may read
+    /// or write it during the call, e.g. because every render of the scene
+    /// owning it holds a lock that the caller holds too.
+    pub unsafe fn write(&self, values: &[Float]) {
+        assert_eq!(values.len(), self.rows * self.cols, "raw data shape mismatch");
+        std::ptr::copy_nonoverlapping(values.as_ptr(), self.ptr, values.len());
+    }
+}
+
+/// Matrix that may be overwritten through its `RawDataView` while shared,
+/// e.g. by an optimizer stepping a texture between renders. The view's
+/// pointer is taken from exclusive access, so writes through it are
+/// allowed; see `RawDataView::write` for when they may happen.
+pub struct RawData {
+    matrix: UnsafeCell<MatrixXF>,
+    ptr: *mut Float,
+}
+
+unsafe impl Send for RawData {}
+unsafe impl Sync for RawData {}
+
+impl RawData {
+    pub fn new(mut matrix: MatrixXF) -> Self {
+        let ptr = matrix.as_mut_ptr();
+        Self { matrix: UnsafeCell::new(matrix), ptr }
+    }
+
+    pub fn matrix(&self) -> &MatrixXF {
+        // Writes only happen while nothing reads the matrix.
+        unsafe { &*self.matrix.get() }
+    }
+
+    /// Changes the matrix. Views taken before stay valid unless `f`
+    /// reallocates it, e.g. by changing its shape.
+    pub fn update<F: FnOnce(&mut MatrixXF)>(&mut self, f: F) {
+        let matrix = self.matrix.get_mut();
+        f(matrix);
+        self.ptr = matrix.as_mut_ptr();
+    }
+
+    pub fn view(&self) -> RawDataView {
+        let matrix = self.matrix();
+        RawDataView { ptr: self.ptr, rows: matrix.nrows(), cols: matrix.ncols() }
     }
 }
 
@@ -147,6 +190,27 @@ impl Scene {
         self.bvh = None;
     }
 
+    /// Removes the object at `index` together with its area emitter.
+    pub fn remove_object(&mut self, index: usize) -> SceneObject {
+        let object = self.objects.remove(index);
+        self.sync_area_emitters();
+        self.bvh = None;
+        object
+    }
+
+    /// Index of the first object named `name`.
+    pub fn find_object(&self, name: &str) -> Option<usize> {
+        self.objects.iter().position(|o| o.name.as_deref() == Some(name))
+    }
+
+    /// Rebuilds the area emitters from the objects' shapes and emission,
+    /// e.g. after editing objects through `objects_mut`. Other emitters are
+    /// kept.
+    pub fn sync_area_emitters(&mut self) {
+        self.emitters.retain(|e| !e.get_flag().contains(EmitterFlag::SURFACE));
+        self.emitters.extend(Self::emitters_from_objects(&self.objects));
+    }
+
     pub fn objects(&self) -> &Vec<SceneObject> {
         &self.objects
     }
@@ -357,6 +421,33 @@ impl Scene {
         Some(area_pdf * select_pdf * dist2 / cos_light)
     }
 
+    /// Solid angle pdf of `sample_emitter` returning `direction` from an
+    /// emitter at infinity, for MIS on rays that escape the scene.
+    pub fn pdf_environment(&self, direction: &Vector3f) -> Float {
+        if self.emitters.is_empty() {
+            return 0.0;
+        }
+        let origin = SurfaceIntersection::new(
+            Vector3f::zeros(),
+            Vector3f::zeros(),
+            Vector3f::zeros(),
+            Vector2f::zeros(),
+            0.0,
+            RGBSpectrum::default(),
+            None,
+            None,
+        );
+        let select_pdf = 1.0 / (self.emitters.len() as Float);
+        self.emitters
+            .iter()
+            .filter(|emitter| {
+                let flag = emitter.get_flag();
+                flag.contains(EmitterFlag::DIRECTION) && !flag.contains(EmitterFlag::DELTA)
+            })
+            .map(|emitter| emitter.pdf_direction(&origin, direction) * select_pdf)
+            .sum()
+    }
+
     pub fn sample_emitter(&self, u1: Float, u2: &Vector2f) -> Option<EmitterSample> {
         if self.emitters.is_empty() {
             return None;
@@ -545,6 +636,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scene_remove_object_drops_area_emitter() {
+        let mut scene = Scene::new();
+        let white = RGBSpectrum::new(1.0, 1.0, 1.0);
+        scene.add_object(SceneObject::with_emission(Arc::new(TestShape::new(5.0)), Arc::new(TestBSDF), white).with_name("lamp".to_string()));
+        scene.add_object(SceneObject::new(Arc::new(TestShape::new(2.0)), Arc::new(TestBSDF)).with_name("wall".to_string()));
+        assert_eq!(scene.emitters().len(), 1);
+
+        scene.objects_mut()[1].emission = white;
+        scene.sync_area_emitters();
+        assert_eq!(scene.emitters().len(), 2);
+
+        let index = scene.find_object("lamp").expect("lamp");
+        let removed = scene.remove_object(index);
+        assert_eq!(removed.name.as_deref(), Some("lamp"));
+        assert_eq!(scene.len(), 1);
+        assert_eq!(scene.emitters().len(), 1);
+        assert_eq!(scene.find_object("wall"), Some(0));
+    }
+
     #[test]
     fn test_scene_camera_access() {
         let mut scene = Scene::new();
@@ -557,4 +668,18 @@ mod tests {
         assert!(scene.camera(1).is_some());
         assert!(scene.camera(2).is_none());
     }
+
+    #[test]
+    fn test_raw_data_write_through_view() {
+        let mut data = RawData::new(MatrixXF::zeros(2, 3));
+        let view = data.view();
+        unsafe { view.write(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]) };
+        assert_eq!(data.matrix()[(1, 0)], 2.0);
+        assert_eq!(data.matrix()[(0, 2)], 5.0);
+
+        // In-place updates keep the buffer, and with it the view.
+        data.update(|m| m.scale_mut(2.0));
+        assert_eq!(data.view().ptr, view.ptr);
+        assert_eq!(data.matrix()[(1, 2)], 12.0);
+    }
 }
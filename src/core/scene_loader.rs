@@ -1,15 +1,19 @@
 // Copyright @yucwang 2026
 
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
 
 use quick_xml::events::Event;
 use quick_xml::Reader;
 
+use crate::core::gltf_loader::{import_gltf, load_gltf_scene};
 use crate::core::scene::{RawDataView, Scene};
 use crate::core::medium::Medium;
 use crate::core::volume::Volume;
+use crate::math::animated_transform::AnimatedTransform;
 use crate::math::constants::{Float, Matrix3f, Matrix4f, Vector3f};
 use crate::math::transform::Transform;
 use crate::sensors::perspective::PerspectiveCamera;
@@ -18,19 +22,23 @@ use crate::materials::null::NullBSDF;
 use crate::materials::roughconductor::RoughConductorBSDF;
 use crate::materials::roughdielectric::RoughDielectricBSDF;
 use crate::materials::blend::BlendBSDF;
-use crate::math::spectrum::RGBSpectrum;
+use crate::math::spectrum::{RGBSpectrum, Spectrum};
+use crate::shapes::animated::AnimatedShape;
 use crate::shapes::rectangle::Rectangle;
 use crate::shapes::cube::Cube;
 use crate::shapes::triangle_mesh::TriangleMesh;
 use crate::textures::constant::ConstantTexture;
 use crate::textures::image::{FilterMode, ImageTexture, WrapMode};
+use crate::textures::vertex_color::VertexColorTexture;
 use crate::emitters::directional::DirectionalEmitter;
 use crate::emitters::envmap::EnvMap;
 use crate::core::scene::SceneObject;
 use crate::core::bsdf::BSDF;
 use crate::core::integrator::Integrator;
-use crate::integrators::path::PathIntegrator;
-use crate::integrators::raymarching::RaymarchingIntegrator;
+use crate::integrators::create_integrator;
+use crate::integrators::path::FireflySettings;
+use crate::integrators::pssmlt::MltSettings;
+use crate::integrators::sppm::SppmSettings;
 use crate::media::homogeneous::HomogeneousMedium;
 use crate::media::heterogeneous::HeterogeneousMedium;
 use crate::volumes::constant::ConstantVolume;
@@ -44,9 +52,12 @@ struct BsdfTextureState {
     tex_type: Option<String>,
     tex_name: Option<String>,
     tex_filename: Option<String>,
+    tex_attribute: Option<String>,
     raw: bool,
     filter_type: Option<String>,
     wrap_mode: Option<String>,
+    wrap_mode_u: Option<String>,
+    wrap_mode_v: Option<String>,
     to_uv: Matrix3f,
 }
 
@@ -69,6 +80,7 @@ struct BsdfState {
     texture: Option<BsdfTextureState>,
     texture_active: bool,
     children: Vec<Arc<dyn BSDF>>,
+    location: Option<XmlLocation>,
 }
 
 impl BsdfState {
@@ -92,6 +104,7 @@ impl BsdfState {
             texture: None,
             texture_active: false,
             children: Vec::new(),
+            location: None,
         }
     }
 }
@@ -107,6 +120,7 @@ struct VolumeState {
     wrap_mode: Option<String>,
     to_world: Matrix4f,
     use_grid_bbox: Option<bool>,
+    location: Option<XmlLocation>,
 }
 
 impl VolumeState {
@@ -121,6 +135,7 @@ impl VolumeState {
             wrap_mode: None,
             to_world: Matrix4f::identity(),
             use_grid_bbox: None,
+            location: None,
         }
     }
 }
@@ -138,6 +153,7 @@ struct MediumState {
     albedo_ref: Option<String>,
     scale: Option<Float>,
     phase_function: Option<String>,
+    location: Option<XmlLocation>,
 }
 
 impl MediumState {
@@ -154,6 +170,7 @@ impl MediumState {
             albedo_ref: None,
             scale: None,
             phase_function: None,
+            location: None,
         }
     }
 }
@@ -163,6 +180,13 @@ pub enum SceneLoadError {
     Io(std::io::Error),
     Parse(String),
     MissingField(&'static str),
+    /// Error raised while reading `file`, at the 1-based (line, column) of
+    /// the element being processed when known.
+    At {
+        file: PathBuf,
+        position: Option<(usize, usize)>,
+        error: Box<SceneLoadError>,
+    },
 }
 
 impl From<std::io::Error> for SceneLoadError {
@@ -171,32 +195,178 @@ impl From<std::io::Error> for SceneLoadError {
     }
 }
 
+impl fmt::Display for SceneLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneLoadError::Io(err) => write!(f, "io error: {}", err),
+            SceneLoadError::Parse(err) => write!(f, "parse error: {}", err),
+            SceneLoadError::MissingField(name) => write!(f, "missing field: {}", name),
+            SceneLoadError::At { file, position: Some((line, column)), error } => {
+                write!(f, "{}:{}:{}: {}", file.display(), line, column, error)
+            }
+            SceneLoadError::At { file, position: None, error } => write!(f, "{}: {}", file.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for SceneLoadError {}
+
+// Where an element starts, kept for errors raised once it has been read.
+#[derive(Debug, Clone)]
+struct XmlLocation {
+    file: PathBuf,
+    position: (usize, usize),
+}
+
+impl XmlLocation {
+    fn locate(&self, error: SceneLoadError) -> SceneLoadError {
+        match error {
+            SceneLoadError::At { .. } => error,
+            error => SceneLoadError::At { file: self.file.clone(), position: Some(self.position), error: Box::new(error) },
+        }
+    }
+}
+
+fn locate_at(location: Option<&XmlLocation>, error: SceneLoadError) -> SceneLoadError {
+    match location {
+        Some(location) => location.locate(error),
+        None => error,
+    }
+}
+
+// One XML file being read; includes push further sources on a stack.
+struct XmlSource {
+    path: PathBuf,
+    canonical: PathBuf,
+    reader: Reader<Cursor<Vec<u8>>>,
+    event_start: usize,
+}
+
+impl XmlSource {
+    fn open(path: &Path) -> Result<Self, SceneLoadError> {
+        let canonical = fs::canonicalize(path)?;
+        let data = fs::read(path)?;
+        let mut reader = Reader::from_reader(Cursor::new(data));
+        reader.trim_text(true);
+        Ok(Self { path: path.to_path_buf(), canonical, reader, event_start: 0 })
+    }
+
+    fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> quick_xml::Result<Event<'b>> {
+        self.event_start = self.reader.buffer_position();
+        self.reader.read_event_into(buf)
+    }
+
+    // Line and column of the current event, skipping the whitespace that
+    // text trimming leaves in front of it.
+    fn position(&self) -> (usize, usize) {
+        let data = self.reader.get_ref().get_ref();
+        let mut offset = self.event_start.min(data.len());
+        while offset < data.len() && data[offset].is_ascii_whitespace() {
+            offset += 1;
+        }
+        let before = &data[..offset];
+        let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+        let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |p| p + 1);
+        (line, offset - line_start + 1)
+    }
+
+    fn location(&self) -> XmlLocation {
+        XmlLocation { file: self.path.clone(), position: self.position() }
+    }
+
+    // Relative filenames inside this file resolve against its directory.
+    fn base_dir(&self) -> PathBuf {
+        self.path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf()
+    }
+
+    fn locate(&self, error: SceneLoadError) -> SceneLoadError {
+        self.location().locate(error)
+    }
+}
+
+// Opens `<include filename=...>` relative to the including file, refusing
+// files that are already being read further up the include chain.
+fn open_include(sources: &[XmlSource], filename: &str) -> Result<XmlSource, SceneLoadError> {
+    let parent = sources.last().map_or_else(|| PathBuf::from("."), XmlSource::base_dir);
+    let path = parent.join(filename);
+    let canonical = fs::canonicalize(&path)
+        .map_err(|e| SceneLoadError::Parse(format!("cannot include {}: {}", path.display(), e)))?;
+    if sources.iter().any(|s| s.canonical == canonical) {
+        let chain: Vec<String> = sources.iter().map(|s| s.path.display().to_string())
+            .chain(std::iter::once(path.display().to_string()))
+            .collect();
+        return Err(SceneLoadError::Parse(format!("include cycle: {}", chain.join(" -> "))));
+    }
+    XmlSource::open(&path)
+}
+
 pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneLoadError> {
     let result = load_scene_with_settings(path)?;
     Ok(result.scene)
 }
 
+/// Sampler types accepted in `<sampler type=...>`; see `samplers::create_sampler`.
+const SAMPLER_TYPES: &[&str] = &["independent", "stratified", "sobol", "ldsampler", "pmj02", "multijitter"];
+
 pub struct SceneLoadResult {
     pub scene: Scene,
+    /// Per-pixel integrator, built when the scene sets `max_depth`. `None`
+    /// for `ptracer`, `sppm` and `pssmlt`, which need the renderers of the
+    /// same names instead.
     pub integrator: Option<Box<dyn Integrator>>,
     pub integrator_type: Option<String>,
+    pub sampler_type: Option<String>,
     pub samples_per_pixel: Option<u32>,
     pub max_depth: Option<u32>,
+    pub step_size: Option<Float>,
+    pub firefly: FireflySettings,
+    pub sppm: SppmSettings,
+    pub mlt: MltSettings,
 }
 
+/// Loads a Mitsuba-style XML scene, or a `.gltf`/`.glb` asset with default
+/// render settings.
 pub fn load_scene_with_settings<P: AsRef<Path>>(path: P) -> Result<SceneLoadResult, SceneLoadError> {
+    load_scene_with_defines(path, &HashMap::new())
+}
+
+/// Like `load_scene_with_settings`, with `defines` overriding the scene's
+/// `<default>` values (Mitsuba's `-D name=value`).
+pub fn load_scene_with_defines<P: AsRef<Path>>(path: P,
+                                               defines: &HashMap<String, String>) -> Result<SceneLoadResult, SceneLoadError> {
     let path = path.as_ref();
-    let xml = fs::read_to_string(path)?;
-    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
-    parse_scene(&xml, base_dir)
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("").to_ascii_lowercase();
+    if ext == "gltf" || ext == "glb" {
+        let scene = load_gltf_scene(path).map_err(|e| SceneLoadError::Parse(format!("gltf load failed: {}", e)))?;
+        return Ok(SceneLoadResult {
+            scene,
+            integrator: None,
+            integrator_type: None,
+            sampler_type: None,
+            samples_per_pixel: None,
+            max_depth: None,
+            step_size: None,
+            firefly: FireflySettings::default(),
+            sppm: SppmSettings::default(),
+            mlt: MltSettings::default(),
+        });
+    }
+    let in_file = |error| match error {
+        SceneLoadError::At { .. } => error,
+        error => SceneLoadError::At { file: path.to_path_buf(), position: None, error: Box::new(error) },
+    };
+    let mut sources = vec![XmlSource::open(path).map_err(in_file)?];
+    parse_scene(&mut sources, defines).map_err(|error| match sources.last() {
+        Some(source) => source.locate(error),
+        None => in_file(error),
+    })
 }
 
-fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadError> {
-    let mut reader = Reader::from_str(xml);
-    reader.trim_text(true);
+fn parse_scene(sources: &mut Vec<XmlSource>,
+               defines: &HashMap<String, String>) -> Result<SceneLoadResult, SceneLoadError> {
     let mut buf = Vec::new();
 
-    let mut defaults: HashMap<String, String> = HashMap::new();
+    let mut defaults: HashMap<String, String> = defines.clone();
 
     let mut in_sensor = false;
     let mut in_film = false;
@@ -214,6 +384,7 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
     let mut in_medium = false;
     let mut in_shape_medium = false;
     let mut in_medium_volume = false;
+    let mut in_animation = false;
 
     let mut fov_deg: Option<Float> = None;
     let mut origin: Option<Vector3f> = None;
@@ -221,13 +392,19 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
     let mut up: Option<Vector3f> = None;
     let mut near_clip: Option<Float> = None;
     let mut far_clip: Option<Float> = None;
+    let mut shutter_open: Option<Float> = None;
+    let mut shutter_close: Option<Float> = None;
     let mut fov_axis: Option<String> = None;
     let mut width: Option<usize> = None;
     let mut height: Option<usize> = None;
     let mut max_depth: Option<u32> = None;
     let mut spp: Option<u32> = None;
     let mut integrator_type: Option<String> = None;
+    let mut sampler_type: Option<String> = None;
     let mut raymarch_step_size: Option<Float> = None;
+    let mut firefly = FireflySettings::default();
+    let mut sppm = SppmSettings::default();
+    let mut mlt = MltSettings::default();
 
     let mut bsdfs: HashMap<String, Arc<dyn BSDF>> = HashMap::new();
     let mut raw_data: HashMap<String, RawDataView> = HashMap::new();
@@ -243,10 +420,15 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
     let mut current_envmap_scale: Option<Float> = None;
     let mut current_emitter_transform = Matrix4f::identity();
     let mut current_sensor_transform = Matrix4f::identity();
+    let mut current_sensor_motion: Vec<(Float, Matrix4f)> = Vec::new();
+    let mut current_keyframe_time: Option<Float> = None;
+    let mut current_keyframes: Vec<(Float, Matrix4f)> = Vec::new();
     let mut current_shape_emissive: bool = false;
     let mut current_shape_id: Option<String> = None;
     let mut current_shape_transform = Matrix4f::identity();
+    let mut current_shape_motion: Vec<(Float, Matrix4f)> = Vec::new();
     let mut current_shape_face_normals = false;
+    let mut current_shape_index: usize = 0;
     let mut current_volume: Option<VolumeState> = None;
     let mut current_medium: Option<MediumState> = None;
     let mut current_shape_medium: Option<MediumState> = None;
@@ -254,8 +436,12 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
     let mut current_medium_volume_name: Option<String> = None;
     let mut pending_media: Vec<MediumState> = Vec::new();
 
+    // Directory of the file currently being read; includes change it.
+    let mut base_dir = sources.last().map_or_else(|| PathBuf::from("."), XmlSource::base_dir);
+    let mut integrator_location: Option<XmlLocation> = None;
+
     let mut scene = Scene::new();
-    scene.set_base_dir(base_dir.to_path_buf());
+    scene.set_base_dir(base_dir.clone());
 
     macro_rules! handle_start {
         ($e:expr, $is_empty:expr) => {{
@@ -271,8 +457,11 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 _ => {}
                             }
                         }
+                        // Defines from the caller win over scene defaults.
                         if let (Some(k), Some(v)) = (key, value) {
-                            defaults.insert(k, v);
+                            if !defines.contains_key(&k) {
+                                defaults.insert(k, v);
+                            }
                         }
                     }
                     b"sensor" => {
@@ -290,13 +479,26 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 let integrator_value = resolve_value(&attr.unescape_value().unwrap_or_default(), &defaults);
                                 let integrator_value = integrator_value.trim().to_lowercase();
                                 integrator_type = Some(integrator_value.clone());
-                                if integrator_value != "path" && integrator_value != "raymarching" {
+                                if !matches!(integrator_value.as_str(), "path" | "raymarching" | "ptracer" | "sppm" | "pssmlt") {
                                     return Err(SceneLoadError::Parse(format!("unsupported integrator: {}", integrator_value)));
                                 }
                             }
                         }
+                        integrator_location = sources.last().map(XmlSource::location);
                         in_integrator = true;
                     }
+                    b"sampler" => {
+                        for attr in e.attributes().flatten() {
+                            if attr.key.as_ref() == b"type" {
+                                let sampler_value = resolve_value(&attr.unescape_value().unwrap_or_default(), &defaults);
+                                let sampler_value = sampler_value.trim().to_lowercase();
+                                if !SAMPLER_TYPES.contains(&sampler_value.as_str()) {
+                                    return Err(SceneLoadError::Parse(format!("unsupported sampler: {}", sampler_value)));
+                                }
+                                sampler_type = Some(sampler_value);
+                            }
+                        }
+                    }
                     b"film" => {
                         in_film = true;
                     }
@@ -311,7 +513,24 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 }
                             }
                         }
-                        if in_sensor {
+                        if in_animation {
+                            // Keyframe of an <animation>: each one starts from identity.
+                            let mut time: Option<Float> = None;
+                            for attr in e.attributes().flatten() {
+                                if attr.key.as_ref() == b"time" {
+                                    time = Some(parse_float(&resolve_value(&attr.unescape_value().unwrap_or_default(), &defaults))?);
+                                }
+                            }
+                            current_keyframe_time = Some(time.ok_or(SceneLoadError::MissingField("animation.transform.time"))?);
+                            if in_sensor {
+                                in_transform = true;
+                                in_sensor_transform = true;
+                                current_sensor_transform = Matrix4f::identity();
+                            } else {
+                                in_shape_transform = true;
+                                current_shape_transform = Matrix4f::identity();
+                            }
+                        } else if in_sensor {
                             for attr in e.attributes().flatten() {
                                 if attr.key.as_ref() == b"name" {
                                     let name = attr.unescape_value().unwrap_or_default();
@@ -342,8 +561,37 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                             }
                         }
                     }
+                    b"animation" => {
+                        if in_sensor || in_shape {
+                            for attr in e.attributes().flatten() {
+                                if attr.key.as_ref() == b"name" {
+                                    let name = attr.unescape_value().unwrap_or_default();
+                                    in_animation = name.as_ref() == "to_world";
+                                }
+                            }
+                            current_keyframes.clear();
+                        }
+                    }
                     b"lookat" => {
-                        if in_sensor && in_transform {
+                        if in_animation && (in_sensor_transform || in_shape_transform) {
+                            let mut o = Vector3f::zeros();
+                            let mut t = Vector3f::new(0.0, 0.0, 1.0);
+                            let mut u = Vector3f::new(0.0, 1.0, 0.0);
+                            for attr in e.attributes().flatten() {
+                                match attr.key.as_ref() {
+                                    b"origin" => o = parse_vec3(&attr.unescape_value().unwrap_or_default())?,
+                                    b"target" => t = parse_vec3(&attr.unescape_value().unwrap_or_default())?,
+                                    b"up" => u = parse_vec3(&attr.unescape_value().unwrap_or_default())?,
+                                    _ => {}
+                                }
+                            }
+                            let m = lookat_matrix(o, t, u);
+                            if in_sensor {
+                                current_sensor_transform = m * current_sensor_transform;
+                            } else {
+                                current_shape_transform = m * current_shape_transform;
+                            }
+                        } else if in_sensor && in_transform {
                             let mut o: Option<Vector3f> = None;
                             let mut t: Option<Vector3f> = None;
                             let mut u: Option<Vector3f> = None;
@@ -644,6 +892,37 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                             if in_integrator && name_attr == "step_size" {
                                 raymarch_step_size = Some(parse_float(&value_attr)?);
                             }
+                            if in_integrator && matches!(name_attr.as_str(), "clamp_direct" | "clamp_indirect" | "regularization") {
+                                let value = parse_float(&value_attr)?;
+                                if value <= 0.0 {
+                                    return Err(SceneLoadError::Parse(format!("integrator {} must be positive", name_attr)));
+                                }
+                                match name_attr.as_str() {
+                                    "clamp_direct" => firefly.clamp_direct = Some(value),
+                                    "clamp_indirect" => firefly.clamp_indirect = Some(value),
+                                    _ => firefly.regularization = Some(value),
+                                }
+                            }
+                            if in_integrator && matches!(name_attr.as_str(), "initial_radius" | "alpha") {
+                                let value = parse_float(&value_attr)?;
+                                if value <= 0.0 || (name_attr == "alpha" && value > 1.0) {
+                                    return Err(SceneLoadError::Parse(format!("integrator {} out of range: {}", name_attr, value)));
+                                }
+                                match name_attr.as_str() {
+                                    "initial_radius" => sppm.initial_radius = Some(value),
+                                    _ => sppm.alpha = Some(value),
+                                }
+                            }
+                            if in_integrator && matches!(name_attr.as_str(), "large_step_probability" | "sigma") {
+                                let value = parse_float(&value_attr)?;
+                                if value <= 0.0 || (name_attr == "large_step_probability" && value > 1.0) {
+                                    return Err(SceneLoadError::Parse(format!("integrator {} out of range: {}", name_attr, value)));
+                                }
+                                match name_attr.as_str() {
+                                    "large_step_probability" => mlt.large_step_probability = Some(value),
+                                    _ => mlt.sigma = Some(value),
+                                }
+                            }
                             if in_sensor {
                                 if name_attr == "fov" {
                                     fov_deg = Some(parse_float(&value_attr)?);
@@ -654,6 +933,12 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 if name_attr == "far_clip" {
                                     far_clip = Some(parse_float(&value_attr)?);
                                 }
+                                if name_attr == "shutter_open" {
+                                    shutter_open = Some(parse_float(&value_attr)?);
+                                }
+                                if name_attr == "shutter_close" {
+                                    shutter_close = Some(parse_float(&value_attr)?);
+                                }
                             }
                             if in_emitter {
                                 if name_attr == "radiance" {
@@ -766,6 +1051,20 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                             if name_attr == "sample_count" {
                                 spp = Some(parse_u32(&value_attr)?);
                             }
+                            if in_integrator && matches!(name_attr.as_str(), "photon_count" | "bootstrap_samples" | "chains") {
+                                let value = parse_u32(&value_attr)?;
+                                if value == 0 {
+                                    return Err(SceneLoadError::Parse(format!("integrator {} must be positive", name_attr)));
+                                }
+                                match name_attr.as_str() {
+                                    "photon_count" => sppm.photon_count = Some(value),
+                                    "bootstrap_samples" => mlt.bootstrap_samples = Some(value),
+                                    _ => mlt.chains = Some(value),
+                                }
+                            }
+                            if in_shape && name_attr == "shape_index" {
+                                current_shape_index = parse_usize(&value_attr)?;
+                            }
                         }
                     }
                     b"boolean" => {
@@ -809,7 +1108,9 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                             }
                         }
                         if let Some(bsdf_type) = bsdf_type {
-                            bsdf_stack.push(BsdfState::new(bsdf_type, bsdf_id));
+                            let mut state = BsdfState::new(bsdf_type, bsdf_id);
+                            state.location = sources.last().map(XmlSource::location);
+                            bsdf_stack.push(state);
                         }
                     }
                     b"volume" => {
@@ -835,11 +1136,15 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 if let Some(inline_type) = inline_type {
                                     in_medium_volume = true;
                                     current_medium_volume_name = volume_name;
-                                    current_medium_volume = Some(VolumeState::new(inline_type, None));
+                                    let mut state = VolumeState::new(inline_type, None);
+                                    state.location = sources.last().map(XmlSource::location);
+                                    current_medium_volume = Some(state);
                                 }
                             } else if volume_type == "const" || volume_type == "grid" {
                                 in_volume = true;
-                                current_volume = Some(VolumeState::new(volume_type, volume_id));
+                                let mut state = VolumeState::new(volume_type, volume_id);
+                                state.location = sources.last().map(XmlSource::location);
+                                current_volume = Some(state);
                             } else {
                                 in_volume = false;
                                 current_volume = None;
@@ -865,11 +1170,13 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                     in_shape_medium = true;
                                     let mut state = MediumState::new(medium_type, medium_id);
                                     state.name = medium_name;
+                                    state.location = sources.last().map(XmlSource::location);
                                     current_shape_medium = Some(state);
                                 } else {
                                     in_medium = true;
                                     let mut state = MediumState::new(medium_type, medium_id);
                                     state.name = medium_name;
+                                    state.location = sources.last().map(XmlSource::location);
                                     current_medium = Some(state);
                                 }
                             } else {
@@ -899,9 +1206,12 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 tex_type,
                                 tex_name,
                                 tex_filename: None,
+                                tex_attribute: None,
                                 raw: false,
                                 filter_type: None,
                                 wrap_mode: None,
+                                wrap_mode_u: None,
+                                wrap_mode_v: None,
                                 to_uv: Matrix3f::identity(),
                             });
                         }
@@ -966,10 +1276,16 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                     if let Some(ref mut tex) = current_bsdf.texture {
                                         if name_attr == "filename" {
                                             tex.tex_filename = Some(value_attr.clone());
+                                        } else if name_attr == "name" {
+                                            tex.tex_attribute = Some(value_attr.clone());
                                         } else if name_attr == "filter_type" {
                                             tex.filter_type = Some(value_attr.clone());
                                         } else if name_attr == "wrap_mode" {
                                             tex.wrap_mode = Some(value_attr.clone());
+                                        } else if name_attr == "wrap_mode_u" {
+                                            tex.wrap_mode_u = Some(value_attr.clone());
+                                        } else if name_attr == "wrap_mode_v" {
+                                            tex.wrap_mode_v = Some(value_attr.clone());
                                         }
                                     }
                                 } else if name_attr == "distribution" {
@@ -1069,7 +1385,7 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 shape_id = Some(attr.unescape_value().unwrap_or_default().to_string());
                             }
                         }
-                        if matches!(shape_type.as_deref(), Some("obj") | Some("ply") | Some("rectangle") | Some("cube")) {
+                        if matches!(shape_type.as_deref(), Some("obj") | Some("ply") | Some("serialized") | Some("rectangle") | Some("cube") | Some("gltf")) {
                             in_shape = true;
                             current_shape_type = shape_type;
                             current_shape_filename = None;
@@ -1079,7 +1395,9 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                             current_emitter_radiance = None;
                             current_shape_id = shape_id;
                             current_shape_transform = Matrix4f::identity();
+                            current_shape_motion.clear();
                             current_shape_face_normals = false;
+                            current_shape_index = 0;
                             current_shape_medium = None;
                             in_shape_medium = false;
                         } else {
@@ -1148,7 +1466,8 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                 b"bsdf" => {
                     if let Some(state) = bsdf_stack.pop() {
                         let bsdf_id = state.id.clone();
-                        let bsdf = build_bsdf(state, base_dir, &mut raw_data)?;
+                        let location = state.location.clone();
+                        let bsdf = build_bsdf(state, &base_dir, &mut raw_data).map_err(|e| locate_at(location.as_ref(), e))?;
 
                         if let Some(parent) = bsdf_stack.last_mut() {
                             parent.children.push(bsdf);
@@ -1171,7 +1490,8 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                         in_medium_volume = false;
                     } else {
                         if let Some(state) = current_volume.take() {
-                            let (id, volume) = build_volume(state, base_dir, &mut raw_data)?;
+                            let location = state.location.clone();
+                            let (id, volume) = build_volume(state, &base_dir, &mut raw_data).map_err(|e| locate_at(location.as_ref(), e))?;
                             scene.add_volume(id, volume);
                         }
                         in_volume = false;
@@ -1195,9 +1515,26 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
         }};
     }
 
-    loop {
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Eof) => break,
+    while let Some(source) = sources.last_mut() {
+        match source.read_event(&mut buf) {
+            Ok(Event::Eof) => {
+                sources.pop();
+                if let Some(source) = sources.last() {
+                    base_dir = source.base_dir();
+                }
+            }
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.name().as_ref() == b"include" => {
+                let mut filename: Option<String> = None;
+                for attr in e.attributes().flatten() {
+                    if attr.key.as_ref() == b"filename" {
+                        filename = Some(resolve_value(&attr.unescape_value().unwrap_or_default(), &defaults));
+                    }
+                }
+                let filename = filename.ok_or(SceneLoadError::MissingField("include.filename"))?;
+                let include = open_include(sources, &filename)?;
+                base_dir = include.base_dir();
+                sources.push(include);
+            }
             Ok(Event::Start(e)) => {
                 handle_start!(e, false)?;
             }
@@ -1212,6 +1549,16 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                     b"sensor" => {
                         if in_sensor {
                             let fov_deg = fov_deg.ok_or(SceneLoadError::MissingField("sensor.fov"))?;
+                            let shutter_open = shutter_open.unwrap_or(0.0);
+                            let shutter_close = shutter_close.unwrap_or(shutter_open);
+                            let motion = if current_sensor_motion.is_empty() {
+                                None
+                            } else {
+                                let motion = AnimatedTransform::new(std::mem::take(&mut current_sensor_motion));
+                                current_sensor_transform = motion.matrix_at(shutter_open);
+                                origin = None;
+                                Some(motion)
+                            };
                             let (origin, target, up) = if origin.is_some() && target.is_some() && up.is_some() {
                                 (origin.unwrap(), target.unwrap(), up.unwrap())
                             } else {
@@ -1251,7 +1598,12 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                                 height,
                                 near_clip.unwrap_or(1e-2),
                                 far_clip.unwrap_or(1e4),
-                            );
+                            )
+                            .with_shutter(shutter_open, shutter_close);
+                            let camera = match motion {
+                                Some(motion) if motion.is_animated() => camera.with_motion(motion),
+                                _ => camera,
+                            };
                             scene.add_sensor(Box::new(camera));
                         }
 
@@ -1265,15 +1617,35 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                         up = None;
                         near_clip = None;
                         far_clip = None;
+                        shutter_open = None;
+                        shutter_close = None;
                         fov_axis = None;
                         width = None;
                         height = None;
                         current_sensor_transform = Matrix4f::identity();
+                        current_sensor_motion.clear();
+                        in_animation = false;
                     }
                     b"film" => {
                         in_film = false;
                     }
+                    b"animation" => {
+                        if in_animation {
+                            if in_sensor {
+                                current_sensor_motion = std::mem::take(&mut current_keyframes);
+                            } else if in_shape {
+                                current_shape_motion = std::mem::take(&mut current_keyframes);
+                            }
+                        }
+                        in_animation = false;
+                    }
                     b"transform" => {
+                        if in_animation {
+                            if let Some(time) = current_keyframe_time.take() {
+                                let m = if in_sensor { current_sensor_transform } else { current_shape_transform };
+                                current_keyframes.push((time, m));
+                            }
+                        }
                         in_transform = false;
                         in_sensor_transform = false;
                         in_shape_transform = false;
@@ -1289,7 +1661,8 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                     b"bsdf" => {
                         if let Some(state) = bsdf_stack.pop() {
                             let bsdf_id = state.id.clone();
-                            let bsdf = build_bsdf(state, base_dir, &mut raw_data)?;
+                            let location = state.location.clone();
+                            let bsdf = build_bsdf(state, &base_dir, &mut raw_data).map_err(|e| locate_at(location.as_ref(), e))?;
 
                             if let Some(parent) = bsdf_stack.last_mut() {
                                 parent.children.push(bsdf);
@@ -1312,7 +1685,8 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                             in_medium_volume = false;
                         } else {
                             if let Some(state) = current_volume.take() {
-                            let (id, volume) = build_volume(state, base_dir, &mut raw_data)?;
+                            let location = state.location.clone();
+                            let (id, volume) = build_volume(state, &base_dir, &mut raw_data).map_err(|e| locate_at(location.as_ref(), e))?;
                             scene.add_volume(id, volume);
                         }
                             in_volume = false;
@@ -1367,74 +1741,142 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                     }
                     b"shape" => {
                         if in_shape {
-                            let material = if let Some(bsdf) = current_shape_bsdf_inline.take() {
-                                bsdf
-                            } else if let Some(bsdf_id) = current_shape_bsdf_ref.take() {
-                                bsdfs.get(&bsdf_id)
-                                    .ok_or_else(|| SceneLoadError::Parse(format!("missing bsdf ref: {}", bsdf_id)))?
-                                    .clone()
+                            // Animated shapes are built in object space and moved per ray.
+                            let motion = if current_shape_motion.is_empty() {
+                                None
                             } else {
-                                return Err(SceneLoadError::MissingField("shape.bsdf_ref"));
+                                let motion = AnimatedTransform::new(std::mem::take(&mut current_shape_motion));
+                                current_shape_transform = motion.matrix_at(motion.time_range().0);
+                                if motion.is_animated() {
+                                    current_shape_transform = Matrix4f::identity();
+                                    Some(motion)
+                                } else {
+                                    None
+                                }
                             };
 
-                            let shape: Arc<dyn crate::core::shape::Shape> = match current_shape_type.as_deref() {
-                                Some("obj") | Some("ply") => {
-                                    let filename = current_shape_filename.take().ok_or(SceneLoadError::MissingField("shape.filename"))?;
-                                    let filename = if Path::new(&filename).is_absolute() {
-                                        filename
-                                    } else {
-                                        base_dir.join(filename).to_string_lossy().to_string()
-                                    };
-
-                                    let mesh = match current_shape_type.as_deref() {
-                                        Some("obj") => TriangleMesh::from_obj(&filename)
-                                            .map_err(|e| SceneLoadError::Parse(format!("obj load failed: {}", e)))?,
-                                        Some("ply") => TriangleMesh::from_ply(&filename)
-                                            .map_err(|e| SceneLoadError::Parse(format!("ply load failed: {}", e)))?,
-                                        _ => unreachable!(),
-                                    };
-
-                                    let mut mesh = mesh;
-                                    mesh.set_face_normals(current_shape_face_normals);
-                                    let transform = Transform::new(current_shape_transform);
-                                    mesh.apply_transform_matrix(&transform);
-                                    Arc::new(mesh)
-                                }
-                                Some("rectangle") => {
-                                    let transform = Transform::new(current_shape_transform);
-                                    Arc::new(Rectangle::new(transform, current_shape_id.clone()))
-                                }
-                                Some("cube") => {
-                                    let transform = Transform::new(current_shape_transform);
-                                    Arc::new(Cube::new(transform, current_shape_id.clone()))
-                                }
-                                Some(other) => {
-                                    return Err(SceneLoadError::Parse(format!("unsupported shape type: {}", other)));
-                                }
-                                None => {
-                                    return Err(SceneLoadError::Parse("missing shape type".to_string()));
+                            if current_shape_type.as_deref() == Some("gltf") {
+                                // Imported assets bring their own materials; a bsdf on the
+                                // shape overrides all of them.
+                                let material = if let Some(bsdf) = current_shape_bsdf_inline.take() {
+                                    Some(bsdf)
+                                } else if let Some(bsdf_id) = current_shape_bsdf_ref.take() {
+                                    Some(bsdfs.get(&bsdf_id)
+                                        .ok_or_else(|| SceneLoadError::Parse(format!("missing bsdf ref: {}", bsdf_id)))?
+                                        .clone())
+                                } else {
+                                    None
+                                };
+                                let filename = current_shape_filename.take().ok_or(SceneLoadError::MissingField("shape.filename"))?;
+                                let filename = base_dir.join(filename);
+                                let import = import_gltf(&filename, &current_shape_transform)
+                                    .map_err(|e| SceneLoadError::Parse(format!("gltf load failed: {}", e)))?;
+                                for mut object in import.objects {
+                                    if let Some(material) = &material {
+                                        object.material = material.clone();
+                                    }
+                                    if let Some(motion) = &motion {
+                                        if !object.emission.is_black() {
+                                            return Err(SceneLoadError::Parse("animated emitters are not supported".to_string()));
+                                        }
+                                        object.shape = Arc::new(AnimatedShape::new(object.shape.clone(), motion.clone()));
+                                    }
+                                    if let (Some(prefix), Some(name)) = (current_shape_id.as_ref(), object.name.as_ref()) {
+                                        object.name = Some(format!("{}.{}", prefix, name));
+                                    }
+                                    scene.add_object(object);
                                 }
-                            };
+                                for emitter in import.emitters {
+                                    scene.add_emitter(emitter);
+                                }
+                            } else {
+                                let material = if let Some(bsdf) = current_shape_bsdf_inline.take() {
+                                    bsdf
+                                } else if let Some(bsdf_id) = current_shape_bsdf_ref.take() {
+                                    bsdfs.get(&bsdf_id)
+                                        .ok_or_else(|| SceneLoadError::Parse(format!("missing bsdf ref: {}", bsdf_id)))?
+                                        .clone()
+                                } else {
+                                    return Err(SceneLoadError::MissingField("shape.bsdf_ref"));
+                                };
 
-                            let mut object = SceneObject::new(shape.clone(), material);
-                            if let Some(id) = current_shape_id.take() {
-                                object = object.with_name(id);
-                            }
-                            if let Some(state) = current_shape_medium.take() {
-                                let is_interior = state.name.as_deref().unwrap_or("interior") == "interior";
-                                if is_interior {
-                                    let (id, medium) = build_medium(state, &scene)?;
-                                    if let Some(id) = id {
-                                        scene.add_medium(id, medium.clone());
+                                let shape: Arc<dyn crate::core::shape::Shape> = match current_shape_type.as_deref() {
+                                    Some("obj") | Some("ply") | Some("serialized") => {
+                                        let filename = current_shape_filename.take().ok_or(SceneLoadError::MissingField("shape.filename"))?;
+                                        let filename = if Path::new(&filename).is_absolute() {
+                                            filename
+                                        } else {
+                                            base_dir.join(filename).to_string_lossy().to_string()
+                                        };
+
+                                        let mesh = match current_shape_type.as_deref() {
+                                            Some("obj") => TriangleMesh::from_obj(&filename)
+                                                .map_err(|e| SceneLoadError::Parse(format!("obj load failed: {}", e)))?,
+                                            Some("ply") => TriangleMesh::from_ply(&filename)
+                                                .map_err(|e| SceneLoadError::Parse(format!("ply load failed: {}", e)))?,
+                                            Some("serialized") => TriangleMesh::from_serialized(&filename, current_shape_index)
+                                            .map_err(|e| SceneLoadError::Parse(format!("serialized load failed: {}", e)))?,
+                                            _ => unreachable!(),
+                                        };
+
+                                        // `face_normals` only switches them on; serialized files may
+                                        // already request them.
+                                        let mut mesh = mesh;
+                                        if current_shape_face_normals {
+                                            mesh.set_face_normals(true);
+                                        }
+                                        if !mesh.colors().is_empty() && !material.uses_vertex_colors() {
+                                            log::warn!("{} has vertex colors, which only a diffuse bsdf reads; bsdf {} ignores them.",
+                                                       filename, material.id());
+                                        }
+                                        let transform = Transform::new(current_shape_transform);
+                                        mesh.apply_transform_matrix(&transform);
+                                        Arc::new(mesh)
+                                    }
+                                    Some("rectangle") => {
+                                        let transform = Transform::new(current_shape_transform);
+                                        Arc::new(Rectangle::new(transform, current_shape_id.clone()))
+                                    }
+                                    Some("cube") => {
+                                        let transform = Transform::new(current_shape_transform);
+                                        Arc::new(Cube::new(transform, current_shape_id.clone()))
+                                    }
+                                    Some(other) => {
+                                        return Err(SceneLoadError::Parse(format!("unsupported shape type: {}", other)));
                                     }
-                                    object = object.with_interior_medium(Some(medium));
+                                    None => {
+                                        return Err(SceneLoadError::Parse("missing shape type".to_string()));
+                                    }
+                                };
+                                if motion.is_some() && current_shape_emissive {
+                                    return Err(SceneLoadError::Parse("animated emitters are not supported".to_string()));
                                 }
+                                let shape: Arc<dyn crate::core::shape::Shape> = match motion {
+                                    Some(motion) => Arc::new(AnimatedShape::new(shape, motion)),
+                                    None => shape,
+                                };
+
+                                let mut object = SceneObject::new(shape.clone(), material);
+                                if let Some(id) = current_shape_id.take() {
+                                    object = object.with_name(id);
+                                }
+                                if let Some(state) = current_shape_medium.take() {
+                                    let is_interior = state.name.as_deref().unwrap_or("interior") == "interior";
+                                    if is_interior {
+                                        let location = state.location.clone();
+                                        let (id, medium) = build_medium(state, &scene).map_err(|e| locate_at(location.as_ref(), e))?;
+                                        if let Some(id) = id {
+                                            scene.add_medium(id, medium.clone());
+                                        }
+                                        object = object.with_interior_medium(Some(medium));
+                                    }
+                                }
+                                if current_shape_emissive {
+                                    let radiance = current_emitter_radiance.unwrap_or(RGBSpectrum::new(1.0, 1.0, 1.0));
+                                    object.emission = radiance;
+                                }
+                                scene.add_object(object);
                             }
-                            if current_shape_emissive {
-                                let radiance = current_emitter_radiance.unwrap_or(RGBSpectrum::new(1.0, 1.0, 1.0));
-                                object.emission = radiance;
-                            }
-                            scene.add_object(object);
                         }
                         in_shape = false;
                         current_shape_type = None;
@@ -1445,7 +1887,10 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
                         current_emitter_radiance = None;
                         current_shape_id = None;
                         current_shape_transform = Matrix4f::identity();
+                        current_shape_motion.clear();
+                        in_animation = false;
                         current_shape_face_normals = false;
+                        current_shape_index = 0;
                         current_shape_medium = None;
                         in_shape_medium = false;
                     }
@@ -1466,23 +1911,20 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
 
     if !pending_media.is_empty() {
         for state in pending_media {
-            let (id, medium) = build_medium(state, &scene)?;
+            let location = state.location.clone();
+            let (id, medium) = build_medium(state, &scene).map_err(|e| locate_at(location.as_ref(), e))?;
             if let Some(id) = id {
                 scene.add_medium(id, medium);
             }
         }
     }
 
-    let integrator = if let Some(depth) = max_depth {
-        let integrator_name = integrator_type.as_deref().unwrap_or("path");
+    let integrator_name = integrator_type.as_deref().unwrap_or("path");
+    let integrator = if let (Some(depth), false) = (max_depth, matches!(integrator_name, "ptracer" | "sppm" | "pssmlt")) {
         let spp = spp.unwrap_or(1);
-        let integrator: Box<dyn Integrator> = match integrator_name {
-            "path" => Box::new(PathIntegrator::new(depth, spp)),
-            "raymarching" => Box::new(RaymarchingIntegrator::new(depth, spp, raymarch_step_size)),
-            other => {
-                return Err(SceneLoadError::Parse(format!("unsupported integrator: {}", other)));
-            }
-        };
+        let integrator = create_integrator(integrator_name, depth, spp, raymarch_step_size, firefly)
+            .ok_or_else(|| SceneLoadError::Parse(format!("unsupported integrator: {}", integrator_name)))
+            .map_err(|e| locate_at(integrator_location.as_ref(), e))?;
         Some(integrator)
     } else {
         None
@@ -1492,8 +1934,13 @@ fn parse_scene(xml: &str, base_dir: &Path) -> Result<SceneLoadResult, SceneLoadE
         scene,
         integrator,
         integrator_type,
+        sampler_type,
         samples_per_pixel: spp,
         max_depth,
+        step_size: raymarch_step_size,
+        firefly,
+        sppm,
+        mlt,
     })
 }
 
@@ -1531,23 +1978,31 @@ fn build_bsdf(
                         };
                         image.set_filter_mode(mode);
                     }
-                    if let Some(wrap) = tex.wrap_mode {
-                        let wrap = wrap.trim().to_lowercase();
-                        let mode = match wrap.as_str() {
-                            "repeat" => WrapMode::Repeat,
-                            "mirror" => WrapMode::Mirror,
-                            "clamp" => WrapMode::Clamp,
-                            other => {
-                                return Err(SceneLoadError::Parse(format!("unsupported wrap_mode: {}", other)));
-                            }
-                        };
-                        image.set_wrap_mode(mode);
-                    }
+                    // `wrap_mode_u` and `wrap_mode_v` override `wrap_mode`
+                    // along one axis.
+                    let parse_wrap = |wrap: Option<String>| -> Result<WrapMode, SceneLoadError> {
+                        match wrap.map(|w| w.trim().to_lowercase()).as_deref() {
+                            None | Some("repeat") => Ok(WrapMode::Repeat),
+                            Some("mirror") => Ok(WrapMode::Mirror),
+                            Some("clamp") => Ok(WrapMode::Clamp),
+                            Some(other) => Err(SceneLoadError::Parse(format!("unsupported wrap_mode: {}", other))),
+                        }
+                    };
+                    let wrap_u = parse_wrap(tex.wrap_mode_u.or(tex.wrap_mode.clone()))?;
+                    let wrap_v = parse_wrap(tex.wrap_mode_v.or(tex.wrap_mode))?;
+                    image.set_wrap_modes(wrap_u, wrap_v);
                     image.set_uv_transform(tex.to_uv);
                     if let Some((key, view)) = raw_view {
                         raw_data.insert(key, view);
                     }
                     Arc::new(image) as Arc<dyn crate::core::texture::Texture>
+                } else if tex_type == "mesh_attribute" {
+                    let attribute = tex.tex_attribute.unwrap_or_else(|| "vertex_color".to_string());
+                    if attribute != "vertex_color" {
+                        return Err(SceneLoadError::Parse(format!("unsupported mesh attribute: {}", attribute)));
+                    }
+                    let fallback = state.reflectance.unwrap_or(RGBSpectrum::new(0.5, 0.5, 0.5));
+                    Arc::new(VertexColorTexture::new(fallback)) as Arc<dyn crate::core::texture::Texture>
                 } else {
                     let refl = state.reflectance.unwrap_or(RGBSpectrum::new(0.5, 0.5, 0.5));
                     Arc::new(ConstantTexture::new(refl)) as Arc<dyn crate::core::texture::Texture>
@@ -1844,6 +2299,19 @@ fn resolve_value(raw: &str, defaults: &HashMap<String, String>) -> String {
     out
 }
 
+// Camera-style look-at matrix with columns (left, up, forward, origin).
+pub(crate) fn lookat_matrix(origin: Vector3f, target: Vector3f, up: Vector3f) -> Matrix4f {
+    let forward = (target - origin).normalize();
+    let left = up.cross(&forward).normalize();
+    let up = forward.cross(&left);
+    Matrix4f::new(
+        left.x, up.x, forward.x, origin.x,
+        left.y, up.y, forward.y, origin.y,
+        left.z, up.z, forward.z, origin.z,
+        0.0, 0.0, 0.0, 1.0,
+    )
+}
+
 fn parse_float(value: &str) -> Result<Float, SceneLoadError> {
     value.parse::<Float>().map_err(|_| SceneLoadError::Parse(format!("invalid float: {}", value)))
 }
@@ -1964,7 +2432,7 @@ fn parse_vec3_spectrum(value: &str) -> Result<RGBSpectrum, SceneLoadError> {
     Ok(RGBSpectrum::new(v.x, v.y, v.z))
 }
 
-fn fov_y_from_axis(fov_deg: Float, axis: &str, width: usize, height: usize) -> Result<Float, SceneLoadError> {
+pub(crate) fn fov_y_from_axis(fov_deg: Float, axis: &str, width: usize, height: usize) -> Result<Float, SceneLoadError> {
     let fov_rad = fov_deg * std::f32::consts::PI / 180.0;
     let aspect = width as Float / height as Float;
     let tan_half = (0.5 * fov_rad).tan();
@@ -1993,3 +2461,189 @@ fn fov_y_from_axis(fov_deg: Float, axis: &str, width: usize, height: usize) -> R
     };
     Ok(2.0 * tan_half_y.atan())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{load_scene_with_defines, load_scene_with_settings, SceneLoadError};
+    use crate::integrators::pssmlt::MltSettings;
+    use crate::integrators::sppm::SppmSettings;
+    use std::collections::HashMap;
+    use std::fs;
+
+    const SENSOR: &str = r#"
+  <default name="width" value="8"/>
+  <sensor type="perspective">
+    <float name="fov" value="45"/>
+    <film type="hdrfilm">
+      <integer name="width" value="$width"/>
+      <integer name="height" value="4"/>
+    </film>
+  </sensor>"#;
+
+    fn write_files(files: &[(&str, String)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("dacquoise_loader_{}_{}", std::process::id(), files[0].0));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).expect("write scene file");
+        }
+        dir
+    }
+
+    #[test]
+    fn test_include_and_defines() {
+        let dir = write_files(&[
+            ("main.xml", format!("<scene>{}\n  <include filename=\"objects.xml\"/>\n</scene>", SENSOR)),
+            ("objects.xml", "<scene>\n  <bsdf type=\"diffuse\" id=\"white\"/>\n  <shape type=\"cube\"><ref id=\"white\"/></shape>\n</scene>".to_string()),
+        ]);
+        let main = dir.join("main.xml");
+
+        let result = load_scene_with_settings(&main).expect("load scene");
+        assert_eq!(result.scene.len(), 1);
+        assert_eq!(result.scene.camera(0).expect("camera").bitmap().width(), 8);
+
+        let mut defines = HashMap::new();
+        defines.insert("width".to_string(), "16".to_string());
+        let result = load_scene_with_defines(&main, &defines).expect("load scene with defines");
+        assert_eq!(result.scene.camera(0).expect("camera").bitmap().width(), 16);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_integrator_firefly_parameters() {
+        let integrator = |body: &str| format!("<scene>{}\n  <integrator type=\"path\">\n    <integer name=\"max_depth\" value=\"3\"/>\n{}  </integrator>\n</scene>", SENSOR, body);
+        let dir = write_files(&[
+            ("firefly.xml", integrator("    <float name=\"clamp_direct\" value=\"20\"/>\n    <float name=\"clamp_indirect\" value=\"2\"/>\n    <float name=\"regularization\" value=\"0.25\"/>\n")),
+            ("negative.xml", integrator("    <float name=\"clamp_indirect\" value=\"-1\"/>\n")),
+        ]);
+        let result = load_scene_with_settings(dir.join("firefly.xml")).expect("load scene");
+        assert_eq!(result.firefly.clamp_direct, Some(20.0));
+        assert_eq!(result.firefly.clamp_indirect, Some(2.0));
+        assert_eq!(result.firefly.regularization, Some(0.25));
+        assert!(result.integrator.is_some());
+
+        let err = load_scene_with_settings(dir.join("negative.xml")).err().expect("negative clamp must fail");
+        assert!(err.to_string().contains("clamp_indirect"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_particle_tracer_integrator() {
+        let dir = write_files(&[(
+            "ptracer.xml",
+            format!("<scene>{}
+  <integrator type=\"ptracer\">\n    <integer name=\"max_depth\" value=\"4\"/>\n  </integrator>\n</scene>", SENSOR),
+        )]);
+        let result = load_scene_with_settings(dir.join("ptracer.xml")).expect("load scene");
+        assert_eq!(result.integrator_type.as_deref(), Some("ptracer"));
+        assert_eq!(result.max_depth, Some(4));
+        assert!(result.integrator.is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sppm_integrator_parameters() {
+        let integrator = |body: &str| format!("<scene>{}\n  <integrator type=\"sppm\">\n    <integer name=\"max_depth\" value=\"5\"/>\n{}  </integrator>\n</scene>", SENSOR, body);
+        let dir = write_files(&[
+            ("sppm.xml", integrator("    <integer name=\"photon_count\" value=\"50000\"/>\n    <float name=\"initial_radius\" value=\"0.05\"/>\n    <float name=\"alpha\" value=\"0.5\"/>\n")),
+            ("alpha.xml", integrator("    <float name=\"alpha\" value=\"1.5\"/>\n")),
+            ("photons.xml", integrator("    <integer name=\"photon_count\" value=\"0\"/>\n")),
+        ]);
+        let result = load_scene_with_settings(dir.join("sppm.xml")).expect("load scene");
+        assert_eq!(result.integrator_type.as_deref(), Some("sppm"));
+        assert!(result.integrator.is_none());
+        assert_eq!(result.sppm, SppmSettings { photon_count: Some(50000), initial_radius: Some(0.05), alpha: Some(0.5) });
+
+        for name in ["alpha.xml", "photons.xml"] {
+            assert!(load_scene_with_settings(dir.join(name)).is_err(), "{} must fail", name);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_pssmlt_integrator_parameters() {
+        let integrator = |body: &str| format!("<scene>{}\n  <integrator type=\"pssmlt\">\n    <integer name=\"max_depth\" value=\"8\"/>\n{}  </integrator>\n</scene>", SENSOR, body);
+        let dir = write_files(&[
+            ("mlt.xml", integrator("    <integer name=\"bootstrap_samples\" value=\"5000\"/>\n    <integer name=\"chains\" value=\"32\"/>\n    <float name=\"large_step_probability\" value=\"0.2\"/>\n    <float name=\"sigma\" value=\"0.02\"/>\n")),
+            ("large_step.xml", integrator("    <float name=\"large_step_probability\" value=\"2\"/>\n")),
+            ("chains.xml", integrator("    <integer name=\"chains\" value=\"0\"/>\n")),
+        ]);
+        let result = load_scene_with_settings(dir.join("mlt.xml")).expect("load scene");
+        assert_eq!(result.integrator_type.as_deref(), Some("pssmlt"));
+        assert!(result.integrator.is_none());
+        let expected = MltSettings { bootstrap_samples: Some(5000), chains: Some(32), large_step_probability: Some(0.2), sigma: Some(0.02) };
+        assert_eq!(result.mlt, expected);
+
+        for name in ["large_step.xml", "chains.xml"] {
+            assert!(load_scene_with_settings(dir.join(name)).is_err(), "{} must fail", name);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_include_cycle_and_error_location() {
+        let dir = write_files(&[
+            ("a.xml", "<scene>\n  <include filename=\"b.xml\"/>\n</scene>".to_string()),
+            ("b.xml", "<scene>\n\n    <include filename=\"a.xml\"/>\n</scene>".to_string()),
+        ]);
+        let err = load_scene_with_settings(dir.join("a.xml")).err().expect("cycle must fail");
+        match &err {
+            SceneLoadError::At { file, position, error } => {
+                assert!(file.ends_with("b.xml"));
+                assert_eq!(*position, Some((3, 5)));
+                assert!(error.to_string().contains("include cycle"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(err.to_string().contains("b.xml:3:5"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_animated_emitter_is_rejected() {
+        let dir = write_files(&[(
+            "moving_light.xml",
+            format!("<scene>{}
+  <shape type=\"cube\">
+    <animation name=\"to_world\">
+      <transform time=\"0\"><translate x=\"-1\"/></transform>
+      <transform time=\"1\"><translate x=\"1\"/></transform>
+    </animation>
+    <bsdf type=\"diffuse\"/>
+    <emitter type=\"area\"><rgb name=\"radiance\" value=\"1, 1, 1\"/></emitter>
+  </shape>
+</scene>", SENSOR),
+        )]);
+        let err = load_scene_with_settings(dir.join("moving_light.xml")).err().expect("animated emitter must fail");
+        assert!(err.to_string().contains("animated emitters"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_included_filenames_and_late_errors_are_located() {
+        let dir = write_files(&[
+            ("nested.xml", format!("<scene>{}\n  <include filename=\"parts/mesh.xml\"/>\n</scene>", SENSOR)),
+            ("medium.xml", format!("<scene>{}\n\n  <medium type=\"homogeneous\" id=\"fog\"/>\n</scene>", SENSOR)),
+        ]);
+        let parts = dir.join("parts");
+        fs::create_dir_all(&parts).expect("create include dir");
+        fs::write(parts.join("mesh.xml"), "<scene>\n  <shape type=\"obj\">\n    <string name=\"filename\" value=\"tri.obj\"/>\n    <bsdf type=\"diffuse\"/>\n  </shape>\n</scene>")
+            .expect("write include");
+        fs::write(parts.join("tri.obj"), "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").expect("write obj");
+
+        // `tri.obj` sits next to the file that names it, not the main scene.
+        let result = load_scene_with_settings(dir.join("nested.xml")).expect("load scene");
+        assert_eq!(result.scene.len(), 1);
+
+        // Media are built after the whole file is read; the error still
+        // points at the element.
+        let err = load_scene_with_settings(dir.join("medium.xml")).err().expect("medium without sigma_t must fail");
+        match &err {
+            SceneLoadError::At { file, position, .. } => {
+                assert!(file.ends_with("medium.xml"));
+                assert_eq!(*position, Some((11, 3)));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
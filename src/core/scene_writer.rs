@@ -0,0 +1,634 @@
+// Copyright @yucwang 2026
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use quick_xml::escape::escape;
+
+use crate::core::bsdf::BSDF;
+use crate::core::medium::Medium;
+use crate::core::scene::Scene;
+use crate::core::scene_loader::SceneLoadResult;
+use crate::core::volume::Volume;
+use crate::integrators::path::FireflySettings;
+use crate::integrators::pssmlt::MltSettings;
+use crate::integrators::sppm::SppmSettings;
+use crate::math::constants::{Float, Matrix3f, Matrix4f, Vector3f};
+use crate::math::spectrum::{RGBSpectrum, Spectrum};
+
+#[derive(Debug)]
+pub enum SceneWriteError {
+    Io(std::io::Error),
+    Unsupported(String),
+}
+
+impl From<std::io::Error> for SceneWriteError {
+    fn from(err: std::io::Error) -> Self {
+        SceneWriteError::Io(err)
+    }
+}
+
+impl fmt::Display for SceneWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneWriteError::Io(err) => write!(f, "io error: {}", err),
+            SceneWriteError::Unsupported(what) => write!(f, "cannot write {} as xml", what),
+        }
+    }
+}
+
+impl std::error::Error for SceneWriteError {}
+
+/// How meshes end up in a written scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshExport {
+    /// Reference the file a mesh was loaded from, with its transform;
+    /// meshes built in memory are written as PLY.
+    Reference,
+    /// Write every mesh as a world-space PLY file.
+    Ply,
+}
+
+/// Render settings written next to the scene description.
+#[derive(Clone, Debug, Default)]
+pub struct SceneSettings {
+    pub integrator_type: Option<String>,
+    pub sampler_type: Option<String>,
+    pub samples_per_pixel: Option<u32>,
+    pub max_depth: Option<u32>,
+    pub step_size: Option<Float>,
+    pub firefly: FireflySettings,
+    pub sppm: SppmSettings,
+    pub mlt: MltSettings,
+}
+
+impl From<&SceneLoadResult> for SceneSettings {
+    fn from(result: &SceneLoadResult) -> Self {
+        Self {
+            integrator_type: result.integrator_type.clone(),
+            sampler_type: result.sampler_type.clone(),
+            samples_per_pixel: result.samples_per_pixel,
+            max_depth: result.max_depth,
+            step_size: result.step_size,
+            firefly: result.firefly,
+            sppm: result.sppm,
+            mlt: result.mlt,
+        }
+    }
+}
+
+/// One element of a Mitsuba-style scene file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlElement {
+    pub tag: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    pub fn new(tag: &str) -> Self {
+        Self { tag: tag.to_string(), attributes: Vec::new(), children: Vec::new() }
+    }
+
+    /// `<tag type="..."/>`, the usual head of a plugin element.
+    pub fn typed(tag: &str, type_name: &str) -> Self {
+        Self::new(tag).with_attr("type", type_name)
+    }
+
+    pub fn with_attr<V: ToString>(mut self, key: &str, value: V) -> Self {
+        self.set_attr(key, value);
+        self
+    }
+
+    pub fn with_child(mut self, child: XmlElement) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn with_children<I: IntoIterator<Item = XmlElement>>(mut self, children: I) -> Self {
+        self.children.extend(children);
+        self
+    }
+
+    pub fn push(&mut self, child: XmlElement) {
+        self.children.push(child);
+    }
+
+    /// Sets `key`, replacing an existing value.
+    pub fn set_attr<V: ToString>(&mut self, key: &str, value: V) {
+        let value = value.to_string();
+        match self.attributes.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.attributes.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Removes and returns the first child `<tag name="...">`.
+    pub fn take_child(&mut self, tag: &str, name: &str) -> Option<XmlElement> {
+        let index = self.children.iter().position(|c| c.tag == tag && c.attr("name") == Some(name))?;
+        Some(self.children.remove(index))
+    }
+
+    pub fn float(name: &str, value: Float) -> Self {
+        Self::new("float").with_attr("name", name).with_attr("value", value)
+    }
+
+    pub fn integer<V: ToString>(name: &str, value: V) -> Self {
+        Self::new("integer").with_attr("name", name).with_attr("value", value)
+    }
+
+    pub fn boolean(name: &str, value: bool) -> Self {
+        Self::new("boolean").with_attr("name", name).with_attr("value", value)
+    }
+
+    pub fn string(name: &str, value: &str) -> Self {
+        Self::new("string").with_attr("name", name).with_attr("value", value)
+    }
+
+    pub fn rgb(name: &str, value: RGBSpectrum) -> Self {
+        Self::new("rgb").with_attr("name", name).with_attr("value", format!("{}, {}, {}", value[0], value[1], value[2]))
+    }
+
+    pub fn vector(name: &str, value: Vector3f) -> Self {
+        Self::new("vector").with_attr("name", name).with_attr("value", format!("{}, {}, {}", value.x, value.y, value.z))
+    }
+
+    pub fn reference(name: Option<&str>, id: &str) -> Self {
+        let mut element = Self::new("ref");
+        if let Some(name) = name {
+            element.set_attr("name", name);
+        }
+        element.with_attr("id", id)
+    }
+
+    /// `<matrix value="..."/>` with the 16 entries in row-major order.
+    pub fn matrix(matrix: &Matrix4f) -> Self {
+        let values: Vec<String> = (0..16).map(|i| matrix[(i / 4, i % 4)].to_string()).collect();
+        Self::new("matrix").with_attr("value", values.join(" "))
+    }
+
+    pub fn matrix3(matrix: &Matrix3f) -> Self {
+        let values: Vec<String> = (0..9).map(|i| matrix[(i / 3, i % 3)].to_string()).collect();
+        Self::new("matrix").with_attr("value", values.join(" "))
+    }
+
+    /// `<transform name="..."><matrix .../></transform>`.
+    pub fn transform(name: &str, matrix: &Matrix4f) -> Self {
+        Self::new("transform").with_attr("name", name).with_child(Self::matrix(matrix))
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+        out.push_str(&indent);
+        out.push('<');
+        out.push_str(&self.tag);
+        for (key, value) in &self.attributes {
+            out.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+        }
+        if self.children.is_empty() {
+            out.push_str("/>\n");
+            return;
+        }
+        out.push_str(">\n");
+        for child in &self.children {
+            child.write(out, depth + 1);
+        }
+        out.push_str(&indent);
+        out.push_str(&format!("</{}>\n", self.tag));
+    }
+}
+
+impl fmt::Display for XmlElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        f.write_str(&out)
+    }
+}
+
+/// State shared by the `to_xml` methods while one scene is written: where
+/// the file goes, where generated assets go, and the ids given to shared
+/// volumes.
+pub struct WriteContext {
+    dir: PathBuf,
+    asset_dir: PathBuf,
+    mesh_export: MeshExport,
+    asset_names: HashSet<String>,
+    // Volumes written at the top of the scene, with their element when it
+    // was already built.
+    volumes: Vec<(Arc<dyn Volume>, String, Option<XmlElement>)>,
+}
+
+impl WriteContext {
+    /// Context for a scene written to `xml_path`. Generated assets go to a
+    /// `<stem>_assets` directory next to it.
+    pub fn new<P: AsRef<Path>>(xml_path: P, mesh_export: MeshExport) -> Self {
+        let xml_path = absolute(xml_path.as_ref());
+        let dir = xml_path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let stem = xml_path.file_stem().and_then(|s| s.to_str()).unwrap_or("scene");
+        let asset_dir = dir.join(format!("{}_assets", stem));
+        Self {
+            dir,
+            asset_dir,
+            mesh_export,
+            asset_names: HashSet::new(),
+            volumes: Vec::new(),
+        }
+    }
+
+    pub fn mesh_export(&self) -> MeshExport {
+        self.mesh_export
+    }
+
+    /// `path` as written in the scene: relative to the scene file when it
+    /// lies below it, absolute otherwise.
+    pub fn path_value<P: AsRef<Path>>(&self, path: P) -> String {
+        let path = absolute(path.as_ref());
+        match path.strip_prefix(&self.dir) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Reserves a new asset file named after `name`; returns its path.
+    pub fn asset_path(&mut self, name: &str, extension: &str) -> Result<PathBuf, SceneWriteError> {
+        fs::create_dir_all(&self.asset_dir)?;
+        let base: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+            .collect();
+        let mut file_name = format!("{}.{}", base, extension);
+        let mut suffix = 1;
+        while !self.asset_names.insert(file_name.clone()) {
+            file_name = format!("{}_{}.{}", base, suffix, extension);
+            suffix += 1;
+        }
+        Ok(self.asset_dir.join(file_name))
+    }
+
+    /// Id of a volume referenced by a medium. Volumes not seen before get
+    /// an id and are written among the scene's volumes.
+    pub fn volume_id(&mut self, volume: &Arc<dyn Volume>) -> String {
+        if let Some((_, id, _)) = self.volumes.iter().find(|(v, _, _)| same_object(v, volume)) {
+            return id.clone();
+        }
+        let id = format!("volume_{}", self.volumes.len());
+        self.volumes.push((volume.clone(), id.clone(), None));
+        id
+    }
+
+    /// `volume` as the `name` property of a medium: constant volumes are
+    /// written inline, others by reference.
+    pub fn volume_property(&mut self, name: &str, volume: &Arc<dyn Volume>) -> Result<XmlElement, SceneWriteError> {
+        if !self.volumes.iter().any(|(v, _, _)| same_object(v, volume)) {
+            let mut element = volume.to_xml(self)?;
+            if element.attr("type") == Some("const") {
+                element.set_attr("name", name);
+                return Ok(element);
+            }
+            let id = format!("volume_{}", self.volumes.len());
+            self.volumes.push((volume.clone(), id, Some(element)));
+        }
+        Ok(XmlElement::reference(Some(name), &self.volume_id(volume)))
+    }
+}
+
+/// Writes `scene` as XML that `load_scene` reads back. Meshes, images and
+/// grids without a source file are written next to it.
+pub fn write_scene<P: AsRef<Path>>(path: P,
+                                   scene: &Scene,
+                                   settings: &SceneSettings,
+                                   mesh_export: MeshExport) -> Result<(), SceneWriteError> {
+    let mut ctx = WriteContext::new(path.as_ref(), mesh_export);
+    let xml = scene_to_xml(scene, settings, &mut ctx)?;
+    fs::write(path, xml)?;
+    Ok(())
+}
+
+/// Builds the XML document for `scene`. Objects become shapes referencing
+/// shared bsdfs; area emitters are written on their shapes.
+pub fn scene_to_xml(scene: &Scene, settings: &SceneSettings, ctx: &mut WriteContext) -> Result<String, SceneWriteError> {
+    let mut root = XmlElement::new("scene").with_attr("version", "3.0.0");
+
+    if settings.integrator_type.is_some() || settings.max_depth.is_some() {
+        let mut integrator = XmlElement::typed("integrator", settings.integrator_type.as_deref().unwrap_or("path"));
+        if let Some(max_depth) = settings.max_depth {
+            integrator.push(XmlElement::integer("max_depth", max_depth));
+        }
+        if let Some(step_size) = settings.step_size {
+            integrator.push(XmlElement::float("step_size", step_size));
+        }
+        for (name, value) in [
+            ("clamp_direct", settings.firefly.clamp_direct),
+            ("clamp_indirect", settings.firefly.clamp_indirect),
+            ("regularization", settings.firefly.regularization),
+            ("initial_radius", settings.sppm.initial_radius),
+            ("alpha", settings.sppm.alpha),
+            ("large_step_probability", settings.mlt.large_step_probability),
+            ("sigma", settings.mlt.sigma),
+        ] {
+            if let Some(value) = value {
+                integrator.push(XmlElement::float(name, value));
+            }
+        }
+        for (name, value) in [
+            ("photon_count", settings.sppm.photon_count),
+            ("bootstrap_samples", settings.mlt.bootstrap_samples),
+            ("chains", settings.mlt.chains),
+        ] {
+            if let Some(value) = value {
+                integrator.push(XmlElement::integer(name, value));
+            }
+        }
+        root.push(integrator);
+    }
+
+    for sensor in scene.sensors() {
+        let mut element = sensor.to_xml()?;
+        if settings.sampler_type.is_some() || settings.samples_per_pixel.is_some() {
+            let mut sampler = XmlElement::typed("sampler", settings.sampler_type.as_deref().unwrap_or("independent"));
+            if let Some(spp) = settings.samples_per_pixel {
+                sampler.push(XmlElement::integer("sample_count", spp));
+            }
+            element.push(sampler);
+        }
+        root.push(element);
+    }
+
+    let mut volume_ids: Vec<_> = scene.volumes().iter().collect();
+    volume_ids.sort_by(|a, b| a.0.cmp(b.0));
+    for (id, volume) in volume_ids {
+        ctx.volumes.push((volume.clone(), id.clone(), None));
+    }
+
+    // Media inside a shape are written there; the rest stand alone.
+    let mut media: Vec<_> = scene.media().iter().collect();
+    media.sort_by(|a, b| a.0.cmp(b.0));
+    let medium_id = |medium: &Arc<dyn Medium>| {
+        media.iter().find(|(_, m)| same_object(m, medium)).map(|(id, _)| id.to_string())
+    };
+    let mut body = Vec::new();
+    for (id, medium) in &media {
+        let interior = scene.objects().iter().any(|o| {
+            o.interior_medium.as_ref().is_some_and(|m| same_object(m, medium))
+        });
+        if !interior {
+            body.push(medium.to_xml(ctx)?.with_attr("id", id));
+        }
+    }
+
+    let mut bsdfs: Vec<(Arc<dyn BSDF>, String)> = Vec::new();
+    let mut shapes = Vec::new();
+    for object in scene.objects() {
+        let bsdf_id = match bsdfs.iter().find(|(b, _)| same_object(b, &object.material)) {
+            Some((_, id)) => id.clone(),
+            None => {
+                let mut id = object.material.id().to_string();
+                while bsdfs.iter().any(|(_, other)| *other == id) {
+                    id = format!("{}_{}", id, bsdfs.len());
+                }
+                body.push(object.material.to_xml(ctx)?.with_attr("id", &id));
+                bsdfs.push((object.material.clone(), id.clone()));
+                id
+            }
+        };
+
+        let mut shape = object.shape.to_xml(ctx)?;
+        if let Some(name) = &object.name {
+            shape.set_attr("id", name);
+        }
+        shape.push(XmlElement::reference(None, &bsdf_id));
+        if !object.emission.is_black() {
+            shape.push(XmlElement::typed("emitter", "area").with_child(XmlElement::rgb("radiance", object.emission)));
+        }
+        if let Some(medium) = &object.interior_medium {
+            let mut element = medium.to_xml(ctx)?.with_attr("name", "interior");
+            if let Some(id) = medium_id(medium) {
+                element.set_attr("id", id);
+            }
+            shape.push(element);
+        }
+        shapes.push(shape);
+    }
+    body.extend(shapes);
+
+    for emitter in scene.emitters() {
+        if let Some(element) = emitter.to_xml(ctx)? {
+            body.push(element);
+        }
+    }
+
+    // Volumes go first so media can reference them.
+    for (volume, id, element) in std::mem::take(&mut ctx.volumes) {
+        let element = match element {
+            Some(element) => element,
+            None => volume.to_xml(ctx)?,
+        };
+        root.push(element.with_attr("id", id));
+    }
+    root.children.extend(body);
+
+    Ok(root.to_string())
+}
+
+/// Same allocation, ignoring trait-object metadata.
+fn same_object<T: ?Sized, U: ?Sized>(a: &Arc<T>, b: &Arc<U>) -> bool {
+    Arc::as_ptr(a) as *const u8 == Arc::as_ptr(b) as *const u8
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_scene, MeshExport, SceneSettings};
+    use crate::core::scene_loader::load_scene_with_settings;
+    use crate::integrators::pssmlt::MltSettings;
+    use crate::integrators::sppm::SppmSettings;
+    use crate::math::constants::Vector3f;
+    use crate::shapes::triangle_mesh::TriangleMesh;
+    use std::fs;
+
+    const SCENE: &str = r#"<scene version="3.0.0">
+  <integrator type="path">
+    <integer name="max_depth" value="4"/>
+    <float name="clamp_indirect" value="8"/>
+    <float name="regularization" value="0.1"/>
+  </integrator>
+  <sensor type="perspective">
+    <float name="fov" value="40"/>
+    <transform name="to_world">
+      <lookat origin="0, 1, -5" target="0, 0, 0" up="0, 1, 0"/>
+    </transform>
+    <sampler type="stratified">
+      <integer name="sample_count" value="4"/>
+    </sampler>
+    <film type="hdrfilm">
+      <integer name="width" value="16"/>
+      <integer name="height" value="8"/>
+    </film>
+  </sensor>
+  <volume type="const" id="density">
+    <float name="value" value="0.5"/>
+  </volume>
+  <medium type="heterogeneous" id="fog">
+    <ref name="sigma_t" id="density"/>
+    <rgb name="albedo" value="0.8, 0.8, 0.8"/>
+  </medium>
+  <bsdf type="diffuse" id="white">
+    <rgb name="reflectance" value="0.7, 0.7, 0.7"/>
+  </bsdf>
+  <bsdf type="blendbsdf" id="mix">
+    <float name="weight" value="0.25"/>
+    <bsdf type="roughconductor">
+      <string name="distribution" value="ggx"/>
+      <float name="alpha" value="0.2"/>
+    </bsdf>
+    <bsdf type="null"/>
+  </bsdf>
+  <shape type="rectangle" id="light">
+    <transform name="to_world">
+      <translate x="0" y="2" z="0"/>
+    </transform>
+    <ref id="white"/>
+    <emitter type="area">
+      <rgb name="radiance" value="4, 4, 4"/>
+    </emitter>
+  </shape>
+  <shape type="cube" id="box">
+    <transform name="to_world">
+      <scale value="0.5"/>
+    </transform>
+    <ref id="mix"/>
+    <medium type="homogeneous" name="interior">
+      <rgb name="sigma_t" value="1, 2, 3"/>
+      <rgb name="albedo" value="0.5, 0.5, 0.5"/>
+    </medium>
+  </shape>
+  <shape type="ply" id="tri">
+    <string name="filename" value="tri.ply"/>
+    <transform name="to_world">
+      <translate x="1" y="0" z="0"/>
+    </transform>
+    <ref id="white"/>
+  </shape>
+  <emitter type="directional">
+    <vector name="direction" value="0, -1, 0"/>
+    <rgb name="irradiance" value="1, 1, 1"/>
+  </emitter>
+</scene>"#;
+
+    fn setup(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("dacquoise_writer_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let mesh = TriangleMesh::from_indexed(
+            vec![Vector3f::new(0.0, 0.0, 0.0), Vector3f::new(1.0, 0.0, 0.0), Vector3f::new(0.0, 1.0, 0.0)],
+            vec![[0, 1, 2]],
+            None,
+        );
+        mesh.write_ply(&dir.join("tri.ply").to_string_lossy()).expect("write ply");
+        fs::write(dir.join("scene.xml"), SCENE).expect("write scene");
+        dir
+    }
+
+    #[test]
+    fn test_written_scene_loads_back() {
+        let dir = setup("reload");
+        let first = load_scene_with_settings(dir.join("scene.xml")).expect("load scene");
+        let settings = SceneSettings::from(&first);
+        write_scene(dir.join("out.xml"), &first.scene, &settings, MeshExport::Reference).expect("write scene");
+
+        let second = load_scene_with_settings(dir.join("out.xml")).expect("load written scene");
+        assert_eq!(second.scene.len(), first.scene.len());
+        assert_eq!(second.scene.emitters().len(), first.scene.emitters().len());
+        assert_eq!(second.scene.media().len(), 1);
+        assert_eq!(second.scene.volumes().len(), 1);
+        assert_eq!(second.sampler_type.as_deref(), Some("stratified"));
+        assert_eq!(second.samples_per_pixel, Some(4));
+        assert_eq!(second.max_depth, Some(4));
+        assert_eq!(second.firefly, first.firefly);
+        assert_eq!((second.firefly.clamp_direct, second.firefly.clamp_indirect), (None, Some(8.0)));
+        assert_eq!(second.firefly.regularization, Some(0.1));
+        let camera = second.scene.camera(0).expect("camera");
+        assert_eq!((camera.bitmap().width(), camera.bitmap().height()), (16, 8));
+        let light = second.scene.objects().iter().find(|o| o.name.as_deref() == Some("light")).expect("light");
+        assert_eq!(light.emission[0], 4.0);
+        let tri = second.scene.objects().iter().find(|o| o.name.as_deref() == Some("tri")).expect("tri");
+        let bbox = tri.shape.bounding_box();
+        assert!((bbox.p_min.x - 1.0).abs() < 1e-6 && (bbox.p_max.x - 2.0).abs() < 1e-6);
+
+        // Writing the written scene again gives the same file, up to the
+        // camera basis, which is re-normalized on load.
+        let after_sensor = |xml: String| xml[xml.find("</sensor>").expect("sensor")..].to_string();
+        let xml = fs::read_to_string(dir.join("out.xml")).expect("read written scene");
+        let settings = SceneSettings::from(&second);
+        write_scene(dir.join("again.xml"), &second.scene, &settings, MeshExport::Reference).expect("write again");
+        let again = fs::read_to_string(dir.join("again.xml")).expect("read again");
+        assert_eq!(after_sensor(again), after_sensor(xml));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ply_export_writes_world_space_meshes() {
+        let dir = setup("ply");
+        let result = load_scene_with_settings(dir.join("scene.xml")).expect("load scene");
+        write_scene(dir.join("out.xml"), &result.scene, &SceneSettings::default(), MeshExport::Ply).expect("write scene");
+
+        let xml = fs::read_to_string(dir.join("out.xml")).expect("read written scene");
+        assert!(xml.contains("out_assets/"));
+        assert!(!xml.contains("tri.ply\""));
+        let reloaded = load_scene_with_settings(dir.join("out.xml")).expect("load written scene");
+        let tri = reloaded.scene.objects().iter().find(|o| o.name.as_deref() == Some("tri")).expect("tri");
+        let bbox = tri.shape.bounding_box();
+        assert!((bbox.p_min.x - 1.0).abs() < 1e-6 && (bbox.p_max.x - 2.0).abs() < 1e-6);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sppm_settings_round_trip() {
+        let dir = setup("sppm");
+        let result = load_scene_with_settings(dir.join("scene.xml")).expect("load scene");
+        let settings = SceneSettings {
+            integrator_type: Some("sppm".to_string()),
+            max_depth: Some(6),
+            sppm: SppmSettings { photon_count: Some(20000), initial_radius: Some(0.05), alpha: Some(0.6) },
+            ..SceneSettings::default()
+        };
+        write_scene(dir.join("out.xml"), &result.scene, &settings, MeshExport::Reference).expect("write scene");
+
+        let reloaded = load_scene_with_settings(dir.join("out.xml")).expect("load written scene");
+        assert_eq!(reloaded.integrator_type.as_deref(), Some("sppm"));
+        assert_eq!(reloaded.sppm, settings.sppm);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_mlt_settings_round_trip() {
+        let dir = setup("mlt");
+        let result = load_scene_with_settings(dir.join("scene.xml")).expect("load scene");
+        let settings = SceneSettings {
+            integrator_type: Some("pssmlt".to_string()),
+            max_depth: Some(8),
+            mlt: MltSettings { bootstrap_samples: Some(5000), chains: Some(32), large_step_probability: Some(0.25), sigma: Some(0.02) },
+            ..SceneSettings::default()
+        };
+        write_scene(dir.join("out.xml"), &result.scene, &settings, MeshExport::Reference).expect("write scene");
+
+        let reloaded = load_scene_with_settings(dir.join("out.xml")).expect("load written scene");
+        assert_eq!(reloaded.integrator_type.as_deref(), Some("pssmlt"));
+        assert_eq!(reloaded.mlt, settings.mlt);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
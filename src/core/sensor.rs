@@ -1,14 +1,60 @@
 // Copyright @yucwang 2026
 
+use crate::core::scene_writer::{SceneWriteError, XmlElement};
 use crate::math::bitmap::Bitmap;
-use crate::math::constants::Vector2f;
+use crate::math::constants::{Float, Vector2f, Vector3f};
 use crate::math::ray::Ray3f;
 
-pub trait Sensor: Sync {
+/// Connection from a world point to a sensor, see `Sensor::eval_importance`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImportanceRecord {
+    /// Raster position in pixels, (0, 0) being the top-left image corner.
+    pub pixel: Vector2f,
+    /// Unit direction from the world point toward the sensor.
+    pub direction: Vector3f,
+    /// Distance from the world point to the sensor's aperture.
+    pub distance: Float,
+    /// Length of the connection that must be unoccluded; shorter than
+    /// `distance` when the sensor clips geometry near it.
+    pub visible_distance: Float,
+    /// Importance We per unit solid angle at the sensor, normalized so that
+    /// a pixel's value is the integral of We·L over the directions it sees.
+    pub importance: Float,
+}
+
+pub trait Sensor: Send + Sync {
     fn sample_ray(&self, u: &Vector2f) -> Ray3f;
+
+    /// Interval during which the shutter is open; rays get times inside it.
+    fn shutter_interval(&self) -> (Float, Float) {
+        (0.0, 0.0)
+    }
+
+    /// Maps `u` in [0, 1) to a time inside the shutter interval.
+    fn sample_time(&self, u: Float) -> Float {
+        let (open, close) = self.shutter_interval();
+        open + (close - open) * u
+    }
+
+    /// Like `sample_ray`, for sensors whose pose changes over the shutter.
+    fn sample_ray_at_time(&self, u: &Vector2f, time: Float) -> Ray3f {
+        self.sample_ray(u).with_time(time)
+    }
+
+    /// Importance the sensor receives from world point `p`, or `None` when
+    /// no pixel sees it. Used to connect light paths to the sensor.
+    fn eval_importance(&self, _p: &Vector3f) -> Option<ImportanceRecord> {
+        None
+    }
+
     fn bitmap(&self) -> &Bitmap;
     fn bitmap_mut(&mut self) -> &mut Bitmap;
     fn describe(&self) -> String {
         String::from("Sensor")
     }
+
+    /// `<sensor>` element, film included, for a written scene.
+    fn to_xml(&self) -> Result<XmlElement, SceneWriteError> {
+        Err(SceneWriteError::Unsupported(self.describe()))
+    }
 }
@@ -2,6 +2,7 @@
 
 use crate::core::computation_node::ComputationNode;
 use crate::core::interaction::{ SurfaceIntersection, SurfaceSampleRecord };
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants::{ Float, Vector2f };
 use crate::math::ray::Ray3f;
@@ -12,4 +13,10 @@ pub trait Shape: ComputationNode + Send + Sync {
     fn ray_intersection_t(&self, ray: &Ray3f) -> bool;
     fn sample(&self, u: &Vector2f) -> SurfaceSampleRecord;
     fn surface_area(&self) -> Float;
+
+    /// `<shape>` element for this shape in a written scene, without its
+    /// bsdf, emitter or medium.
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Err(SceneWriteError::Unsupported(format!("shape {}", self.id())))
+    }
 }
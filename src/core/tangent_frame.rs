@@ -13,6 +13,20 @@ pub fn build_tangent_frame(n: &Vector3f) -> (Vector3f, Vector3f) {
     (tangent, bitangent)
 }
 
+/// Frame around `n` aligned with `tangent` when the shape provides one,
+/// falling back to `build_tangent_frame`.
+pub fn build_shading_frame(n: &Vector3f, tangent: Option<Vector3f>) -> (Vector3f, Vector3f) {
+    if let Some(t) = tangent {
+        let t = t - n * n.dot(&t);
+        let len = t.norm();
+        if len > 1e-6 {
+            let tangent = t / len;
+            return (tangent, n.cross(&tangent).normalize());
+        }
+    }
+    build_tangent_frame(n)
+}
+
 pub fn world_to_local(v: &Vector3f, t: &Vector3f, b: &Vector3f, n: &Vector3f) -> Vector3f {
     Vector3f::new(v.dot(t), v.dot(b), v.dot(n))
 }
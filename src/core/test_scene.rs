@@ -0,0 +1,112 @@
+// Copyright @yucwang 2026
+
+//! Small scene shared by integrator and renderer tests: a diffuse floor at
+//! z = 0 lit by a square area light facing down, seen by a perspective
+//! camera looking at the origin. Tests change only what they rely on.
+
+use crate::core::scene::{Scene, SceneObject};
+use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+use crate::math::constants::{Float, Matrix4f, Vector3f, Vector4f};
+use crate::math::spectrum::RGBSpectrum;
+use crate::math::transform::Transform;
+use crate::sensors::perspective::PerspectiveCamera;
+use crate::shapes::rectangle::Rectangle;
+use crate::textures::constant::ConstantTexture;
+use std::sync::Arc;
+
+/// Square of half-size `scale` in the plane z = `z`, facing up, or down
+/// when `flip` is set.
+pub fn rectangle(scale: Float, z: Float, flip: bool) -> Arc<Rectangle> {
+    let s = if flip { -scale } else { scale };
+    let mut matrix = Matrix4f::from_diagonal(&Vector4f::new(scale, s, s, 1.0));
+    matrix[(2, 3)] = z;
+    Arc::new(Rectangle::new(Transform::new(matrix), None))
+}
+
+pub struct TestScene {
+    albedo: RGBSpectrum,
+    floor_size: Float,
+    light_size: Float,
+    light_height: Float,
+    radiance: Float,
+    eye: Vector3f,
+    fov: Float,
+    resolution: usize,
+    objects: Vec<SceneObject>,
+}
+
+impl Default for TestScene {
+    fn default() -> Self {
+        Self {
+            albedo: RGBSpectrum::new(0.7, 0.5, 0.3),
+            floor_size: 0.5,
+            light_size: 0.5,
+            light_height: 2.0,
+            radiance: 4.0,
+            eye: Vector3f::new(0.0, -2.0, 2.0),
+            fov: 0.9,
+            resolution: 4,
+            objects: Vec::new(),
+        }
+    }
+}
+
+impl TestScene {
+    /// Reflectance of the floor, which the light also uses.
+    pub fn with_albedo(mut self, albedo: RGBSpectrum) -> Self {
+        self.albedo = albedo;
+        self
+    }
+
+    pub fn with_floor(mut self, size: Float) -> Self {
+        self.floor_size = size;
+        self
+    }
+
+    pub fn with_light(mut self, size: Float, height: Float, radiance: Float) -> Self {
+        self.light_size = size;
+        self.light_height = height;
+        self.radiance = radiance;
+        self
+    }
+
+    /// Square film of `resolution` pixels a side.
+    pub fn with_camera(mut self, eye: Vector3f, fov: Float, resolution: usize) -> Self {
+        self.eye = eye;
+        self.fov = fov;
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_object(mut self, object: SceneObject) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    pub fn build(self) -> Scene {
+        let diffuse = Arc::new(LambertianDiffuseBSDF::new(Arc::new(ConstantTexture::new(self.albedo)), None));
+        let mut objects = vec![
+            SceneObject::new(rectangle(self.floor_size, 0.0, false), diffuse.clone()),
+            SceneObject::with_emission(
+                rectangle(self.light_size, self.light_height, true),
+                diffuse,
+                RGBSpectrum::new(self.radiance, self.radiance, self.radiance),
+            ),
+        ];
+        objects.extend(self.objects);
+        let mut scene = Scene::with_objects(objects);
+        scene.add_sensor(Box::new(PerspectiveCamera::new(
+            self.eye,
+            Vector3f::new(0.0, 0.0, 0.0),
+            Vector3f::new(0.0, 0.0, 1.0),
+            self.fov,
+            1.0,
+            self.resolution,
+            self.resolution,
+            0.01,
+            1.0e4,
+        )));
+        scene.build_bvh();
+        scene
+    }
+}
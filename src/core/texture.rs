@@ -1,11 +1,28 @@
 // Copyright @yucwang 2026
 
+use crate::core::gradient::GradientBuffers;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::Vector2f;
 use crate::math::spectrum::RGBSpectrum;
 
 pub trait Texture: Send + Sync {
     fn eval(&self, uv: Vector2f) -> RGBSpectrum;
+
+    /// Evaluates with the interpolated vertex color of the hit, if any.
+    /// Only textures that read mesh attributes need to override this.
+    fn eval_with_color(&self, uv: Vector2f, _vertex_color: Option<RGBSpectrum>) -> RGBSpectrum {
+        self.eval(uv)
+    }
     fn describe(&self) -> String {
         String::from("Texture")
     }
+
+    /// Adds the gradient of `grad · eval(uv)` with respect to the texture's
+    /// raw data to `grads`. Textures without raw data have nothing to add.
+    fn backward(&self, _uv: Vector2f, _vertex_color: Option<RGBSpectrum>, _grad: &RGBSpectrum, _grads: &mut GradientBuffers) {}
+
+    /// Properties setting the bsdf parameter `name` to this texture.
+    fn to_xml(&self, _name: &str, _ctx: &mut WriteContext) -> Result<Vec<XmlElement>, SceneWriteError> {
+        Err(SceneWriteError::Unsupported(self.describe()))
+    }
 }
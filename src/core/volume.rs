@@ -1,5 +1,7 @@
 // Copyright @yucwang 2026
 
+use crate::core::gradient::GradientBuffers;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants::Vector3f;
 
@@ -9,4 +11,12 @@ pub trait Volume: Send + Sync {
     }
     fn channels(&self) -> usize;
     fn eval(&self, p_world: Vector3f) -> Vector3f;
+
+    /// Adds the gradient of `grad · eval(p_world)` with respect to the
+    /// volume's raw data to `grads`.
+    fn backward(&self, _p_world: Vector3f, _grad: &Vector3f, _grads: &mut GradientBuffers) {}
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Err(SceneWriteError::Unsupported("volume".to_string()))
+    }
 }
@@ -3,6 +3,7 @@
 use crate::core::computation_node::{ComputationNode, generate_node_id, indent_string};
 use crate::core::emitter::{Emitter, EmitterFlag};
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::shape::Shape;
 use crate::core::tangent_frame::{build_tangent_frame, local_to_world};
 use crate::math::constants::{Float, Vector2f, Vector3f};
@@ -87,4 +88,9 @@ impl Emitter for AreaEmitter {
             0.0
         }
     }
+
+    // Written as the `<emitter type="area">` of its shape.
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<Option<XmlElement>, SceneWriteError> {
+        Ok(None)
+    }
 }
@@ -3,6 +3,7 @@
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::emitter::{Emitter, EmitterFlag};
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::tangent_frame::build_tangent_frame;
 use crate::math::aabb::AABB;
 use crate::math::constants::{Float, Vector2f, Vector3f};
@@ -81,7 +82,7 @@ impl Emitter for DirectionalEmitter {
         let n = emit_dir;
         let uv = Vector2f::new(0.0, 0.0);
         let intersection = SurfaceIntersection::new(p, n, n, uv, 0.0, self.irradiance, None, None);
-        let pdf = 1.0 / (std::f32::consts::PI * self.bsphere_radius * self.bsphere_radius).max(1e-6);
+        let pdf = self.pdf_position(&intersection);
         SurfaceSampleRecord::new(intersection, pdf)
     }
 
@@ -95,11 +96,17 @@ impl Emitter for DirectionalEmitter {
     }
 
     fn pdf_position(&self, _position: &SurfaceIntersection) -> Float {
-        0.0
+        1.0 / (std::f32::consts::PI * self.bsphere_radius * self.bsphere_radius).max(1e-6)
     }
 
     fn pdf_direction(&self, _position: &SurfaceIntersection, direction: &Vector3f) -> Float {
         let _ = direction;
         0.0
     }
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<Option<XmlElement>, SceneWriteError> {
+        Ok(Some(XmlElement::typed("emitter", "directional")
+            .with_child(XmlElement::vector("direction", self.direction))
+            .with_child(XmlElement::rgb("irradiance", self.irradiance))))
+    }
 }
@@ -3,6 +3,7 @@
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::emitter::{Emitter, EmitterFlag};
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::tangent_frame::build_tangent_frame;
 use crate::math::aabb::AABB;
 use crate::math::constants::{Float, Vector2f, Vector3f, PI};
@@ -34,6 +35,11 @@ impl EnvMap {
 
     pub fn from_file_with_id(path: &str, scale: Float, id: Option<String>) -> std::result::Result<Self, String> {
         let texture = ImageTexture::from_file(path)?;
+        Self::from_texture(texture, scale, id)
+    }
+
+    /// Environment map over an already loaded latitude-longitude texture.
+    pub fn from_texture(texture: ImageTexture, scale: Float, id: Option<String>) -> std::result::Result<Self, String> {
         let (width, height) = texture.dimensions();
         if width == 0 || height == 0 {
             return Err(format!("envmap has invalid resolution: {}x{}", width, height));
@@ -112,12 +118,20 @@ impl EnvMap {
         }
 
         let weight = (self.col_cdf[y][x + 1] - self.col_cdf[y][x]).max(0.0);
-        let mut u_coord = (x as Float + 0.5) / (self.cdf_width as Float);
+        // Reuse what is left of the random numbers to place the direction
+        // inside the chosen texel rather than at its center.
+        let frac_v = ((target_row - self.row_cdf[y]) / row_weight).clamp(0.0, 0.999_999);
+        let frac_u = if weight > 0.0 {
+            ((target_col - self.col_cdf[y][x]) / weight).clamp(0.0, 0.999_999)
+        } else {
+            0.5
+        };
+        let mut u_coord = (x as Float + frac_u) / (self.cdf_width as Float);
         u_coord += 0.5 / (self.width as Float);
         if u_coord >= 1.0 {
             u_coord -= 1.0;
         }
-        let v_coord = (y as Float + 0.5) / (self.height as Float);
+        let v_coord = (y as Float + frac_v) / (self.height as Float);
 
         let theta = v_coord * PI;
         let sin_theta = theta.sin().max(1e-8);
@@ -248,7 +262,8 @@ impl Emitter for EnvMap {
         let p = self.bsphere_center + (perp_offset - dir) * self.bsphere_radius;
         let n = dir;
         let intersection = SurfaceIntersection::new(p, n, n, uv, 0.0, le, None, None);
-        SurfaceSampleRecord::new(intersection, 0.0)
+        let pdf = self.pdf_position(&intersection);
+        SurfaceSampleRecord::new(intersection, pdf)
     }
 
     fn sample_direction(&self, u: &Vector2f, _position: &SurfaceIntersection) -> Vector3f {
@@ -258,7 +273,7 @@ impl Emitter for EnvMap {
     }
 
     fn pdf_position(&self, _position: &SurfaceIntersection) -> Float {
-        0.0
+        1.0 / (PI * self.bsphere_radius * self.bsphere_radius).max(1e-6)
     }
 
     fn pdf_direction(&self, _position: &SurfaceIntersection, direction: &Vector3f) -> Float {
@@ -298,4 +313,14 @@ impl Emitter for EnvMap {
         let pdf = pdf_uv / (2.0 * PI * PI * sin_theta);
         pdf.max(0.0)
     }
+
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<Option<XmlElement>, SceneWriteError> {
+        let path = ctx.asset_path(&self.id, "exr")?;
+        self.texture.write_exr(&path.to_string_lossy())
+            .map_err(|e| SceneWriteError::Io(std::io::Error::other(e)))?;
+        Ok(Some(XmlElement::typed("emitter", "envmap")
+            .with_child(XmlElement::string("filename", &ctx.path_value(&path)))
+            .with_child(XmlElement::float("scale", self.scale))
+            .with_child(XmlElement::transform("to_world", self.to_world.matrix()))))
+    }
 }
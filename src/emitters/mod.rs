@@ -3,3 +3,4 @@
 pub mod area;
 pub mod directional;
 pub mod envmap;
+pub mod validation;
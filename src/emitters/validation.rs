@@ -0,0 +1,398 @@
+// Copyright @yucwang 2026
+
+//! Checks that emitters sample the positions and directions their pdfs
+//! report, both on their own and through `Scene::sample_emitter`,
+//! `Scene::pdf_light` and `Scene::pdf_environment`. The histograms can be
+//! written out for inspection with the `emitter_histogram` tool.
+
+use crate::core::emitter::{Emitter, EmitterFlag, EmitterSample};
+use crate::core::interaction::SurfaceIntersection;
+use crate::core::rng::LcgRng;
+use crate::core::scene::Scene;
+use crate::core::tangent_frame::build_tangent_frame;
+use crate::materials::validation::{direction_histogram, ChiSquareSettings, Histogram};
+use crate::math::aabb::AABB;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::math::ray::Ray3f;
+use crate::math::spectrum::RGBSpectrum;
+
+use std::f64::consts::PI;
+
+/// Histogram of the directions from `reference` to the points that
+/// `scene.sample_emitter` draws on area emitters, against `scene.pdf_light`
+/// of the surface seen in each direction. Points facing away from
+/// `reference` or hidden behind other geometry are dropped, as a shadow ray
+/// would drop them. The scene's BVH must be built. The pdf grows without
+/// bound where a surface is seen edge-on, like the silhouette of a sphere,
+/// and the expected counts of such bins are only roughly integrated.
+pub fn area_light_histogram(scene: &Scene, reference: Vector3f, settings: &ChiSquareSettings) -> Histogram {
+    direction_histogram(
+        |u1, u2| match scene.sample_emitter(u1.x, &u2)? {
+            EmitterSample::Surface(record) => visible_direction(scene, reference, record.intersection()),
+            EmitterSample::Direction { .. } => None,
+        },
+        |direction| {
+            scene
+                .ray_intersection(&Ray3f::new(reference, *direction, None, None))
+                .and_then(|hit| scene.pdf_light(&hit, &reference))
+                .unwrap_or(0.0)
+        },
+        settings,
+    )
+}
+
+/// Histogram of the directions `scene.sample_emitter` draws from emitters
+/// at infinity, such as environment maps, against `scene.pdf_environment`.
+/// Delta emitters are skipped.
+pub fn environment_histogram(scene: &Scene, settings: &ChiSquareSettings) -> Histogram {
+    direction_histogram(
+        |u1, u2| match scene.sample_emitter(u1.x, &u2)? {
+            EmitterSample::Direction { direction, is_delta: false, .. } => Some(direction),
+            _ => None,
+        },
+        |direction| scene.pdf_environment(direction),
+        settings,
+    )
+}
+
+/// Histogram of `emitter.sample_direction` from the position drawn with
+/// `position_sample`, against `emitter.pdf_direction` there. Meaningless for
+/// delta emitters, whose direction has no density.
+pub fn emitter_direction_histogram(
+    emitter: &dyn Emitter,
+    position_sample: Vector2f,
+    settings: &ChiSquareSettings,
+) -> Histogram {
+    let record = emitter.sample_position(&position_sample);
+    let position = record.intersection();
+    direction_histogram(
+        |u1, _| Some(emitter.sample_direction(&u1, position)),
+        |direction| emitter.pdf_direction(position, direction),
+        settings,
+    )
+}
+
+/// Histogram of the positions that an emitter at infinity draws on the disk
+/// its rays start from, against `pdf_position`. The disk is the one
+/// `set_scene_bounds` places around `bounds`, seen in the tangent frame of
+/// each sample's direction. Rows bin the squared radius and columns the
+/// angle, coordinates in which the disk's area is uniform.
+pub fn emitter_disk_histogram(emitter: &dyn Emitter, bounds: &AABB, settings: &ChiSquareSettings) -> Histogram {
+    let center = bounds.center();
+    let radius = (bounds.p_max - center).norm().max(1e-6);
+    let disk_area = PI * (radius * radius) as f64;
+    let mut normal = Vector3f::new(0.0, 0.0, 1.0);
+    let histogram = square_histogram(
+        |u| {
+            let record = emitter.sample_position(&u);
+            let n = record.intersection().geo_normal();
+            normal = n;
+            let offset = (record.intersection().p() - center) / radius + n;
+            let (tangent, bitangent) = build_tangent_frame(&n);
+            let (x, y) = (offset.dot(&tangent) as f64, offset.dot(&bitangent) as f64);
+            let angle = y.atan2(x).rem_euclid(2.0 * PI) / (2.0 * PI);
+            Some(Vector2f::new(angle as Float, (x * x + y * y) as Float))
+        },
+        |_| 0.0,
+        settings,
+    );
+
+    // The density only depends on where a point lies on the disk, so it is
+    // queried on the disk of the last sampled direction.
+    let (tangent, bitangent) = build_tangent_frame(&normal);
+    let pdf = |st: &Vector2f| {
+        let (angle, r) = (st.x as f64 * 2.0 * PI, (st.y as f64).sqrt());
+        let offset = tangent * (r * angle.cos()) as Float + bitangent * (r * angle.sin()) as Float;
+        let p = center + (offset - normal) * radius;
+        let position = SurfaceIntersection::new(p, normal, normal, Vector2f::zeros(), 0.0, RGBSpectrum::default(), None, None);
+        emitter.pdf_position(&position) as f64 * disk_area
+    };
+    let expected = integrate_square(pdf, settings);
+    Histogram { expected, ..histogram }
+}
+
+/// Number of `sample_position` draws whose pdf differs from `pdf_position`
+/// of the returned position by more than `tolerance`, relatively.
+pub fn position_pdf_mismatches(emitter: &dyn Emitter, sample_count: usize, tolerance: Float, seed: u64) -> usize {
+    let mut rng = LcgRng::new(seed);
+    (0..sample_count)
+        .filter(|_| {
+            let u = Vector2f::new(rng.next_f32(), rng.next_f32());
+            let record = emitter.sample_position(&u);
+            !close(record.pdf(), emitter.pdf_position(record.intersection()), tolerance)
+        })
+        .count()
+}
+
+/// Number of `scene.sample_emitter` draws whose reported pdf disagrees with
+/// the emitter's own pdf (times the selection probability), or, for points
+/// on area emitters visible from `reference`, with `scene.pdf_light` after
+/// conversion to solid angle.
+pub fn sample_emitter_pdf_mismatches(
+    scene: &Scene,
+    reference: Vector3f,
+    sample_count: usize,
+    tolerance: Float,
+    seed: u64,
+) -> usize {
+    let emitters = scene.emitters();
+    if emitters.is_empty() {
+        return 0;
+    }
+    let select_pdf = 1.0 / emitters.len() as Float;
+    let mut rng = LcgRng::new(seed);
+    let mut mismatches = 0;
+    for _ in 0..sample_count {
+        let u1 = rng.next_f32();
+        let u2 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        // Same selection as `Scene::sample_emitter`.
+        let emitter = &emitters[((u1 * emitters.len() as Float) as usize).min(emitters.len() - 1)];
+        let consistent = match scene.sample_emitter(u1, &u2) {
+            Some(EmitterSample::Surface(record)) => {
+                let position = record.intersection();
+                let mut consistent = close(record.pdf(), emitter.pdf_position(position) * select_pdf, tolerance);
+                if let Some(direction) = visible_direction(scene, reference, position) {
+                    let hit = scene.ray_intersection(&Ray3f::new(reference, direction, None, None));
+                    let light_pdf = hit.and_then(|hit| scene.pdf_light(&hit, &reference)).unwrap_or(0.0);
+                    let dist2 = (position.p() - reference).norm_squared();
+                    let cos_light = position.geo_normal().dot(&direction).abs();
+                    consistent &= close(light_pdf, record.pdf() * dist2 / cos_light, tolerance);
+                }
+                consistent
+            }
+            Some(EmitterSample::Direction { direction, pdf, is_delta, .. }) => {
+                if is_delta {
+                    emitter.get_flag().contains(EmitterFlag::DELTA)
+                } else {
+                    let position = emitter.sample_position(&u2);
+                    close(pdf, emitter.pdf_direction(position.intersection(), &direction) * select_pdf, tolerance)
+                }
+            }
+            None => true,
+        };
+        if !consistent {
+            mismatches += 1;
+        }
+    }
+    mismatches
+}
+
+fn close(a: Float, b: Float, tolerance: Float) -> bool {
+    (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1e-6)
+}
+
+/// Direction from `reference` to `position` if the point faces it and
+/// nothing else is in between.
+fn visible_direction(scene: &Scene, reference: Vector3f, position: &SurfaceIntersection) -> Option<Vector3f> {
+    let to_light = position.p() - reference;
+    let dist = to_light.norm();
+    if dist <= 0.0 {
+        return None;
+    }
+    let direction = to_light / dist;
+    if position.geo_normal().dot(&direction) >= 0.0 {
+        return None;
+    }
+    let hit = scene.ray_intersection(&Ray3f::new(reference, direction, None, None))?;
+    if (hit.p() - position.p()).norm() > 1e-3 * dist.max(1.0) {
+        return None;
+    }
+    Some(direction)
+}
+
+/// Histogram of the points in [0, 1]^2 drawn by `sample` against the
+/// integral of `pdf` over each bin; `settings.theta_bins` are the rows (in
+/// `y`) and `settings.phi_bins` the columns (in `x`).
+fn square_histogram<S, P>(mut sample: S, pdf: P, settings: &ChiSquareSettings) -> Histogram
+where
+    S: FnMut(Vector2f) -> Option<Vector2f>,
+    P: Fn(&Vector2f) -> f64,
+{
+    let (width, height) = (settings.phi_bins, settings.theta_bins);
+    let mut observed = vec![0.0f64; width * height];
+    let mut rng = LcgRng::new(settings.seed);
+    for _ in 0..settings.sample_count {
+        let u = Vector2f::new(rng.next_f32(), rng.next_f32());
+        if let Some(p) = sample(u) {
+            if p.iter().all(|v| v.is_finite()) {
+                let x = ((p.x.clamp(0.0, 1.0) as f64 * width as f64) as usize).min(width - 1);
+                let y = ((p.y.clamp(0.0, 1.0) as f64 * height as f64) as usize).min(height - 1);
+                observed[y * width + x] += 1.0;
+            }
+        }
+    }
+    Histogram { width, height, observed, expected: integrate_square(pdf, settings) }
+}
+
+fn integrate_square<P: Fn(&Vector2f) -> f64>(pdf: P, settings: &ChiSquareSettings) -> Vec<f64> {
+    let (width, height) = (settings.phi_bins, settings.theta_bins);
+    let res = settings.integration_resolution.max(1);
+    let cell_area = 1.0 / (width * height * res * res) as f64;
+    let mut expected = vec![0.0f64; width * height];
+    for (bin, value) in expected.iter_mut().enumerate() {
+        let (y, x) = (bin / width, bin % width);
+        let mut integral = 0.0;
+        for i in 0..res {
+            for j in 0..res {
+                let p = Vector2f::new(
+                    ((x * res + j) as f64 + 0.5) as Float / (width * res) as Float,
+                    ((y * res + i) as f64 + 0.5) as Float / (height * res) as Float,
+                );
+                let density = pdf(&p);
+                if density.is_finite() {
+                    integral += density;
+                }
+            }
+        }
+        *value = integral * cell_area * settings.sample_count as f64;
+    }
+    expected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::SceneObject;
+    use crate::emitters::area::AreaEmitter;
+    use crate::emitters::directional::DirectionalEmitter;
+    use crate::emitters::envmap::EnvMap;
+    use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+    use crate::materials::validation::corrected_significance;
+    use crate::math::constants::{Matrix4f, MatrixXF};
+    use crate::math::transform::Transform;
+    use crate::shapes::cube::Cube;
+    use crate::shapes::rectangle::Rectangle;
+    use crate::shapes::triangle_mesh::TriangleMesh;
+    use crate::textures::constant::ConstantTexture;
+    use crate::textures::image::ImageTexture;
+    use std::sync::Arc;
+
+    const SIGNIFICANCE: f64 = 0.01;
+    const TEST_COUNT: usize = 10;
+
+    fn settings() -> ChiSquareSettings {
+        ChiSquareSettings { theta_bins: 16, phi_bins: 32, integration_resolution: 8, ..ChiSquareSettings::default() }
+    }
+
+    fn assert_passes(name: &str, histogram: &Histogram) {
+        let result = histogram.chi_square(settings().min_expected);
+        assert!(result.passed(corrected_significance(SIGNIFICANCE, TEST_COUNT)), "{}: {:?}", name, result);
+    }
+
+    // Midpoint integration can miss a sliver of an area light in a bin on
+    // its silhouette, leaving a few samples where nothing is expected.
+    fn assert_passes_with_silhouette(name: &str, histogram: &Histogram) {
+        let result = histogram.chi_square(settings().min_expected);
+        let significance = corrected_significance(SIGNIFICANCE, TEST_COUNT);
+        assert!(result.p_value >= significance, "{}: {:?}", name, result);
+        assert!(result.unexpected_samples <= settings().sample_count / 1000, "{}: {:?}", name, result);
+    }
+
+    fn translate_scale(offset: Vector3f, scale: Float) -> Transform {
+        let mut matrix = Matrix4f::identity() * scale;
+        matrix[(3, 3)] = 1.0;
+        matrix[(0, 3)] = offset.x;
+        matrix[(1, 3)] = offset.y;
+        matrix[(2, 3)] = offset.z;
+        Transform::new(matrix)
+    }
+
+    fn light(shape: Arc<dyn crate::core::shape::Shape>) -> SceneObject {
+        let bsdf = Arc::new(LambertianDiffuseBSDF::new(Arc::new(ConstantTexture::new(RGBSpectrum::new(0.5, 0.5, 0.5))), None));
+        SceneObject::with_emission(shape, bsdf, RGBSpectrum::new(1.0, 1.0, 1.0))
+    }
+
+    fn scene_with(objects: Vec<SceneObject>) -> Scene {
+        let mut scene = Scene::with_objects(objects);
+        scene.build_bvh();
+        scene
+    }
+
+    // A latitude-longitude map with a bright spot, so the sampling
+    // distribution is far from uniform.
+    fn environment_map() -> EnvMap {
+        let (width, height) = (16, 8);
+        let mut data = MatrixXF::zeros(height, width * 3);
+        for y in 0..height {
+            for x in 0..width {
+                let value = if (x, y) == (5, 2) { 20.0 } else { 0.2 + 0.1 * x as Float };
+                for c in 0..3 {
+                    data[(y, x * 3 + c)] = value;
+                }
+            }
+        }
+        EnvMap::from_texture(ImageTexture::from_matrix(data), 1.0, None).unwrap()
+    }
+
+    #[test]
+    fn test_area_light_sampling_matches_pdf_light() {
+        // A bent grid of unequal triangles, seen from where no part of it is
+        // edge-on.
+        let mut positions = Vec::new();
+        let coords = [-1.0, -0.7, 0.1, 0.4, 1.0];
+        for y in coords {
+            for x in coords {
+                positions.push(Vector3f::new(x, y, 0.2 * x * x));
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..4u32 {
+            for x in 0..4u32 {
+                let i = y * 5 + x;
+                indices.push([i, i + 1, i + 6]);
+                indices.push([i, i + 6, i + 5]);
+            }
+        }
+        let mesh = TriangleMesh::from_indexed(positions, indices, None);
+        let cases: Vec<(&str, Scene, Vector3f)> = vec![
+            ("rectangle", scene_with(vec![light(Arc::new(Rectangle::new(Transform::default(), None)))]), Vector3f::new(0.3, 0.2, 0.8)),
+            ("cube", scene_with(vec![light(Arc::new(Cube::new(Transform::default(), None)))]), Vector3f::new(2.6, 2.2, 3.0)),
+            ("mesh", scene_with(vec![light(Arc::new(mesh))]), Vector3f::new(0.2, 0.1, 1.5)),
+            (
+                "rectangle and cube",
+                scene_with(vec![
+                    light(Arc::new(Rectangle::new(Transform::default(), None))),
+                    light(Arc::new(Cube::new(translate_scale(Vector3f::new(0.5, 0.0, 0.6), 0.2), None))),
+                ]),
+                Vector3f::new(0.3, 0.2, 1.5),
+            ),
+        ];
+        for (name, scene, reference) in &cases {
+            assert_passes_with_silhouette(name, &area_light_histogram(scene, *reference, &settings()));
+            assert_eq!(sample_emitter_pdf_mismatches(scene, *reference, 10_000, 1e-3, 5), 0, "{}", name);
+            for emitter in scene.emitters() {
+                assert_eq!(position_pdf_mismatches(emitter.as_ref(), 1000, 1e-4, 5), 0, "{}", name);
+            }
+        }
+
+        let emitter = AreaEmitter::from_shape(Arc::new(Rectangle::new(Transform::default(), None)), RGBSpectrum::new(1.0, 1.0, 1.0), None);
+        assert_passes("area emission", &emitter_direction_histogram(&emitter, Vector2f::new(0.3, 0.6), &settings()));
+    }
+
+    #[test]
+    fn test_environment_sampling_matches_pdf() {
+        let mut scene = scene_with(vec![light(Arc::new(Rectangle::new(translate_scale(Vector3f::zeros(), 0.5), None)))]);
+        let mut envmap = environment_map();
+        envmap.set_scene_bounds(scene.scene_bounds());
+        scene.add_emitter(Box::new(envmap));
+        assert_passes("environment", &environment_histogram(&scene, &settings()));
+        assert_eq!(sample_emitter_pdf_mismatches(&scene, Vector3f::new(0.1, 0.0, 1.0), 10_000, 1e-3, 5), 0);
+
+        let envmap = environment_map();
+        assert_passes("envmap directions", &emitter_direction_histogram(&envmap, Vector2f::new(0.5, 0.5), &settings()));
+    }
+
+    #[test]
+    fn test_infinite_emitter_positions_match_pdf() {
+        let bounds = AABB::new(Vector3f::new(-1.0, 0.0, -2.0), Vector3f::new(3.0, 1.0, 2.0));
+        let mut directional = DirectionalEmitter::new_with(Vector3f::new(0.3, -1.0, 0.2), RGBSpectrum::new(1.0, 1.0, 1.0), None);
+        directional.set_scene_bounds(&bounds);
+        let mut envmap = environment_map();
+        envmap.set_scene_bounds(&bounds);
+        let emitters: [(&str, &dyn Emitter); 2] = [("directional", &directional), ("envmap", &envmap)];
+        for (name, emitter) in emitters {
+            assert_passes(name, &emitter_disk_histogram(emitter, &bounds, &settings()));
+            assert_eq!(position_pdf_mismatches(emitter, 1000, 1e-4, 5), 0, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright @yucwang 2026
+
+use crate::core::bsdf::BSDFSampleRecord;
+use crate::core::integrator::Integrator;
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::core::sensor::Sensor;
+use crate::core::tangent_frame::{build_shading_frame, world_to_local};
+use crate::integrators::path::compute_scatter_ray;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::math::spectrum::RGBSpectrum;
+
+/// Auxiliary quantity recorded at the first surface hit. Pixels whose
+/// camera ray escapes are zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AovType {
+    /// Directional albedo of the BSDF towards the camera, estimated from one
+    /// BSDF sample per pixel sample.
+    Albedo,
+    /// World-space shading normal.
+    Normal,
+    /// Distance along the camera ray.
+    Depth,
+    /// World-space hit position.
+    Position,
+    Uv,
+}
+
+impl AovType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "albedo" => Some(AovType::Albedo),
+            "normal" | "sh_normal" => Some(AovType::Normal),
+            "depth" => Some(AovType::Depth),
+            "position" => Some(AovType::Position),
+            "uv" => Some(AovType::Uv),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AovType::Albedo => "albedo",
+            AovType::Normal => "normal",
+            AovType::Depth => "depth",
+            AovType::Position => "position",
+            AovType::Uv => "uv",
+        }
+    }
+
+    /// Number of meaningful channels; the remaining RGB channels are zero.
+    pub fn channels(&self) -> usize {
+        match self {
+            AovType::Depth => 1,
+            AovType::Uv => 2,
+            _ => 3,
+        }
+    }
+}
+
+// Bounces followed through specular and null surfaces before giving up.
+const MAX_SPECULAR_BOUNCES: u32 = 8;
+
+pub struct AovIntegrator {
+    pub aov: AovType,
+    pub samples_per_pixel: u32,
+    /// Record the first non-specular hit instead of the first hit, as
+    /// denoiser features want. Albedo is then scaled by the specular
+    /// throughput and depth is the distance along the whole path.
+    pub skip_specular: bool,
+}
+
+impl AovIntegrator {
+    pub fn new(aov: AovType, samples_per_pixel: u32) -> Self {
+        Self { aov, samples_per_pixel, skip_specular: false }
+    }
+
+    pub fn with_skip_specular(mut self, skip_specular: bool) -> Self {
+        self.skip_specular = skip_specular;
+        self
+    }
+}
+
+impl Integrator for AovIntegrator {
+    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, sampler: &mut dyn Sampler) -> RGBSpectrum {
+        let (width, height) = {
+            let bmp = sensor.bitmap();
+            (bmp.width(), bmp.height())
+        };
+        if width == 0 || height == 0 {
+            return RGBSpectrum::default();
+        }
+
+        let offset = sampler.get_pixel_2d();
+        let u = (pixel.x + offset.x) / (width as Float);
+        let v = (pixel.y + offset.y) / (height as Float);
+        let time = sensor.sample_time(sampler.get_1d());
+        let mut ray = sensor.sample_ray_at_time(&Vector2f::new(u, v), time);
+        let mut throughput = Vector3f::new(1.0, 1.0, 1.0);
+        let mut distance = 0.0;
+        let mut bounce = 0;
+        let intersection = loop {
+            let intersection = match scene.ray_intersection(&ray) {
+                Some(h) => h,
+                None => return RGBSpectrum::default(),
+            };
+            let passes_through = match intersection.material() {
+                Some(m) => m.is_null() || m.is_specular(),
+                None => false,
+            };
+            if !self.skip_specular || !passes_through || bounce >= MAX_SPECULAR_BOUNCES {
+                break intersection;
+            }
+            let material = intersection.material().expect("checked above");
+            let n_sh = intersection.sh_normal();
+            let (tangent, bitangent) = build_shading_frame(&n_sh, intersection.tangent());
+            let wi_local = world_to_local(&(-ray.dir()), &tangent, &bitangent, &n_sh);
+            let (next_ray, weight, _) = match compute_scatter_ray(
+                material,
+                sampler.get_2d(),
+                sampler.get_2d(),
+                wi_local,
+                intersection.uv(),
+                intersection.color(),
+                intersection.p(),
+                n_sh,
+                intersection.geo_normal(),
+                &tangent,
+                &bitangent,
+            ) {
+                Some(v) => v,
+                None => return RGBSpectrum::default(),
+            };
+            throughput = throughput.component_mul(&weight);
+            distance += intersection.t();
+            ray = next_ray.with_time(time);
+            bounce += 1;
+        };
+
+        match self.aov {
+            AovType::Albedo => {
+                let material = match intersection.material() {
+                    Some(m) => m,
+                    None => return RGBSpectrum::default(),
+                };
+                let n_sh = intersection.sh_normal();
+                let (tangent, bitangent) = build_shading_frame(&n_sh, intersection.tangent());
+                let wi_local = world_to_local(&(-ray.dir()), &tangent, &bitangent, &n_sh);
+                let mut sample: BSDFSampleRecord = material.sample(sampler.get_2d(), sampler.get_2d(), wi_local);
+                if sample.pdf <= 0.0 {
+                    return RGBSpectrum::default();
+                }
+                sample.uv = intersection.uv();
+                sample.color = intersection.color();
+                let pdf = sample.pdf;
+                let cos_theta = sample.wo.z.abs();
+                let eval = material.eval(sample);
+                let albedo = throughput * (cos_theta / pdf);
+                RGBSpectrum::new(eval.value[0] * albedo.x, eval.value[1] * albedo.y, eval.value[2] * albedo.z)
+            }
+            AovType::Normal => {
+                let n = intersection.sh_normal();
+                RGBSpectrum::new(n.x, n.y, n.z)
+            }
+            AovType::Depth => RGBSpectrum::new(distance + intersection.t(), 0.0, 0.0),
+            AovType::Position => {
+                let p = intersection.p();
+                RGBSpectrum::new(p.x, p.y, p.z)
+            }
+            AovType::Uv => {
+                let uv = intersection.uv();
+                RGBSpectrum::new(uv.x, uv.y, 0.0)
+            }
+        }
+    }
+
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn describe(&self) -> String {
+        format!("AovIntegrator\n  aov: {}", self.aov.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::SceneObject;
+    use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+    use crate::math::transform::Transform;
+    use crate::samplers::independent::IndependentSampler;
+    use crate::sensors::perspective::PerspectiveCamera;
+    use crate::shapes::rectangle::Rectangle;
+    use crate::textures::constant::ConstantTexture;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_aov_integrator_hits_facing_rectangle() {
+        let texture = Arc::new(ConstantTexture::new(RGBSpectrum::new(0.25, 0.5, 0.75)));
+        let bsdf = Arc::new(LambertianDiffuseBSDF::new(texture, None));
+        let shape = Arc::new(Rectangle::new(Transform::new(crate::math::constants::Matrix4f::new_scaling(10.0)), None));
+        let mut scene = Scene::with_objects(vec![SceneObject::new(shape, bsdf)]);
+        scene.build_bvh();
+        let camera = PerspectiveCamera::new(
+            Vector3f::new(0.0, 0.0, 2.0),
+            Vector3f::new(0.0, 0.0, 0.0),
+            Vector3f::new(0.0, 1.0, 0.0),
+            0.5,
+            1.0,
+            4,
+            4,
+            0.0,
+            1.0e4,
+        );
+
+        let mut sampler = IndependentSampler::new(1, 7);
+        sampler.start_pixel_sample(1, 1, 0);
+        let depth = AovIntegrator::new(AovType::Depth, 1).trace_ray_forward(&scene, &camera, Vector2f::new(1.0, 1.0), &mut sampler);
+        assert!(depth[0] > 1.9 && depth[0] < 2.2, "depth {}", depth[0]);
+
+        sampler.start_pixel_sample(1, 1, 1);
+        let normal = AovIntegrator::new(AovType::Normal, 1).trace_ray_forward(&scene, &camera, Vector2f::new(1.0, 1.0), &mut sampler);
+        assert!((normal[2].abs() - 1.0).abs() < 1e-4);
+
+        // A Lambertian BSDF sampled by its own cosine pdf has weight = reflectance.
+        sampler.start_pixel_sample(1, 1, 2);
+        let albedo = AovIntegrator::new(AovType::Albedo, 1).trace_ray_forward(&scene, &camera, Vector2f::new(1.0, 1.0), &mut sampler);
+        assert!((albedo[0] - 0.25).abs() < 1e-3 && (albedo[2] - 0.75).abs() < 1e-3, "albedo {:?}", albedo);
+    }
+}
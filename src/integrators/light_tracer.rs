@@ -0,0 +1,239 @@
+// Copyright @yucwang 2026
+
+use crate::core::bsdf::{BSDFSampleRecord, BSDF};
+use crate::core::interaction::SurfaceIntersection;
+use crate::core::emitter::EmitterSample;
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::core::sensor::Sensor;
+use crate::core::tangent_frame::{build_shading_frame, build_tangent_frame, local_to_world, world_to_local};
+use crate::math::constants::{Float, Vector2f, Vector3f, PI};
+use crate::math::ray::Ray3f;
+use crate::math::spectrum::RGBSpectrum;
+use crate::math::warp::{sample_cosine_hemisphere, sample_uniform_disk_concentric};
+
+/// Particle tracer: follows paths from the emitters and connects every
+/// vertex to the sensor. It renders caustics seen by the camera that a path
+/// tracer finds only by chance, but never sees emitters at infinity
+/// directly or anything through a specular surface next to the camera.
+pub struct LightTracerIntegrator {
+    pub max_depth: u32,
+    pub samples_per_pixel: u32,
+}
+
+impl LightTracerIntegrator {
+    pub fn new(max_depth: u32, samples_per_pixel: u32) -> Self {
+        Self { max_depth, samples_per_pixel }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("LightTracerIntegrator [max_depth={}, spp={}]", self.max_depth, self.samples_per_pixel)
+    }
+
+    /// Traces one particle and hands every sensor connection to `splat` as
+    /// a raster position and its contribution. An image is the sum of the
+    /// splats of `N` particles divided by `N`.
+    pub fn trace_particle(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        sampler: &mut dyn Sampler,
+        splat: &mut dyn FnMut(Vector2f, Vector3f),
+    ) {
+        if self.max_depth == 0 {
+            return;
+        }
+        let particle = match sample_particle(scene, sampler) {
+            Some(particle) => particle,
+            None => return,
+        };
+        if let Some((p, n, le_over_pdf)) = particle.surface {
+            // Emitter seen directly.
+            if let Some((pixel, contribution)) = connect_to_sensor(scene, sensor, &p, &n) {
+                let cos_light = contribution.direction.dot(&n);
+                if cos_light > 0.0 {
+                    splat(pixel, le_over_pdf * (cos_light * contribution.importance / contribution.distance2));
+                }
+            }
+        }
+
+        let mut ray = particle.ray;
+        let mut throughput = particle.throughput;
+        for depth in 1..self.max_depth {
+            let intersection = match scene.ray_intersection(&ray) {
+                Some(hit) => hit,
+                None => break,
+            };
+            let vertex = match ParticleVertex::new(&intersection, &ray) {
+                Some(vertex) => vertex,
+                None => break,
+            };
+
+            if let Some((pixel, contribution)) = connect_to_sensor(scene, sensor, &intersection.p(), &intersection.geo_normal()) {
+                let value = throughput.component_mul(&vertex.eval(&contribution.direction));
+                splat(pixel, value * (contribution.importance / contribution.distance2));
+            }
+
+            if depth + 1 >= self.max_depth {
+                break;
+            }
+            let (next_ray, weight) = match vertex.sample(sampler) {
+                Some(v) => v,
+                None => break,
+            };
+            throughput = throughput.component_mul(&weight);
+            if throughput.x <= 0.0 && throughput.y <= 0.0 && throughput.z <= 0.0 {
+                break;
+            }
+
+            let rr_depth: u32 = 5;
+            if depth >= rr_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).clamp(0.05, 0.95);
+                if sampler.get_1d() > survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = next_ray;
+        }
+    }
+}
+
+/// First segment of a particle path.
+pub(crate) struct Particle {
+    pub ray: Ray3f,
+    /// Flux the particle carries, for an estimate made of this one particle.
+    pub throughput: Vector3f,
+    /// Position, geometric normal and Le / area pdf of the emitter point the
+    /// particle leaves from; `None` for emitters at infinity.
+    pub surface: Option<(Vector3f, Vector3f, Vector3f)>,
+}
+
+/// Starts a particle on one of the scene's emitters.
+pub(crate) fn sample_particle(scene: &Scene, sampler: &mut dyn Sampler) -> Option<Particle> {
+    let light_sample = scene.sample_emitter(sampler.get_1d(), &sampler.get_2d())?;
+    let u_direction = sampler.get_2d();
+    match light_sample {
+        EmitterSample::Surface(sample) => {
+            let light = sample.intersection();
+            let le = to_vector(&light.le());
+            if sample.pdf() <= 0.0 || le == Vector3f::zeros() {
+                return None;
+            }
+            let p = light.p();
+            let n = light.geo_normal();
+            let (tangent, bitangent) = build_tangent_frame(&n);
+            let direction = local_to_world(&sample_cosine_hemisphere(&u_direction), &tangent, &bitangent, &n);
+            Some(Particle {
+                ray: Ray3f::new(p + n * 1e-4, direction, Some(1e-4), None),
+                throughput: le * (PI / sample.pdf()),
+                surface: Some((p, n, le / sample.pdf())),
+            })
+        }
+        EmitterSample::Direction { direction, irradiance, pdf, .. } => {
+            if pdf <= 0.0 {
+                return None;
+            }
+            // Light from infinity enters through a disk facing it that
+            // covers the scene's bounding sphere.
+            let bounds = scene.scene_bounds();
+            let center = bounds.center();
+            let radius = (bounds.p_max - center).norm().max(1e-4);
+            let (tangent, bitangent) = build_tangent_frame(&direction);
+            let disk = sample_uniform_disk_concentric(&u_direction) * radius;
+            let origin = center + direction * radius + tangent * disk.x + bitangent * disk.y;
+            Some(Particle {
+                ray: Ray3f::new(origin, -direction, Some(0.0), None),
+                throughput: to_vector(&irradiance) * (PI * radius * radius / pdf),
+                surface: None,
+            })
+        }
+    }
+}
+
+/// Surface hit of a particle, set up to evaluate and sample its BSDF in the
+/// adjoint direction.
+pub(crate) struct ParticleVertex<'a> {
+    intersection: &'a SurfaceIntersection,
+    material: &'a dyn BSDF,
+    tangent: Vector3f,
+    bitangent: Vector3f,
+    // Direction back toward the light; it plays the role of the sampled
+    // direction of a camera path, so BSDFs are evaluated with their
+    // arguments in radiance order.
+    wl_local: Vector3f,
+    cos_wl_geo: Float,
+}
+
+impl<'a> ParticleVertex<'a> {
+    /// `None` when the hit has no BSDF or `ray` grazes it.
+    pub fn new(intersection: &'a SurfaceIntersection, ray: &Ray3f) -> Option<Self> {
+        let material = intersection.material()?;
+        let n_sh = intersection.sh_normal();
+        let (tangent, bitangent) = build_shading_frame(&n_sh, intersection.tangent());
+        let wl = -ray.dir();
+        let cos_wl_geo = wl.dot(&intersection.geo_normal()).abs();
+        if cos_wl_geo <= 0.0 {
+            return None;
+        }
+        let wl_local = world_to_local(&wl, &tangent, &bitangent, &n_sh);
+        Some(Self { intersection, material, tangent, bitangent, wl_local, cos_wl_geo })
+    }
+
+    /// BSDF value for light scattered toward `wc`, times the cosine on the
+    /// light side with the adjoint shading-normal correction applied.
+    pub fn eval(&self, wc: &Vector3f) -> Vector3f {
+        let record = BSDFSampleRecord {
+            wi: world_to_local(wc, &self.tangent, &self.bitangent, &self.intersection.sh_normal()),
+            wo: self.wl_local,
+            uv: self.intersection.uv(),
+            color: self.intersection.color(),
+            ..BSDFSampleRecord::default()
+        };
+        let scale = self.wl_local.z.abs() * wc.dot(&self.intersection.geo_normal()).abs() / self.cos_wl_geo;
+        to_vector(&self.material.eval(record).value) * scale
+    }
+
+    /// Continues the particle; returns the next ray and the factor its
+    /// throughput is multiplied by.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> Option<(Ray3f, Vector3f)> {
+        let sample = self.material.sample(sampler.get_2d(), sampler.get_2d(), self.wl_local);
+        if sample.pdf <= 0.0 {
+            return None;
+        }
+        let n_geo = self.intersection.geo_normal();
+        let direction = local_to_world(&sample.wo, &self.tangent, &self.bitangent, &self.intersection.sh_normal());
+        let offset = if direction.dot(&n_geo) >= 0.0 { n_geo } else { -n_geo };
+        let ray = Ray3f::new(self.intersection.p() + offset * 1e-6, direction, Some(1e-4), None);
+        Some((ray, self.eval(&direction) / sample.pdf))
+    }
+}
+
+/// Sensor importance at `p` when the connection to the sensor is
+/// unoccluded.
+struct SensorConnection {
+    direction: Vector3f,
+    distance2: Float,
+    importance: Float,
+}
+
+fn connect_to_sensor(scene: &Scene, sensor: &dyn Sensor, p: &Vector3f, n_geo: &Vector3f) -> Option<(Vector2f, SensorConnection)> {
+    let record = sensor.eval_importance(p)?;
+    let offset = if record.direction.dot(n_geo) >= 0.0 { *n_geo } else { -n_geo };
+    let shadow_ray = Ray3f::new(p + offset * 1e-4, record.direction, Some(1e-3), Some(record.visible_distance - 1e-3));
+    if scene.ray_intersection_t(&shadow_ray) {
+        return None;
+    }
+    Some((
+        record.pixel,
+        SensorConnection {
+            direction: record.direction,
+            distance2: record.distance * record.distance,
+            importance: record.importance,
+        },
+    ))
+}
+
+pub(crate) fn to_vector(value: &RGBSpectrum) -> Vector3f {
+    Vector3f::new(value[0], value[1], value[2])
+}
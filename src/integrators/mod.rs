@@ -1,2 +1,47 @@
+// Copyright @yucwang 2026
+
+pub mod aov;
+pub mod light_tracer;
 pub mod path;
+pub mod pssmlt;
 pub mod raymarching;
+pub mod sppm;
+
+use crate::core::integrator::{DifferentiableIntegrator, Integrator};
+use crate::math::constants::Float;
+use path::{FireflySettings, PathIntegrator};
+use raymarching::RaymarchingIntegrator;
+
+/// Builds an integrator from a Mitsuba-style `<integrator type=...>` name.
+/// `step_size` is only used by `raymarching`, `firefly` only by `path`.
+pub fn create_integrator(
+    name: &str,
+    max_depth: u32,
+    samples_per_pixel: u32,
+    step_size: Option<Float>,
+    firefly: FireflySettings,
+) -> Option<Box<dyn Integrator>> {
+    let integrator: Box<dyn Integrator> = match name {
+        "path" => Box::new(PathIntegrator::new(max_depth, samples_per_pixel).with_firefly_settings(firefly)),
+        "raymarching" => Box::new(RaymarchingIntegrator::new(max_depth, samples_per_pixel, step_size)),
+        _ => return None,
+    };
+    Some(integrator)
+}
+
+/// Like `create_integrator`, restricted to integrators that can
+/// back-propagate image gradients.
+pub fn create_differentiable_integrator(
+    name: &str,
+    max_depth: u32,
+    samples_per_pixel: u32,
+    step_size: Option<Float>,
+    firefly: FireflySettings,
+) -> Option<Box<dyn DifferentiableIntegrator>> {
+    let integrator: Box<dyn DifferentiableIntegrator> = match name {
+        "path" => Box::new(PathIntegrator::new(max_depth, samples_per_pixel).with_firefly_settings(firefly)),
+        "raymarching" => Box::new(RaymarchingIntegrator::new(max_depth, samples_per_pixel, step_size)),
+        _ => return None,
+    };
+    Some(integrator)
+}
@@ -1,29 +1,91 @@
 // Copyright @yucwang 2026
 
-use crate::core::integrator::Integrator;
-use crate::core::rng::LcgRng;
+use crate::core::gradient::GradientBuffers;
+use crate::core::integrator::{DifferentiableIntegrator, Integrator};
+use crate::core::path_record::{EscapeRecord, NeeRecord, PathVertex, RecordedPath};
+use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
 use crate::core::sensor::Sensor;
-use crate::core::tangent_frame::{build_tangent_frame, local_to_world, world_to_local};
-use crate::core::bsdf::BSDFSampleRecord;
+use crate::core::tangent_frame::{build_shading_frame, local_to_world, world_to_local};
+use crate::core::bsdf::{BSDFSampleRecord, BSDF};
 use crate::core::emitter::EmitterSample;
 use crate::math::constants::{Float, Vector2f, Vector3f};
 use crate::math::ray::Ray3f;
 use crate::math::spectrum::{RGBSpectrum, Spectrum};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Biased firefly suppression for `PathIntegrator`, all off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FireflySettings {
+    /// Largest channel value a sample may get from emission seen directly
+    /// or after one bounce. Larger samples are scaled down, keeping hue.
+    pub clamp_direct: Option<Float>,
+    /// Same for light that bounced more than once.
+    pub clamp_indirect: Option<Float>,
+    /// After `k` non-specular bounces, microfacet roughness is raised to at
+    /// least `min(k * regularization, 1)`.
+    pub regularization: Option<Float>,
+}
 
 pub struct PathIntegrator {
     pub max_depth: u32,
     pub samples_per_pixel: u32,
+    pub firefly: FireflySettings,
+    regularized: RwLock<HashMap<(usize, u32), RegularizedBSDF>>,
+}
+
+/// Cached `BSDF::regularized` of a scene material. The original is kept
+/// alive so that its address, the cache key, cannot be reused.
+struct RegularizedBSDF {
+    _original: Arc<dyn BSDF>,
+    bsdf: Option<Arc<dyn BSDF>>,
 }
 
 impl PathIntegrator {
     pub fn new(max_depth: u32, samples_per_pixel: u32) -> Self {
-        Self { max_depth, samples_per_pixel }
+        Self { max_depth, samples_per_pixel, firefly: FireflySettings::default(), regularized: RwLock::new(HashMap::new()) }
+    }
+
+    pub fn with_firefly_settings(mut self, firefly: FireflySettings) -> Self {
+        self.firefly = firefly;
+        self
+    }
+
+    /// `material.regularized(min_alpha)`, built once per material and
+    /// roughness instead of at every path vertex.
+    fn regularized(&self, material: &Arc<dyn BSDF>, min_alpha: Float) -> Option<Arc<dyn BSDF>> {
+        let key = (Arc::as_ptr(material) as *const () as usize, min_alpha.to_bits());
+        if let Some(entry) = self.regularized.read().expect("regularized cache lock").get(&key) {
+            return entry.bsdf.clone();
+        }
+        let bsdf: Option<Arc<dyn BSDF>> = material.regularized(min_alpha).map(Arc::from);
+        let mut cache = self.regularized.write().expect("regularized cache lock");
+        let entry = cache.entry(key).or_insert(RegularizedBSDF { _original: Arc::clone(material), bsdf });
+        entry.bsdf.clone()
     }
 }
 
-impl Integrator for PathIntegrator {
-    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, rng: &mut LcgRng) -> RGBSpectrum {
+/// Diffuse vertex of a traced path, with the radiance collected before it
+/// scattered. Everything collected afterwards went through its BSDF.
+struct ScatterVertex {
+    object_index: usize,
+    uv: Vector2f,
+    color: Option<RGBSpectrum>,
+    reflectance: RGBSpectrum,
+    radiance_before: Vector3f,
+}
+
+impl PathIntegrator {
+    fn trace(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        mut vertices: Option<&mut Vec<ScatterVertex>>,
+        mut record: Option<&mut RecordedPath>,
+    ) -> RGBSpectrum {
         let (width, height) = {
             let bmp = sensor.bitmap();
             (bmp.width(), bmp.height())
@@ -34,12 +96,21 @@ impl Integrator for PathIntegrator {
 
         let px = pixel.x;
         let py = pixel.y;
-        let u = (px + rng.next_f32()) / (width as Float);
-        let v = (py + rng.next_f32()) / (height as Float);
-        let mut ray = sensor.sample_ray(&Vector2f::new(u, v));
+        let offset = sampler.get_pixel_2d();
+        let u = (px + offset.x) / (width as Float);
+        let v = (py + offset.y) / (height as Float);
+        let time = sensor.sample_time(sampler.get_1d());
+        let mut ray = sensor.sample_ray_at_time(&Vector2f::new(u, v), time);
+        if let Some(record) = record.as_deref_mut() {
+            record.origin = ray.origin();
+        }
         let mut radiance = Vector3f::zeros();
         let mut throughput = Vector3f::new(1.0, 1.0, 1.0);
         let mut prev_bsdf_pdf: Float = 0.0;
+        let mut non_specular_bounces: u32 = 0;
+        // Radiance gathered before the second vertex's light sample, i.e.
+        // from paths with at most one bounce.
+        let mut direct: Option<Vector3f> = None;
 
         for bounce in 0..self.max_depth {
             let intersection = match scene.ray_intersection(&ray) {
@@ -49,13 +120,39 @@ impl Integrator for PathIntegrator {
                     for emitter in scene.emitters() {
                         env += emitter.eval_direction(&ray.dir());
                     }
+                    let mut weight = 0.0;
+                    let mut emitted = Vector3f::zeros();
                     if env.is_black() == false {
-                        radiance += throughput.component_mul(&Vector3f::new(env[0], env[1], env[2]));
+                        // Next event estimation samples the environment too.
+                        weight = if bounce == 0 {
+                            1.0
+                        } else {
+                            power_heuristic(prev_bsdf_pdf, scene.pdf_environment(&ray.dir()))
+                        };
+                        emitted = throughput.component_mul(&Vector3f::new(env[0], env[1], env[2])) * weight;
+                        radiance += emitted;
+                    }
+                    if let Some(record) = record.as_deref_mut() {
+                        record.escaped = Some(EscapeRecord {
+                            direction: ray.dir(),
+                            throughput,
+                            environment_pdf: scene.pdf_environment(&ray.dir()),
+                            mis_weight: weight,
+                            emitted,
+                        });
                     }
                     break;
                 }
             };
 
+            if let Some(record) = record.as_deref_mut() {
+                let mut vertex = PathVertex::new(intersection.p(), intersection.geo_normal(), intersection.sh_normal(), throughput);
+                vertex.bsdf = intersection
+                    .material()
+                    .map(|m| (m.name().rsplit("::").next().unwrap_or_default().to_string(), m.id().to_string()));
+                record.vertices.push(vertex);
+            }
+
             let le = intersection.le();
             if le.is_black() == false {
                 let cos_light_hit = intersection.geo_normal().dot(&(-ray.dir()));
@@ -64,10 +161,21 @@ impl Integrator for PathIntegrator {
                     // Continue path, since the light might still be hittable by BSDF sampling.
                 } else
                 if bounce == 0 {
-                    radiance += throughput.component_mul(&Vector3f::new(le[0], le[1], le[2]));
+                    let emitted = throughput.component_mul(&Vector3f::new(le[0], le[1], le[2]));
+                    radiance += emitted;
+                    if let Some(vertex) = last_vertex(&mut record) {
+                        vertex.emitted = emitted;
+                        vertex.emission_mis_weight = 1.0;
+                    }
                 } else if let Some(light_pdf) = scene.pdf_light(&intersection, &ray.origin()) {
                     let weight = power_heuristic(prev_bsdf_pdf, light_pdf);
-                    radiance += throughput.component_mul(&Vector3f::new(le[0], le[1], le[2])) * weight;
+                    let emitted = throughput.component_mul(&Vector3f::new(le[0], le[1], le[2])) * weight;
+                    radiance += emitted;
+                    if let Some(vertex) = last_vertex(&mut record) {
+                        vertex.emitted = emitted;
+                        vertex.light_pdf = light_pdf;
+                        vertex.emission_mis_weight = weight;
+                    }
                 }
             }
 
@@ -75,20 +183,43 @@ impl Integrator for PathIntegrator {
             let n_sh = intersection.sh_normal();
 
             let wi_world = -ray.dir();
-            let (tangent, bitangent) = build_tangent_frame(&n_sh);
+            let (tangent, bitangent) = build_shading_frame(&n_sh, intersection.tangent());
             let wi_local = world_to_local(&wi_world, &tangent, &bitangent, &n_sh);
 
-            let material = match intersection.material() {
+            let material = match intersection.material_arc() {
                 Some(m) => m,
                 None => break,
             };
+            let is_specular = material.is_specular();
+            let regularized = match self.firefly.regularization {
+                Some(step) if non_specular_bounces > 0 => {
+                    self.regularized(material, (step * non_specular_bounces as Float).min(1.0))
+                }
+                _ => None,
+            };
+            let material: &dyn BSDF = regularized.as_deref().unwrap_or(material.as_ref());
+            if let (Some(vertices), Some(object_index)) = (vertices.as_deref_mut(), intersection.object_index()) {
+                if let Some(reflectance) = material.diffuse_reflectance(intersection.uv(), intersection.color()) {
+                    vertices.push(ScatterVertex {
+                        object_index,
+                        uv: intersection.uv(),
+                        color: intersection.color(),
+                        reflectance,
+                        radiance_before: radiance,
+                    });
+                }
+            }
+
 
+            if bounce == 1 {
+                direct = Some(radiance);
+            }
 
             // Next Event Estimation (direct lighting), skip if this is the last bounce
             if bounce + 1 < self.max_depth {
                 if let Some(light_sample) = scene.sample_emitter(
-                    rng.next_f32(),
-                    &Vector2f::new(rng.next_f32(), rng.next_f32()),
+                    sampler.get_1d(),
+                    &sampler.get_2d(),
                 ) {
                     match light_sample {
                         EmitterSample::Surface(light_sample) => {
@@ -110,7 +241,8 @@ impl Integrator for PathIntegrator {
                                             wo_world,
                                             Some(1e-3),
                                             None,
-                                        );
+                                        )
+                                        .with_time(time);
 
                                         let shadow_hit = scene.ray_intersection(&shadow_ray);
                                         let is_occluded = match shadow_hit {
@@ -129,6 +261,7 @@ impl Integrator for PathIntegrator {
                                             eval_record.wo = wo_local;
                                             eval_record.pdf = 0.0;
                                             eval_record.uv = intersection.uv();
+                                            eval_record.color = intersection.color();
                                             let eval = material.eval(eval_record);
                                             let f = Vector3f::new(eval.value[0], eval.value[1], eval.value[2]);
                                             let cos_theta = wo_local.z.abs();
@@ -142,7 +275,28 @@ impl Integrator for PathIntegrator {
                                                 let contrib = throughput.component_mul(&f) * (cos_theta / light_pdf) * weight;
                                                 let direct = contrib.component_mul(&le_vec);
                                                 radiance += direct;
+                                                if let Some(vertex) = last_vertex(&mut record) {
+                                                    vertex.nee = Some(NeeRecord {
+                                                        light_position: Some(p_light),
+                                                        direction: wo_world,
+                                                        light_pdf,
+                                                        bsdf_pdf,
+                                                        mis_weight: weight,
+                                                        occluded: false,
+                                                        contribution: direct,
+                                                    });
+                                                }
                                             }
+                                        } else if let Some(vertex) = last_vertex(&mut record) {
+                                            vertex.nee = Some(NeeRecord {
+                                                light_position: Some(p_light),
+                                                direction: wo_world,
+                                                light_pdf: light_sample.pdf() * dist2 / cos_light,
+                                                bsdf_pdf: 0.0,
+                                                mis_weight: 0.0,
+                                                occluded: true,
+                                                contribution: Vector3f::zeros(),
+                                            });
                                         }
                                     }
                                 }
@@ -157,14 +311,17 @@ impl Integrator for PathIntegrator {
                                         wo_world,
                                         Some(1e-3),
                                         None,
-                                    );
-                                if !scene.ray_intersection_t(&shadow_ray) {
+                                    )
+                                    .with_time(time);
+                                let occluded = scene.ray_intersection_t(&shadow_ray);
+                                if !occluded {
                                     let wo_local = world_to_local(&wo_world, &tangent, &bitangent, &n_sh);
                                     let mut eval_record = BSDFSampleRecord::default();
                                     eval_record.wi = wi_local;
                                     eval_record.wo = wo_local;
                                     eval_record.pdf = 0.0;
                                     eval_record.uv = intersection.uv();
+                                    eval_record.color = intersection.color();
                                     let eval = material.eval(eval_record);
                                     let f = Vector3f::new(eval.value[0], eval.value[1], eval.value[2]);
                                     let cos_theta = wo_local.z.abs();
@@ -173,8 +330,30 @@ impl Integrator for PathIntegrator {
                                         let weight = if is_delta { 1.0 } else { power_heuristic(pdf, bsdf_pdf) };
                                         let le_vec = Vector3f::new(irradiance[0], irradiance[1], irradiance[2]);
                                         let contrib = throughput.component_mul(&f) * (cos_theta / pdf) * weight;
-                                        radiance += contrib.component_mul(&le_vec);
+                                        let direct = contrib.component_mul(&le_vec);
+                                        radiance += direct;
+                                        if let Some(vertex) = last_vertex(&mut record) {
+                                            vertex.nee = Some(NeeRecord {
+                                                light_position: None,
+                                                direction: wo_world,
+                                                light_pdf: pdf,
+                                                bsdf_pdf,
+                                                mis_weight: weight,
+                                                occluded: false,
+                                                contribution: direct,
+                                            });
+                                        }
                                     }
+                                } else if let Some(vertex) = last_vertex(&mut record) {
+                                    vertex.nee = Some(NeeRecord {
+                                        light_position: None,
+                                        direction: wo_world,
+                                        light_pdf: pdf,
+                                        bsdf_pdf: 0.0,
+                                        mis_weight: 0.0,
+                                        occluded: true,
+                                        contribution: Vector3f::zeros(),
+                                    });
                                 }
                             }
                         }
@@ -182,14 +361,15 @@ impl Integrator for PathIntegrator {
                 }
             }
 
-            let u1 = Vector2f::new(rng.next_f32(), rng.next_f32());
-            let u2 = Vector2f::new(rng.next_f32(), rng.next_f32());
+            let u1 = sampler.get_2d();
+            let u2 = sampler.get_2d();
             let (next_ray, bsdf_weight, bsdf_pdf) = match compute_scatter_ray(
                 material,
                 u1,
                 u2,
                 wi_local,
                 intersection.uv(),
+                intersection.color(),
                 intersection.p(),
                 n_sh,
                 n_geo,
@@ -200,6 +380,11 @@ impl Integrator for PathIntegrator {
                 None => break,
             };
             throughput = throughput.component_mul(&bsdf_weight);
+            if let Some(vertex) = last_vertex(&mut record) {
+                vertex.sampled_direction = Some(next_ray.dir());
+                vertex.bsdf_pdf = bsdf_pdf;
+                vertex.bsdf_weight = bsdf_weight;
+            }
 
             if throughput.x <= 0.0 && throughput.y <= 0.0 && throughput.z <= 0.0 {
                 break;
@@ -209,28 +394,107 @@ impl Integrator for PathIntegrator {
             if bounce >= rr_depth {
                 let max_comp = throughput.x.max(throughput.y).max(throughput.z);
                 let survival = max_comp.min(0.95).max(0.05);
-                if rng.next_f32() > survival {
+                if let Some(vertex) = last_vertex(&mut record) {
+                    vertex.survival = survival;
+                }
+                if sampler.get_1d() > survival {
                     break;
                 }
                 throughput /= survival;
             }
 
+            if !is_specular {
+                non_specular_bounces += 1;
+            }
             prev_bsdf_pdf = bsdf_pdf;
-            ray = next_ray;
+            ray = next_ray.with_time(time);
         }
 
+        if self.firefly.clamp_direct.is_some() || self.firefly.clamp_indirect.is_some() {
+            let direct = direct.unwrap_or(radiance);
+            let indirect = radiance - direct;
+            radiance = clamp_radiance(direct, self.firefly.clamp_direct) + clamp_radiance(indirect, self.firefly.clamp_indirect);
+        }
         RGBSpectrum::new(radiance[0], radiance[1], radiance[2])
     }
+}
+
+impl Integrator for PathIntegrator {
+    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, sampler: &mut dyn Sampler) -> RGBSpectrum {
+        self.trace(scene, sensor, pixel, sampler, None, None)
+    }
+
+    fn trace_ray_recorded(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        path: &mut RecordedPath,
+    ) -> RGBSpectrum {
+        self.trace(scene, sensor, pixel, sampler, None, Some(path))
+    }
 
     fn samples_per_pixel(&self) -> u32 {
         self.samples_per_pixel
     }
 
+    fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
     fn describe(&self) -> String {
         String::from("PathIntegrator")
     }
 }
 
+// Path replay: the radiance a diffuse vertex scatters is everything
+// collected after it, and it is linear in the vertex's reflectance, so its
+// gradient is that radiance divided by the reflectance. Only the vertex
+// list has to be kept, not the whole path.
+impl DifferentiableIntegrator for PathIntegrator {
+    fn trace_ray_backward(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        adjoint: &RGBSpectrum,
+        grads: &mut GradientBuffers,
+    ) -> RGBSpectrum {
+        let mut vertices = Vec::new();
+        let radiance = self.trace(scene, sensor, pixel, sampler, Some(&mut vertices), None);
+        for vertex in &vertices {
+            let mut grad = RGBSpectrum::default();
+            for c in 0..3 {
+                let scattered = radiance[c] - vertex.radiance_before[c];
+                if vertex.reflectance[c] > 0.0 {
+                    grad[c] = adjoint[c] * scattered / vertex.reflectance[c];
+                }
+            }
+            if grad.is_black() {
+                continue;
+            }
+            let material = &scene.objects()[vertex.object_index].material;
+            material.backward_reflectance(vertex.uv, vertex.color, &grad, grads);
+        }
+        radiance
+    }
+}
+
+/// Scales `value` down so no channel exceeds `max`.
+fn clamp_radiance(value: Vector3f, max: Option<Float>) -> Vector3f {
+    let peak = value.x.max(value.y).max(value.z);
+    match max {
+        Some(max) if peak > max => value * (max / peak),
+        _ => value,
+    }
+}
+
+fn last_vertex<'a>(record: &'a mut Option<&mut RecordedPath>) -> Option<&'a mut PathVertex> {
+    record.as_deref_mut().and_then(|r| r.vertices.last_mut())
+}
+
 fn power_heuristic(pdf_a: Float, pdf_b: Float) -> Float {
     let a2 = pdf_a * pdf_a;
     let b2 = pdf_b * pdf_b;
@@ -241,12 +505,13 @@ fn power_heuristic(pdf_a: Float, pdf_b: Float) -> Float {
     }
 }
 
-fn compute_scatter_ray(
+pub(crate) fn compute_scatter_ray(
     material: &dyn crate::core::bsdf::BSDF,
     u1: Vector2f,
     u2: Vector2f,
     wi_local: Vector3f,
     uv: Vector2f,
+    color: Option<RGBSpectrum>,
     p: Vector3f,
     n_sh: Vector3f,
     n_geo: Vector3f,
@@ -255,6 +520,7 @@ fn compute_scatter_ray(
 ) -> Option<(Ray3f, Vector3f, Float)> {
     let mut sample = material.sample(u1, u2, wi_local);
     sample.uv = uv;
+    sample.color = color;
     let pdf = sample.pdf;
     if pdf <= 0.0 {
         return None;
@@ -271,3 +537,96 @@ fn compute_scatter_ray(
     let origin = p + offset_dir * 1e-6;
     Some((Ray3f::new(origin, wo_world, Some(1e-4), None), bsdf_weight, pdf))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::computation_node::ComputationNode;
+    use crate::core::test_scene::TestScene;
+    use crate::materials::blend::BlendBSDF;
+    use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+    use crate::materials::microfacet::MicrofacetType;
+    use crate::materials::roughconductor::RoughConductorBSDF;
+    use crate::samplers::independent::IndependentSampler;
+    use crate::textures::constant::ConstantTexture;
+    use std::sync::Arc;
+
+    // A diffuse floor under a small, very bright area light, which gives
+    // plenty of fireflies through the indirect bounces.
+    fn scene() -> Scene {
+        TestScene::default()
+            .with_albedo(RGBSpectrum::new(0.8, 0.8, 0.8))
+            .with_floor(4.0)
+            .with_light(0.1, 1.0, 400.0)
+            .with_camera(Vector3f::new(0.0, -4.0, 2.0), 0.6, 4)
+            .build()
+    }
+
+    fn render(integrator: &PathIntegrator, scene: &Scene, count: u32) -> Vec<RGBSpectrum> {
+        let sensor = scene.camera(0).unwrap();
+        let mut sampler = IndependentSampler::new(1, 11);
+        let mut samples = Vec::new();
+        for sample_index in 0..count {
+            for (x, y) in [(1, 1), (2, 2), (1, 3)] {
+                sampler.start_pixel_sample(x, y, sample_index);
+                samples.push(integrator.trace_ray_forward(scene, sensor, Vector2f::new(x as Float, y as Float), &mut sampler));
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn test_clamping_bounds_each_sample() {
+        let scene = scene();
+        let plain = render(&PathIntegrator::new(4, 1), &scene, 200);
+        let peak = |s: &RGBSpectrum| s[0].max(s[1]).max(s[2]);
+        assert!(plain.iter().any(|s| peak(s) > 3.0));
+
+        let firefly = FireflySettings { clamp_direct: Some(2.0), clamp_indirect: Some(1.0), regularization: None };
+        let clamped = render(&PathIntegrator::new(4, 1).with_firefly_settings(firefly), &scene, 200);
+        for (a, b) in plain.iter().zip(&clamped) {
+            assert!(peak(b) <= 3.0 + 1e-4, "{:?}", b);
+            assert!(peak(b) <= peak(a) + 1e-4, "{:?} vs {:?}", b, a);
+        }
+
+        // Thresholds no sample reaches leave the image untouched.
+        let loose = FireflySettings { clamp_direct: Some(1.0e9), clamp_indirect: Some(1.0e9), regularization: None };
+        assert_eq!(render(&PathIntegrator::new(4, 1).with_firefly_settings(loose), &scene, 20), render(&PathIntegrator::new(4, 1), &scene, 20));
+    }
+
+    #[test]
+    fn test_regularization_raises_microfacet_roughness() {
+        let conductor = Arc::new(RoughConductorBSDF::new(
+            MicrofacetType::GGX,
+            0.01,
+            0.3,
+            true,
+            RGBSpectrum::new(0.2, 0.9, 1.1),
+            RGBSpectrum::new(3.9, 2.4, 2.2),
+            RGBSpectrum::new(1.0, 1.0, 1.0),
+            None,
+        ));
+        assert!(conductor.regularized(0.005).is_none());
+        let rough = conductor.regularized(0.3).expect("alpha_u is below 0.3");
+        assert!(!rough.is_specular());
+        assert_eq!(rough.id(), conductor.id());
+
+        // The integrator builds each regularized copy once.
+        let integrator = PathIntegrator::new(4, 1);
+        let material: Arc<dyn BSDF> = conductor.clone();
+        let first = integrator.regularized(&material, 0.3).expect("alpha_u is below 0.3");
+        assert!(Arc::ptr_eq(&first, &integrator.regularized(&material, 0.3).unwrap()));
+        assert!(integrator.regularized(&material, 0.005).is_none());
+
+        let diffuse = Arc::new(LambertianDiffuseBSDF::new(Arc::new(ConstantTexture::new(RGBSpectrum::new(0.5, 0.5, 0.5))), None));
+        assert!(diffuse.regularized(1.0).is_none());
+        let blend = BlendBSDF::new(diffuse, conductor, 0.5, None);
+        assert!(blend.regularized(0.3).is_some());
+        assert!(blend.regularized(0.005).is_none());
+
+        // Without microfacet BSDFs in the scene there is nothing to roughen.
+        let scene = scene();
+        let firefly = FireflySettings { regularization: Some(0.2), ..FireflySettings::default() };
+        assert_eq!(render(&PathIntegrator::new(4, 1).with_firefly_settings(firefly), &scene, 20), render(&PathIntegrator::new(4, 1), &scene, 20));
+    }
+}
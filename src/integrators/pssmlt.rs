@@ -0,0 +1,200 @@
+// Copyright @yucwang 2026
+
+use crate::core::integrator::Integrator;
+use crate::core::rng::LcgRng;
+use crate::core::scene::Scene;
+use crate::core::sensor::Sensor;
+use crate::integrators::light_tracer::to_vector;
+use crate::integrators::path::PathIntegrator;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::samplers::mlt::MltSampler;
+use crate::samplers::scramble::hash;
+
+/// Parameters of `<integrator type="pssmlt">`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MltSettings {
+    /// Paths traced to estimate the image's brightness and to start the
+    /// chains from; 100000 when unset.
+    pub bootstrap_samples: Option<u32>,
+    /// Number of Markov chains; 1000 when unset.
+    pub chains: Option<u32>,
+    /// Probability that a mutation proposes an independent new path; 0.3
+    /// when unset.
+    pub large_step_probability: Option<Float>,
+    /// Standard deviation of small steps in primary sample space; 0.01 when
+    /// unset.
+    pub sigma: Option<Float>,
+}
+
+/// Primary sample space Metropolis light transport (Kelemen et al. 2002).
+/// `PathIntegrator` draws its random numbers from an `MltSampler`, so each
+/// path is a function of a point in [0, 1)^n, and Markov chains over those
+/// points visit paths in proportion to their luminance. Paths that are hard
+/// to find but close to each other, such as light through a door ajar, are
+/// explored locally once one is found.
+pub struct PssmltIntegrator {
+    path: PathIntegrator,
+    pub mutations_per_pixel: u32,
+    pub settings: MltSettings,
+}
+
+/// Luminance of the bootstrap paths, which chains pick their start from.
+pub struct Bootstrap {
+    seed: u64,
+    cdf: Vec<Float>,
+    /// Mean luminance of a path, i.e. of the image over the whole film.
+    pub brightness: Float,
+}
+
+impl Bootstrap {
+    /// `luminance[i]` is `PssmltIntegrator::bootstrap_luminance` of path
+    /// `i` for the same `seed`.
+    pub fn new(seed: u64, luminance: &[Float]) -> Self {
+        let mut sum = 0.0;
+        let cdf = luminance
+            .iter()
+            .map(|&l| {
+                sum += l;
+                sum
+            })
+            .collect();
+        Self { seed, cdf, brightness: sum / luminance.len().max(1) as Float }
+    }
+
+    /// Index of the bootstrap path with probability proportional to its
+    /// luminance.
+    fn sample(&self, u: Float) -> usize {
+        let total = self.cdf.last().copied().unwrap_or(0.0);
+        self.cdf.partition_point(|&c| c <= u * total).min(self.cdf.len().saturating_sub(1))
+    }
+}
+
+impl PssmltIntegrator {
+    pub fn new(path: PathIntegrator, mutations_per_pixel: u32) -> Self {
+        Self { path, mutations_per_pixel, settings: MltSettings::default() }
+    }
+
+    pub fn with_settings(mut self, settings: MltSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn bootstrap_samples(&self) -> u32 {
+        self.settings.bootstrap_samples.unwrap_or(100000).max(1)
+    }
+
+    pub fn chains(&self) -> u32 {
+        self.settings.chains.unwrap_or(1000).max(1)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "PssmltIntegrator [path={}, mutations_per_pixel={}, bootstrap_samples={}, chains={}, large_step_probability={}, sigma={}]",
+            self.path.describe(),
+            self.mutations_per_pixel,
+            self.bootstrap_samples(),
+            self.chains(),
+            self.large_step_probability(),
+            self.sigma()
+        )
+    }
+
+    fn large_step_probability(&self) -> Float {
+        self.settings.large_step_probability.unwrap_or(0.3)
+    }
+
+    fn sigma(&self) -> Float {
+        self.settings.sigma.unwrap_or(0.01)
+    }
+
+    /// Sampler whose initial state is bootstrap path `index`.
+    fn sampler(&self, sensor: &dyn Sensor, seed: u64, index: usize) -> MltSampler {
+        let bmp = sensor.bitmap();
+        MltSampler::new(bmp.width(), bmp.height(), hash(&[seed, 0, index as u64]))
+            .with_mutation(self.sigma(), self.large_step_probability())
+    }
+
+    /// Traces the path of the sampler's current state; returns its raster
+    /// position and radiance.
+    fn evaluate(&self, scene: &Scene, sensor: &dyn Sensor, sampler: &mut MltSampler) -> (Vector2f, Vector3f) {
+        let raster = sampler.raster_position();
+        let pixel = Vector2f::new(raster.x.floor(), raster.y.floor());
+        let radiance = to_vector(&self.path.trace_ray_forward(scene, sensor, pixel, sampler));
+        if radiance.iter().all(|c| c.is_finite() && *c >= 0.0) {
+            (raster, radiance)
+        } else {
+            (raster, Vector3f::zeros())
+        }
+    }
+
+    /// Luminance of bootstrap path `index`.
+    pub fn bootstrap_luminance(&self, scene: &Scene, sensor: &dyn Sensor, seed: u64, index: usize) -> Float {
+        let mut sampler = self.sampler(sensor, seed, index);
+        luminance(&self.evaluate(scene, sensor, &mut sampler).1)
+    }
+
+    /// Runs chain `chain` for `mutations` steps, handing every contribution
+    /// to `splat` as a raster position and a value. The image is the sum of
+    /// all chains' splats times `bootstrap.brightness / mutations_per_pixel`.
+    pub fn run_chain(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        bootstrap: &Bootstrap,
+        chain: usize,
+        mutations: u64,
+        splat: &mut dyn FnMut(Vector2f, Vector3f),
+    ) {
+        let seed = bootstrap.seed;
+        let mut rng = LcgRng::new(hash(&[seed, 1, chain as u64]));
+        let mut sampler = self.sampler(sensor, seed, bootstrap.sample(rng.next_f32()));
+        let (mut current_raster, mut current) = self.evaluate(scene, sensor, &mut sampler);
+        let mut current_luminance = luminance(&current);
+        if current_luminance <= 0.0 {
+            return;
+        }
+        sampler.reseed(hash(&[seed, 2, chain as u64]));
+
+        for _ in 0..mutations {
+            sampler.start_iteration();
+            let (raster, proposed) = self.evaluate(scene, sensor, &mut sampler);
+            let proposed_luminance = luminance(&proposed);
+            let accept = (proposed_luminance / current_luminance).min(1.0);
+            // Both states are splatted, weighted by their acceptance
+            // probability, which costs nothing and removes much of the
+            // noise of splatting only the chain's state.
+            if accept > 0.0 {
+                splat(raster, proposed * (accept / proposed_luminance));
+            }
+            splat(current_raster, current * ((1.0 - accept) / current_luminance));
+            if rng.next_f32() < accept {
+                current_raster = raster;
+                current = proposed;
+                current_luminance = proposed_luminance;
+                sampler.accept();
+            } else {
+                sampler.reject();
+            }
+        }
+    }
+}
+
+fn luminance(rgb: &Vector3f) -> Float {
+    0.212671 * rgb.x + 0.715160 * rgb.y + 0.072169 * rgb.z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bootstrap_picks_paths_by_luminance() {
+        let bootstrap = Bootstrap::new(0, &[0.0, 1.0, 0.0, 3.0]);
+        assert_eq!(bootstrap.brightness, 1.0);
+        let mut counts = [0; 4];
+        for i in 0..1000 {
+            counts[bootstrap.sample((i as Float + 0.5) / 1000.0)] += 1;
+        }
+        assert_eq!(counts, [0, 250, 0, 750]);
+    }
+}
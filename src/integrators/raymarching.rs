@@ -1,8 +1,9 @@
 // Copyright @yucwang 2026
 
-use crate::core::integrator::Integrator;
+use crate::core::gradient::GradientBuffers;
+use crate::core::integrator::{DifferentiableIntegrator, Integrator};
 use crate::core::medium::Medium;
-use crate::core::rng::LcgRng;
+use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
 use crate::core::sensor::Sensor;
 use crate::math::constants::{Float, Vector2f, Vector3f};
@@ -22,8 +23,34 @@ impl RaymarchingIntegrator {
     }
 }
 
-impl Integrator for RaymarchingIntegrator {
-    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, rng: &mut LcgRng) -> RGBSpectrum {
+/// One ray marching step through a medium segment.
+struct MarchStep {
+    p: Vector3f,
+    sigma: Float,
+    alpha: Float,
+    color: Vector3f,
+    transmittance: Vector3f,
+}
+
+/// Steps of the medium segment a camera ray crossed, and the radiance
+/// arriving from behind it.
+struct MarchRecord {
+    medium: Option<Arc<dyn Medium>>,
+    steps: Vec<MarchStep>,
+    dt: Float,
+    transmittance: Vector3f,
+    behind: Vector3f,
+}
+
+impl RaymarchingIntegrator {
+    fn trace(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        mut record: Option<&mut MarchRecord>,
+    ) -> RGBSpectrum {
         let (width, height) = {
             let bmp = sensor.bitmap();
             (bmp.width(), bmp.height())
@@ -32,9 +59,11 @@ impl Integrator for RaymarchingIntegrator {
             return RGBSpectrum::default();
         }
 
-        let u = (pixel.x + rng.next_f32()) / (width as Float);
-        let v = (pixel.y + rng.next_f32()) / (height as Float);
-        let ray = sensor.sample_ray(&Vector2f::new(u, v));
+        let offset = sampler.get_pixel_2d();
+        let u = (pixel.x + offset.x) / (width as Float);
+        let v = (pixel.y + offset.y) / (height as Float);
+        let time = sensor.sample_time(sampler.get_1d());
+        let ray = sensor.sample_ray_at_time(&Vector2f::new(u, v), time);
 
         // No global medium: only handle participating media bound to shapes.
         match scene.ray_intersection(&ray) {
@@ -47,26 +76,28 @@ impl Integrator for RaymarchingIntegrator {
 
                 if material.is_null() {
                     if let Some((medium, t_entry, t_exit)) = interior_medium_segment(scene, &ray, &hit) {
-                        let (vol_radiance, beta) = march_nerf_segment(
+                        let (vol_radiance, beta) = self.march_nerf_segment(
                             &ray,
                             t_entry,
                             t_exit,
                             medium.as_ref(),
-                            rng,
-                            self.max_depth,
-                            self.step_size,
+                            sampler,
+                            record.as_deref_mut(),
                         );
                         let mut color = vol_radiance;
 
                         // Continue tracing after the medium boundary.
                         let new_origin = ray.at(t_exit + 1e-4);
-                        let exit_ray = Ray3f::new(new_origin, ray.dir(), Some(0.0), None);
-                        if let Some(hit2) = scene.ray_intersection(&exit_ray) {
-                            let surf = shade_surface_basic(&hit2);
-                            color += beta.component_mul(&surf);
-                        } else {
-                            let env = env_radiance(scene, &exit_ray);
-                            color += beta.component_mul(&env);
+                        let exit_ray = Ray3f::new(new_origin, ray.dir(), Some(0.0), None).with_time(ray.time);
+                        let behind = match scene.ray_intersection(&exit_ray) {
+                            Some(hit2) => shade_surface_basic(&hit2),
+                            None => env_radiance(scene, &exit_ray),
+                        };
+                        color += beta.component_mul(&behind);
+                        if let Some(record) = record {
+                            record.medium = Some(medium);
+                            record.transmittance = beta;
+                            record.behind = behind;
                         }
 
                         return rgb_from_vec(color);
@@ -78,67 +109,130 @@ impl Integrator for RaymarchingIntegrator {
         }
     }
 
+    fn march_nerf_segment(
+        &self,
+        ray: &Ray3f,
+        t0: Float,
+        t1: Float,
+        medium: &dyn Medium,
+        sampler: &mut dyn Sampler,
+        mut record: Option<&mut MarchRecord>,
+    ) -> (Vector3f, Vector3f) {
+        let (max_depth, step_size) = (self.max_depth, self.step_size);
+        let max_steps = max_depth.max(1) as usize;
+        let length = t1 - t0;
+        if length <= 0.0 || max_steps == 0 {
+            return (Vector3f::zeros(), Vector3f::new(1.0, 1.0, 1.0));
+        }
+
+        let (dt, steps) = if let Some(step) = step_size {
+            if step > 0.0 {
+                let needed = (length / step).ceil() as usize;
+                let steps = needed.max(1).min(max_steps);
+                (step, steps)
+            } else {
+                (length / (max_steps as Float), max_steps)
+            }
+        } else {
+            (length / (max_steps as Float), max_steps)
+        };
+        if let Some(record) = record.as_deref_mut() {
+            record.dt = dt;
+        }
+        let mut t = t0;
+        let mut color = Vector3f::zeros();
+        let mut beta = Vector3f::new(1.0, 1.0, 1.0);
+
+        for _ in 0..steps {
+            let jitter = sampler.get_1d();
+            let t_sample = (t + jitter * dt).min(t1);
+            let p = ray.at(t_sample);
+
+            let (sigma, c) = field_eval(medium, p, ray.dir());
+            let alpha = 1.0 - (-sigma * dt).exp();
+            if let Some(record) = record.as_deref_mut() {
+                record.steps.push(MarchStep { p, sigma, alpha, color: c, transmittance: beta });
+            }
+            let w = beta * alpha;
+            color += w.component_mul(&c);
+            beta *= 1.0 - alpha;
+
+            if beta.x.max(beta.y).max(beta.z) < 1e-4 {
+                break;
+            }
+
+            t += dt;
+            if t >= t1 {
+                break;
+            }
+        }
+
+        (color, beta)
+    }
+}
+
+impl Integrator for RaymarchingIntegrator {
+    fn trace_ray_forward(&self, scene: &Scene, sensor: &dyn Sensor, pixel: Vector2f, sampler: &mut dyn Sampler) -> RGBSpectrum {
+        self.trace(scene, sensor, pixel, sampler, None)
+    }
+
     fn samples_per_pixel(&self) -> u32 {
         self.samples_per_pixel
     }
 
+    fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
     fn describe(&self) -> String {
         String::from("RaymarchingIntegrator")
     }
 }
 
-fn march_nerf_segment(
-    ray: &Ray3f,
-    t0: Float,
-    t1: Float,
-    medium: &dyn Medium,
-    rng: &mut LcgRng,
-    max_depth: u32,
-    step_size: Option<Float>,
-) -> (Vector3f, Vector3f) {
-    let max_steps = max_depth.max(1) as usize;
-    let length = t1 - t0;
-    if length <= 0.0 || max_steps == 0 {
-        return (Vector3f::zeros(), Vector3f::new(1.0, 1.0, 1.0));
-    }
-
-    let (dt, steps) = if let Some(step) = step_size {
-        if step > 0.0 {
-            let needed = (length / step).ceil() as usize;
-            let steps = needed.max(1).min(max_steps);
-            (step, steps)
-        } else {
-            (length / (max_steps as Float), max_steps)
-        }
-    } else {
-        (length / (max_steps as Float), max_steps)
-    };
-    let mut t = t0;
-    let mut color = Vector3f::zeros();
-    let mut beta = Vector3f::new(1.0, 1.0, 1.0);
-
-    for _ in 0..steps {
-        let jitter = rng.next_f32();
-        let t_sample = (t + jitter * dt).min(t1);
-        let p = ray.at(t_sample);
-
-        let (sigma, c) = field_eval(medium, p, ray.dir());
-        let alpha = 1.0 - (-sigma * dt).exp();
-        let w = beta * alpha;
-        color += w.component_mul(&c);
-        beta *= 1.0 - alpha;
-
-        if beta.x.max(beta.y).max(beta.z) < 1e-4 {
-            break;
-        }
+// Emission-absorption compositing: C = sum_k T_k a_k c_k + T_N L, with
+// a_k = 1 - exp(-sigma_k dt) and T_{k+1} = T_k (1 - a_k). Walking the steps
+// backwards, dC/dsigma_k = dt (T_{k+1} c_k - C_after_k), where C_after_k is
+// what the later steps and L contribute, and dC/dc_k = T_k a_k.
+impl DifferentiableIntegrator for RaymarchingIntegrator {
+    fn trace_ray_backward(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+        adjoint: &RGBSpectrum,
+        grads: &mut GradientBuffers,
+    ) -> RGBSpectrum {
+        let mut record = MarchRecord {
+            medium: None,
+            steps: Vec::new(),
+            dt: 0.0,
+            transmittance: Vector3f::zeros(),
+            behind: Vector3f::zeros(),
+        };
+        let radiance = self.trace(scene, sensor, pixel, sampler, Some(&mut record));
+        let medium = match &record.medium {
+            Some(medium) => medium,
+            None => return radiance,
+        };
 
-        t += dt;
-        if t >= t1 {
-            break;
+        let adjoint = Vector3f::new(adjoint[0], adjoint[1], adjoint[2]);
+        let mut after = record.transmittance.component_mul(&record.behind);
+        for step in record.steps.iter().rev() {
+            let emitted = step.transmittance * step.alpha;
+            if step.sigma > 0.0 {
+                let next = step.transmittance * (1.0 - step.alpha);
+                let d_sigma = adjoint.dot(&(next.component_mul(&step.color) - after)) * record.dt;
+                // sigma is the channel mean of sigma_t.
+                let g = d_sigma / 3.0;
+                medium.backward_sigma_t(step.p, &RGBSpectrum::new(g, g, g), grads);
+            }
+            let d_color = adjoint.component_mul(&emitted);
+            medium.backward_albedo(step.p, &rgb_from_vec(d_color), grads);
+            after += emitted.component_mul(&step.color);
         }
+        radiance
     }
-
-    (color, beta)
 }
 
 fn field_eval(medium: &dyn Medium, p: Vector3f, _dir: Vector3f) -> (Float, Vector3f) {
@@ -0,0 +1,351 @@
+// Copyright @yucwang 2026
+
+use crate::core::bsdf::BSDFSampleRecord;
+use crate::core::emitter::EmitterSample;
+use crate::core::interaction::SurfaceIntersection;
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::core::sensor::Sensor;
+use crate::core::tangent_frame::{build_shading_frame, world_to_local};
+use crate::integrators::light_tracer::{sample_particle, to_vector, ParticleVertex};
+use crate::integrators::path::compute_scatter_ray;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::math::ray::Ray3f;
+use std::collections::HashMap;
+
+/// Parameters of `<integrator type="sppm">`, named as in Mitsuba.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SppmSettings {
+    /// Photons traced per iteration; the sensor's pixel count when unset.
+    pub photon_count: Option<u32>,
+    /// Gather radius of the first iteration; 1/100 of the scene's bounding
+    /// box diagonal when unset.
+    pub initial_radius: Option<Float>,
+    /// Fraction of each iteration's photons kept in the running estimate;
+    /// smaller values shrink the radii faster. 0.7 when unset.
+    pub alpha: Option<Float>,
+}
+
+/// Stochastic progressive photon mapping. Every iteration follows one
+/// camera path per pixel through specular surfaces to a visible point, then
+/// traces photons from the emitters and gathers those landing within each
+/// visible point's radius. Radii shrink across iterations, so the estimate
+/// converges, and caustics seen through glass come out as well as direct
+/// ones.
+pub struct SppmIntegrator {
+    pub max_depth: u32,
+    pub iterations: u32,
+    pub settings: SppmSettings,
+}
+
+/// First non-specular vertex of a camera path, which gathers photons.
+pub struct VisiblePoint {
+    intersection: SurfaceIntersection,
+    tangent: Vector3f,
+    bitangent: Vector3f,
+    wi_local: Vector3f,
+    /// Camera path throughput up to this point.
+    pub throughput: Vector3f,
+}
+
+impl VisiblePoint {
+    pub fn position(&self) -> Vector3f {
+        self.intersection.p()
+    }
+
+    /// BSDF value for light arriving from direction `wo`.
+    fn eval(&self, wo: &Vector3f) -> (Vector3f, Float) {
+        let wo_local = world_to_local(wo, &self.tangent, &self.bitangent, &self.intersection.sh_normal());
+        let record = BSDFSampleRecord {
+            wi: self.wi_local,
+            wo: wo_local,
+            uv: self.intersection.uv(),
+            color: self.intersection.color(),
+            ..BSDFSampleRecord::default()
+        };
+        match self.intersection.material() {
+            Some(material) => (to_vector(&material.eval(record).value), wo_local.z.abs()),
+            None => (Vector3f::zeros(), 0.0),
+        }
+    }
+}
+
+impl SppmIntegrator {
+    pub fn new(max_depth: u32, iterations: u32) -> Self {
+        Self { max_depth, iterations, settings: SppmSettings::default() }
+    }
+
+    pub fn with_settings(mut self, settings: SppmSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn alpha(&self) -> Float {
+        self.settings.alpha.unwrap_or(0.7)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "SppmIntegrator [max_depth={}, iterations={}, photon_count={:?}, initial_radius={:?}, alpha={}]",
+            self.max_depth,
+            self.iterations,
+            self.settings.photon_count,
+            self.settings.initial_radius,
+            self.alpha()
+        )
+    }
+
+    /// Follows a camera ray through specular surfaces to the first rough
+    /// one. Returns the emission found on the way plus direct lighting at
+    /// the visible point, and the visible point itself.
+    pub fn trace_camera(
+        &self,
+        scene: &Scene,
+        sensor: &dyn Sensor,
+        pixel: Vector2f,
+        sampler: &mut dyn Sampler,
+    ) -> (Vector3f, Option<VisiblePoint>) {
+        let (width, height) = {
+            let bmp = sensor.bitmap();
+            (bmp.width() as Float, bmp.height() as Float)
+        };
+        let offset = sampler.get_pixel_2d();
+        let mut ray = sensor.sample_ray(&Vector2f::new((pixel.x + offset.x) / width, (pixel.y + offset.y) / height));
+        let mut radiance = Vector3f::zeros();
+        let mut throughput = Vector3f::new(1.0, 1.0, 1.0);
+
+        for depth in 0..self.max_depth {
+            let intersection = match scene.ray_intersection(&ray) {
+                Some(hit) => hit,
+                None => {
+                    for emitter in scene.emitters() {
+                        radiance += throughput.component_mul(&to_vector(&emitter.eval_direction(&ray.dir())));
+                    }
+                    break;
+                }
+            };
+            // Paths up to here are specular, so no light sample could have
+            // found this emitter.
+            if intersection.geo_normal().dot(&(-ray.dir())) > 0.0 {
+                radiance += throughput.component_mul(&to_vector(&intersection.le()));
+            }
+            let material = match intersection.material() {
+                Some(m) => m,
+                None => break,
+            };
+            let n_sh = intersection.sh_normal();
+            let (tangent, bitangent) = build_shading_frame(&n_sh, intersection.tangent());
+            let wi_local = world_to_local(&(-ray.dir()), &tangent, &bitangent, &n_sh);
+
+            if !material.is_specular() {
+                let point = VisiblePoint { intersection, tangent, bitangent, wi_local, throughput };
+                if depth + 1 < self.max_depth {
+                    radiance += self.direct_lighting(scene, &point, sampler);
+                }
+                return (radiance, Some(point));
+            }
+            if depth + 1 >= self.max_depth {
+                break;
+            }
+            let (next_ray, weight, _) = match compute_scatter_ray(
+                material,
+                sampler.get_2d(),
+                sampler.get_2d(),
+                wi_local,
+                intersection.uv(),
+                intersection.color(),
+                intersection.p(),
+                n_sh,
+                intersection.geo_normal(),
+                &tangent,
+                &bitangent,
+            ) {
+                Some(v) => v,
+                None => break,
+            };
+            throughput = throughput.component_mul(&weight);
+            ray = next_ray;
+        }
+        (radiance, None)
+    }
+
+    /// One light sample at `point`, weighted by its throughput.
+    fn direct_lighting(&self, scene: &Scene, point: &VisiblePoint, sampler: &mut dyn Sampler) -> Vector3f {
+        let light_sample = match scene.sample_emitter(sampler.get_1d(), &sampler.get_2d()) {
+            Some(sample) => sample,
+            None => return Vector3f::zeros(),
+        };
+        let p = point.position();
+        let n_geo = point.intersection.geo_normal();
+        let (direction, distance, le_over_pdf) = match light_sample {
+            EmitterSample::Surface(sample) => {
+                let light = sample.intersection();
+                let to_light = light.p() - p;
+                let distance = to_light.norm();
+                if distance <= 0.0 || sample.pdf() <= 0.0 {
+                    return Vector3f::zeros();
+                }
+                let direction = to_light / distance;
+                let cos_light = light.geo_normal().dot(&(-direction));
+                if cos_light <= 0.0 {
+                    return Vector3f::zeros();
+                }
+                (direction, Some(distance), to_vector(&light.le()) * (cos_light / (distance * distance * sample.pdf())))
+            }
+            EmitterSample::Direction { direction, irradiance, pdf, .. } => {
+                if pdf <= 0.0 {
+                    return Vector3f::zeros();
+                }
+                (direction.normalize(), None, to_vector(&irradiance) / pdf)
+            }
+        };
+        if le_over_pdf == Vector3f::zeros() {
+            return Vector3f::zeros();
+        }
+
+        let offset = if direction.dot(&n_geo) >= 0.0 { n_geo } else { -n_geo };
+        let shadow_ray = Ray3f::new(p + offset * 1e-4, direction, Some(1e-4), distance.map(|d| d * (1.0 - 1e-3)));
+        if scene.ray_intersection_t(&shadow_ray) {
+            return Vector3f::zeros();
+        }
+        let (f, cos) = point.eval(&direction);
+        point.throughput.component_mul(&f).component_mul(&le_over_pdf) * cos
+    }
+
+    /// Traces one photon and hands each (visible point index, flux times
+    /// BSDF) pair it lands on to `deposit`. Photons arriving straight from
+    /// an emitter are skipped, since `trace_camera` samples direct light.
+    pub fn trace_photon(
+        &self,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        grid: &PhotonGrid,
+        visible_points: &[Option<VisiblePoint>],
+        radii: &[Float],
+        deposit: &mut dyn FnMut(usize, Vector3f),
+    ) {
+        let particle = match sample_particle(scene, sampler) {
+            Some(particle) => particle,
+            None => return,
+        };
+        let mut ray = particle.ray;
+        let mut throughput = particle.throughput;
+        for depth in 0..self.max_depth {
+            let intersection = match scene.ray_intersection(&ray) {
+                Some(hit) => hit,
+                None => break,
+            };
+            let vertex = match ParticleVertex::new(&intersection, &ray) {
+                Some(vertex) => vertex,
+                None => break,
+            };
+            let is_specular = intersection.material().is_some_and(|m| m.is_specular());
+            if depth > 0 && !is_specular {
+                let p = intersection.p();
+                for &index in grid.candidates(&p) {
+                    let index = index as usize;
+                    if let Some(point) = &visible_points[index] {
+                        if (point.position() - p).norm_squared() <= radii[index] * radii[index] {
+                            deposit(index, throughput.component_mul(&point.eval(&(-ray.dir())).0));
+                        }
+                    }
+                }
+            }
+
+            if depth + 1 >= self.max_depth {
+                break;
+            }
+            let (next_ray, weight) = match vertex.sample(sampler) {
+                Some(v) => v,
+                None => break,
+            };
+            throughput = throughput.component_mul(&weight);
+            if throughput.x <= 0.0 && throughput.y <= 0.0 && throughput.z <= 0.0 {
+                break;
+            }
+
+            let rr_depth: u32 = 5;
+            if depth >= rr_depth {
+                let survival = throughput.x.max(throughput.y).max(throughput.z).clamp(0.05, 0.95);
+                if sampler.get_1d() > survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = next_ray;
+        }
+    }
+}
+
+/// Uniform hash grid over visible points. Each point is listed in every
+/// cell its gather sphere overlaps, so a photon only checks its own cell.
+pub struct PhotonGrid {
+    cell_size: Float,
+    cells: HashMap<[i32; 3], Vec<u32>>,
+}
+
+impl PhotonGrid {
+    /// Grid over `points`, where each entry is a position and a radius.
+    /// Cells are as large as the largest radius.
+    pub fn build(points: &[Option<(Vector3f, Float)>]) -> Self {
+        let cell_size = points.iter().flatten().fold(0.0 as Float, |max, &(_, r)| max.max(r)).max(1e-6);
+        let mut grid = Self { cell_size, cells: HashMap::new() };
+        for (index, &(p, r)) in points.iter().enumerate().filter_map(|(i, point)| point.as_ref().map(|point| (i, point))) {
+            let lo = grid.cell(&(p - Vector3f::new(r, r, r)));
+            let hi = grid.cell(&(p + Vector3f::new(r, r, r)));
+            for x in lo[0]..=hi[0] {
+                for y in lo[1]..=hi[1] {
+                    for z in lo[2]..=hi[2] {
+                        grid.cells.entry([x, y, z]).or_default().push(index as u32);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell(&self, p: &Vector3f) -> [i32; 3] {
+        [
+            (p.x / self.cell_size).floor() as i32,
+            (p.y / self.cell_size).floor() as i32,
+            (p.z / self.cell_size).floor() as i32,
+        ]
+    }
+
+    /// Indices of the points whose gather sphere may contain `p`.
+    pub fn candidates(&self, p: &Vector3f) -> &[u32] {
+        self.cells.get(&self.cell(p)).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_finds_every_point_in_range() {
+        let points: Vec<Option<(Vector3f, Float)>> = (0..200)
+            .map(|i| {
+                let t = i as Float * 0.37;
+                if i % 7 == 0 {
+                    None
+                } else {
+                    Some((Vector3f::new(t.sin() * 3.0, (t * 1.3).cos() * 2.0, t * 0.05 - 4.0), 0.1 + 0.2 * (i % 3) as Float))
+                }
+            })
+            .collect();
+        let grid = PhotonGrid::build(&points);
+        for j in 0..500 {
+            let s = j as Float * 0.91;
+            let p = Vector3f::new((s * 0.7).sin() * 3.0, (s * 0.9).cos() * 2.0, s * 0.0092 - 4.0);
+            let candidates = grid.candidates(&p);
+            for (index, point) in points.iter().enumerate() {
+                if let Some((q, r)) = point {
+                    if (q - p).norm() <= *r {
+                        assert!(candidates.contains(&(index as u32)), "point {} missing at {:?}", index, p);
+                    }
+                }
+            }
+        }
+    }
+}
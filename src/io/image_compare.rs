@@ -0,0 +1,555 @@
+// Copyright @yucwang 2026
+
+use crate::io::image_io::{srgb_to_linear, Image};
+use crate::io::tonemap::TransferFunction;
+use crate::math::constants::{Float, Matrix3f, Vector3f};
+
+use std::fmt::Write;
+
+// relMSE and SMAPE denominators are offset by this to stay finite on black.
+const EPSILON: Float = 1e-2;
+
+/// Per-pixel error measures that `ErrorMaps` keeps and can render as
+/// heatmaps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorMetric {
+    Mse,
+    RelMse,
+    Smape,
+    /// Shown as `1 - SSIM`.
+    Ssim,
+    Flip,
+}
+
+impl ErrorMetric {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "mse" => Some(ErrorMetric::Mse),
+            "relmse" => Some(ErrorMetric::RelMse),
+            "smape" => Some(ErrorMetric::Smape),
+            "ssim" => Some(ErrorMetric::Ssim),
+            "flip" => Some(ErrorMetric::Flip),
+            _ => None,
+        }
+    }
+}
+
+/// Pixel rectangle `[x0, x1) x [y0, y1)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl Region {
+    pub fn full(width: usize, height: usize) -> Self {
+        Self { x0: 0, y0: 0, x1: width, y1: height }
+    }
+
+    /// Parses `x0,y0,x1,y1`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let values = text
+            .split(',')
+            .map(|v| v.trim().parse::<usize>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        match values[..] {
+            [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Some(Self { x0, y0, x1, y1 }),
+            _ => None,
+        }
+    }
+
+    fn clamped(&self, width: usize, height: usize) -> Self {
+        Self {
+            x0: self.x0.min(width),
+            y0: self.y0.min(height),
+            x1: self.x1.min(width),
+            y1: self.y1.min(height),
+        }
+    }
+}
+
+/// Summary of how a test image differs from a reference over a region.
+/// Means are taken over finite pixels only; PSNR uses a peak of 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageMetrics {
+    pub region: Region,
+    pub pixel_count: usize,
+    /// Pixels where either image is NaN or infinite.
+    pub nan_count: usize,
+    pub inf_count: usize,
+    pub mse: f64,
+    pub rel_mse: f64,
+    pub smape: f64,
+    pub psnr: f64,
+    pub ssim: f64,
+    pub flip: f64,
+    pub mean_abs: [f64; 3],
+    pub max_abs: [f64; 3],
+    pub mean_luminance_test: f64,
+    pub mean_luminance_reference: f64,
+}
+
+impl ImageMetrics {
+    /// One JSON object, with `null` for undefined values.
+    pub fn to_json(&self) -> String {
+        let r = &self.region;
+        let mut out = String::from("{");
+        let _ = write!(out, "\"region\": [{}, {}, {}, {}], ", r.x0, r.y0, r.x1, r.y1);
+        let _ = write!(out, "\"pixels\": {}, \"nan\": {}, \"inf\": {}, ", self.pixel_count, self.nan_count, self.inf_count);
+        for (name, value) in [
+            ("mse", self.mse),
+            ("relmse", self.rel_mse),
+            ("smape", self.smape),
+            ("psnr", self.psnr),
+            ("ssim", self.ssim),
+            ("flip", self.flip),
+            ("mean_luminance_test", self.mean_luminance_test),
+            ("mean_luminance_reference", self.mean_luminance_reference),
+        ] {
+            let _ = write!(out, "\"{}\": {}, ", name, json_number(value));
+        }
+        let _ = write!(out, "\"mean_abs\": [{}], ", json_numbers(&self.mean_abs));
+        let _ = write!(out, "\"max_abs\": [{}]}}", json_numbers(&self.max_abs));
+        out
+    }
+}
+
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_numbers(values: &[f64]) -> String {
+    values.iter().map(|v| json_number(*v)).collect::<Vec<_>>().join(", ")
+}
+
+/// Settings for `ErrorMaps::new`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompareSettings {
+    /// Pixels per degree of visual angle used by FLIP. The default matches
+    /// a 0.7 m viewing distance to a 0.7 m wide 4K monitor.
+    pub pixels_per_degree: Float,
+}
+
+impl Default for CompareSettings {
+    fn default() -> Self {
+        Self { pixels_per_degree: 67.0 }
+    }
+}
+
+/// Per-pixel errors of a test image against a reference, computed once so
+/// that any number of regions can be summarized.
+pub struct ErrorMaps {
+    pub width: usize,
+    pub height: usize,
+    test: Vec<Vector3f>,
+    reference: Vec<Vector3f>,
+    ssim: Vec<Float>,
+    flip: Vec<Float>,
+}
+
+impl ErrorMaps {
+    pub fn new(test: &Image, reference: &Image, settings: &CompareSettings) -> Result<Self, String> {
+        if test.width != reference.width || test.height != reference.height {
+            return Err(format!(
+                "size mismatch: {}x{} vs {}x{}",
+                test.width, test.height, reference.width, reference.height
+            ));
+        }
+        let (width, height) = (test.width, test.height);
+        let pixels = |image: &Image| -> Vec<Vector3f> {
+            (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| image.rgb_at(x, y)).collect()
+        };
+        let test = pixels(test);
+        let reference = pixels(reference);
+        let ssim = ssim_map(&test, &reference, width, height);
+        let flip = flip_map(&test, &reference, width, height, settings.pixels_per_degree);
+        Ok(Self { width, height, test, reference, ssim, flip })
+    }
+
+    /// Per-pixel error, larger is worse. Non-finite pixels are NaN.
+    pub fn error(&self, metric: ErrorMetric) -> Vec<Float> {
+        (0..self.test.len())
+            .map(|i| {
+                let (a, b) = (self.test[i], self.reference[i]);
+                if !is_finite(&a) || !is_finite(&b) {
+                    return Float::NAN;
+                }
+                match metric {
+                    ErrorMetric::Mse => (a - b).norm_squared() / 3.0,
+                    ErrorMetric::RelMse => (0..3).map(|c| (a[c] - b[c]).powi(2) / (b[c] * b[c] + EPSILON)).sum::<Float>() / 3.0,
+                    ErrorMetric::Smape => (0..3).map(|c| (a[c] - b[c]).abs() / (a[c].abs() + b[c].abs() + EPSILON)).sum::<Float>() / 3.0,
+                    ErrorMetric::Ssim => 1.0 - self.ssim[i],
+                    ErrorMetric::Flip => self.flip[i],
+                }
+            })
+            .collect()
+    }
+
+    pub fn metrics(&self, region: Region) -> ImageMetrics {
+        let region = region.clamped(self.width, self.height);
+        let mut count = 0usize;
+        let (mut nan_count, mut inf_count) = (0usize, 0usize);
+        let (mut mse, mut rel_mse, mut smape, mut ssim, mut flip) = (0.0f64, 0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        let mut mean_abs = [0.0f64; 3];
+        let mut max_abs = [0.0f64; 3];
+        let (mut luminance_test, mut luminance_reference) = (0.0f64, 0.0f64);
+        for y in region.y0..region.y1 {
+            for x in region.x0..region.x1 {
+                let i = y * self.width + x;
+                let (a, b) = (self.test[i], self.reference[i]);
+                if !is_finite(&a) || !is_finite(&b) {
+                    if a.iter().chain(b.iter()).any(|v| v.is_nan()) {
+                        nan_count += 1;
+                    } else {
+                        inf_count += 1;
+                    }
+                    continue;
+                }
+                count += 1;
+                for c in 0..3 {
+                    let (a, b) = (a[c] as f64, b[c] as f64);
+                    let diff = (a - b).abs();
+                    mse += diff * diff / 3.0;
+                    rel_mse += diff * diff / (b * b + EPSILON as f64) / 3.0;
+                    smape += diff / (a.abs() + b.abs() + EPSILON as f64) / 3.0;
+                    mean_abs[c] += diff;
+                    max_abs[c] = max_abs[c].max(diff);
+                }
+                ssim += self.ssim[i] as f64;
+                flip += self.flip[i] as f64;
+                luminance_test += luminance(&a) as f64;
+                luminance_reference += luminance(&b) as f64;
+            }
+        }
+        let n = count as f64;
+        let mse = mse / n;
+        ImageMetrics {
+            region,
+            pixel_count: (region.x1 - region.x0) * (region.y1 - region.y0),
+            nan_count,
+            inf_count,
+            mse,
+            rel_mse: rel_mse / n,
+            smape: smape / n,
+            psnr: -10.0 * mse.log10(),
+            ssim: ssim / n,
+            flip: flip / n,
+            mean_abs: mean_abs.map(|v| v / n),
+            max_abs,
+            mean_luminance_test: luminance_test / n,
+            mean_luminance_reference: luminance_reference / n,
+        }
+    }
+}
+
+/// Metrics of `test` against `reference` over the whole image.
+pub fn compare(test: &Image, reference: &Image) -> Result<ImageMetrics, String> {
+    let maps = ErrorMaps::new(test, reference, &CompareSettings::default())?;
+    Ok(maps.metrics(Region::full(maps.width, maps.height)))
+}
+
+fn is_finite(v: &Vector3f) -> bool {
+    v.iter().all(|c| c.is_finite())
+}
+
+fn luminance(rgb: &Vector3f) -> Float {
+    0.2126 * rgb.x + 0.7152 * rgb.y + 0.0722 * rgb.z
+}
+
+/// Colors `values` with the magma colormap, mapping 0 to black and `max`
+/// to pale yellow. Non-finite values are drawn green. The result is
+/// linear RGB, so it can be written to EXR or, unchanged, to PNG.
+pub fn heatmap(values: &[Float], width: usize, height: usize, max: Float) -> Image {
+    const MAGMA: [u32; 9] = [0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55064, 0xfb8761, 0xfec287, 0xfcfdbf];
+    let color = |hex: u32| Vector3f::new((hex >> 16) as Float, ((hex >> 8) & 0xff) as Float, (hex & 0xff) as Float) / 255.0;
+    let mut image = Image::rgb(width, height);
+    for (i, value) in values.iter().enumerate().take(width * height) {
+        let encoded = if value.is_finite() {
+            let t = (value / max.max(Float::MIN_POSITIVE)).clamp(0.0, 1.0) * (MAGMA.len() - 1) as Float;
+            let k = (t.floor() as usize).min(MAGMA.len() - 2);
+            color(MAGMA[k]).lerp(&color(MAGMA[k + 1]), t - k as Float)
+        } else {
+            Vector3f::new(0.0, 1.0, 0.0)
+        };
+        image.pixel_mut(i % width, i / width).copy_from_slice(&encoded.map(srgb_to_linear).as_slice()[..3]);
+    }
+    image
+}
+
+// Separable filter with clamp-to-edge borders.
+fn convolve(data: &[Float], width: usize, height: usize, kernel_x: &[Float], kernel_y: &[Float]) -> Vec<Float> {
+    let pass = |input: &[Float], kernel: &[Float], horizontal: bool| -> Vec<Float> {
+        let radius = (kernel.len() / 2) as isize;
+        let mut out = vec![0.0; input.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0.0;
+                for (k, w) in kernel.iter().enumerate() {
+                    let offset = k as isize - radius;
+                    let (sx, sy) = if horizontal {
+                        ((x as isize + offset).clamp(0, width as isize - 1) as usize, y)
+                    } else {
+                        (x, (y as isize + offset).clamp(0, height as isize - 1) as usize)
+                    };
+                    sum += w * input[sy * width + sx];
+                }
+                out[y * width + x] = sum;
+            }
+        }
+        out
+    };
+    pass(&pass(data, kernel_x, true), kernel_y, false)
+}
+
+fn gaussian_kernel(radius: usize, sigma: Float) -> Vec<Float> {
+    let kernel: Vec<Float> = (0..=2 * radius)
+        .map(|i| {
+            let x = i as Float - radius as Float;
+            (-x * x / (2.0 * sigma * sigma)).exp()
+        })
+        .collect();
+    let sum: Float = kernel.iter().sum();
+    kernel.into_iter().map(|w| w / sum).collect()
+}
+
+// SSIM (Wang et al. 2004) of the sRGB-encoded luminance, with the usual
+// 11x11 Gaussian window of standard deviation 1.5.
+fn ssim_map(test: &[Vector3f], reference: &[Vector3f], width: usize, height: usize) -> Vec<Float> {
+    const C1: Float = 0.01 * 0.01;
+    const C2: Float = 0.03 * 0.03;
+    let encode = |pixels: &[Vector3f]| -> Vec<Float> {
+        pixels
+            .iter()
+            .map(|p| {
+                let p = p.map(|v| if v.is_finite() { v.clamp(0.0, 1.0) } else { 0.0 });
+                TransferFunction::Srgb.encode(luminance(&p))
+            })
+            .collect()
+    };
+    let a = encode(test);
+    let b = encode(reference);
+    let kernel = gaussian_kernel(5, 1.5);
+    let blur = |data: &[Float]| convolve(data, width, height, &kernel, &kernel);
+    let product = |u: &[Float], v: &[Float]| -> Vec<Float> { u.iter().zip(v).map(|(x, y)| x * y).collect() };
+    let mu_a = blur(&a);
+    let mu_b = blur(&b);
+    let aa = blur(&product(&a, &a));
+    let bb = blur(&product(&b, &b));
+    let ab = blur(&product(&a, &b));
+    (0..a.len())
+        .map(|i| {
+            let (ma, mb) = (mu_a[i], mu_b[i]);
+            let var_a = (aa[i] - ma * ma).max(0.0);
+            let var_b = (bb[i] - mb * mb).max(0.0);
+            let cov = ab[i] - ma * mb;
+            ((2.0 * ma * mb + C1) * (2.0 * cov + C2)) / ((ma * ma + mb * mb + C1) * (var_a + var_b + C2))
+        })
+        .collect()
+}
+
+fn rgb_to_xyz() -> Matrix3f {
+    Matrix3f::new(
+        0.4124564, 0.3575761, 0.1804375,
+        0.2126729, 0.7151522, 0.072175,
+        0.0193339, 0.119192, 0.9503041,
+    )
+}
+
+fn cielab(rgb: &Vector3f, to_xyz: &Matrix3f, white: &Vector3f) -> Vector3f {
+    let xyz = (to_xyz * rgb).component_div(white);
+    let f = |t: Float| {
+        const DELTA: Float = 6.0 / 29.0;
+        if t > DELTA * DELTA * DELTA {
+            t.cbrt()
+        } else {
+            t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+        }
+    };
+    let (fx, fy, fz) = (f(xyz.x), f(xyz.y), f(xyz.z));
+    Vector3f::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+// Hunt-adjusted Lab, then the HyAB distance.
+fn hyab(lab_a: &Vector3f, lab_b: &Vector3f) -> Float {
+    let hunt = |lab: &Vector3f| Vector3f::new(lab.x, 0.01 * lab.x * lab.y, 0.01 * lab.x * lab.z);
+    let (a, b) = (hunt(lab_a), hunt(lab_b));
+    (a.x - b.x).abs() + ((a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+// LDR-FLIP (Andersson et al. 2020, "FLIP: A Difference Evaluator for
+// Alternating Images") of the images clamped to [0, 1].
+fn flip_map(test: &[Vector3f], reference: &[Vector3f], width: usize, height: usize, ppd: Float) -> Vec<Float> {
+    use std::f32::consts::PI;
+    const QC: Float = 0.7;
+    const QF: Float = 0.5;
+    const PC: Float = 0.4;
+    const PT: Float = 0.95;
+
+    let to_xyz = rgb_to_xyz();
+    let to_rgb = to_xyz.try_inverse().expect("invertible color matrix");
+    let white = to_xyz * Vector3f::new(1.0, 1.0, 1.0);
+    let clamp = |p: &Vector3f| p.map(|v| if v.is_finite() { v.clamp(0.0, 1.0) } else { 0.0 });
+    // Linearized CIELAB: Y, Cx and Cz planes.
+    let ycxcz = |pixels: &[Vector3f]| -> [Vec<Float>; 3] {
+        let mut planes = [vec![0.0; pixels.len()], vec![0.0; pixels.len()], vec![0.0; pixels.len()]];
+        for (i, p) in pixels.iter().enumerate() {
+            let xyz = (to_xyz * clamp(p)).component_div(&white);
+            planes[0][i] = 116.0 * xyz.y - 16.0;
+            planes[1][i] = 500.0 * (xyz.x - xyz.y);
+            planes[2][i] = 200.0 * (xyz.y - xyz.z);
+        }
+        planes
+    };
+
+    // Contrast sensitivity filters per opponent channel, as (a1, b1, a2, b2).
+    let csf: [(Float, Float, Float, Float); 3] =
+        [(1.0, 0.0047, 0.0, 1e-5), (1.0, 0.0053, 0.0, 1e-5), (34.1, 0.04, 13.5, 0.025)];
+    let radius = (3.0 * (0.04 / (2.0 * PI * PI)).sqrt() * ppd).ceil() as usize;
+    let csf_gaussian = |b: Float| -> (Vec<Float>, Float) {
+        let g: Vec<Float> = (0..=2 * radius)
+            .map(|i| {
+                let x = (i as Float - radius as Float) / ppd;
+                (-PI * PI * x * x / b).exp()
+            })
+            .collect();
+        let sum: Float = g.iter().sum();
+        (g.iter().map(|w| w / sum).collect(), sum)
+    };
+    let spatial_filter = |planes: [Vec<Float>; 3]| -> Vec<Vector3f> {
+        let mut filtered = Vec::with_capacity(3);
+        for (plane, &(a1, b1, a2, b2)) in planes.iter().zip(csf.iter()) {
+            let (k1, s1) = csf_gaussian(b1);
+            // Weights of the two Gaussians in the normalized 2D kernel.
+            let w1 = a1 * (PI / b1).sqrt() * s1 * s1;
+            let mut out = convolve(plane, width, height, &k1, &k1);
+            if a2 > 0.0 {
+                let (k2, s2) = csf_gaussian(b2);
+                let w2 = a2 * (PI / b2).sqrt() * s2 * s2;
+                let second = convolve(plane, width, height, &k2, &k2);
+                for (o, s) in out.iter_mut().zip(second) {
+                    *o = (w1 * *o + w2 * s) / (w1 + w2);
+                }
+            }
+            filtered.push(out);
+        }
+        (0..width * height)
+            .map(|i| {
+                let yy = (filtered[0][i] + 16.0) / 116.0;
+                let xyz = Vector3f::new(filtered[1][i] / 500.0 + yy, yy, yy - filtered[2][i] / 200.0).component_mul(&white);
+                cielab(&clamp(&(to_rgb * xyz)), &to_xyz, &white)
+            })
+            .collect()
+    };
+    let lab_test = spatial_filter(ycxcz(test));
+    let lab_reference = spatial_filter(ycxcz(reference));
+
+    let green = cielab(&Vector3f::new(0.0, 1.0, 0.0), &to_xyz, &white);
+    let blue = cielab(&Vector3f::new(0.0, 0.0, 1.0), &to_xyz, &white);
+    let cmax = hyab(&green, &blue).powf(QC);
+    let pccmax = PC * cmax;
+
+    // Edge and point detectors: first and second derivatives of a Gaussian,
+    // each lobe normalized to unit weight.
+    let sd = 0.5 * 0.082 * ppd;
+    let feature_radius = (3.0 * sd).ceil() as usize;
+    let gaussian = gaussian_kernel(feature_radius, sd);
+    let normalize_lobes = |kernel: Vec<Float>| -> Vec<Float> {
+        let positive: Float = kernel.iter().filter(|w| **w > 0.0).sum();
+        let negative: Float = -kernel.iter().filter(|w| **w < 0.0).sum::<Float>();
+        kernel.into_iter().map(|w| if w > 0.0 { w / positive } else { w / negative }).collect()
+    };
+    let offsets = (0..=2 * feature_radius).map(|i| i as Float - feature_radius as Float);
+    let edge = normalize_lobes(offsets.clone().zip(&gaussian).map(|(x, g)| -x * g).collect());
+    let point = normalize_lobes(offsets.zip(&gaussian).map(|(x, g)| (x * x / (sd * sd) - 1.0) * g).collect());
+    let features = |pixels: &[Vector3f]| -> (Vec<Float>, Vec<Float>) {
+        let y: Vec<Float> = pixels.iter().map(|p| (to_xyz * clamp(p)).y / white.y).collect();
+        let magnitude = |kernel: &[Float]| -> Vec<Float> {
+            let dx = convolve(&y, width, height, kernel, &gaussian);
+            let dy = convolve(&y, width, height, &gaussian, kernel);
+            dx.iter().zip(&dy).map(|(x, y)| (x * x + y * y).sqrt()).collect()
+        };
+        (magnitude(&edge), magnitude(&point))
+    };
+    let (edge_test, point_test) = features(test);
+    let (edge_reference, point_reference) = features(reference);
+
+    (0..width * height)
+        .map(|i| {
+            let mut color = hyab(&lab_test[i], &lab_reference[i]).powf(QC);
+            color = if color < pccmax {
+                color * PT / pccmax
+            } else {
+                PT + (color - pccmax) / (cmax - pccmax) * (1.0 - PT)
+            };
+            let feature = (edge_test[i] - edge_reference[i]).abs().max((point_test[i] - point_reference[i]).abs());
+            let feature = (feature / Float::sqrt(2.0)).powf(QF);
+            color.powf(1.0 - feature)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient_image(width: usize, height: usize, scale: Float) -> Image {
+        let mut image = Image::rgb(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let v = scale * (x + y) as Float / (width + height) as Float;
+                image.pixel_mut(x, y).copy_from_slice(&[v, 0.5 * v, 0.25]);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_identical_images_have_no_error() {
+        let image = gradient_image(24, 16, 1.0);
+        let metrics = compare(&image, &image).unwrap();
+        assert_eq!(metrics.mse, 0.0);
+        assert_eq!(metrics.rel_mse, 0.0);
+        assert_eq!(metrics.smape, 0.0);
+        assert!(metrics.psnr.is_infinite());
+        assert!((metrics.ssim - 1.0).abs() < 1e-4, "{}", metrics.ssim);
+        assert!(metrics.flip < 1e-4, "{}", metrics.flip);
+        assert!(metrics.to_json().contains("\"psnr\": null"));
+    }
+
+    #[test]
+    fn test_metrics_grow_with_error_and_respect_regions() {
+        let reference = gradient_image(32, 32, 1.0);
+        let mut test = reference.clone();
+        // A bright square in the top-left corner and a NaN far from it.
+        for y in 0..8 {
+            for x in 0..8 {
+                test.pixel_mut(x, y).copy_from_slice(&[1.0, 1.0, 1.0]);
+            }
+        }
+        test.pixel_mut(31, 31)[0] = Float::NAN;
+        let maps = ErrorMaps::new(&test, &reference, &CompareSettings::default()).unwrap();
+
+        let full = maps.metrics(Region::full(32, 32));
+        assert_eq!(full.nan_count, 1);
+        assert!(full.mse > 0.0 && full.psnr.is_finite());
+        assert!(full.ssim < 0.99 && full.flip > 0.0);
+
+        let corner = maps.metrics(Region::parse("0,0,8,8").unwrap());
+        let far = maps.metrics(Region::parse("16,0,32,8").unwrap());
+        assert!(corner.flip > 0.3, "{}", corner.flip);
+        assert!(corner.mse > 10.0 * full.mse / 16.0);
+        assert_eq!(far.mse, 0.0);
+        assert!(far.flip < corner.flip * 0.1);
+        assert!(Region::parse("4,4,2,8").is_none());
+
+        let map = heatmap(&maps.error(ErrorMetric::Flip), 32, 32, 1.0);
+        assert!(map.rgb_at(2, 2).norm() > map.rgb_at(24, 4).norm());
+        assert_eq!(map.rgb_at(31, 31), Vector3f::new(0.0, 1.0, 0.0));
+    }
+}
@@ -0,0 +1,650 @@
+// Copyright @yucwang 2026
+
+use crate::math::bitmap::Bitmap;
+use crate::math::constants::{Float, MatrixXF, Vector3f};
+use crate::io::tonemap::TonemapSettings;
+
+use exr::prelude::traits::*;
+use exr::prelude::{
+    f16, AnyChannel, AnyChannels, AttributeValue, Blocks, Compression, Encoding, FlatSamples,
+    Layer, LayerAttributes, LineOrder, SmallVec, Text,
+};
+use image::codecs::hdr::{HdrDecoder, HdrEncoder};
+use image::codecs::jpeg::JpegEncoder;
+use image::Rgb;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ImageIoError {
+    Io(std::io::Error),
+    /// The file is not a valid image of its format.
+    Format(String),
+    /// The file or image uses something this module cannot handle.
+    Unsupported(String),
+}
+
+impl From<std::io::Error> for ImageIoError {
+    fn from(err: std::io::Error) -> Self {
+        ImageIoError::Io(err)
+    }
+}
+
+impl From<exr::error::Error> for ImageIoError {
+    fn from(err: exr::error::Error) -> Self {
+        match err {
+            exr::error::Error::Io(err) => ImageIoError::Io(err),
+            exr::error::Error::NotSupported(what) => ImageIoError::Unsupported(what.to_string()),
+            err => ImageIoError::Format(err.to_string()),
+        }
+    }
+}
+
+impl From<image::ImageError> for ImageIoError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => ImageIoError::Io(err),
+            image::ImageError::Unsupported(err) => ImageIoError::Unsupported(err.to_string()),
+            err => ImageIoError::Format(err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ImageIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageIoError::Io(err) => write!(f, "io error: {}", err),
+            ImageIoError::Format(err) => write!(f, "invalid image: {}", err),
+            ImageIoError::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ImageIoError {}
+
+/// A floating point image with named channels, stored interleaved in row
+/// major order with the first row at the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub channels: Vec<String>,
+    pub data: Vec<Float>,
+    /// Text attributes. Only EXR files keep them.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, channels: Vec<String>) -> Self {
+        let data = vec![0.0; width * height * channels.len()];
+        Self { width, height, channels, data, metadata: BTreeMap::new() }
+    }
+
+    pub fn rgb(width: usize, height: usize) -> Self {
+        Self::new(width, height, vec!["R".to_string(), "G".to_string(), "B".to_string()])
+    }
+
+    pub fn from_bitmap(bitmap: &Bitmap) -> Self {
+        let mut image = Self::rgb(bitmap.width(), bitmap.height());
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let p = bitmap.pixel(x, y);
+                image.pixel_mut(x, y).copy_from_slice(&[p.x, p.y, p.z]);
+            }
+        }
+        image
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn channel_index(&self, name: &str) -> Option<usize> {
+        self.channels.iter().position(|c| c == name)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> &[Float] {
+        let n = self.channels.len();
+        let base = (y * self.width + x) * n;
+        &self.data[base..base + n]
+    }
+
+    pub fn pixel_mut(&mut self, x: usize, y: usize) -> &mut [Float] {
+        let n = self.channels.len();
+        let base = (y * self.width + x) * n;
+        &mut self.data[base..base + n]
+    }
+
+    /// Channels read as red, green and blue: `R`, `G` and `B` when present,
+    /// a single channel repeated, or else the first three.
+    fn rgb_indices(&self) -> [usize; 3] {
+        match (self.channel_index("R"), self.channel_index("G"), self.channel_index("B")) {
+            (Some(r), Some(g), Some(b)) => [r, g, b],
+            _ => {
+                let last = self.channels.len().saturating_sub(1);
+                [0, 1.min(last), 2.min(last)]
+            }
+        }
+    }
+
+    pub fn rgb_at(&self, x: usize, y: usize) -> Vector3f {
+        if self.channels.is_empty() {
+            return Vector3f::zeros();
+        }
+        let [r, g, b] = self.rgb_indices();
+        let pixel = self.pixel(x, y);
+        Vector3f::new(pixel[r], pixel[g], pixel[b])
+    }
+
+    /// RGB plus the `A` channel, which is 1 when absent.
+    pub fn rgba_at(&self, x: usize, y: usize) -> [Float; 4] {
+        let rgb = self.rgb_at(x, y);
+        let a = self.channel_index("A").map_or(1.0, |a| self.pixel(x, y)[a]);
+        [rgb.x, rgb.y, rgb.z, a]
+    }
+
+    pub fn to_bitmap(&self) -> Bitmap {
+        let mut bitmap = Bitmap::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                bitmap.set_pixel(x, y, self.rgb_at(x, y));
+            }
+        }
+        bitmap
+    }
+
+    /// RGB texels laid out as `height x (width * 3)`, as used by
+    /// `ImageTexture`.
+    pub fn to_rgb_matrix(&self) -> MatrixXF {
+        let mut data = MatrixXF::zeros(self.height, self.width * 3);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let rgb = self.rgb_at(x, y);
+                for c in 0..3 {
+                    data[(y, x * 3 + c)] = rgb[c];
+                }
+            }
+        }
+        data
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Exr,
+    Hdr,
+    Pfm,
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    pub fn is_ldr(&self) -> bool {
+        matches!(self, ImageFormat::Png | ImageFormat::Jpeg)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "exr" => Some(ImageFormat::Exr),
+            "hdr" | "rgbe" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            "png" => Some(ImageFormat::Png),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrPrecision {
+    Half,
+    Float,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    Rle,
+    /// Zip over single scan lines.
+    Zips,
+    /// Zip over blocks of 16 scan lines.
+    Zip,
+    Piz,
+    Pxr24,
+    B44,
+    B44a,
+}
+
+impl ExrCompression {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "uncompressed" => Some(ExrCompression::None),
+            "rle" => Some(ExrCompression::Rle),
+            "zips" => Some(ExrCompression::Zips),
+            "zip" => Some(ExrCompression::Zip),
+            "piz" => Some(ExrCompression::Piz),
+            "pxr24" => Some(ExrCompression::Pxr24),
+            "b44" => Some(ExrCompression::B44),
+            "b44a" => Some(ExrCompression::B44a),
+            _ => None,
+        }
+    }
+
+    fn to_exr(self) -> Compression {
+        match self {
+            ExrCompression::None => Compression::Uncompressed,
+            ExrCompression::Rle => Compression::RLE,
+            ExrCompression::Zips => Compression::ZIP1,
+            ExrCompression::Zip => Compression::ZIP16,
+            ExrCompression::Piz => Compression::PIZ,
+            ExrCompression::Pxr24 => Compression::PXR24,
+            ExrCompression::B44 => Compression::B44,
+            ExrCompression::B44a => Compression::B44A,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExrOptions {
+    pub precision: ExrPrecision,
+    pub compression: ExrCompression,
+}
+
+impl Default for ExrOptions {
+    fn default() -> Self {
+        Self { precision: ExrPrecision::Float, compression: ExrCompression::Zip }
+    }
+}
+
+/// Reads an EXR, Radiance HDR, PFM, PNG or JPEG file, chosen by extension.
+/// 8-bit images are decoded from sRGB to linear values.
+pub fn read_image<P: AsRef<Path>>(path: P) -> Result<Image, ImageIoError> {
+    let path = path.as_ref();
+    match ImageFormat::from_path(path) {
+        Some(ImageFormat::Exr) => read_exr(path),
+        Some(ImageFormat::Hdr) => read_hdr(path),
+        Some(ImageFormat::Pfm) => read_pfm(path),
+        Some(ImageFormat::Png) | Some(ImageFormat::Jpeg) => read_ldr(path),
+        None => Err(unsupported_extension(path)),
+    }
+}
+
+/// Writes an image in the format given by the extension, with
+/// `ExrOptions::default()` for EXR and `TonemapSettings::default()` for
+/// PNG and JPEG.
+pub fn write_image<P: AsRef<Path>>(path: P, image: &Image) -> Result<(), ImageIoError> {
+    let path = path.as_ref();
+    match ImageFormat::from_path(path) {
+        Some(ImageFormat::Exr) => write_exr(path, image, &ExrOptions::default()),
+        Some(ImageFormat::Hdr) => write_hdr(path, image),
+        Some(ImageFormat::Pfm) => write_pfm(path, image),
+        Some(ImageFormat::Png) | Some(ImageFormat::Jpeg) => write_ldr(path, image, &TonemapSettings::default()),
+        None => Err(unsupported_extension(path)),
+    }
+}
+
+fn unsupported_extension(path: &Path) -> ImageIoError {
+    ImageIoError::Unsupported(format!("image format of {}", path.display()))
+}
+
+fn check_size(image: &Image) -> Result<(), ImageIoError> {
+    if image.data.len() != image.width * image.height * image.channels.len() {
+        return Err(ImageIoError::Format(format!(
+            "{} values for a {}x{} image with {} channels",
+            image.data.len(), image.width, image.height, image.channels.len()
+        )));
+    }
+    Ok(())
+}
+
+// EXR keeps channels sorted by name; R, G, B and A are moved to the front.
+fn channel_order(name: &str) -> (usize, &str) {
+    let rank = ["R", "G", "B", "A"].iter().position(|c| *c == name).unwrap_or(4);
+    (rank, name)
+}
+
+// Standard attributes the exr crate parses into fields of their own.
+const EXR_TEXT_FIELDS: [&str; 4] = ["owner", "comments", "capDate", "software"];
+
+/// Reads every channel of the first layer of an EXR file at full
+/// resolution, together with its text attributes.
+pub fn read_exr<P: AsRef<Path>>(path: P) -> Result<Image, ImageIoError> {
+    let exr_image = exr::prelude::read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .all_channels()
+        .first_valid_layer()
+        .all_attributes()
+        .from_file(path)?;
+
+    let layer = exr_image.layer_data;
+    let (width, height) = (layer.size.width(), layer.size.height());
+    let mut channels: Vec<(String, Vec<f32>)> = layer
+        .channel_data
+        .list
+        .iter()
+        .map(|channel| (channel.name.to_string(), channel.sample_data.values_as_f32().collect()))
+        .collect();
+    channels.sort_by(|a, b| channel_order(&a.0).cmp(&channel_order(&b.0)));
+
+    let mut image = Image::new(width, height, channels.iter().map(|c| c.0.clone()).collect());
+    let n = channels.len();
+    for (c, (_, values)) in channels.iter().enumerate() {
+        for (i, value) in values.iter().enumerate() {
+            image.data[i * n + c] = *value;
+        }
+    }
+
+    let attributes = &layer.attributes;
+    let fields = [&attributes.owner, &attributes.comments, &attributes.capture_date, &attributes.software_name];
+    for (name, value) in EXR_TEXT_FIELDS.iter().zip(fields.iter()) {
+        if let Some(value) = value {
+            image.metadata.insert(name.to_string(), value.to_string());
+        }
+    }
+    let other = exr_image.attributes.other.iter().chain(attributes.other.iter());
+    for (name, value) in other {
+        if let AttributeValue::Text(value) = value {
+            image.metadata.insert(name.to_string(), value.to_string());
+        }
+    }
+    Ok(image)
+}
+
+fn exr_text(value: &str) -> Result<Text, ImageIoError> {
+    Text::new_or_none(value)
+        .ok_or_else(|| ImageIoError::Unsupported(format!("exr text {:?}", value)))
+}
+
+pub fn write_exr<P: AsRef<Path>>(path: P, image: &Image, options: &ExrOptions) -> Result<(), ImageIoError> {
+    check_size(image)?;
+    let n = image.channels.len();
+    let pixel_count = image.width * image.height;
+    let mut list = SmallVec::new();
+    for (c, name) in image.channels.iter().enumerate() {
+        let values = (0..pixel_count).map(|i| image.data[i * n + c]);
+        let samples = match options.precision {
+            ExrPrecision::Half => FlatSamples::F16(values.map(f16::from_f32).collect()),
+            ExrPrecision::Float => FlatSamples::F32(values.collect()),
+        };
+        list.push(AnyChannel::new(exr_text(name)?, samples));
+    }
+
+    let mut attributes = LayerAttributes::default();
+    for (name, value) in &image.metadata {
+        let text = Some(exr_text(value)?);
+        match name.as_str() {
+            "owner" => attributes.owner = text,
+            "comments" => attributes.comments = text,
+            "capDate" => attributes.capture_date = text,
+            "software" => attributes.software_name = text,
+            _ => {
+                attributes.other.insert(exr_text(name)?, AttributeValue::Text(exr_text(value)?));
+            }
+        }
+    }
+
+    let encoding = Encoding {
+        compression: options.compression.to_exr(),
+        blocks: Blocks::ScanLines,
+        line_order: LineOrder::Increasing,
+    };
+    let layer = Layer::new((image.width, image.height), attributes, encoding, AnyChannels::sort(list));
+    exr::prelude::Image::from_layer(layer).write().to_file(path)?;
+    Ok(())
+}
+
+pub fn read_hdr<P: AsRef<Path>>(path: P) -> Result<Image, ImageIoError> {
+    let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr()?;
+    let mut image = Image::rgb(metadata.width as usize, metadata.height as usize);
+    for (i, pixel) in pixels.iter().enumerate() {
+        image.data[i * 3..i * 3 + 3].copy_from_slice(&pixel.0);
+    }
+    Ok(image)
+}
+
+/// Writes the RGB channels (see `Image::rgb_at`) as RGBE.
+pub fn write_hdr<P: AsRef<Path>>(path: P, image: &Image) -> Result<(), ImageIoError> {
+    check_size(image)?;
+    let mut pixels = Vec::with_capacity(image.width * image.height);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let rgb = image.rgb_at(x, y);
+            pixels.push(Rgb([rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0)]));
+        }
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    HdrEncoder::new(&mut writer).encode(&pixels, image.width, image.height)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn pfm_token<R: BufRead>(reader: &mut R) -> Result<String, ImageIoError> {
+    let mut token = Vec::new();
+    for byte in reader.bytes() {
+        let byte = byte?;
+        if byte.is_ascii_whitespace() {
+            if token.is_empty() {
+                continue;
+            }
+            break;
+        }
+        token.push(byte);
+    }
+    String::from_utf8(token).map_err(|_| ImageIoError::Format("pfm header is not ascii".to_string()))
+}
+
+fn pfm_number<T: std::str::FromStr, R: BufRead>(reader: &mut R, what: &str) -> Result<T, ImageIoError> {
+    let token = pfm_token(reader)?;
+    token
+        .parse()
+        .map_err(|_| ImageIoError::Format(format!("pfm {} {:?}", what, token)))
+}
+
+/// Reads a color (`PF`) or grayscale (`Pf`) portable float map. A
+/// negative scale marks little-endian data; rows are stored bottom-up.
+pub fn read_pfm<P: AsRef<Path>>(path: P) -> Result<Image, ImageIoError> {
+    let file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+    let channels = match pfm_token(&mut reader)?.as_str() {
+        "PF" => vec!["R".to_string(), "G".to_string(), "B".to_string()],
+        "Pf" => vec!["Y".to_string()],
+        magic => return Err(ImageIoError::Format(format!("pfm magic {:?}", magic))),
+    };
+    let width: usize = pfm_number(&mut reader, "width")?;
+    let height: usize = pfm_number(&mut reader, "height")?;
+    let scale: f32 = pfm_number(&mut reader, "scale")?;
+    let remaining = file_len.saturating_sub(reader.stream_position()?);
+    let data_len = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels.len() * 4))
+        .filter(|&n| n as u64 <= remaining);
+    if data_len.is_none() {
+        return Err(ImageIoError::Format(format!(
+            "pfm {}x{} image does not fit in the {} bytes after the header",
+            width, height, remaining
+        )));
+    }
+
+    let mut image = Image::new(width, height, channels);
+    let row_len = width * image.channel_count();
+    let mut bytes = vec![0u8; row_len * 4];
+    for row in 0..height {
+        reader.read_exact(&mut bytes)?;
+        let y = height - 1 - row;
+        let out = &mut image.data[y * row_len..(y + 1) * row_len];
+        for (value, chunk) in out.iter_mut().zip(bytes.chunks_exact(4)) {
+            let chunk = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            *value = if scale < 0.0 { f32::from_le_bytes(chunk) } else { f32::from_be_bytes(chunk) };
+        }
+    }
+    Ok(image)
+}
+
+/// Writes a little-endian PFM: grayscale for single channel images, the
+/// RGB channels otherwise.
+pub fn write_pfm<P: AsRef<Path>>(path: P, image: &Image) -> Result<(), ImageIoError> {
+    check_size(image)?;
+    let gray = image.channel_count() == 1;
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(writer, "{}\n{} {}\n-1.0\n", if gray { "Pf" } else { "PF" }, image.width, image.height)?;
+    for y in (0..image.height).rev() {
+        for x in 0..image.width {
+            if gray {
+                writer.write_all(&image.pixel(x, y)[0].to_le_bytes())?;
+            } else {
+                for value in image.rgb_at(x, y).iter() {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_ldr(path: &Path) -> Result<Image, ImageIoError> {
+    let decoded = image::open(path)?.to_rgb32f();
+    let (width, height) = (decoded.width() as usize, decoded.height() as usize);
+    let mut image = Image::rgb(width, height);
+    for (value, encoded) in image.data.iter_mut().zip(decoded.as_raw().iter()) {
+        *value = srgb_to_linear(*encoded);
+    }
+    Ok(image)
+}
+
+pub(crate) fn srgb_to_linear(v: Float) -> Float {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The RGB channels of `image` mapped through `settings`, as 8-bit rows.
+pub fn tonemap_rgb8(image: &Image, settings: &TonemapSettings) -> Vec<u8> {
+    let mut out = Vec::with_capacity(image.width * image.height * 3);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            out.extend_from_slice(&settings.quantize(image.rgb_at(x, y), x, y));
+        }
+    }
+    out
+}
+
+/// Writes a PNG or JPEG (quality 95) through the tonemapping pipeline.
+pub fn write_ldr<P: AsRef<Path>>(path: P, image: &Image, settings: &TonemapSettings) -> Result<(), ImageIoError> {
+    check_size(image)?;
+    let path = path.as_ref();
+    let pixels = tonemap_rgb8(image, settings);
+    let (width, height) = (image.width as u32, image.height as u32);
+    match ImageFormat::from_path(path) {
+        Some(ImageFormat::Png) => {
+            image::save_buffer_with_format(path, &pixels, width, height, image::ColorType::Rgb8, image::ImageFormat::Png)?;
+        }
+        Some(ImageFormat::Jpeg) => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            JpegEncoder::new_with_quality(&mut writer, 95).encode(&pixels, width, height, image::ColorType::Rgb8)?;
+            writer.flush()?;
+        }
+        _ => return Err(unsupported_extension(path)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image() -> Image {
+        let channels = ["B", "G", "R", "depth"].iter().map(|c| c.to_string()).collect();
+        let mut image = Image::new(3, 2, channels);
+        for (i, value) in image.data.iter_mut().enumerate() {
+            *value = i as Float * 0.25;
+        }
+        image
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("dacquoise_image_io_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_exr_round_trip_keeps_channels_and_metadata() {
+        let mut image = test_image();
+        image.metadata.insert("comments".to_string(), "test render".to_string());
+        image.metadata.insert("integrator".to_string(), "path".to_string());
+        let path = temp_path("round_trip.exr");
+        for precision in [ExrPrecision::Float, ExrPrecision::Half] {
+            let options = ExrOptions { precision, compression: ExrCompression::Piz };
+            write_exr(&path, &image, &options).unwrap();
+            let read = read_exr(&path).unwrap();
+            assert_eq!(read.channels, vec!["R", "G", "B", "depth"]);
+            assert_eq!(read.metadata, image.metadata);
+            for y in 0..2 {
+                for x in 0..3 {
+                    assert_eq!(read.rgb_at(x, y), image.rgb_at(x, y));
+                    let depth = image.pixel(x, y)[3];
+                    assert_eq!(read.pixel(x, y)[3], depth);
+                }
+            }
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_hdr_and_pfm_round_trip() {
+        let image = test_image();
+        let pfm = temp_path("round_trip.pfm");
+        write_image(&pfm, &image).unwrap();
+        let read = read_image(&pfm).unwrap();
+        assert_eq!(read.channels, vec!["R", "G", "B"]);
+        assert_eq!(read.rgb_at(2, 0), image.rgb_at(2, 0));
+        assert_eq!(read.rgb_at(0, 1), image.rgb_at(0, 1));
+        std::fs::remove_file(&pfm).ok();
+
+        // RGBE keeps 8 mantissa bits relative to the largest channel.
+        let hdr = temp_path("round_trip.hdr");
+        write_image(&hdr, &image).unwrap();
+        let read = read_image(&hdr).unwrap();
+        let (a, b) = (read.rgb_at(1, 1), image.rgb_at(1, 1));
+        assert!((a - b).abs().max() <= b.max() / 128.0, "{:?} vs {:?}", a, b);
+        std::fs::remove_file(&hdr).ok();
+    }
+
+    #[test]
+    fn test_png_round_trip_is_srgb_encoded() {
+        let mut image = Image::rgb(2, 1);
+        image.data.copy_from_slice(&[0.0, 0.214, 1.0, 0.5, 0.5, 2.0]);
+        let path = temp_path("round_trip.png");
+        write_image(&path, &image).unwrap();
+        let read = read_image(&path).unwrap();
+        for (a, b) in read.data.iter().zip(image.data.iter()) {
+            assert!((a - b.min(1.0)).abs() < 0.01, "{} vs {}", a, b);
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(read_image("missing.exr"), Err(ImageIoError::Io(_))));
+        assert!(matches!(read_image("image.tga"), Err(ImageIoError::Unsupported(_))));
+        assert!(matches!(read_image("missing.png"), Err(ImageIoError::Io(_))));
+        let path = temp_path("bad.pfm");
+        std::fs::write(&path, b"P6\n1 1\n255\n").unwrap();
+        assert!(matches!(read_pfm(&path), Err(ImageIoError::Format(_))));
+        // Sizes the data cannot hold are rejected before allocating.
+        std::fs::write(&path, b"PF\n2 1\n-1.0\n\0\0\0\0").unwrap();
+        assert!(matches!(read_pfm(&path), Err(ImageIoError::Format(_))));
+        std::fs::write(&path, format!("PF\n{} {}\n-1.0\n", usize::MAX / 2, 3)).unwrap();
+        assert!(matches!(read_pfm(&path), Err(ImageIoError::Format(_))));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -1,2 +1,5 @@
-pub mod exr_utils;
+pub mod image_compare;
+pub mod image_io;
 pub mod obj_utils;
+pub mod serialized_utils;
+pub mod tonemap;
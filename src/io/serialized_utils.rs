@@ -0,0 +1,307 @@
+// Copyright @yucwang 2026
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::ZlibDecoder;
+
+const FORMAT_MAGIC: u16 = 0x041C;
+
+const FLAG_VERTEX_NORMALS: u32 = 0x0001;
+const FLAG_TEXCOORDS: u32 = 0x0002;
+const FLAG_VERTEX_COLORS: u32 = 0x0008;
+const FLAG_FACE_NORMALS: u32 = 0x0010;
+const FLAG_SINGLE_PRECISION: u32 = 0x1000;
+const FLAG_DOUBLE_PRECISION: u32 = 0x2000;
+
+#[derive(Debug)]
+pub enum SerializedLoadError {
+    Io(std::io::Error),
+    Parse(String),
+}
+
+impl From<std::io::Error> for SerializedLoadError {
+    fn from(err: std::io::Error) -> Self {
+        SerializedLoadError::Io(err)
+    }
+}
+
+impl fmt::Display for SerializedLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializedLoadError::Io(err) => write!(f, "io error: {}", err),
+            SerializedLoadError::Parse(err) => write!(f, "parse error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SerializedLoadError {}
+
+/// One shape of a Mitsuba `.serialized` file, as stored. Attribute buffers
+/// are empty when absent; UVs keep Mitsuba's top-left texture origin.
+#[derive(Debug, Default)]
+pub struct SerializedShape {
+    pub name: Option<String>,
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub colors: Vec<[f32; 3]>,
+    pub indices: Vec<[u32; 3]>,
+    pub face_normals: bool,
+}
+
+/// Reads shape `shape_index` of a `.serialized` file. The file is a run of
+/// zlib-compressed shape records followed by a table of record offsets and
+/// the shape count.
+pub fn load_serialized_from_file<P: AsRef<Path>>(path: P, shape_index: usize) -> Result<SerializedShape, SerializedLoadError> {
+    let data = fs::read(path)?;
+    load_serialized_from_bytes(&data, shape_index)
+}
+
+pub fn load_serialized_from_bytes(data: &[u8], shape_index: usize) -> Result<SerializedShape, SerializedLoadError> {
+    if data.len() < 8 {
+        return Err(SerializedLoadError::Parse("file too short".to_string()));
+    }
+    let version = read_header(data)?;
+
+    // The offset table holds u64 entries since version 4 and u32 before.
+    let count = u32::from_le_bytes(tail(data, 4, 0)?) as usize;
+    if shape_index >= count {
+        return Err(SerializedLoadError::Parse(format!(
+            "shape_index {} out of range, file has {} shapes", shape_index, count)));
+    }
+    let offset = if version >= 4 {
+        let entry = tail::<8>(data, 4 + 8 * count, 8 * shape_index)?;
+        u64::from_le_bytes(entry) as usize
+    } else {
+        let entry = tail::<4>(data, 4 + 4 * count, 4 * shape_index)?;
+        u32::from_le_bytes(entry) as usize
+    };
+    let record = data
+        .get(offset..)
+        .ok_or_else(|| SerializedLoadError::Parse(format!("shape offset {} out of range", offset)))?;
+    let version = read_header(record)?;
+    // Decompressed up front so that the counts in the record can be checked
+    // against the bytes it actually holds before anything is allocated.
+    let mut body = Vec::new();
+    ZlibDecoder::new(&record[4..]).read_to_end(&mut body)?;
+    parse_shape(&body, version)
+}
+
+fn read_header(record: &[u8]) -> Result<u16, SerializedLoadError> {
+    if record.len() < 4 || u16::from_le_bytes([record[0], record[1]]) != FORMAT_MAGIC {
+        return Err(SerializedLoadError::Parse("not a serialized shape record".to_string()));
+    }
+    let version = u16::from_le_bytes([record[2], record[3]]);
+    if version != 3 && version != 4 {
+        return Err(SerializedLoadError::Parse(format!("unsupported format version {}", version)));
+    }
+    Ok(version)
+}
+
+// `N` bytes at `start` within the trailing `from_end` bytes of `data`.
+fn tail<const N: usize>(data: &[u8], from_end: usize, start: usize) -> Result<[u8; N], SerializedLoadError> {
+    let begin = data.len().checked_sub(from_end)
+        .ok_or_else(|| SerializedLoadError::Parse("truncated offset table".to_string()))?;
+    let mut out = [0u8; N];
+    out.copy_from_slice(&data[begin + start..begin + start + N]);
+    Ok(out)
+}
+
+fn parse_shape(mut reader: &[u8], version: u16) -> Result<SerializedShape, SerializedLoadError> {
+    let flags = read_u32(&mut reader)?;
+    let double = if flags & FLAG_DOUBLE_PRECISION != 0 {
+        true
+    } else if flags & FLAG_SINGLE_PRECISION != 0 {
+        false
+    } else {
+        return Err(SerializedLoadError::Parse("unknown scalar precision".to_string()));
+    };
+
+    let name = if version >= 4 {
+        let mut bytes = Vec::new();
+        loop {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            if byte[0] == 0 {
+                break;
+            }
+            bytes.push(byte[0]);
+        }
+        Some(String::from_utf8_lossy(&bytes).into_owned()).filter(|n| !n.is_empty())
+    } else {
+        None
+    };
+
+    let vertex_count = read_u64(&mut reader)? as usize;
+    let face_count = read_u64(&mut reader)? as usize;
+
+    let scalar = if double { 8 } else { 4 };
+    let mut vertex_size = 3 * scalar;
+    for (flag, components) in [(FLAG_VERTEX_NORMALS, 3), (FLAG_TEXCOORDS, 2), (FLAG_VERTEX_COLORS, 3)] {
+        if flags & flag != 0 {
+            vertex_size += components * scalar;
+        }
+    }
+    check_size(reader, vertex_count, vertex_size, "vertices")?;
+
+    let mut shape = SerializedShape { name, face_normals: flags & FLAG_FACE_NORMALS != 0, ..Default::default() };
+    shape.positions = read_vectors::<_, 3>(&mut reader, vertex_count, double)?;
+    if flags & FLAG_VERTEX_NORMALS != 0 {
+        shape.normals = read_vectors::<_, 3>(&mut reader, vertex_count, double)?;
+    }
+    if flags & FLAG_TEXCOORDS != 0 {
+        shape.uvs = read_vectors::<_, 2>(&mut reader, vertex_count, double)?;
+    }
+    if flags & FLAG_VERTEX_COLORS != 0 {
+        shape.colors = read_vectors::<_, 3>(&mut reader, vertex_count, double)?;
+    }
+
+    // Indices widen to u64 only for meshes beyond the u32 range.
+    let wide = vertex_count > u32::MAX as usize;
+    check_size(reader, face_count, if wide { 24 } else { 12 }, "faces")?;
+    shape.indices = Vec::with_capacity(face_count);
+    for _ in 0..face_count {
+        let mut face = [0u32; 3];
+        for index in face.iter_mut() {
+            *index = if wide {
+                u32::try_from(read_u64(&mut reader)?)
+                    .map_err(|_| SerializedLoadError::Parse("vertex index exceeds u32".to_string()))?
+            } else {
+                read_u32(&mut reader)?
+            };
+        }
+        shape.indices.push(face);
+    }
+    Ok(shape)
+}
+
+/// Fails unless `count` items of `item_size` bytes fit in `remaining`.
+fn check_size(remaining: &[u8], count: usize, item_size: usize, what: &str) -> Result<(), SerializedLoadError> {
+    match count.checked_mul(item_size) {
+        Some(size) if size <= remaining.len() => Ok(()),
+        _ => Err(SerializedLoadError::Parse(format!(
+            "{} {} do not fit in the {} bytes left in the record",
+            count, what, remaining.len()
+        ))),
+    }
+}
+
+fn read_vectors<R: Read, const N: usize>(reader: &mut R, count: usize, double: bool) -> Result<Vec<[f32; N]>, SerializedLoadError> {
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut value = [0.0f32; N];
+        for component in value.iter_mut() {
+            *component = if double {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf)?;
+                f64::from_le_bytes(buf) as f32
+            } else {
+                let mut buf = [0u8; 4];
+                reader.read_exact(&mut buf)?;
+                f32::from_le_bytes(buf)
+            };
+        }
+        out.push(value);
+    }
+    Ok(out)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, SerializedLoadError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, SerializedLoadError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn record(name: &str, flags: u32, offset_x: f32) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&flags.to_le_bytes());
+        body.extend_from_slice(name.as_bytes());
+        body.push(0);
+        body.extend_from_slice(&3u64.to_le_bytes());
+        body.extend_from_slice(&1u64.to_le_bytes());
+        for p in [[offset_x, 0.0, 0.0], [offset_x + 1.0, 0.0, 0.0], [offset_x, 1.0, 0.0]] {
+            for c in p {
+                body.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        if flags & FLAG_TEXCOORDS != 0 {
+            for c in [0.0f32, 0.0, 1.0, 0.0, 0.0, 1.0] {
+                body.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        for i in [0u32, 1, 2] {
+            body.extend_from_slice(&i.to_le_bytes());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&body).unwrap();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&FORMAT_MAGIC.to_le_bytes());
+        out.extend_from_slice(&4u16.to_le_bytes());
+        out.extend_from_slice(&encoder.finish().unwrap());
+        out
+    }
+
+    #[test]
+    fn test_load_serialized_by_shape_index() {
+        let first = record("a", FLAG_SINGLE_PRECISION, 0.0);
+        let second = record("b", FLAG_SINGLE_PRECISION | FLAG_TEXCOORDS | FLAG_FACE_NORMALS, 5.0);
+        let mut data = Vec::new();
+        data.extend_from_slice(&first);
+        data.extend_from_slice(&second);
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&(first.len() as u64).to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+
+        let shape = load_serialized_from_bytes(&data, 1).expect("second shape");
+        assert_eq!(shape.name.as_deref(), Some("b"));
+        assert_eq!(shape.positions[1], [6.0, 0.0, 0.0]);
+        assert_eq!(shape.uvs.len(), 3);
+        assert!(shape.normals.is_empty());
+        assert!(shape.face_normals);
+        assert_eq!(shape.indices, vec![[0, 1, 2]]);
+
+        let shape = load_serialized_from_bytes(&data, 0).expect("first shape");
+        assert_eq!(shape.name.as_deref(), Some("a"));
+        assert!(shape.uvs.is_empty());
+        assert!(load_serialized_from_bytes(&data, 2).is_err());
+    }
+
+    #[test]
+    fn test_counts_beyond_the_record_are_rejected() {
+        for (vertices, faces) in [(1u64 << 40, 1u64), (3, u64::MAX / 4)] {
+            let mut body = Vec::new();
+            body.extend_from_slice(&FLAG_SINGLE_PRECISION.to_le_bytes());
+            body.push(0);
+            body.extend_from_slice(&vertices.to_le_bytes());
+            body.extend_from_slice(&faces.to_le_bytes());
+            body.extend_from_slice(&[0u8; 36]);
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&body).unwrap();
+            let mut data = Vec::new();
+            data.extend_from_slice(&FORMAT_MAGIC.to_le_bytes());
+            data.extend_from_slice(&4u16.to_le_bytes());
+            data.extend_from_slice(&encoder.finish().unwrap());
+            data.extend_from_slice(&0u64.to_le_bytes());
+            data.extend_from_slice(&1u32.to_le_bytes());
+            assert!(matches!(load_serialized_from_bytes(&data, 0), Err(SerializedLoadError::Parse(_))));
+        }
+    }
+}
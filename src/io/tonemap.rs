@@ -0,0 +1,221 @@
+// Copyright @yucwang 2026
+
+use crate::math::constants::{Float, Matrix3f, Vector3f};
+
+/// Maps scene-referred linear RGB to linear display values in [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Clips at 1.
+    Clamp,
+    /// Per-channel `x / (1 + x)`.
+    Reinhard,
+    /// Stephen Hill's fit of the ACES RRT and sRGB ODT.
+    AcesFitted,
+    /// Troy Sobotka's AgX base look, with the polynomial sigmoid fit used in
+    /// real-time ports.
+    Agx,
+}
+
+impl Tonemapper {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "clamp" | "linear" => Some(Tonemapper::Clamp),
+            "reinhard" => Some(Tonemapper::Reinhard),
+            "aces" | "aces_fitted" => Some(Tonemapper::AcesFitted),
+            "agx" => Some(Tonemapper::Agx),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, rgb: Vector3f) -> Vector3f {
+        let rgb = rgb.map(|v| if v.is_finite() { v.max(0.0) } else { 0.0 });
+        let mapped = match self {
+            Tonemapper::Clamp => rgb,
+            Tonemapper::Reinhard => rgb.map(|v| v / (1.0 + v)),
+            Tonemapper::AcesFitted => aces_fitted(rgb),
+            Tonemapper::Agx => agx(rgb),
+        };
+        mapped.map(|v| v.clamp(0.0, 1.0))
+    }
+}
+
+fn aces_fitted(rgb: Vector3f) -> Vector3f {
+    let input = Matrix3f::new(
+        0.59719, 0.35458, 0.04823,
+        0.07600, 0.90834, 0.01566,
+        0.02840, 0.13383, 0.83777,
+    );
+    let output = Matrix3f::new(
+        1.60475, -0.53108, -0.07367,
+        -0.10208, 1.10813, -0.00605,
+        -0.00327, -0.07276, 1.07602,
+    );
+    let v = input * rgb;
+    let fitted = v.map(|x| (x * (x + 0.0245786) - 0.000090537) / (x * (0.983729 * x + 0.432951) + 0.238081));
+    output * fitted
+}
+
+fn agx(rgb: Vector3f) -> Vector3f {
+    const MIN_EV: Float = -12.47393;
+    const MAX_EV: Float = 4.026069;
+    let inset = Matrix3f::new(
+        0.8424791, 0.0784336, 0.07922375,
+        0.04232824, 0.8784686, 0.07916613,
+        0.04237565, 0.0784336, 0.879143,
+    );
+    let outset = Matrix3f::new(
+        1.196879, -0.09802088, -0.09902974,
+        -0.05289685, 1.151903, -0.09896118,
+        -0.05297164, -0.09804345, 1.151074,
+    );
+    let encoded = (inset * rgb).map(|v| {
+        let ev = v.max(1e-10).log2().clamp(MIN_EV, MAX_EV);
+        let x = (ev - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+    });
+    // The sigmoid produces display-encoded values; decode them with a 2.2
+    // gamma so every tonemapper hands linear values to the transfer function.
+    (outset * encoded).map(|v| v.max(0.0).powf(2.2))
+}
+
+/// Opto-electronic transfer function encoding linear display values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferFunction {
+    Linear,
+    Srgb,
+    Rec709,
+}
+
+impl TransferFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "linear" => Some(TransferFunction::Linear),
+            "srgb" => Some(TransferFunction::Srgb),
+            "rec709" | "bt709" => Some(TransferFunction::Rec709),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, v: Float) -> Float {
+        match self {
+            TransferFunction::Linear => v,
+            TransferFunction::Srgb => {
+                if v <= 0.0031308 {
+                    12.92 * v
+                } else {
+                    1.055 * v.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Rec709 => {
+                if v < 0.018 {
+                    4.5 * v
+                } else {
+                    1.099 * v.powf(0.45) - 0.099
+                }
+            }
+        }
+    }
+}
+
+/// Exposure, tonemapping and encoding applied when writing 8-bit images.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemapSettings {
+    /// Exposure compensation in stops.
+    pub exposure: Float,
+    pub tonemapper: Tonemapper,
+    pub transfer: TransferFunction,
+    /// Adds triangular noise of one quantization step before rounding, which
+    /// hides banding in smooth gradients.
+    pub dither: bool,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tonemapper: Tonemapper::Clamp,
+            transfer: TransferFunction::Srgb,
+            dither: false,
+        }
+    }
+}
+
+impl TonemapSettings {
+    /// Display-encoded value in [0, 1] of a linear scene color.
+    pub fn apply(&self, rgb: Vector3f) -> Vector3f {
+        let mapped = self.tonemapper.apply(rgb * (2.0 as Float).powf(self.exposure));
+        mapped.map(|v| self.transfer.encode(v).clamp(0.0, 1.0))
+    }
+
+    /// Quantizes `apply(rgb)` of pixel (`x`, `y`) to 8 bits. Dithering noise
+    /// depends only on the pixel position, so output is deterministic.
+    pub fn quantize(&self, rgb: Vector3f, x: usize, y: usize) -> [u8; 3] {
+        let encoded = self.apply(rgb);
+        let mut out = [0u8; 3];
+        for c in 0..3 {
+            let noise = if self.dither {
+                let seed = ((y as u64) << 34) ^ ((x as u64) << 2) ^ c as u64;
+                hash_unit(seed) - hash_unit(seed ^ 0x9e37_79b9_7f4a_7c15)
+            } else {
+                0.0
+            };
+            out[c] = (encoded[c] * 255.0 + 0.5 + noise).floor().clamp(0.0, 255.0) as u8;
+        }
+        out
+    }
+}
+
+// Uniform value in [0, 1) from a 64-bit hash (splitmix64 finalizer).
+fn hash_unit(mut z: u64) -> Float {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 40) as Float / (1u64 << 24) as Float
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tonemappers_are_monotonic_and_bounded() {
+        for tonemapper in [Tonemapper::Clamp, Tonemapper::Reinhard, Tonemapper::AcesFitted, Tonemapper::Agx] {
+            let mut previous = -1.0;
+            for i in 0..64 {
+                let v = 0.001 * (1.25 as Float).powi(i);
+                let mapped = tonemapper.apply(Vector3f::new(v, v, v));
+                assert!(mapped.x >= previous - 1e-5, "{:?} decreases at {}", tonemapper, v);
+                assert!((0.0..=1.0).contains(&mapped.x));
+                previous = mapped.x;
+            }
+            assert_eq!(tonemapper.apply(Vector3f::new(Float::NAN, -1.0, 0.0)).x, 0.0);
+        }
+        // AgX maps middle grey close to middle grey.
+        let grey = Tonemapper::Agx.apply(Vector3f::new(0.18, 0.18, 0.18));
+        assert!(grey.x > 0.1 && grey.x < 0.3, "{}", grey.x);
+    }
+
+    #[test]
+    fn test_transfer_functions_and_quantization() {
+        assert!((TransferFunction::Srgb.encode(0.5) - 0.735357).abs() < 1e-5);
+        assert!((TransferFunction::Rec709.encode(0.5) - 0.705515).abs() < 1e-5);
+        assert!((TransferFunction::Rec709.encode(0.01) - 0.045).abs() < 1e-6);
+
+        let settings = TonemapSettings { exposure: 1.0, ..TonemapSettings::default() };
+        assert_eq!(settings.quantize(Vector3f::new(0.5, 0.0, 4.0), 0, 0), [255, 0, 255]);
+
+        // Dithering moves a value by at most one step and keeps the mean.
+        let dithered = TonemapSettings { dither: true, transfer: TransferFunction::Linear, ..TonemapSettings::default() };
+        let v = 100.3 / 255.0;
+        let mut sum = 0.0;
+        for x in 0..4096 {
+            let q = dithered.quantize(Vector3f::new(v, v, v), x, 7)[0];
+            assert!((99..=101).contains(&q));
+            sum += q as Float;
+        }
+        assert!((sum / 4096.0 - 100.3).abs() < 0.05, "{}", sum / 4096.0);
+    }
+}
@@ -12,17 +12,102 @@ pub mod math;
 pub mod emitters;
 pub mod media;
 pub mod renderers;
+pub mod samplers;
 pub mod sensors;
 pub mod shapes;
 pub mod textures;
 pub mod volumes;
 
-use crate::core::scene_loader::load_scene_with_settings;
+#[cfg(feature = "python")]
+mod python;
+
+use crate::core::scene::Scene;
+use crate::core::scene_loader::load_scene_with_defines;
+use crate::core::scene_writer::SceneSettings;
 use crate::core::integrator::Integrator;
+use crate::integrators::aov::{AovIntegrator, AovType};
+use crate::integrators::create_integrator;
+use crate::integrators::light_tracer::LightTracerIntegrator;
 use crate::integrators::path::PathIntegrator;
-use crate::integrators::raymarching::RaymarchingIntegrator;
+use crate::integrators::pssmlt::PssmltIntegrator;
+use crate::integrators::sppm::SppmIntegrator;
+use crate::io::image_io::{write_image, Image};
 use crate::math::bitmap::Bitmap;
-use crate::renderers::simple::{Renderer, SimpleRenderer};
+use crate::math::constants::{Float, Vector3f};
+use crate::renderers::checkpoint::Checkpoint;
+use crate::renderers::denoiser::{denoise, DenoiseSettings, DenoiserFeatures};
+use crate::renderers::light_tracing::LightTracingRenderer;
+use crate::renderers::pssmlt::PssmltRenderer;
+use crate::renderers::simple::{ProgressCallback, Renderer, SimpleRenderer};
+use crate::renderers::sppm::SppmRenderer;
+use crate::samplers::create_sampler;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Path depth used when neither the options nor the scene set `max_depth`.
+pub const DEFAULT_MAX_DEPTH: u32 = 8;
+
+/// Settings for `render_scene_with_options`; `None` fields fall back to the
+/// values found in the scene file.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    pub spp: Option<u32>,
+    /// Falls back to the scene's `max_depth`, then `DEFAULT_MAX_DEPTH`.
+    pub max_depth: Option<u32>,
+    pub seed: u64,
+    pub camera_id: usize,
+    /// Integrator type overriding the scene's `<integrator>`.
+    pub integrator: Option<String>,
+    /// Ray marching step size, used by the `raymarching` integrator.
+    pub step_size: Option<Float>,
+    /// Sampler type overriding the scene's `<sampler>`.
+    pub sampler: Option<String>,
+    /// Relative standard error at which a pixel stops sampling. Enables
+    /// adaptive sampling when set.
+    pub target_error: Option<Float>,
+    /// Upper bound on samples per pixel in adaptive mode; must be at least
+    /// `spp`.
+    pub max_spp: Option<u32>,
+    /// Render one sample pass over the whole image at a time.
+    pub progressive: bool,
+    /// Wall-clock limit for progressive rendering. Passes are unbounded
+    /// unless `spp` is also given.
+    pub time_budget: Option<Duration>,
+    /// Write the progressive estimate every N passes to `snapshot_path`.
+    /// A `{pass}` in the path is replaced by the pass number.
+    pub snapshot_interval: Option<u32>,
+    pub snapshot_path: Option<String>,
+    /// Raised (e.g. by a SIGINT handler) to stop a progressive render early.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Progressive checkpoint file, rewritten every `checkpoint_interval`
+    /// passes (default 16) and when the render stops.
+    pub checkpoint_path: Option<String>,
+    pub checkpoint_interval: Option<u32>,
+    /// Checkpoint to continue from. Resuming with the same options gives a
+    /// bit-identical result to an uninterrupted render.
+    pub resume_path: Option<String>,
+    /// Values overriding the scene's `<default>` parameters.
+    pub defines: HashMap<String, String>,
+    /// Auxiliary outputs rendered next to the image (see `AovType`).
+    pub aovs: Vec<String>,
+    /// Filter the image with the feature-guided denoiser.
+    pub denoise: bool,
+    pub progress: Option<ProgressCallback>,
+}
+
+pub struct RenderOutput {
+    pub image: Bitmap,
+    /// Number of samples taken per pixel (same value in all channels).
+    pub sample_count: Bitmap,
+    /// One bitmap per requested AOV, in request order. Only the first
+    /// `AovType::channels` channels are meaningful.
+    pub aovs: Vec<(AovType, Bitmap)>,
+    /// The unfiltered image when `RenderOptions::denoise` is set.
+    pub noisy: Option<Bitmap>,
+}
 
 pub fn render_scene(
     scene_path: &str,
@@ -31,123 +116,220 @@ pub fn render_scene(
     seed: u64,
     camera_id: usize,
 ) -> Result<Bitmap, String> {
-    let _ = env_logger::try_init();
+    let options = RenderOptions {
+        spp: spp_override,
+        max_depth: max_depth_override,
+        seed,
+        camera_id,
+        ..RenderOptions::default()
+    };
+    render_scene_with_options(scene_path, &options).map(|output| output.image)
+}
 
-    let load_result = load_scene_with_settings(scene_path)
-        .map_err(|err| format!("failed to load scene: {:?}", err))?;
+pub fn render_scene_with_options(scene_path: &str, options: &RenderOptions) -> Result<RenderOutput, String> {
+    let _ = env_logger::try_init();
 
+    let load_result = load_scene_with_defines(scene_path, &options.defines)
+        .map_err(|err| format!("failed to load scene: {}", err))?;
+    let settings = SceneSettings::from(&load_result);
     let mut scene = load_result.scene;
-    let spp = spp_override.or(load_result.samples_per_pixel).unwrap_or(1);
-    let max_depth = max_depth_override.or(load_result.max_depth).unwrap_or(1);
-    let integrator_name = load_result.integrator_type.as_deref().unwrap_or("path");
-    let integrator: Box<dyn Integrator> = match integrator_name {
-        "path" => Box::new(PathIntegrator::new(max_depth, spp)),
-        "raymarching" => Box::new(RaymarchingIntegrator::new(max_depth, spp, None)),
-        other => {
-            eprintln!("Unsupported integrator '{}', falling back to path.", other);
-            Box::new(PathIntegrator::new(max_depth, spp))
-        }
-    };
-
-    let renderer: SimpleRenderer = SimpleRenderer::new(integrator, camera_id, seed);
-    Ok(renderer.render(&mut scene))
+    render_loaded_scene(&mut scene, &settings, options)
 }
 
-#[cfg(feature = "python")]
-mod python {
-    use crate::core::computation_node::ComputationNode;
-    use crate::core::scene::Scene;
-    use crate::core::scene_loader::load_scene_with_settings;
-    use crate::core::integrator::Integrator;
-    use crate::integrators::path::PathIntegrator;
-    use crate::renderers::simple::{Renderer, SimpleRenderer};
-    use crate::math::constants::MatrixXF;
-    use pyo3::exceptions::PyRuntimeError;
-    use pyo3::prelude::*;
-    use pyo3::types::PyDict;
-    use nalgebra_py::matrix_to_numpy;
-    use std::sync::Mutex;
-
-    #[pyclass(unsendable)]
-    struct PyScene {
-        scene: Mutex<Scene>,
+/// Renders a scene that is already in memory. `settings` hold the scene
+/// file's integrator and sampler choices, used where `options` leave them
+/// unset.
+pub fn render_loaded_scene(scene: &mut Scene, settings: &SceneSettings, options: &RenderOptions) -> Result<RenderOutput, String> {
+    if scene.camera(options.camera_id).is_none() {
+        return Err(format!("scene has no sensor {}", options.camera_id));
     }
-
-    #[pymethods]
-    impl PyScene {
-        #[getter]
-        fn data(&self, py: Python<'_>) -> PyResult<PyObject> {
-            self.raw_data(py)
-        }
-
-        fn raw_data_keys(&self) -> Vec<String> {
-            self.scene
-                .lock()
-                .expect("scene lock")
-                .raw_data()
-                .keys()
-                .cloned()
-                .collect()
+    let spp = options.spp.or(settings.samples_per_pixel).unwrap_or(1);
+    if let Some(max_spp) = options.max_spp.filter(|&max_spp| max_spp < spp) {
+        return Err(format!("max_spp {} is below spp {}", max_spp, spp));
+    }
+    let max_depth = options.max_depth.or(settings.max_depth).unwrap_or(DEFAULT_MAX_DEPTH);
+    let step_size = options.step_size.or(settings.step_size);
+    let progressive = options.progressive
+        || options.time_budget.is_some()
+        || options.checkpoint_path.is_some()
+        || options.resume_path.is_some();
+    // The particle tracer, SPPM and PSSMLT render into the whole film at
+    // once, so they have neither per-pixel sample counts nor passes.
+    let integrator_name = options.integrator.as_deref().or(settings.integrator_type.as_deref());
+    let integrator: Option<Box<dyn Integrator>> = match integrator_name {
+        Some(name @ ("ptracer" | "sppm" | "pssmlt")) if progressive || options.target_error.is_some() || options.max_spp.is_some() => {
+            return Err(format!("the {} integrator supports neither adaptive nor progressive rendering", name));
         }
-
-        fn raw_data(&self, py: Python<'_>) -> PyResult<PyObject> {
-            let dict = PyDict::new(py);
-            let scene = self.scene.lock().expect("scene lock");
-            for (key, view) in scene.raw_data() {
-                let len = view.rows * view.cols;
-                let matrix = if len == 0 {
-                    MatrixXF::zeros(view.rows, view.cols)
-                } else {
-                    let slice = unsafe { std::slice::from_raw_parts(view.ptr, len) };
-                    MatrixXF::from_column_slice(view.rows, view.cols, slice)
-                };
-                dict.set_item(key, matrix_to_numpy(py, &matrix))?;
+        Some("ptracer" | "sppm" | "pssmlt") => None,
+        _ => Some(match &options.integrator {
+            Some(name) => create_integrator(name, max_depth, spp, step_size, settings.firefly)
+                .ok_or_else(|| format!("unsupported integrator: {}", name))?,
+            None => {
+                let name = settings.integrator_type.as_deref().unwrap_or("path");
+                create_integrator(name, max_depth, spp, step_size, settings.firefly).unwrap_or_else(|| {
+                    eprintln!("Unsupported integrator '{}', falling back to path.", name);
+                    Box::new(PathIntegrator::new(max_depth, spp).with_firefly_settings(settings.firefly))
+                })
             }
-            Ok(dict.to_object(py))
-        }
+        }),
+    };
+    let aov_types = options
+        .aovs
+        .iter()
+        .map(|name| AovType::from_name(name).ok_or_else(|| format!("unsupported aov: {}", name)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let sampler_name = options
+        .sampler
+        .as_deref()
+        .or(settings.sampler_type.as_deref())
+        .unwrap_or("independent");
+    let sampler = create_sampler(sampler_name, spp, options.seed)
+        .ok_or_else(|| format!("unsupported sampler: {}", sampler_name))?;
 
-        fn hierarchy(&self) -> String {
-            let scene = self.scene.lock().expect("scene lock");
-            scene.to_string()
+    // AOVs are rendered first so the sensor is left holding the image.
+    let mut aovs = Vec::with_capacity(aov_types.len());
+    for aov in aov_types {
+        let mut renderer = SimpleRenderer::new(Box::new(AovIntegrator::new(aov, spp)), options.camera_id, options.seed)
+            .with_sampler(sampler.clone_box());
+        if let Some(cancel) = &options.cancel {
+            renderer = renderer.with_cancel_flag(Arc::clone(cancel));
         }
+        aovs.push((aov, renderer.render(scene)));
+    }
+    let features = if options.denoise {
+        let mut render_feature = |aov| {
+            let integrator = AovIntegrator::new(aov, spp).with_skip_specular(true);
+            let mut renderer = SimpleRenderer::new(Box::new(integrator), options.camera_id, options.seed)
+                .with_sampler(sampler.clone_box());
+            if let Some(cancel) = &options.cancel {
+                renderer = renderer.with_cancel_flag(Arc::clone(cancel));
+            }
+            renderer.render(scene)
+        };
+        Some(DenoiserFeatures {
+            albedo: render_feature(AovType::Albedo),
+            normal: render_feature(AovType::Normal),
+            depth: render_feature(AovType::Depth),
+        })
+    } else {
+        None
+    };
 
-        fn raw_data_item(&self, py: Python<'_>, key: &str) -> PyResult<Option<PyObject>> {
-            let scene = self.scene.lock().expect("scene lock");
-            let view = match scene.raw_data_view(key) {
-                Some(view) => view,
-                None => return Ok(None),
-            };
-            let len = view.rows * view.cols;
-            let matrix = if len == 0 {
-                MatrixXF::zeros(view.rows, view.cols)
-            } else {
-                let slice = unsafe { std::slice::from_raw_parts(view.ptr, len) };
-                MatrixXF::from_column_slice(view.rows, view.cols, slice)
+    let integrator = match integrator {
+        Some(integrator) => integrator,
+        None => {
+            let renderer: Box<dyn Renderer> = match integrator_name {
+                Some("sppm") => {
+                    let integrator = SppmIntegrator::new(max_depth, spp).with_settings(settings.sppm);
+                    let mut renderer = SppmRenderer::new(integrator, options.camera_id, options.seed);
+                    if let Some(callback) = &options.progress {
+                        renderer = renderer.with_progress_callback(callback.clone());
+                    }
+                    if let Some(cancel) = &options.cancel {
+                        renderer = renderer.with_cancel_flag(Arc::clone(cancel));
+                    }
+                    Box::new(renderer)
+                }
+                Some("pssmlt") => {
+                    let path = PathIntegrator::new(max_depth, spp).with_firefly_settings(settings.firefly);
+                    let integrator = PssmltIntegrator::new(path, spp).with_settings(settings.mlt);
+                    let mut renderer = PssmltRenderer::new(integrator, options.camera_id, options.seed);
+                    if let Some(callback) = &options.progress {
+                        renderer = renderer.with_progress_callback(callback.clone());
+                    }
+                    if let Some(cancel) = &options.cancel {
+                        renderer = renderer.with_cancel_flag(Arc::clone(cancel));
+                    }
+                    Box::new(renderer)
+                }
+                _ => {
+                    let mut renderer =
+                        LightTracingRenderer::new(LightTracerIntegrator::new(max_depth, spp), options.camera_id, options.seed);
+                    if let Some(callback) = &options.progress {
+                        renderer = renderer.with_progress_callback(callback.clone());
+                    }
+                    if let Some(cancel) = &options.cancel {
+                        renderer = renderer.with_cancel_flag(Arc::clone(cancel));
+                    }
+                    Box::new(renderer)
+                }
             };
-            Ok(Some(matrix_to_numpy(py, &matrix)))
+            let image = renderer.render(scene);
+            let mut sample_count = Bitmap::new(image.width(), image.height());
+            for y in 0..image.height() {
+                for x in 0..image.width() {
+                    sample_count.set_pixel(x, y, Vector3f::new(spp as Float, spp as Float, spp as Float));
+                }
+            }
+            return Ok(finish_output(image, sample_count, aovs, features));
         }
+    };
+    let mut renderer: SimpleRenderer = SimpleRenderer::new(integrator, options.camera_id, options.seed)
+        .with_sampler(sampler);
+    if let Some(callback) = &options.progress {
+        renderer = renderer.with_progress_callback(callback.clone());
     }
-
-    #[pyfunction]
-    fn load_scene(py: Python<'_>, scene_path: &str) -> PyResult<Py<PyScene>> {
-        let load_result = load_scene_with_settings(scene_path)
-            .map_err(|err| PyRuntimeError::new_err(format!("failed to load scene: {:?}", err)))?;
-        Py::new(py, PyScene { scene: Mutex::new(load_result.scene) })
+    if options.target_error.is_some() || options.max_spp.is_some() {
+        let target_error = options.target_error.unwrap_or(0.0);
+        let max_spp = options.max_spp.unwrap_or(spp.saturating_mul(16));
+        renderer = renderer.with_adaptive_sampling(target_error, max_spp);
+    }
+    if let Some(cancel) = &options.cancel {
+        renderer = renderer.with_cancel_flag(Arc::clone(cancel));
+    }
+    if !progressive {
+        let (image, sample_count) = renderer.render_with_sample_count(scene);
+        return Ok(finish_output(image, sample_count, aovs, features));
     }
 
-    #[pyfunction]
-    fn render(py: Python<'_>, scene: &PyScene, spp: u32, max_depth: u32) -> PyResult<PyObject> {
-        let integrator: Box<dyn Integrator> = Box::new(PathIntegrator::new(max_depth, spp));
-        let renderer: SimpleRenderer = SimpleRenderer::new(integrator, 0, 0);
-        let mut scene = scene.scene.lock().expect("scene lock");
-        let image = renderer.render(&mut scene);
-        Ok(matrix_to_numpy(py, image.matrix()))
+    let max_passes = options.time_budget.and(options.spp);
+    renderer = renderer.with_progressive(options.time_budget, max_passes);
+    if let Some(path) = &options.resume_path {
+        let checkpoint = Checkpoint::read_from_file(path)
+            .map_err(|err| format!("failed to read checkpoint {}: {}", path, err))?;
+        let (width, height) = scene
+            .camera(options.camera_id)
+            .map(|sensor| (sensor.bitmap().width(), sensor.bitmap().height()))
+            .unwrap_or((0, 0));
+        if let Some(mismatch) = renderer.checkpoint_mismatch(&checkpoint, width, height) {
+            return Err(format!("checkpoint {} {}", path, mismatch));
+        }
+        renderer = renderer.with_resume(checkpoint);
     }
+    if let Some(path) = options.checkpoint_path.as_ref().or(options.resume_path.as_ref()) {
+        let interval = options.checkpoint_interval.unwrap_or(16);
+        renderer = renderer.with_checkpoint(PathBuf::from(path), interval);
+    }
+    let snapshot_interval = match options.snapshot_path {
+        Some(_) => options.snapshot_interval.unwrap_or(0),
+        None => 0,
+    };
+    let mut write_snapshot = |pass: u32, image: &Bitmap| {
+        if let Some(path) = &options.snapshot_path {
+            let path = path.replace("{pass}", &format!("{:05}", pass));
+            if let Err(err) = write_image(&path, &Image::from_bitmap(image)) {
+                log::warn!("Failed to write snapshot {}: {}", path, err);
+            }
+        }
+    };
+    let (image, sample_count) = renderer.render_progressive(scene, snapshot_interval, &mut write_snapshot);
+    Ok(finish_output(image, sample_count, aovs, features))
+}
 
-    #[pymodule]
-    fn dacquoise(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
-        m.add_function(wrap_pyfunction!(render, m)?)?;
-        m.add_function(wrap_pyfunction!(load_scene, m)?)?;
-        Ok(())
+fn finish_output(
+    image: Bitmap,
+    sample_count: Bitmap,
+    aovs: Vec<(AovType, Bitmap)>,
+    features: Option<DenoiserFeatures>,
+) -> RenderOutput {
+    match features {
+        Some(features) => RenderOutput {
+            image: denoise(&image, &features, &DenoiseSettings::default()),
+            sample_count,
+            aovs,
+            noisy: Some(image),
+        },
+        None => RenderOutput { image, sample_count, aovs, noisy: None },
     }
 }
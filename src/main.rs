@@ -1,9 +1,14 @@
 // Copyright 2020 TwoCookingMice
 
-use dacquoise::io::exr_utils;
-use dacquoise::render_scene;
+use dacquoise::io::image_io::{write_image, write_ldr, Image, ImageFormat};
+use dacquoise::io::tonemap::{TonemapSettings, Tonemapper, TransferFunction};
+use dacquoise::{render_scene_with_options, RenderOptions};
 
 use std::env;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 fn main() {
     env::set_var("RUST_LOG", "info");
@@ -11,47 +16,193 @@ fn main() {
 
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
-        eprintln!("Usage: {} <scene.xml> <output.exr> [--spp N] [--max-depth N] [--seed N] [--camera N]", args[0]);
+        eprintln!("Usage: {} <scene.xml> <output.exr|.hdr|.pfm|.png|.jpg> [--spp N] [--max-depth N] [--seed N] [--camera N] [--sampler TYPE] [--target-error E] [--max-spp N] [--sample-count out.exr] [--progressive] [--time-budget SECONDS] [--snapshot-every N] [--checkpoint file] [--checkpoint-every N] [--resume file] [--exposure STOPS] [--tonemap clamp|reinhard|aces|agx] [--transfer srgb|rec709|linear] [--dither] [--denoise] [-D name=value]", args[0]);
         std::process::exit(1);
     }
 
     let input_path = &args[1];
     let output_path = &args[2];
-    let mut spp_override: Option<u32> = None;
-    let mut max_depth_override: Option<u32> = None;
-    let mut seed: u64 = 0;
-    let mut camera_id: usize = 0;
+    let mut options = RenderOptions::default();
+    let mut sample_count_path: Option<String> = None;
+    let mut tonemap = TonemapSettings::default();
 
     let mut i = 3;
     while i < args.len() {
         match args[i].as_str() {
             "--spp" => {
                 i += 1;
-                spp_override = args.get(i).and_then(|v| v.parse::<u32>().ok());
+                options.spp = args.get(i).and_then(|v| v.parse::<u32>().ok());
             }
             "--max-depth" => {
                 i += 1;
-                max_depth_override = args.get(i).and_then(|v| v.parse::<u32>().ok());
+                options.max_depth = args.get(i).and_then(|v| v.parse::<u32>().ok());
             }
             "--seed" => {
                 i += 1;
-                seed = args.get(i).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+                options.seed = args.get(i).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
             }
             "--camera" => {
                 i += 1;
-                camera_id = args.get(i).and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+                options.camera_id = args.get(i).and_then(|v| v.parse::<usize>().ok()).unwrap_or(0);
+            }
+            "--sampler" => {
+                i += 1;
+                options.sampler = args.get(i).cloned();
+            }
+            "--target-error" => {
+                i += 1;
+                options.target_error = args.get(i).and_then(|v| v.parse::<f32>().ok());
+            }
+            "--max-spp" => {
+                i += 1;
+                options.max_spp = args.get(i).and_then(|v| v.parse::<u32>().ok());
+            }
+            "--progressive" => {
+                options.progressive = true;
+            }
+            "--time-budget" => {
+                i += 1;
+                options.time_budget = args
+                    .get(i)
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .map(Duration::from_secs_f64);
+            }
+            "--snapshot-every" => {
+                i += 1;
+                options.snapshot_interval = args.get(i).and_then(|v| v.parse::<u32>().ok());
+            }
+            "--checkpoint" => {
+                i += 1;
+                options.checkpoint_path = args.get(i).cloned();
+            }
+            "--checkpoint-every" => {
+                i += 1;
+                options.checkpoint_interval = args.get(i).and_then(|v| v.parse::<u32>().ok());
+            }
+            "--resume" => {
+                i += 1;
+                options.resume_path = args.get(i).cloned();
+            }
+            "--sample-count" => {
+                i += 1;
+                sample_count_path = args.get(i).cloned();
+            }
+            "--exposure" => {
+                i += 1;
+                tonemap.exposure = args.get(i).and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+            }
+            "--tonemap" => {
+                i += 1;
+                let name = args.get(i).map(String::as_str).unwrap_or("");
+                match Tonemapper::from_name(name) {
+                    Some(tonemapper) => tonemap.tonemapper = tonemapper,
+                    None => eprintln!("Ignoring unknown tonemapper '{}'.", name),
+                }
+            }
+            "--transfer" => {
+                i += 1;
+                let name = args.get(i).map(String::as_str).unwrap_or("");
+                match TransferFunction::from_name(name) {
+                    Some(transfer) => tonemap.transfer = transfer,
+                    None => eprintln!("Ignoring unknown transfer function '{}'.", name),
+                }
+            }
+            "--dither" => {
+                tonemap.dither = true;
+            }
+            "--denoise" => {
+                options.denoise = true;
+            }
+            // Accepts both `-D name=value` and `-Dname=value`.
+            arg if arg.starts_with("-D") => {
+                let define = if arg == "-D" {
+                    i += 1;
+                    args.get(i).map(String::as_str).unwrap_or("")
+                } else {
+                    &arg[2..]
+                };
+                match define.split_once('=') {
+                    Some((name, value)) if !name.is_empty() => {
+                        options.defines.insert(name.to_string(), value.to_string());
+                    }
+                    _ => {
+                        eprintln!("Ignoring malformed define '{}', expected -D name=value.", define);
+                    }
+                }
             }
             _ => {}
         }
         i += 1;
     }
 
-    let image = match render_scene(input_path, spp_override, max_depth_override, seed, camera_id) {
-        Ok(image) => image,
+    if options.snapshot_interval.is_some() {
+        let stem = Path::new(output_path).with_extension("");
+        let stem = stem.to_string_lossy();
+        options.snapshot_path = Some(format!("{}_pass{{pass}}.exr", stem));
+        options.progressive = true;
+    }
+
+    // In progressive mode Ctrl-C stops the render after the current pass so
+    // the estimate so far is still written out.
+    if options.progressive
+        || options.time_budget.is_some()
+        || options.checkpoint_path.is_some()
+        || options.resume_path.is_some()
+    {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let handler_flag = Arc::clone(&cancel);
+        if let Err(err) = ctrlc::set_handler(move || handler_flag.store(true, Ordering::Relaxed)) {
+            eprintln!("Failed to install Ctrl-C handler: {}", err);
+        }
+        options.cancel = Some(cancel);
+    }
+
+    let output = match render_scene_with_options(input_path, &options) {
+        Ok(output) => output,
         Err(err) => {
             eprintln!("Render failed: {}", err);
             std::process::exit(1);
         }
     };
-    exr_utils::write_exr_to_file(&image.raw_copy(), image.width(), image.height(), output_path);
+    match &output.noisy {
+        // EXR keeps the noisy image as a `noisy` layer of the same file.
+        Some(noisy) if ImageFormat::from_path(output_path) == Some(ImageFormat::Exr) => {
+            let (width, height) = (output.image.width(), output.image.height());
+            let channels = ["R", "G", "B", "noisy.R", "noisy.G", "noisy.B"];
+            let mut image = Image::new(width, height, channels.iter().map(|c| c.to_string()).collect());
+            for y in 0..height {
+                for x in 0..width {
+                    let (denoised, raw) = (output.image.pixel(x, y), noisy.pixel(x, y));
+                    image.pixel_mut(x, y).copy_from_slice(&[denoised.x, denoised.y, denoised.z, raw.x, raw.y, raw.z]);
+                }
+            }
+            write_output(&image, output_path, &tonemap);
+        }
+        Some(noisy) => {
+            write_output(&Image::from_bitmap(&output.image), output_path, &tonemap);
+            let path = Path::new(output_path);
+            let extension = path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+            let noisy_path = format!("{}_noisy.{}", path.with_extension("").to_string_lossy(), extension);
+            write_output(&Image::from_bitmap(noisy), &noisy_path, &tonemap);
+        }
+        None => write_output(&Image::from_bitmap(&output.image), output_path, &tonemap),
+    }
+    if let Some(path) = sample_count_path {
+        write_output(&Image::from_bitmap(&output.sample_count), &path, &tonemap);
+    }
+}
+
+// PNG and JPEG outputs go through the tonemapping pipeline.
+fn write_output(image: &Image, path: &str, tonemap: &TonemapSettings) {
+    let result = match ImageFormat::from_path(path) {
+        Some(format) if format.is_ldr() => write_ldr(path, image, tonemap),
+        _ => write_image(path, image),
+    };
+    match result {
+        Ok(()) => log::info!("Image written to: {}.", path),
+        Err(err) => {
+            eprintln!("Failed to write {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
 }
@@ -4,8 +4,10 @@ use std::sync::Arc;
 
 use crate::core::bsdf::{BSDFSampleRecord, BSDFEvalResult, BSDF};
 use crate::core::computation_node::{ComputationNode, generate_node_id, indent_string};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{Float, Vector2f, Vector3f};
 
+/// `weight` times `bsdf_a` plus `1 - weight` times `bsdf_b`.
 pub struct BlendBSDF {
     id: String,
     bsdf_a: Arc<dyn BSDF>,
@@ -35,12 +37,22 @@ impl BlendBSDF {
 }
 
 impl BSDF for BlendBSDF {
+    fn is_specular(&self) -> bool {
+        self.bsdf_a.is_specular() && self.bsdf_b.is_specular()
+    }
+
+    fn uses_vertex_colors(&self) -> bool {
+        self.bsdf_a.uses_vertex_colors() || self.bsdf_b.uses_vertex_colors()
+    }
+
     fn eval(&self, sample_record: BSDFSampleRecord) -> BSDFEvalResult {
         if sample_record.wi.z <= 0.0 {
             return BSDFEvalResult::default();
         }
-        let rec_a = BSDFSampleRecord::new(sample_record.wi, sample_record.wo, 0.0, sample_record.uv);
-        let rec_b = BSDFSampleRecord::new(sample_record.wi, sample_record.wo, 0.0, sample_record.uv);
+        let rec_a = BSDFSampleRecord::new(sample_record.wi, sample_record.wo, 0.0, sample_record.uv)
+            .with_color(sample_record.color);
+        let rec_b = BSDFSampleRecord::new(sample_record.wi, sample_record.wo, 0.0, sample_record.uv)
+            .with_color(sample_record.color);
 
         let eval_a = self.bsdf_a.eval(rec_a);
         let eval_b = self.bsdf_b.eval(rec_b);
@@ -82,4 +94,59 @@ impl BSDF for BlendBSDF {
         let sampling_record = self.sample(u1, u2, wi);
         self.eval(sampling_record)
     }
+
+    fn regularized(&self, min_alpha: Float) -> Option<Box<dyn BSDF>> {
+        let bsdf_a = self.bsdf_a.regularized(min_alpha);
+        let bsdf_b = self.bsdf_b.regularized(min_alpha);
+        if bsdf_a.is_none() && bsdf_b.is_none() {
+            return None;
+        }
+        Some(Box::new(Self {
+            id: self.id.clone(),
+            bsdf_a: bsdf_a.map(Arc::from).unwrap_or_else(|| self.bsdf_a.clone()),
+            bsdf_b: bsdf_b.map(Arc::from).unwrap_or_else(|| self.bsdf_b.clone()),
+            weight: self.weight,
+        }))
+    }
+
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("bsdf", "blendbsdf")
+            .with_child(XmlElement::float("weight", self.weight))
+            .with_child(self.bsdf_a.to_xml(ctx)?)
+            .with_child(self.bsdf_b.to_xml(ctx)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+    use crate::materials::null::NullBSDF;
+    use crate::math::spectrum::RGBSpectrum;
+    use crate::textures::constant::ConstantTexture;
+
+    fn diffuse(albedo: Float) -> Arc<dyn BSDF> {
+        Arc::new(LambertianDiffuseBSDF::new(Arc::new(ConstantTexture::new(RGBSpectrum::new(albedo, albedo, albedo))), None))
+    }
+
+    #[test]
+    fn test_weight_is_the_weight_of_the_first_bsdf() {
+        let wi = Vector3f::new(0.0, 0.0, 1.0);
+        let wo = Vector3f::new(0.6, 0.0, 0.8);
+        let eval = |weight: Float| {
+            let blend = BlendBSDF::new(diffuse(0.2), diffuse(0.8), weight, None);
+            blend.eval(BSDFSampleRecord::new(wi, wo, 0.0, Vector2f::zeros())).value[0]
+        };
+        let value = |albedo: Float| diffuse(albedo).eval(BSDFSampleRecord::new(wi, wo, 0.0, Vector2f::zeros())).value[0];
+        assert_eq!(eval(1.0), value(0.2));
+        assert_eq!(eval(0.0), value(0.8));
+        assert!((eval(0.25) - (0.25 * value(0.2) + 0.75 * value(0.8))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_uses_vertex_colors_through_either_bsdf() {
+        let null = || Arc::new(NullBSDF::new(None)) as Arc<dyn BSDF>;
+        assert!(BlendBSDF::new(null(), diffuse(0.5), 0.5, None).uses_vertex_colors());
+        assert!(!BlendBSDF::new(null(), null(), 0.5, None).uses_vertex_colors());
+    }
 }
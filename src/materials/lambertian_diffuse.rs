@@ -2,6 +2,8 @@
 
 use crate::core::bsdf::{BSDFSampleRecord, BSDFEvalResult, BSDF};
 use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::gradient::GradientBuffers;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::texture::Texture;
 use crate::math::constants::{ INV_PI, Vector2f, Vector3f };
 use crate::math::spectrum::RGBSpectrum;
@@ -25,6 +27,10 @@ impl ComputationNode for LambertianDiffuseBSDF {
 }
 
 impl BSDF for LambertianDiffuseBSDF {
+    fn uses_vertex_colors(&self) -> bool {
+        true
+    }
+
     fn eval(&self, sample_record: BSDFSampleRecord) -> BSDFEvalResult {
         let mut eval_result = BSDFEvalResult::default();
         if sample_record.wi.z <= 0.0 || sample_record.wo.z <= 0.0 {
@@ -33,7 +39,7 @@ impl BSDF for LambertianDiffuseBSDF {
             return eval_result;
         }
 
-        let color = self.texture.eval(sample_record.uv);
+        let color = self.texture.eval_with_color(sample_record.uv, sample_record.color);
         eval_result.value = color * INV_PI;
         eval_result.pdf = sample_cosine_hemisphere_pdf(sample_record.wo.z.abs());
 
@@ -64,6 +70,18 @@ impl BSDF for LambertianDiffuseBSDF {
 
         return eval_result
     }
+
+    fn diffuse_reflectance(&self, uv: Vector2f, color: Option<RGBSpectrum>) -> Option<RGBSpectrum> {
+        Some(self.texture.eval_with_color(uv, color))
+    }
+
+    fn backward_reflectance(&self, uv: Vector2f, color: Option<RGBSpectrum>, grad: &RGBSpectrum, grads: &mut GradientBuffers) {
+        self.texture.backward(uv, color, grad, grads);
+    }
+
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("bsdf", "diffuse").with_children(self.texture.to_xml("reflectance", ctx)?))
+    }
 }
 
 impl LambertianDiffuseBSDF {
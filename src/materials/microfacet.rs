@@ -1,5 +1,6 @@
 // Copyright @yucwang 2026
 
+use crate::core::scene_writer::XmlElement;
 use crate::math::constants::{Float, Vector2f, Vector3f, PI};
 use crate::math::spectrum::RGBSpectrum;
 use crate::math::warp::sample_uniform_disk_concentric;
@@ -72,6 +73,21 @@ impl MicrofacetDistribution {
         String::from("MicrofacetDistribution\n    m_type: MicrofacetType\n    alpha_u: Float\n    alpha_v: Float\n    sample_visible: bool")
     }
 
+    /// `distribution`, `alpha_u`, `alpha_v` and `sample_visible` properties
+    /// of a rough bsdf element.
+    pub fn xml_properties(&self) -> Vec<XmlElement> {
+        let name = match self.m_type {
+            MicrofacetType::Beckmann => "beckmann",
+            MicrofacetType::GGX => "ggx",
+        };
+        vec![
+            XmlElement::string("distribution", name),
+            XmlElement::float("alpha_u", self.alpha_u),
+            XmlElement::float("alpha_v", self.alpha_v),
+            XmlElement::boolean("sample_visible", self.sample_visible),
+        ]
+    }
+
     pub fn new(m_type: MicrofacetType, alpha_u: Float, alpha_v: Float, sample_visible: bool) -> Self {
         let alpha_u = alpha_u.max(1e-4);
         let alpha_v = alpha_v.max(1e-4);
@@ -83,6 +99,21 @@ impl MicrofacetDistribution {
         }
     }
 
+    /// Copy with both roughnesses raised to at least `min_alpha`, `None`
+    /// if they already are.
+    pub fn regularized(&self, min_alpha: Float) -> Option<Self> {
+        if self.alpha_u >= min_alpha && self.alpha_v >= min_alpha {
+            return None;
+        }
+        Some(Self { alpha_u: self.alpha_u.max(min_alpha), alpha_v: self.alpha_v.max(min_alpha), ..*self })
+    }
+
+    /// Roughness low enough for the lobe to act as a mirror, e.g. when
+    /// gathering denoiser features.
+    pub fn is_nearly_specular(&self) -> bool {
+        self.alpha_u.max(self.alpha_v) < 0.05
+    }
+
     pub fn eval(&self, m: &Vector3f) -> Float {
         let cos_theta = m.z;
         if cos_theta <= 0.0 {
@@ -6,3 +6,4 @@ pub mod roughconductor;
 pub mod roughdielectric;
 pub mod blend;
 pub mod null;
+pub mod validation;
@@ -2,6 +2,7 @@
 
 use crate::core::bsdf::{BSDFSampleRecord, BSDFEvalResult, BSDF};
 use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{Vector2f, Vector3f};
 use crate::math::spectrum::RGBSpectrum;
 
@@ -63,6 +64,10 @@ impl BSDF for NullBSDF {
         let sample = self.sample(u1, u2, wi);
         self.eval(sample)
     }
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("bsdf", "null"))
+    }
 }
 
 #[cfg(test)]
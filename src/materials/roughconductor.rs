@@ -2,6 +2,7 @@
 
 use crate::core::bsdf::{BSDFSampleRecord, BSDFEvalResult, BSDF};
 use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{Float, Vector2f, Vector3f};
 use crate::math::spectrum::RGBSpectrum;
 use crate::materials::microfacet::{
@@ -52,6 +53,10 @@ impl RoughConductorBSDF {
 }
 
 impl BSDF for RoughConductorBSDF {
+    fn is_specular(&self) -> bool {
+        self.distribution.is_nearly_specular()
+    }
+
     fn eval(&self, sample_record: BSDFSampleRecord) -> BSDFEvalResult {
         let mut eval_result = BSDFEvalResult::default();
         let wi = sample_record.wi;
@@ -78,7 +83,7 @@ impl BSDF for RoughConductorBSDF {
             return eval_result;
         }
         let g = self.distribution.g(&wi, &wo, &m);
-        let denom = 4.0 * wi.z;
+        let denom = 4.0 * wi.z * wo.z;
         if denom <= 1e-6 {
             return eval_result;
         }
@@ -126,4 +131,17 @@ impl BSDF for RoughConductorBSDF {
         let sampling_record = self.sample(u1, u2, wi);
         self.eval(sampling_record)
     }
+
+    fn regularized(&self, min_alpha: Float) -> Option<Box<dyn BSDF>> {
+        let distribution = self.distribution.regularized(min_alpha)?;
+        Some(Box::new(Self { id: self.id.clone(), distribution, ..*self }))
+    }
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("bsdf", "roughconductor")
+            .with_children(self.distribution.xml_properties())
+            .with_child(XmlElement::rgb("eta", self.eta))
+            .with_child(XmlElement::rgb("k", self.k))
+            .with_child(XmlElement::rgb("specular_reflectance", self.specular_reflectance)))
+    }
 }
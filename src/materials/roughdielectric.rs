@@ -2,6 +2,7 @@
 
 use crate::core::bsdf::{BSDFSampleRecord, BSDFEvalResult, BSDF};
 use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{Float, Vector2f, Vector3f};
 use crate::math::spectrum::RGBSpectrum;
 use crate::materials::microfacet::{
@@ -62,6 +63,10 @@ impl RoughDielectricBSDF {
 }
 
 impl BSDF for RoughDielectricBSDF {
+    fn is_specular(&self) -> bool {
+        self.distribution.is_nearly_specular()
+    }
+
     fn eval(&self, sample_record: BSDFSampleRecord) -> BSDFEvalResult {
         let mut eval_result = BSDFEvalResult::default();
         let wi = sample_record.wi;
@@ -181,4 +186,18 @@ impl BSDF for RoughDielectricBSDF {
         let sampling_record = self.sample(u1, u2, wi);
         self.eval(sampling_record)
     }
+
+    fn regularized(&self, min_alpha: Float) -> Option<Box<dyn BSDF>> {
+        let distribution = self.distribution.regularized(min_alpha)?;
+        Some(Box::new(Self { id: self.id.clone(), distribution, ..*self }))
+    }
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("bsdf", "roughdielectric")
+            .with_children(self.distribution.xml_properties())
+            .with_child(XmlElement::float("int_ior", self.int_ior))
+            .with_child(XmlElement::float("ext_ior", self.ext_ior))
+            .with_child(XmlElement::rgb("specular_reflectance", self.specular_reflectance))
+            .with_child(XmlElement::rgb("specular_transmittance", self.specular_transmittance)))
+    }
 }
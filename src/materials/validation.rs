@@ -0,0 +1,651 @@
+// Copyright @yucwang 2026
+
+//! Statistical checks that a BSDF's `sample`, `eval` and `sample_and_eval`
+//! describe the same distribution, in the spirit of Mitsuba's chi-square
+//! tests. Every check takes a `&dyn BSDF`, so new materials are validated by
+//! adding them to the test list at the bottom of this file.
+
+use crate::core::bsdf::{BSDFSampleRecord, BSDF};
+use crate::core::rng::LcgRng;
+use crate::io::image_io::Image;
+use crate::materials::microfacet::MicrofacetDistribution;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+
+use std::f64::consts::PI;
+
+/// Resolution and size of a chi-square test over the sphere of directions.
+#[derive(Clone, Copy, Debug)]
+pub struct ChiSquareSettings {
+    /// Bins in `cos(theta)` over [-1, 1]; bins have equal solid angle.
+    pub theta_bins: usize,
+    pub phi_bins: usize,
+    pub sample_count: usize,
+    /// Sub-cells per bin side used to integrate the pdf.
+    pub integration_resolution: usize,
+    /// Bins expecting fewer samples are pooled into one.
+    pub min_expected: f64,
+    pub seed: u64,
+}
+
+impl Default for ChiSquareSettings {
+    fn default() -> Self {
+        Self {
+            theta_bins: 10,
+            phi_bins: 20,
+            sample_count: 100_000,
+            integration_resolution: 16,
+            min_expected: 5.0,
+            seed: 7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChiSquareResult {
+    pub statistic: f64,
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+    /// Samples that landed where the pdf integrates to zero. Any such
+    /// sample fails the test.
+    pub unexpected_samples: usize,
+}
+
+impl ChiSquareResult {
+    pub fn passed(&self, significance: f64) -> bool {
+        self.unexpected_samples == 0 && self.p_value >= significance
+    }
+}
+
+/// Per-test significance that keeps the chance of any of `test_count`
+/// tests failing by accident at `significance` (Šidák correction).
+pub fn corrected_significance(significance: f64, test_count: usize) -> f64 {
+    1.0 - (1.0 - significance).powf(1.0 / test_count.max(1) as f64)
+}
+
+const EDGE_REFINEMENT: usize = 4;
+
+/// Observed and expected sample counts on a `width` x `height` grid of
+/// bins, stored row by row.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub width: usize,
+    pub height: usize,
+    pub observed: Vec<f64>,
+    pub expected: Vec<f64>,
+}
+
+impl Histogram {
+    /// Pearson's chi-square test of `observed` against `expected`. Bins
+    /// expecting fewer than `min_expected` samples are pooled into one.
+    pub fn chi_square(&self, min_expected: f64) -> ChiSquareResult {
+        let mut statistic = 0.0;
+        let mut cells = 0usize;
+        let mut unexpected_samples = 0usize;
+        let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+        for (o, e) in self.observed.iter().zip(&self.expected) {
+            if *e <= 0.0 {
+                unexpected_samples += *o as usize;
+            } else if *e < min_expected {
+                pooled_observed += o;
+                pooled_expected += e;
+            } else {
+                statistic += (o - e) * (o - e) / e;
+                cells += 1;
+            }
+        }
+        if pooled_expected > 0.0 {
+            statistic += (pooled_observed - pooled_expected).powi(2) / pooled_expected;
+            cells += 1;
+        }
+        let degrees_of_freedom = cells.saturating_sub(1).max(1);
+        ChiSquareResult {
+            statistic,
+            degrees_of_freedom,
+            p_value: chi_square_survival(statistic, degrees_of_freedom),
+            unexpected_samples,
+        }
+    }
+
+    /// Image with `observed`, `expected` and `residual` channels, the
+    /// residual being `(observed - expected) / sqrt(expected)`.
+    pub fn to_image(&self) -> Image {
+        let channels = ["observed", "expected", "residual"];
+        let mut image = Image::new(self.width, self.height, channels.iter().map(|c| c.to_string()).collect());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (o, e) = (self.observed[y * self.width + x], self.expected[y * self.width + x]);
+                let residual = if e > 0.0 { (o - e) / e.sqrt() } else { o };
+                image.pixel_mut(x, y).copy_from_slice(&[o as Float, e as Float, residual as Float]);
+            }
+        }
+        image
+    }
+}
+
+/// Histogram of the directions drawn by `sample` (which gets two uniform 2D
+/// samples and may fail) next to the integral of `pdf` over each bin. Rows
+/// are `settings.theta_bins` bands of equal solid angle from `+z` down to
+/// `-z`, columns `settings.phi_bins` azimuth wedges. Failed samples count
+/// towards the total, so the pdf must integrate to the probability that
+/// sampling succeeds.
+pub fn direction_histogram<S, P>(mut sample: S, pdf: P, settings: &ChiSquareSettings) -> Histogram
+where
+    S: FnMut(Vector2f, Vector2f) -> Option<Vector3f>,
+    P: Fn(&Vector3f) -> Float,
+{
+    let (theta_bins, phi_bins) = (settings.theta_bins, settings.phi_bins);
+    let bin_of = |d: &Vector3f| -> usize {
+        let d = d.normalize();
+        let t = (((1.0 - d.z as f64) * 0.5 * theta_bins as f64) as usize).min(theta_bins - 1);
+        let phi = (d.y as f64).atan2(d.x as f64).rem_euclid(2.0 * PI);
+        let p = ((phi / (2.0 * PI) * phi_bins as f64) as usize).min(phi_bins - 1);
+        t * phi_bins + p
+    };
+
+    let mut observed = vec![0.0f64; theta_bins * phi_bins];
+    let mut rng = LcgRng::new(settings.seed);
+    for _ in 0..settings.sample_count {
+        let u1 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        let u2 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        if let Some(d) = sample(u1, u2) {
+            if d.iter().all(|v| v.is_finite()) && d.norm_squared() > 0.0 {
+                observed[bin_of(&d)] += 1.0;
+            }
+        }
+    }
+
+    let res = settings.integration_resolution.max(1);
+    let mut expected = vec![0.0f64; theta_bins * phi_bins];
+    for (bin, value) in expected.iter_mut().enumerate() {
+        let (t, p) = (bin / phi_bins, bin % phi_bins);
+        let cos_range = (1.0 - 2.0 * t as f64 / theta_bins as f64, 1.0 - 2.0 * (t + 1) as f64 / theta_bins as f64);
+        let theta = (cos_range.0.clamp(-1.0, 1.0).acos(), cos_range.1.clamp(-1.0, 1.0).acos());
+        let phi = (2.0 * PI * p as f64 / phi_bins as f64, 2.0 * PI * (p + 1) as f64 / phi_bins as f64);
+        let density = |d: Vector3f| {
+            let density = pdf(&d) as f64;
+            if density.is_finite() {
+                density
+            } else {
+                0.0
+            }
+        };
+        let (mut zero, mut nonzero) = (false, false);
+        let mut integral = integrate_directions(theta, phi, res, |d| {
+            let value = density(d);
+            if value > 0.0 {
+                nonzero = true;
+            } else {
+                zero = true;
+            }
+            value
+        });
+        // Bins cut by the edge of the support, like the silhouette of an
+        // area light, converge slowly; integrate those more finely.
+        if zero && nonzero {
+            integral = integrate_directions(theta, phi, res * EDGE_REFINEMENT, density);
+        }
+        *value = integral * settings.sample_count as f64;
+    }
+    Histogram { width: phi_bins, height: theta_bins, observed, expected }
+}
+
+/// Chi-square test of the directions drawn by `sample` against `pdf`; see
+/// `direction_histogram`.
+pub fn chi_square_test<S, P>(sample: S, pdf: P, settings: &ChiSquareSettings) -> ChiSquareResult
+where
+    S: FnMut(Vector2f, Vector2f) -> Option<Vector3f>,
+    P: Fn(&Vector3f) -> Float,
+{
+    direction_histogram(sample, pdf, settings).chi_square(settings.min_expected)
+}
+
+/// Chi-square test of `bsdf.sample` against `bsdf.eval().pdf` for the
+/// incident direction `wi`.
+pub fn bsdf_chi_square(bsdf: &dyn BSDF, wi: Vector3f, settings: &ChiSquareSettings) -> ChiSquareResult {
+    chi_square_test(
+        |u1, u2| {
+            let record = bsdf.sample(u1, u2, wi);
+            if record.pdf > 0.0 {
+                Some(record.wo)
+            } else {
+                None
+            }
+        },
+        |wo| bsdf.eval(BSDFSampleRecord::new(wi, *wo, 0.0, Vector2f::zeros())).pdf,
+        settings,
+    )
+}
+
+/// Chi-square test of the microfacet normals drawn by `distribution` for
+/// `wi` against `distribution.pdf`.
+pub fn microfacet_chi_square(
+    distribution: &MicrofacetDistribution,
+    wi: Vector3f,
+    settings: &ChiSquareSettings,
+) -> ChiSquareResult {
+    chi_square_test(
+        |u1, _| {
+            let (m, pdf) = distribution.sample(&wi, &u1);
+            if pdf > 0.0 {
+                Some(m)
+            } else {
+                None
+            }
+        },
+        |m| distribution.pdf(&wi, m),
+        settings,
+    )
+}
+
+/// Integrals that a microfacet distribution must satisfy for `wi`: the
+/// projected microfacet area `∫ D(m) cos(theta_m) dm`, which is 1, and the
+/// projected area seen from `wi`, `∫ G1(wi, m) max(0, wi·m) D(m) dm`, which
+/// is `cos(theta_i)`.
+pub fn microfacet_projected_areas(distribution: &MicrofacetDistribution, wi: Vector3f, resolution: usize) -> (f64, f64) {
+    let hemisphere = ((0.0, 0.5 * PI), (0.0, 2.0 * PI));
+    let total = integrate_directions(hemisphere.0, hemisphere.1, resolution, |m| {
+        distribution.eval(&m) as f64 * m.z as f64
+    });
+    let visible = integrate_directions(hemisphere.0, hemisphere.1, resolution, |m| {
+        let projected = (wi.dot(&m) as f64).max(0.0);
+        distribution.eval(&m) as f64 * distribution.smith_g1(&wi, &m) as f64 * projected
+    });
+    (total, visible)
+}
+
+/// Directional albedo of `bsdf` for `wi`, estimated twice: as the mean
+/// sample weight `f cos / pdf` from `sample_and_eval`, and by integrating
+/// `eval` over the sphere. They agree when sampling is correct, and an
+/// energy conserving BSDF keeps both at or below 1. Transmitted radiance is
+/// scaled by the squared ratio of refractive indices, so transmission is
+/// weighted by `(eta_o / eta_i)^2` first, with `eta` as in
+/// `reciprocity_error`.
+pub fn white_furnace(bsdf: &dyn BSDF, wi: Vector3f, eta: Float, settings: &ChiSquareSettings) -> (Vector3f, Vector3f) {
+    let side = |d: &Vector3f| if d.z >= 0.0 { 1.0 } else { eta as f64 };
+    let radiance_scale = |wo: &Vector3f| (side(wo) / side(&wi)).powi(2);
+    let mut rng = LcgRng::new(settings.seed);
+    let mut sampled = [0.0f64; 3];
+    for _ in 0..settings.sample_count {
+        let u1 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        let u2 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        let wo = bsdf.sample(u1, u2, wi).wo;
+        let result = bsdf.sample_and_eval(u1, u2, wi);
+        if result.pdf > 0.0 {
+            let weight = radiance_scale(&wo) * wo.z.abs() as f64 / result.pdf as f64;
+            for (c, s) in sampled.iter_mut().enumerate() {
+                *s += result.value[c] as f64 * weight;
+            }
+        }
+    }
+
+    let res = settings.theta_bins * settings.integration_resolution;
+    let mut integrated = [0.0f64; 3];
+    for (c, value) in integrated.iter_mut().enumerate() {
+        *value = integrate_directions((0.0, PI), (0.0, 2.0 * PI), res, |wo| {
+            let f = bsdf.eval(BSDFSampleRecord::new(wi, wo, 0.0, Vector2f::zeros())).value;
+            radiance_scale(&wo) * f[c] as f64 * wo.z.abs() as f64
+        });
+    }
+    let n = settings.sample_count as f64;
+    (
+        Vector3f::new((sampled[0] / n) as Float, (sampled[1] / n) as Float, (sampled[2] / n) as Float),
+        Vector3f::new(integrated[0] as Float, integrated[1] as Float, integrated[2] as Float),
+    )
+}
+
+/// Midpoint rule for `∫ f(d) dd` over the directions with polar angle in
+/// `theta` and azimuth in `phi`, on a `resolution`² grid. Steps are uniform
+/// in angle rather than in cosine so lobes around the poles are resolved.
+pub(crate) fn integrate_directions<F: FnMut(Vector3f) -> f64>(theta: (f64, f64), phi: (f64, f64), resolution: usize, mut f: F) -> f64 {
+    let d_theta = (theta.1 - theta.0) / resolution as f64;
+    let d_phi = (phi.1 - phi.0) / resolution as f64;
+    let mut sum = 0.0;
+    for i in 0..resolution {
+        let t = theta.0 + (i as f64 + 0.5) * d_theta;
+        let (sin_t, cos_t) = t.sin_cos();
+        for j in 0..resolution {
+            let p = phi.0 + (j as f64 + 0.5) * d_phi;
+            let d = Vector3f::new((sin_t * p.cos()) as Float, (sin_t * p.sin()) as Float, cos_t as Float);
+            sum += f(d) * sin_t;
+        }
+    }
+    sum * d_theta * d_phi
+}
+
+/// Relative difference between `f(wi, wo) eta_o^2` and `f(wo, wi) eta_i^2`,
+/// where `eta_*` is the index of refraction on the side of each direction
+/// and `eta` the one below the surface (1 for opaque BSDFs). For
+/// reflection this is plain reciprocity.
+pub fn reciprocity_error(bsdf: &dyn BSDF, wi: Vector3f, wo: Vector3f, eta: Float) -> Float {
+    let side = |d: &Vector3f| if d.z >= 0.0 { 1.0 } else { eta };
+    let forward = bsdf.eval(BSDFSampleRecord::new(wi, wo, 0.0, Vector2f::zeros())).value;
+    let backward = bsdf.eval(BSDFSampleRecord::new(wo, wi, 0.0, Vector2f::zeros())).value;
+    let (eta_i2, eta_o2) = (side(&wi) * side(&wi), side(&wo) * side(&wo));
+    let mut error: Float = 0.0;
+    for c in 0..3 {
+        let (a, b) = (forward[c] * eta_o2, backward[c] * eta_i2);
+        let scale = a.abs().max(b.abs());
+        if scale > 1e-6 {
+            error = error.max((a - b).abs() / scale);
+        }
+    }
+    error
+}
+
+/// Disagreements found by `sample_consistency`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsistencyReport {
+    /// Successful samples checked.
+    pub samples: usize,
+    /// Samples whose pdf differs from `eval` of the sampled direction.
+    pub pdf_mismatches: usize,
+    /// Samples where `sample_and_eval` differs from `eval(sample(..))`.
+    pub eval_mismatches: usize,
+}
+
+/// Checks that `sample(..).pdf` equals `eval().pdf` of the sampled
+/// direction, and that `sample_and_eval` returns the same value and pdf as
+/// `eval(sample(..))`, both to `tolerance` relative error.
+pub fn sample_consistency(bsdf: &dyn BSDF, wi: Vector3f, sample_count: usize, tolerance: Float, seed: u64) -> ConsistencyReport {
+    let close = |a: Float, b: Float| (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1e-4);
+    let mut report = ConsistencyReport::default();
+    let mut rng = LcgRng::new(seed);
+    for _ in 0..sample_count {
+        let u1 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        let u2 = Vector2f::new(rng.next_f32(), rng.next_f32());
+        let record = bsdf.sample(u1, u2, wi);
+        if record.pdf <= 0.0 {
+            continue;
+        }
+        report.samples += 1;
+        let sampled_pdf = record.pdf;
+        let eval = bsdf.eval(record);
+        if !close(sampled_pdf, eval.pdf) {
+            report.pdf_mismatches += 1;
+        }
+        let combined = bsdf.sample_and_eval(u1, u2, wi);
+        if !close(combined.pdf, eval.pdf) || (0..3).any(|c| !close(combined.value[c], eval.value[c])) {
+            report.eval_mismatches += 1;
+        }
+    }
+    report
+}
+
+/// Probability that a chi-square variable with `dof` degrees of freedom
+/// exceeds `x`, i.e. the regularized upper incomplete gamma Q(dof/2, x/2).
+pub fn chi_square_survival(x: f64, dof: usize) -> f64 {
+    let (a, x) = (dof as f64 * 0.5, x * 0.5);
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let log_prefactor = a * x.ln() - x - ln_gamma(a);
+    if x < a + 1.0 {
+        // Series for the lower function P.
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..1000 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        (1.0 - sum * log_prefactor.exp()).max(0.0)
+    } else {
+        // Continued fraction for Q (modified Lentz).
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        (h * log_prefactor.exp()).min(1.0)
+    }
+}
+
+// Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::blend::BlendBSDF;
+    use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+    use crate::materials::microfacet::MicrofacetType;
+    use crate::materials::null::NullBSDF;
+    use crate::materials::roughconductor::RoughConductorBSDF;
+    use crate::materials::roughdielectric::RoughDielectricBSDF;
+    use crate::math::spectrum::RGBSpectrum;
+    use crate::textures::constant::ConstantTexture;
+    use std::sync::Arc;
+
+    const SIGNIFICANCE: f64 = 0.01;
+
+    struct Case {
+        name: String,
+        bsdf: Arc<dyn BSDF>,
+        /// Index of refraction below the surface; 0 for opaque BSDFs.
+        eta: Float,
+    }
+
+    fn white() -> RGBSpectrum {
+        RGBSpectrum::new(1.0, 1.0, 1.0)
+    }
+
+    fn diffuse(value: Float) -> Arc<dyn BSDF> {
+        Arc::new(LambertianDiffuseBSDF::new(Arc::new(ConstantTexture::new(RGBSpectrum::new(value, value, value))), None))
+    }
+
+    // One instance of every BSDF the scene loader can create, with both
+    // microfacet distributions and sampling modes.
+    fn cases() -> Vec<Case> {
+        let mut cases = vec![Case { name: "diffuse".to_string(), bsdf: diffuse(0.8), eta: 0.0 }];
+        for m_type in [MicrofacetType::GGX, MicrofacetType::Beckmann] {
+            for (alpha_u, alpha_v) in [(0.3, 0.3), (0.15, 0.45)] {
+                for sample_visible in [true, false] {
+                    let name = format!("{:?} {}x{} visible={}", m_type, alpha_u, alpha_v, sample_visible);
+                    cases.push(Case {
+                        name: format!("roughconductor {}", name),
+                        bsdf: Arc::new(RoughConductorBSDF::new(
+                            m_type,
+                            alpha_u,
+                            alpha_v,
+                            sample_visible,
+                            RGBSpectrum::new(0.2, 0.9, 1.1),
+                            RGBSpectrum::new(3.9, 2.4, 2.2),
+                            white(),
+                            None,
+                        )),
+                        eta: 0.0,
+                    });
+                }
+            }
+            cases.push(Case {
+                name: format!("roughdielectric {:?}", m_type),
+                bsdf: Arc::new(RoughDielectricBSDF::new(m_type, 0.3, 0.3, true, 1.5, 1.0, white(), white(), None)),
+                eta: 1.5,
+            });
+        }
+        let conductor = cases[1].bsdf.clone();
+        cases.push(Case {
+            name: "blendbsdf".to_string(),
+            bsdf: Arc::new(BlendBSDF::new(diffuse(0.5), conductor, 0.3, None)),
+            eta: 0.0,
+        });
+        cases
+    }
+
+    fn incident_directions(eta: Float) -> Vec<Vector3f> {
+        let mut directions = Vec::new();
+        for theta_deg in [10.0 as Float, 45.0, 80.0] {
+            let theta = theta_deg.to_radians();
+            directions.push(Vector3f::new(theta.sin() * 0.8, theta.sin() * 0.6, theta.cos()));
+        }
+        if eta > 0.0 {
+            // Transmissive BSDFs are also lit from inside.
+            let below: Vec<Vector3f> = directions.iter().map(|d| Vector3f::new(d.x, d.y, -d.z)).collect();
+            directions.extend(below);
+        }
+        directions
+    }
+
+    #[test]
+    fn test_bsdf_sampling_matches_pdf() {
+        let cases = cases();
+        let settings = ChiSquareSettings::default();
+        let test_count: usize = cases.iter().map(|c| incident_directions(c.eta).len()).sum();
+        let significance = corrected_significance(SIGNIFICANCE, test_count);
+        for case in &cases {
+            for wi in incident_directions(case.eta) {
+                let result = bsdf_chi_square(case.bsdf.as_ref(), wi, &settings);
+                assert!(result.passed(significance), "{} wi={:?}: {:?}", case.name, wi, result);
+            }
+        }
+    }
+
+    #[test]
+    fn test_microfacet_sampling_matches_pdf() {
+        let settings = ChiSquareSettings::default();
+        let mut distributions = Vec::new();
+        for m_type in [MicrofacetType::GGX, MicrofacetType::Beckmann] {
+            for (alpha_u, alpha_v) in [(0.1, 0.1), (0.5, 0.5), (0.2, 0.6)] {
+                for sample_visible in [true, false] {
+                    distributions.push(MicrofacetDistribution::new(m_type, alpha_u, alpha_v, sample_visible));
+                }
+            }
+        }
+        let directions = incident_directions(0.0);
+        let significance = corrected_significance(SIGNIFICANCE, distributions.len() * directions.len());
+        for distribution in &distributions {
+            for wi in &directions {
+                let result = microfacet_chi_square(distribution, *wi, &settings);
+                assert!(result.passed(significance), "{:?} wi={:?}: {:?}", distribution, wi, result);
+
+                let (total, visible) = microfacet_projected_areas(distribution, *wi, 400);
+                assert!((total - 1.0).abs() < 0.01, "{:?}: projected area {}", distribution, total);
+                assert!((visible - wi.z as f64).abs() < 0.01 * wi.z as f64 + 0.002, "{:?} wi={:?}: visible area {}", distribution, wi, visible);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bsdf_white_furnace() {
+        let settings = ChiSquareSettings { sample_count: 40_000, ..ChiSquareSettings::default() };
+        for case in cases() {
+            for wi in incident_directions(case.eta) {
+                let (sampled, integrated) = white_furnace(case.bsdf.as_ref(), wi, case.eta.max(1.0), &settings);
+                for c in 0..3 {
+                    assert!(sampled[c] <= 1.01 && integrated[c] <= 1.01, "{} wi={:?} gains energy: {} {}", case.name, wi, sampled, integrated);
+                    assert!(
+                        (sampled[c] - integrated[c]).abs() < 0.02 + 0.02 * integrated[c],
+                        "{} wi={:?}: sampled albedo {} but integrated {}",
+                        case.name,
+                        wi,
+                        sampled,
+                        integrated
+                    );
+                }
+            }
+        }
+        // A white Lambertian surface reflects everything.
+        let (sampled, _) = white_furnace(diffuse(1.0).as_ref(), Vector3f::new(0.0, 0.6, 0.8), 1.0, &settings);
+        assert!((sampled.x - 1.0).abs() < 1e-4, "{}", sampled.x);
+    }
+
+    #[test]
+    fn test_bsdf_reciprocity() {
+        let mut rng = LcgRng::new(3);
+        let mut random_direction = || {
+            let z = 2.0 * rng.next_f32() - 1.0;
+            let phi = 2.0 * std::f32::consts::PI * rng.next_f32();
+            let r = (1.0 - z * z).max(0.0).sqrt();
+            Vector3f::new(r * phi.cos(), r * phi.sin(), z)
+        };
+        for case in cases() {
+            // Microfacets seen at grazing angles leave a little single
+            // precision noise in the Fresnel term, so only rare small
+            // errors are tolerated.
+            let mut inexact = 0;
+            for _ in 0..2000 {
+                let (wi, wo) = (random_direction(), random_direction());
+                if wi.z.abs() < 0.05 || wo.z.abs() < 0.05 {
+                    continue;
+                }
+                let error = reciprocity_error(case.bsdf.as_ref(), wi, wo, case.eta.max(1.0));
+                assert!(error < 0.05, "{} wi={:?} wo={:?}: relative error {}", case.name, wi, wo, error);
+                if error > 1e-3 {
+                    inexact += 1;
+                }
+            }
+            assert!(inexact <= 10, "{}: {} pairs are not reciprocal", case.name, inexact);
+        }
+    }
+
+    #[test]
+    fn test_sample_and_eval_agree_with_eval() {
+        let mut cases = cases();
+        cases.push(Case { name: "null".to_string(), bsdf: Arc::new(NullBSDF::new(None)), eta: 0.0 });
+        for case in cases {
+            for wi in incident_directions(case.eta) {
+                let report = sample_consistency(case.bsdf.as_ref(), wi, 20_000, 1e-3, 11);
+                assert!(report.samples > 0, "{} wi={:?}: no valid samples", case.name, wi);
+                let allowed = report.samples / 1000;
+                assert!(
+                    report.pdf_mismatches <= allowed && report.eval_mismatches <= allowed,
+                    "{} wi={:?}: {:?}",
+                    case.name,
+                    wi,
+                    report
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_chi_square_survival() {
+        // Reference values of the chi-square upper tail.
+        assert!((chi_square_survival(3.841, 1) - 0.05).abs() < 1e-3);
+        assert!((chi_square_survival(18.307, 10) - 0.05).abs() < 1e-3);
+        assert!((chi_square_survival(135.807, 100) - 0.01).abs() < 1e-3);
+        assert!((chi_square_survival(0.0, 5) - 1.0).abs() < 1e-12);
+    }
+}
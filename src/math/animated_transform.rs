@@ -0,0 +1,193 @@
+// Copyright @yucwang 2026
+
+use super::aabb::AABB;
+use super::constants::{Float, Matrix3f, Matrix4f, Vector3f};
+use super::transform::Transform;
+use nalgebra::{Rotation3, UnitQuaternion};
+
+// Number of time steps used to bound a box swept by the animation.
+const MOTION_BOUND_STEPS: usize = 64;
+
+#[derive(Debug, Clone)]
+struct Keyframe {
+    time: Float,
+    translation: Vector3f,
+    rotation: UnitQuaternion<Float>,
+    scale: Matrix3f,
+    matrix: Matrix4f,
+}
+
+/// Keyframed transform. Each key is decomposed into translation, rotation
+/// and scale (M = T R S); between keys translation and scale are blended
+/// linearly and rotation is slerped. Times outside the keys clamp to the
+/// first or last key.
+#[derive(Debug, Clone)]
+pub struct AnimatedTransform {
+    keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    /// Keys are sorted by time; an empty list gives the identity.
+    pub fn new(mut keys: Vec<(Float, Matrix4f)>) -> Self {
+        if keys.is_empty() {
+            keys.push((0.0, Matrix4f::identity()));
+        }
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        let keyframes = keys
+            .into_iter()
+            .map(|(time, matrix)| {
+                let (translation, rotation, scale) = decompose(&matrix);
+                Keyframe { time, translation, rotation, scale, matrix }
+            })
+            .collect();
+        Self { keyframes }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.keyframes.len() > 1
+    }
+
+    /// The keys as given, sorted by time.
+    pub fn keyframes(&self) -> Vec<(Float, Matrix4f)> {
+        self.keyframes.iter().map(|k| (k.time, k.matrix)).collect()
+    }
+
+    pub fn time_range(&self) -> (Float, Float) {
+        let first = self.keyframes.first().map_or(0.0, |k| k.time);
+        let last = self.keyframes.last().map_or(0.0, |k| k.time);
+        (first, last)
+    }
+
+    pub fn matrix_at(&self, time: Float) -> Matrix4f {
+        let keys = &self.keyframes;
+        if keys.len() == 1 || time <= keys[0].time {
+            return keys[0].matrix;
+        }
+        let last = &keys[keys.len() - 1];
+        if time >= last.time {
+            return last.matrix;
+        }
+
+        let next = keys.iter().position(|k| k.time > time).unwrap_or(keys.len() - 1);
+        let (k0, k1) = (&keys[next - 1], &keys[next]);
+        let span = k1.time - k0.time;
+        let alpha = if span > 0.0 { (time - k0.time) / span } else { 0.0 };
+
+        let translation = k0.translation * (1.0 - alpha) + k1.translation * alpha;
+        let rotation = k0.rotation.try_slerp(&k1.rotation, alpha, 1e-6).unwrap_or(k0.rotation);
+        let scale = k0.scale * (1.0 - alpha) + k1.scale * alpha;
+
+        let linear = rotation.to_rotation_matrix().matrix() * scale;
+        let mut matrix = Matrix4f::identity();
+        matrix.fixed_slice_mut::<3, 3>(0, 0).copy_from(&linear);
+        matrix.fixed_slice_mut::<3, 1>(0, 3).copy_from(&translation);
+        matrix
+    }
+
+    pub fn transform_at(&self, time: Float) -> Transform {
+        Transform::new(self.matrix_at(time))
+    }
+
+    /// Bounds of `bbox` swept over the keyframe range, found by sampling the
+    /// animation; the result is padded slightly to cover rotation between
+    /// samples.
+    pub fn motion_bounds(&self, bbox: &AABB) -> AABB {
+        let corners = [
+            Vector3f::new(bbox.p_min.x, bbox.p_min.y, bbox.p_min.z),
+            Vector3f::new(bbox.p_max.x, bbox.p_min.y, bbox.p_min.z),
+            Vector3f::new(bbox.p_min.x, bbox.p_max.y, bbox.p_min.z),
+            Vector3f::new(bbox.p_max.x, bbox.p_max.y, bbox.p_min.z),
+            Vector3f::new(bbox.p_min.x, bbox.p_min.y, bbox.p_max.z),
+            Vector3f::new(bbox.p_max.x, bbox.p_min.y, bbox.p_max.z),
+            Vector3f::new(bbox.p_min.x, bbox.p_max.y, bbox.p_max.z),
+            Vector3f::new(bbox.p_max.x, bbox.p_max.y, bbox.p_max.z),
+        ];
+        let steps = if self.is_animated() { MOTION_BOUND_STEPS } else { 1 };
+        let (t0, t1) = self.time_range();
+        let mut bounds = AABB::default();
+        for step in 0..=steps {
+            let time = t0 + (t1 - t0) * (step as Float / steps as Float);
+            let transform = self.transform_at(time);
+            for corner in &corners {
+                bounds.expand_by_point(&transform.apply_point(*corner));
+            }
+        }
+        if self.is_animated() && bounds.is_valid() {
+            let pad = Vector3f::repeat(1e-3 * bounds.diagnal().norm());
+            bounds = AABB::new(bounds.p_min - pad, bounds.p_max + pad);
+        }
+        bounds
+    }
+}
+
+// Splits the upper 3x3 of `matrix` into rotation and scale with a polar
+// decomposition (iterating R = (R + R^-T) / 2).
+fn decompose(matrix: &Matrix4f) -> (Vector3f, UnitQuaternion<Float>, Matrix3f) {
+    let translation = Vector3f::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+    let linear: Matrix3f = matrix.fixed_slice::<3, 3>(0, 0).into_owned();
+
+    let mut rotation = linear;
+    for _ in 0..100 {
+        let inv_t = match rotation.try_inverse() {
+            Some(inv) => inv.transpose(),
+            None => break,
+        };
+        let next = (rotation + inv_t) * 0.5;
+        let delta = (next - rotation).abs().max();
+        rotation = next;
+        if delta < 1e-7 {
+            break;
+        }
+    }
+    if rotation.determinant() < 0.0 {
+        rotation = -rotation;
+    }
+    let scale = rotation.try_inverse().map_or(Matrix3f::identity(), |inv| inv * linear);
+    let quaternion = UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+    (translation, quaternion, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnimatedTransform;
+    use crate::math::aabb::AABB;
+    use crate::math::constants::{Matrix4f, Vector3f};
+
+    fn rotation_z(angle: f32) -> Matrix4f {
+        let (s, c) = angle.sin_cos();
+        let mut m = Matrix4f::identity();
+        m[(0, 0)] = c;
+        m[(0, 1)] = -s;
+        m[(1, 0)] = s;
+        m[(1, 1)] = c;
+        m
+    }
+
+    #[test]
+    fn test_animated_transform_keys_and_interpolation() {
+        let mut end = rotation_z(std::f32::consts::FRAC_PI_2) * 2.0;
+        end[(3, 3)] = 1.0;
+        end[(0, 3)] = 4.0;
+        let motion = AnimatedTransform::new(vec![(1.0, end), (0.0, Matrix4f::identity())]);
+        assert!(motion.is_animated());
+        assert_eq!(motion.time_range(), (0.0, 1.0));
+        assert!((motion.matrix_at(-1.0) - Matrix4f::identity()).abs().max() < 1e-5);
+        assert!((motion.matrix_at(1.0) - end).abs().max() < 1e-5);
+
+        // Halfway: 45 degree rotation, scale 1.5, translation 2.
+        let p = motion.transform_at(0.5).apply_point(Vector3f::new(1.0, 0.0, 0.0));
+        let h = 1.5 * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((p - Vector3f::new(2.0 + h, h, 0.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_motion_bounds_cover_path() {
+        let mut end = Matrix4f::identity();
+        end[(0, 3)] = 10.0;
+        let motion = AnimatedTransform::new(vec![(0.0, Matrix4f::identity()), (1.0, end)]);
+        let bbox = AABB::new(Vector3f::new(-1.0, -1.0, -1.0), Vector3f::new(1.0, 1.0, 1.0));
+        let bounds = motion.motion_bounds(&bbox);
+        assert!(bounds.p_min.x <= -1.0 && bounds.p_max.x >= 11.0);
+        assert!(bounds.p_max.y >= 1.0 && bounds.p_max.y < 1.1);
+    }
+}
@@ -1,6 +1,9 @@
 // Copyright 2020 @TwoCookingMice
 
 use super::constants::{Float, MatrixXF, Vector3f};
+use crate::io::image_io::{write_ldr, Image, ImageIoError};
+use crate::io::tonemap::TonemapSettings;
+use std::path::Path;
 use std::vec::Vec;
 
 #[derive(Debug, Clone)]
@@ -65,6 +68,22 @@ impl Bitmap {
 
         raw_copy
     }
+
+    /// Display-encoded 8-bit RGB rows produced by `settings`.
+    pub fn to_rgb8(&self, settings: &TonemapSettings) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.width * self.height * 3);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                out.extend_from_slice(&settings.quantize(self.pixel(x, y), x, y));
+            }
+        }
+        out
+    }
+
+    /// Writes a tonemapped PNG or JPEG, chosen by extension.
+    pub fn write_ldr<P: AsRef<Path>>(&self, path: P, settings: &TonemapSettings) -> Result<(), ImageIoError> {
+        write_ldr(path, &Image::from_bitmap(self), settings)
+    }
 }
 
 /* Test for Bitmap */
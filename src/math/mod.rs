@@ -1,6 +1,7 @@
 // Copyright 2020 @TwoCookingMice
 
 pub mod aabb;
+pub mod animated_transform;
 pub mod bitmap;
 pub mod constants;
 pub mod frame;
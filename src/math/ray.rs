@@ -7,7 +7,8 @@ pub struct Ray3f {
     origin: Vector3f,
     dir: Vector3f,
     pub min_t: Float,
-    pub max_t: Float
+    pub max_t: Float,
+    pub time: Float
 }
 
 impl Ray3f {
@@ -15,7 +16,13 @@ impl Ray3f {
                min_t: Option<Float>, max_t: Option<Float>) -> Self {
         Self { origin: o, dir: d.normalize(), 
                min_t: min_t.unwrap_or(0.0),
-               max_t: max_t.unwrap_or(std::f32::MAX)}
+               max_t: max_t.unwrap_or(std::f32::MAX),
+               time: 0.0 }
+    }
+
+    pub fn with_time(mut self, time: Float) -> Self {
+        self.time = time;
+        self
     }
 
     pub fn origin(&self) -> Vector3f {
@@ -22,6 +22,10 @@ impl Transform {
                inv_matrix: matrix.try_inverse().unwrap_or(Matrix4f::identity())}
     }
 
+    pub fn matrix(&self) -> &Matrix4f {
+        &self.matrix
+    }
+
     pub fn apply_point(&self, p: Vector3f) -> Vector3f {
         let x = p[0] * self.matrix[(0, 0)] + p[1] * self.matrix[(0, 1)] + 
             p[2] * self.matrix[(0, 2)] + self.matrix[(0, 3)];
@@ -60,7 +64,7 @@ impl Transform {
         let new_p = self.apply_point(ray.origin());
         let new_d = self.apply_vector(ray.dir());
 
-        Ray3f::new(new_p, new_d, Some(ray.min_t), Some(ray.max_t))
+        Ray3f::new(new_p, new_d, Some(ray.min_t), Some(ray.max_t)).with_time(ray.time)
     }
 
     pub fn inv_apply_point(&self, p: Vector3f) -> Vector3f {
@@ -97,6 +101,6 @@ impl Transform {
         let new_p = self.inv_apply_point(ray.origin());
         let new_d = self.inv_apply_vector(ray.dir());
 
-        Ray3f::new(new_p, new_d, Some(ray.min_t), Some(ray.max_t))
+        Ray3f::new(new_p, new_d, Some(ray.min_t), Some(ray.max_t)).with_time(ray.time)
     }
 }
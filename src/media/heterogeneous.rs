@@ -1,6 +1,8 @@
 // Copyright @yucwang 2026
 
+use crate::core::gradient::GradientBuffers;
 use crate::core::medium::Medium;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::volume::Volume;
 use crate::math::aabb::AABB;
 use crate::math::constants::{Float, Vector3f};
@@ -46,6 +48,26 @@ impl Medium for HeterogeneousMedium {
     fn bbox(&self) -> Option<AABB> {
         self.bbox
     }
+
+    fn backward_sigma_t(&self, p_world: Vector3f, grad: &RGBSpectrum, grads: &mut GradientBuffers) {
+        let grad = Vector3f::new(grad[0], grad[1], grad[2]) * self.scale;
+        self.sigma_t_volume.backward(p_world, &grad, grads);
+    }
+
+    // The albedo is clamped to [0, 1], which blocks the gradient outside.
+    fn backward_albedo(&self, p_world: Vector3f, grad: &RGBSpectrum, grads: &mut GradientBuffers) {
+        let v = self.albedo_volume.eval(p_world);
+        let pass = |c: usize| if (0.0..=1.0).contains(&v[c]) { grad[c] } else { 0.0 };
+        let grad = Vector3f::new(pass(0), pass(1), pass(2));
+        self.albedo_volume.backward(p_world, &grad, grads);
+    }
+
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("medium", "heterogeneous")
+            .with_child(ctx.volume_property("sigma_t", &self.sigma_t_volume)?)
+            .with_child(ctx.volume_property("albedo", &self.albedo_volume)?)
+            .with_child(XmlElement::float("scale", self.scale)))
+    }
 }
 
 fn clamp_spectrum(value: RGBSpectrum) -> RGBSpectrum {
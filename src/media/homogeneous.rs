@@ -1,6 +1,7 @@
 // Copyright @yucwang 2026
 
 use crate::core::medium::Medium;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::volume::Volume;
 use crate::math::aabb::AABB;
 use crate::math::constants::{Float, Vector3f};
@@ -60,6 +61,17 @@ impl Medium for HomogeneousMedium {
     fn bbox(&self) -> Option<AABB> {
         self.bbox
     }
+
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        let mut element = XmlElement::typed("medium", "homogeneous")
+            .with_child(XmlElement::rgb("sigma_t", self.sigma_t))
+            .with_child(XmlElement::float("scale", self.scale));
+        match &self.albedo_volume {
+            Some(volume) => element.push(ctx.volume_property("albedo", volume)?),
+            None => element.push(XmlElement::rgb("albedo", self.albedo)),
+        }
+        Ok(element)
+    }
 }
 
 fn clamp_spectrum(value: RGBSpectrum) -> RGBSpectrum {
@@ -0,0 +1,1126 @@
+// Copyright @yucwang 2026
+
+// pyo3 0.16 expands `#[new]` into impls nested in a const item.
+#![allow(non_local_definitions)]
+
+use crate::core::bsdf::BSDF;
+use crate::core::computation_node::ComputationNode;
+use crate::core::emitter::{Emitter, EmitterFlag};
+use crate::core::medium::Medium;
+use crate::core::scene::{Scene, SceneObject};
+use crate::core::scene_loader::{fov_y_from_axis, load_scene_with_defines, lookat_matrix};
+use crate::core::scene_writer::{write_scene as write_scene_xml, MeshExport, SceneSettings};
+use crate::core::shape::Shape;
+use crate::core::texture::Texture;
+use crate::core::volume::Volume;
+use crate::emitters::directional::DirectionalEmitter;
+use crate::emitters::envmap::EnvMap;
+use crate::materials::blend::BlendBSDF;
+use crate::materials::lambertian_diffuse::LambertianDiffuseBSDF;
+use crate::materials::microfacet::MicrofacetType;
+use crate::materials::null::NullBSDF;
+use crate::materials::roughconductor::RoughConductorBSDF;
+use crate::materials::roughdielectric::RoughDielectricBSDF;
+use crate::media::heterogeneous::HeterogeneousMedium;
+use crate::media::homogeneous::HomogeneousMedium;
+use crate::integrators::create_differentiable_integrator;
+use crate::io::image_io::{read_image as read_image_file, tonemap_rgb8, write_image as write_image_file, write_ldr, Image, ImageFormat};
+use crate::io::tonemap::{TonemapSettings, Tonemapper, TransferFunction};
+use crate::renderers::differentiable::DifferentiableRenderer;
+use crate::renderers::simple::ProgressCallback;
+use crate::samplers::create_sampler;
+use crate::{render_loaded_scene, RenderOptions, DEFAULT_MAX_DEPTH};
+use crate::sensors::perspective::PerspectiveCamera;
+use crate::shapes::cube::Cube;
+use crate::shapes::rectangle::Rectangle;
+use crate::shapes::triangle_mesh::TriangleMesh;
+use crate::textures::constant::ConstantTexture;
+use crate::textures::image::{FilterMode, ImageTexture, WrapMode};
+use crate::textures::vertex_color::VertexColorTexture;
+use crate::volumes::constant::ConstantVolume;
+use crate::volumes::grid::GridVolume;
+use crate::math::bitmap::Bitmap;
+use crate::math::constants::{Float, Matrix4f, MatrixXF, Vector2f, Vector3f};
+use crate::math::spectrum::RGBSpectrum;
+use crate::math::transform::Transform;
+use nalgebra::{Dynamic, OMatrix, Rotation3, Unit};
+use nalgebra_py::{matrix_from_numpy, matrix_to_numpy};
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Copies `value` (any array-like) into a `n x cols` matrix of `dtype`.
+fn numpy_rows<'py>(py: Python<'py>, value: &'py PyAny, dtype: &str, cols: usize) -> PyResult<&'py PyAny> {
+    let array = py.import("numpy")?.call_method1("ascontiguousarray", (value, dtype))?;
+    array.call_method1("reshape", (-1i64, cols))
+}
+
+fn float_rows(py: Python<'_>, value: &PyAny, cols: usize) -> PyResult<MatrixXF> {
+    let array = numpy_rows(py, value, "float32", cols)?;
+    matrix_from_numpy::<Float, Dynamic, Dynamic>(py, array).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn index_rows(py: Python<'_>, value: &PyAny) -> PyResult<OMatrix<u32, Dynamic, Dynamic>> {
+    let array = numpy_rows(py, value, "uint32", 3)?;
+    matrix_from_numpy::<u32, Dynamic, Dynamic>(py, array).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn vector3(value: &PyAny) -> PyResult<Vector3f> {
+    let v: Vec<Float> = value.extract()?;
+    if v.len() != 3 {
+        return Err(PyValueError::new_err(format!("expected 3 components, got {}", v.len())));
+    }
+    Ok(Vector3f::new(v[0], v[1], v[2]))
+}
+
+// A float is a grey value; anything else must have three components.
+fn spectrum(value: &PyAny) -> PyResult<RGBSpectrum> {
+    if let Ok(v) = value.extract::<Float>() {
+        return Ok(RGBSpectrum::new(v, v, v));
+    }
+    let v = vector3(value)?;
+    Ok(RGBSpectrum::new(v.x, v.y, v.z))
+}
+
+fn spectrum_or(value: Option<&PyAny>, default: Float) -> PyResult<RGBSpectrum> {
+    match value {
+        Some(value) => spectrum(value),
+        None => Ok(RGBSpectrum::new(default, default, default)),
+    }
+}
+
+fn microfacet_type(name: &str) -> PyResult<MicrofacetType> {
+    match name.trim().to_lowercase().as_str() {
+        "ggx" => Ok(MicrofacetType::GGX),
+        "beckmann" => Ok(MicrofacetType::Beckmann),
+        other => Err(PyValueError::new_err(format!("unsupported distribution: {}", other))),
+    }
+}
+
+fn to_world_or_identity(to_world: Option<PyRef<'_, PyTransform>>) -> Transform {
+    Transform::new(to_world.map(|t| t.matrix).unwrap_or_else(Matrix4f::identity))
+}
+
+/// 4x4 object-to-world matrix.
+#[pyclass(name = "Transform", unsendable)]
+#[derive(Clone)]
+struct PyTransform {
+    matrix: Matrix4f,
+}
+
+#[pymethods]
+impl PyTransform {
+    #[new]
+    #[args(matrix = "None")]
+    fn new(py: Python<'_>, matrix: Option<&PyAny>) -> PyResult<Self> {
+        let matrix = match matrix {
+            Some(matrix) => {
+                let m = float_rows(py, matrix, 4)?;
+                if m.nrows() != 4 {
+                    return Err(PyValueError::new_err("transform matrix must be 4x4"));
+                }
+                Matrix4f::from_iterator(m.iter().cloned())
+            }
+            None => Matrix4f::identity(),
+        };
+        Ok(Self { matrix })
+    }
+
+    #[staticmethod]
+    fn translate(offset: &PyAny) -> PyResult<Self> {
+        Ok(Self { matrix: Matrix4f::new_translation(&vector3(offset)?) })
+    }
+
+    /// A float scales uniformly.
+    #[staticmethod]
+    fn scale(factor: &PyAny) -> PyResult<Self> {
+        let factor = match factor.extract::<Float>() {
+            Ok(v) => Vector3f::new(v, v, v),
+            Err(_) => vector3(factor)?,
+        };
+        Ok(Self { matrix: Matrix4f::new_nonuniform_scaling(&factor) })
+    }
+
+    /// Rotation by `angle` degrees around `axis`.
+    #[staticmethod]
+    fn rotate(axis: &PyAny, angle: Float) -> PyResult<Self> {
+        let axis = Unit::new_normalize(vector3(axis)?);
+        Ok(Self { matrix: Rotation3::from_axis_angle(&axis, angle.to_radians()).to_homogeneous() })
+    }
+
+    #[staticmethod]
+    fn look_at(origin: &PyAny, target: &PyAny, up: &PyAny) -> PyResult<Self> {
+        Ok(Self { matrix: lookat_matrix(vector3(origin)?, vector3(target)?, vector3(up)?) })
+    }
+
+    fn __matmul__(&self, other: PyRef<'_, PyTransform>) -> Self {
+        Self { matrix: self.matrix * other.matrix }
+    }
+
+    fn matrix(&self, py: Python<'_>) -> PyObject {
+        matrix_to_numpy(py, &self.matrix)
+    }
+}
+
+/// Perspective sensor with a Mitsuba-style field of view.
+#[pyclass(name = "PerspectiveCamera", unsendable)]
+#[derive(Clone)]
+struct PyPerspectiveCamera {
+    fov: Float,
+    fov_axis: String,
+    width: usize,
+    height: usize,
+    to_world: Matrix4f,
+    near_clip: Float,
+    far_clip: Float,
+    shutter: (Float, Float),
+}
+
+impl PyPerspectiveCamera {
+    fn build(&self) -> PyResult<PerspectiveCamera> {
+        let m = &self.to_world;
+        let origin = Vector3f::new(m[(0, 3)], m[(1, 3)], m[(2, 3)]);
+        let forward = Vector3f::new(m[(0, 2)], m[(1, 2)], m[(2, 2)]).normalize();
+        let up = Vector3f::new(m[(0, 1)], m[(1, 1)], m[(2, 1)]).normalize();
+        let fov_y = fov_y_from_axis(self.fov, &self.fov_axis, self.width, self.height)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        let aspect = self.width as Float / self.height as Float;
+        Ok(PerspectiveCamera::new(origin, origin + forward, up, fov_y, aspect, self.width, self.height, self.near_clip, self.far_clip)
+            .with_shutter(self.shutter.0, self.shutter.1))
+    }
+}
+
+#[pymethods]
+impl PyPerspectiveCamera {
+    /// `fov` is in degrees along `fov_axis` (x, y, diagonal, smaller or
+    /// larger); `to_world` places the camera looking down its +z axis.
+    #[new]
+    #[args(to_world = "None", fov_axis = "\"x\"", near_clip = "0.01", far_clip = "10000.0", shutter_open = "0.0", shutter_close = "0.0")]
+    fn new(
+        fov: Float,
+        width: usize,
+        height: usize,
+        to_world: Option<PyRef<'_, PyTransform>>,
+        fov_axis: &str,
+        near_clip: Float,
+        far_clip: Float,
+        shutter_open: Float,
+        shutter_close: Float,
+    ) -> PyResult<Self> {
+        if width == 0 || height == 0 {
+            return Err(PyValueError::new_err("film size must be positive"));
+        }
+        Ok(Self {
+            fov,
+            fov_axis: fov_axis.to_string(),
+            width,
+            height,
+            to_world: *to_world_or_identity(to_world).matrix(),
+            near_clip,
+            far_clip,
+            shutter: (shutter_open, shutter_close),
+        })
+    }
+}
+
+#[pyclass(name = "Texture", unsendable)]
+#[derive(Clone)]
+struct PyTexture {
+    texture: Arc<dyn Texture>,
+}
+
+#[pymethods]
+impl PyTexture {
+    #[staticmethod]
+    fn constant(value: &PyAny) -> PyResult<Self> {
+        Ok(Self { texture: Arc::new(ConstantTexture::new(spectrum(value)?)) })
+    }
+
+    /// Image file texture; `raw` skips the sRGB decoding of LDR images.
+    #[staticmethod]
+    #[args(raw = "false", filter = "\"bilinear\"", wrap = "\"repeat\"")]
+    fn bitmap(path: &str, raw: bool, filter: &str, wrap: &str) -> PyResult<Self> {
+        let mut image = ImageTexture::from_file_with_srgb(path, !raw).map_err(PyRuntimeError::new_err)?;
+        image.set_filter_mode(match filter.trim().to_lowercase().as_str() {
+            "bilinear" => FilterMode::Bilinear,
+            "nearest" => FilterMode::Nearest,
+            other => return Err(PyValueError::new_err(format!("unsupported filter_type: {}", other))),
+        });
+        image.set_wrap_mode(match wrap.trim().to_lowercase().as_str() {
+            "repeat" => WrapMode::Repeat,
+            "mirror" => WrapMode::Mirror,
+            "clamp" => WrapMode::Clamp,
+            other => return Err(PyValueError::new_err(format!("unsupported wrap_mode: {}", other))),
+        });
+        Ok(Self { texture: Arc::new(image) })
+    }
+
+    /// Linear RGB texture from an `(height, width, 3)` array.
+    #[staticmethod]
+    fn from_array(py: Python<'_>, data: &PyAny) -> PyResult<Self> {
+        let array = py.import("numpy")?.call_method1("ascontiguousarray", (data, "float32"))?;
+        let shape: Vec<usize> = array.getattr("shape")?.extract()?;
+        if shape.len() != 3 || shape[2] != 3 {
+            return Err(PyValueError::new_err(format!("expected an (height, width, 3) array, got shape {:?}", shape)));
+        }
+        let data = float_rows(py, array, shape[1] * 3)?;
+        Ok(Self { texture: Arc::new(ImageTexture::from_matrix(data)) })
+    }
+
+    /// Mesh vertex colors, or `fallback` on meshes without them.
+    #[staticmethod]
+    #[args(fallback = "None")]
+    fn vertex_color(fallback: Option<&PyAny>) -> PyResult<Self> {
+        Ok(Self { texture: Arc::new(VertexColorTexture::new(spectrum_or(fallback, 0.5)?)) })
+    }
+}
+
+#[pyclass(name = "BSDF", unsendable)]
+#[derive(Clone)]
+struct PyBSDF {
+    bsdf: Arc<dyn BSDF>,
+}
+
+#[pymethods]
+impl PyBSDF {
+    /// `reflectance` is a float, an RGB triple or a `Texture`.
+    #[staticmethod]
+    #[args(reflectance = "None", id = "None")]
+    fn diffuse(reflectance: Option<&PyAny>, id: Option<String>) -> PyResult<Self> {
+        let texture = match reflectance {
+            Some(value) => match value.extract::<PyRef<'_, PyTexture>>() {
+                Ok(texture) => texture.texture.clone(),
+                Err(_) => Arc::new(ConstantTexture::new(spectrum(value)?)) as Arc<dyn Texture>,
+            },
+            None => Arc::new(ConstantTexture::new(RGBSpectrum::new(0.5, 0.5, 0.5))) as Arc<dyn Texture>,
+        };
+        Ok(Self { bsdf: Arc::new(LambertianDiffuseBSDF::new(texture, id)) })
+    }
+
+    #[staticmethod]
+    #[args(
+        alpha = "0.1",
+        distribution = "\"beckmann\"",
+        eta = "None",
+        k = "None",
+        specular_reflectance = "None",
+        alpha_u = "None",
+        alpha_v = "None",
+        sample_visible = "true",
+        id = "None"
+    )]
+    fn roughconductor(
+        alpha: Float,
+        distribution: &str,
+        eta: Option<&PyAny>,
+        k: Option<&PyAny>,
+        specular_reflectance: Option<&PyAny>,
+        alpha_u: Option<Float>,
+        alpha_v: Option<Float>,
+        sample_visible: bool,
+        id: Option<String>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            bsdf: Arc::new(RoughConductorBSDF::new(
+                microfacet_type(distribution)?,
+                alpha_u.unwrap_or(alpha),
+                alpha_v.unwrap_or(alpha),
+                sample_visible,
+                spectrum_or(eta, 0.0)?,
+                spectrum_or(k, 1.0)?,
+                spectrum_or(specular_reflectance, 1.0)?,
+                id,
+            )),
+        })
+    }
+
+    #[staticmethod]
+    #[args(
+        alpha = "0.1",
+        distribution = "\"beckmann\"",
+        int_ior = "1.5046",
+        ext_ior = "1.000277",
+        specular_reflectance = "None",
+        specular_transmittance = "None",
+        alpha_u = "None",
+        alpha_v = "None",
+        sample_visible = "true",
+        id = "None"
+    )]
+    fn roughdielectric(
+        alpha: Float,
+        distribution: &str,
+        int_ior: Float,
+        ext_ior: Float,
+        specular_reflectance: Option<&PyAny>,
+        specular_transmittance: Option<&PyAny>,
+        alpha_u: Option<Float>,
+        alpha_v: Option<Float>,
+        sample_visible: bool,
+        id: Option<String>,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            bsdf: Arc::new(RoughDielectricBSDF::new(
+                microfacet_type(distribution)?,
+                alpha_u.unwrap_or(alpha),
+                alpha_v.unwrap_or(alpha),
+                sample_visible,
+                int_ior,
+                ext_ior,
+                spectrum_or(specular_reflectance, 1.0)?,
+                spectrum_or(specular_transmittance, 1.0)?,
+                id,
+            )),
+        })
+    }
+
+    /// Mix of `weight` times `a` and `1 - weight` times `b`: `weight` = 1
+    /// gives `a`, 0 gives `b`.
+    #[staticmethod]
+    #[args(weight = "0.5", id = "None")]
+    fn blend(a: PyRef<'_, PyBSDF>, b: PyRef<'_, PyBSDF>, weight: Float, id: Option<String>) -> Self {
+        Self { bsdf: Arc::new(BlendBSDF::new(a.bsdf.clone(), b.bsdf.clone(), weight, id)) }
+    }
+
+    #[staticmethod]
+    #[args(id = "None")]
+    fn null(id: Option<String>) -> Self {
+        Self { bsdf: Arc::new(NullBSDF::new(id)) }
+    }
+}
+
+#[derive(Clone)]
+enum EmitterSpec {
+    Directional { direction: Vector3f, irradiance: RGBSpectrum, id: Option<String> },
+    Envmap { path: String, scale: Float, to_world: Matrix4f },
+}
+
+/// Emitters are created when added to a scene, so one object can be added
+/// to several scenes.
+#[pyclass(name = "Emitter", unsendable)]
+#[derive(Clone)]
+struct PyEmitter {
+    spec: EmitterSpec,
+}
+
+impl PyEmitter {
+    fn build(&self) -> PyResult<Box<dyn Emitter>> {
+        match &self.spec {
+            EmitterSpec::Directional { direction, irradiance, id } => {
+                Ok(Box::new(DirectionalEmitter::new_with(*direction, *irradiance, id.clone())))
+            }
+            EmitterSpec::Envmap { path, scale, to_world } => {
+                let mut envmap = EnvMap::from_file(path, *scale).map_err(PyRuntimeError::new_err)?;
+                envmap.set_transform(Transform::new(*to_world));
+                Ok(Box::new(envmap))
+            }
+        }
+    }
+}
+
+#[pymethods]
+impl PyEmitter {
+    #[staticmethod]
+    #[args(irradiance = "None", id = "None")]
+    fn directional(direction: &PyAny, irradiance: Option<&PyAny>, id: Option<String>) -> PyResult<Self> {
+        Ok(Self {
+            spec: EmitterSpec::Directional {
+                direction: vector3(direction)?.normalize(),
+                irradiance: spectrum_or(irradiance, 1.0)?,
+                id,
+            },
+        })
+    }
+
+    #[staticmethod]
+    #[args(scale = "1.0", to_world = "None")]
+    fn envmap(path: &str, scale: Float, to_world: Option<PyRef<'_, PyTransform>>) -> Self {
+        Self {
+            spec: EmitterSpec::Envmap {
+                path: path.to_string(),
+                scale,
+                to_world: *to_world_or_identity(to_world).matrix(),
+            },
+        }
+    }
+}
+
+#[pyclass(name = "Volume", unsendable)]
+#[derive(Clone)]
+struct PyVolume {
+    volume: Arc<dyn Volume>,
+}
+
+#[pymethods]
+impl PyVolume {
+    /// A float gives a scalar volume, an RGB triple a three-channel one.
+    #[staticmethod]
+    fn constant(value: &PyAny) -> PyResult<Self> {
+        let volume = match value.extract::<Float>() {
+            Ok(v) => ConstantVolume::new_scalar(v),
+            Err(_) => ConstantVolume::new_rgb(vector3(value)?),
+        };
+        Ok(Self { volume: Arc::new(volume) })
+    }
+
+    /// Mitsuba `.vol` grid.
+    #[staticmethod]
+    #[args(to_world = "None")]
+    fn grid(path: &str, to_world: Option<PyRef<'_, PyTransform>>) -> PyResult<Self> {
+        let mut grid = GridVolume::from_file(path).map_err(PyRuntimeError::new_err)?;
+        if let Some(to_world) = to_world {
+            grid.set_transform(Transform::new(to_world.matrix));
+        }
+        Ok(Self { volume: Arc::new(grid) })
+    }
+}
+
+#[pyclass(name = "Medium", unsendable)]
+#[derive(Clone)]
+struct PyMedium {
+    medium: Arc<dyn Medium>,
+}
+
+#[pymethods]
+impl PyMedium {
+    #[staticmethod]
+    #[args(albedo = "None", scale = "1.0")]
+    fn homogeneous(sigma_t: &PyAny, albedo: Option<&PyAny>, scale: Float) -> PyResult<Self> {
+        let medium = HomogeneousMedium::new(spectrum(sigma_t)?, spectrum_or(albedo, 0.75)?).with_scale(scale);
+        Ok(Self { medium: Arc::new(medium) })
+    }
+
+    #[staticmethod]
+    #[args(scale = "1.0")]
+    fn heterogeneous(sigma_t: PyRef<'_, PyVolume>, albedo: PyRef<'_, PyVolume>, scale: Float) -> Self {
+        let medium = HeterogeneousMedium::new(sigma_t.volume.clone(), albedo.volume.clone()).with_scale(scale);
+        Self { medium: Arc::new(medium) }
+    }
+}
+
+#[pyclass(name = "Shape", unsendable)]
+#[derive(Clone)]
+struct PyShape {
+    shape: Arc<dyn Shape>,
+}
+
+#[pymethods]
+impl PyShape {
+    /// Triangle mesh from `(n, 3)` vertex and `(m, 3)` index arrays, with
+    /// optional per-vertex `normals`, `uvs` (`(n, 2)`) and `colors`.
+    #[staticmethod]
+    #[args(normals = "None", uvs = "None", colors = "None", to_world = "None", id = "None")]
+    fn mesh(
+        py: Python<'_>,
+        vertices: &PyAny,
+        indices: &PyAny,
+        normals: Option<&PyAny>,
+        uvs: Option<&PyAny>,
+        colors: Option<&PyAny>,
+        to_world: Option<PyRef<'_, PyTransform>>,
+        id: Option<String>,
+    ) -> PyResult<Self> {
+        let rows3 = |value: &PyAny, what: &str, count: usize| -> PyResult<Vec<Vector3f>> {
+            let m = float_rows(py, value, 3)?;
+            if m.nrows() != count {
+                return Err(PyValueError::new_err(format!("expected {} {}, got {}", count, what, m.nrows())));
+            }
+            Ok(m.row_iter().map(|r| Vector3f::new(r[0], r[1], r[2])).collect())
+        };
+
+        let positions: Vec<Vector3f> = float_rows(py, vertices, 3)?
+            .row_iter()
+            .map(|r| Vector3f::new(r[0], r[1], r[2]))
+            .collect();
+        let count = positions.len();
+        let faces = index_rows(py, indices)?;
+        let mut index_list = Vec::with_capacity(faces.nrows());
+        for face in faces.row_iter() {
+            let face = [face[0], face[1], face[2]];
+            if face.iter().any(|&i| i as usize >= count) {
+                return Err(PyIndexError::new_err(format!("face {:?} references a missing vertex", face)));
+            }
+            index_list.push(face);
+        }
+
+        let mut mesh = TriangleMesh::from_indexed(positions, index_list, id);
+        if let Some(normals) = normals {
+            mesh = mesh.with_normals(rows3(normals, "normals", count)?);
+        }
+        if let Some(uvs) = uvs {
+            let m = float_rows(py, uvs, 2)?;
+            if m.nrows() != count {
+                return Err(PyValueError::new_err(format!("expected {} uvs, got {}", count, m.nrows())));
+            }
+            mesh = mesh.with_uvs(m.row_iter().map(|r| Vector2f::new(r[0], r[1])).collect());
+        }
+        if let Some(colors) = colors {
+            let colors = rows3(colors, "colors", count)?;
+            mesh = mesh.with_colors(colors.iter().map(|c| RGBSpectrum::new(c.x, c.y, c.z)).collect());
+        }
+        if let Some(to_world) = to_world {
+            mesh.apply_transform_matrix(&Transform::new(to_world.matrix));
+        }
+        Ok(Self { shape: Arc::new(mesh) })
+    }
+
+    /// Unit square in the xy plane.
+    #[staticmethod]
+    #[args(to_world = "None", id = "None")]
+    fn rectangle(to_world: Option<PyRef<'_, PyTransform>>, id: Option<String>) -> Self {
+        Self { shape: Arc::new(Rectangle::new(to_world_or_identity(to_world), id)) }
+    }
+
+    /// Cube spanning [-1, 1]^3.
+    #[staticmethod]
+    #[args(to_world = "None", id = "None")]
+    fn cube(to_world: Option<PyRef<'_, PyTransform>>, id: Option<String>) -> Self {
+        Self { shape: Arc::new(Cube::new(to_world_or_identity(to_world), id)) }
+    }
+
+    /// Mesh file (`.obj`, `.ply` or `.serialized`).
+    #[staticmethod]
+    #[args(to_world = "None", shape_index = "0", id = "None")]
+    fn load(path: &str, to_world: Option<PyRef<'_, PyTransform>>, shape_index: usize, id: Option<String>) -> PyResult<Self> {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let err = |err: String| PyRuntimeError::new_err(format!("failed to load {}: {}", path, err));
+        let mut mesh = match extension.as_str() {
+            "obj" => TriangleMesh::from_obj_with_id(path, id).map_err(|e| err(e.to_string()))?,
+            "ply" => TriangleMesh::from_ply_with_id(path, id).map_err(|e| err(e.to_string()))?,
+            "serialized" => TriangleMesh::from_serialized_with_id(path, shape_index, id).map_err(|e| err(e.to_string()))?,
+            other => return Err(PyValueError::new_err(format!("unsupported mesh format: {}", other))),
+        };
+        if let Some(to_world) = to_world {
+            mesh.apply_transform_matrix(&Transform::new(to_world.matrix));
+        }
+        Ok(Self { shape: Arc::new(mesh) })
+    }
+}
+
+#[pyclass(name = "Scene", unsendable)]
+struct PyScene {
+    scene: Mutex<Scene>,
+    settings: SceneSettings,
+}
+
+impl PyScene {
+    // Objects are addressed by index or by name.
+    fn object_index(scene: &Scene, key: &PyAny) -> PyResult<usize> {
+        if let Ok(name) = key.extract::<&str>() {
+            return scene
+                .find_object(name)
+                .ok_or_else(|| PyKeyError::new_err(format!("no object named {}", name)));
+        }
+        let index: usize = key.extract()?;
+        if index >= scene.objects().len() {
+            return Err(PyIndexError::new_err(format!("object index {} out of range", index)));
+        }
+        Ok(index)
+    }
+
+    fn edit_object<F>(&self, key: &PyAny, sync_emitters: bool, edit: F) -> PyResult<()>
+    where
+        F: FnOnce(&mut SceneObject),
+    {
+        let mut scene = self.scene.lock().expect("scene lock");
+        let index = Self::object_index(&scene, key)?;
+        edit(&mut scene.objects_mut()[index]);
+        if sync_emitters {
+            scene.sync_area_emitters();
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyScene {
+    /// Empty scene; fill it with `add_sensor`, `add_object` and
+    /// `add_emitter`.
+    #[new]
+    fn new() -> Self {
+        Self { scene: Mutex::new(Scene::new()), settings: SceneSettings::default() }
+    }
+
+    #[getter]
+    fn data(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.raw_data(py)
+    }
+
+    fn raw_data_keys(&self) -> Vec<String> {
+        self.scene
+            .lock()
+            .expect("scene lock")
+            .raw_data()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn raw_data(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        let scene = self.scene.lock().expect("scene lock");
+        for (key, view) in scene.raw_data() {
+            let len = view.rows * view.cols;
+            let matrix = if len == 0 {
+                MatrixXF::zeros(view.rows, view.cols)
+            } else {
+                let slice = unsafe { std::slice::from_raw_parts(view.ptr, len) };
+                MatrixXF::from_column_slice(view.rows, view.cols, slice)
+            };
+            dict.set_item(key, matrix_to_numpy(py, &matrix))?;
+        }
+        Ok(dict.to_object(py))
+    }
+
+    fn hierarchy(&self) -> String {
+        let scene = self.scene.lock().expect("scene lock");
+        scene.to_string()
+    }
+
+    fn raw_data_item(&self, py: Python<'_>, key: &str) -> PyResult<Option<PyObject>> {
+        let scene = self.scene.lock().expect("scene lock");
+        let view = match scene.raw_data_view(key) {
+            Some(view) => view,
+            None => return Ok(None),
+        };
+        let len = view.rows * view.cols;
+        let matrix = if len == 0 {
+            MatrixXF::zeros(view.rows, view.cols)
+        } else {
+            let slice = unsafe { std::slice::from_raw_parts(view.ptr, len) };
+            MatrixXF::from_column_slice(view.rows, view.cols, slice)
+        };
+        Ok(Some(matrix_to_numpy(py, &matrix)))
+    }
+
+    /// Overwrites a raw data buffer in place, e.g. after a gradient step.
+    /// `value` must have the shape returned by `raw_data_item`.
+    fn set_raw_data_item(&self, py: Python<'_>, key: &str, value: &PyAny) -> PyResult<()> {
+        let scene = self.scene.lock().expect("scene lock");
+        let view = scene
+            .raw_data_view(key)
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))?;
+        if view.rows * view.cols == 0 {
+            return Ok(());
+        }
+        let matrix = float_rows(py, value, view.cols)?;
+        if matrix.nrows() != view.rows {
+            return Err(PyValueError::new_err(format!(
+                "{} expects shape ({}, {}), got {} rows",
+                key, view.rows, view.cols, matrix.nrows()
+            )));
+        }
+        // Raw data belongs to textures and volumes of this scene, which
+        // renders only read while holding the scene lock held here.
+        unsafe { view.write(matrix.as_slice()) };
+        Ok(())
+    }
+
+    fn add_sensor(&self, camera: PyRef<'_, PyPerspectiveCamera>) -> PyResult<usize> {
+        let mut scene = self.scene.lock().expect("scene lock");
+        scene.add_sensor(Box::new(camera.build()?));
+        Ok(scene.sensors().len() - 1)
+    }
+
+    fn set_sensor(&self, index: usize, camera: PyRef<'_, PyPerspectiveCamera>) -> PyResult<()> {
+        let mut scene = self.scene.lock().expect("scene lock");
+        let sensor = Box::new(camera.build()?);
+        match scene.sensors_mut().get_mut(index) {
+            Some(slot) => *slot = sensor,
+            None => return Err(PyIndexError::new_err(format!("sensor index {} out of range", index))),
+        }
+        Ok(())
+    }
+
+    /// Adds an object and returns its index. A non-black `emission` makes
+    /// it an area light.
+    #[args(emission = "None", name = "None", interior_medium = "None")]
+    fn add_object(
+        &self,
+        shape: PyRef<'_, PyShape>,
+        bsdf: PyRef<'_, PyBSDF>,
+        emission: Option<&PyAny>,
+        name: Option<String>,
+        interior_medium: Option<PyRef<'_, PyMedium>>,
+    ) -> PyResult<usize> {
+        let mut object = SceneObject::with_emission(shape.shape.clone(), bsdf.bsdf.clone(), spectrum_or(emission, 0.0)?)
+            .with_interior_medium(interior_medium.map(|m| m.medium.clone()));
+        if let Some(name) = name {
+            object = object.with_name(name);
+        }
+        let mut scene = self.scene.lock().expect("scene lock");
+        scene.add_object(object);
+        Ok(scene.objects().len() - 1)
+    }
+
+    fn remove_object(&self, key: &PyAny) -> PyResult<()> {
+        let mut scene = self.scene.lock().expect("scene lock");
+        let index = Self::object_index(&scene, key)?;
+        scene.remove_object(index);
+        Ok(())
+    }
+
+    fn object_names(&self) -> Vec<Option<String>> {
+        let scene = self.scene.lock().expect("scene lock");
+        scene.objects().iter().map(|o| o.name.clone()).collect()
+    }
+
+    fn set_bsdf(&self, key: &PyAny, bsdf: PyRef<'_, PyBSDF>) -> PyResult<()> {
+        self.edit_object(key, false, |object| object.material = bsdf.bsdf.clone())
+    }
+
+    fn set_shape(&self, key: &PyAny, shape: PyRef<'_, PyShape>) -> PyResult<()> {
+        self.edit_object(key, true, |object| object.shape = shape.shape.clone())
+    }
+
+    fn set_emission(&self, key: &PyAny, emission: &PyAny) -> PyResult<()> {
+        let emission = spectrum(emission)?;
+        self.edit_object(key, true, |object| object.emission = emission)
+    }
+
+    fn set_interior_medium(&self, key: &PyAny, medium: Option<PyRef<'_, PyMedium>>) -> PyResult<()> {
+        self.edit_object(key, false, |object| object.interior_medium = medium.map(|m| m.medium.clone()))
+    }
+
+    fn add_emitter(&self, emitter: PyRef<'_, PyEmitter>) -> PyResult<()> {
+        let emitter = emitter.build()?;
+        self.scene.lock().expect("scene lock").add_emitter(emitter);
+        Ok(())
+    }
+
+    /// Removes all emitters except the area lights of emissive objects.
+    fn clear_emitters(&self) {
+        let mut scene = self.scene.lock().expect("scene lock");
+        scene.emitters_mut().retain(|e| e.get_flag().contains(EmitterFlag::SURFACE));
+    }
+
+    fn __len__(&self) -> usize {
+        self.scene.lock().expect("scene lock").objects().len()
+    }
+}
+
+/// `defines` overrides the scene's `<default>` values, like `-D` on the
+/// command line.
+#[pyfunction]
+fn load_scene(py: Python<'_>, scene_path: &str, defines: Option<HashMap<String, String>>) -> PyResult<Py<PyScene>> {
+    let load_result = load_scene_with_defines(scene_path, &defines.unwrap_or_default())
+        .map_err(|err| PyRuntimeError::new_err(format!("failed to load scene: {}", err)))?;
+    let settings = SceneSettings::from(&load_result);
+    Py::new(py, PyScene { scene: Mutex::new(load_result.scene), settings })
+}
+
+/// Writes `scene` back as XML. Meshes keep pointing at their source
+/// files unless `ply` is set, in which case all of them are written out.
+#[pyfunction]
+fn write_scene(scene: &PyScene, path: &str, ply: Option<bool>) -> PyResult<()> {
+    let mesh_export = if ply.unwrap_or(false) { MeshExport::Ply } else { MeshExport::Reference };
+    let inner = scene.scene.lock().expect("scene lock");
+    write_scene_xml(path, &inner, &scene.settings, mesh_export)
+        .map_err(|err| PyRuntimeError::new_err(format!("failed to write scene: {}", err)))
+}
+
+// Copies the first `channels` channels of `bitmap` into a
+// `(height, width, channels)` array.
+fn bitmap_to_numpy(py: Python<'_>, bitmap: &Bitmap, channels: usize) -> PyResult<PyObject> {
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let mut data = MatrixXF::zeros(height, width * channels);
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = bitmap.pixel(x, y);
+            for c in 0..channels {
+                data[(y, x * channels + c)] = pixel[c];
+            }
+        }
+    }
+    let array = matrix_to_numpy(py, &data);
+    Ok(array.call_method1(py, "reshape", (height, width, channels))?)
+}
+
+/// Renders `scene` and returns an `(H, W, 3)` image, or `(image, aovs)`
+/// when `aovs` names auxiliary outputs (albedo, normal, depth, position,
+/// uv), each an `(H, W, C)` array. With `denoise`, the image is filtered
+/// and the dict also holds the unfiltered image as "noisy". Unset options
+/// fall back to the scene file; `max_depth` defaults to 8 when the scene
+/// does not set it either, e.g. for scenes built in Python.
+/// `progress(done, total)` is called as blocks or passes finish;
+/// returning False (or raising) cancels the render, which then returns the
+/// partial estimate. Ctrl-C also cancels; with or without `progress`, it is
+/// checked whenever a block or pass of the image finishes, so AOV and
+/// denoiser feature passes run to completion. The GIL is released while
+/// rendering, so `progress` must not touch `scene`.
+#[pyfunction(
+    spp = "None",
+    max_depth = "None",
+    integrator = "None",
+    seed = "0",
+    camera = "0",
+    sampler = "None",
+    step_size = "None",
+    aovs = "None",
+    progress = "None",
+    progressive = "false",
+    time_budget = "None",
+    target_error = "None",
+    max_spp = "None",
+    denoise = "false"
+)]
+fn render(
+    py: Python<'_>,
+    scene: &PyScene,
+    spp: Option<u32>,
+    max_depth: Option<u32>,
+    integrator: Option<String>,
+    seed: u64,
+    camera: usize,
+    sampler: Option<String>,
+    step_size: Option<Float>,
+    aovs: Option<Vec<String>>,
+    progress: Option<PyObject>,
+    progressive: bool,
+    time_budget: Option<f64>,
+    target_error: Option<Float>,
+    max_spp: Option<u32>,
+    denoise: bool,
+) -> PyResult<PyObject> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let error: Arc<Mutex<Option<PyErr>>> = Arc::new(Mutex::new(None));
+    // Installed even without `progress`, so Ctrl-C is polled as the render
+    // reports progress.
+    let callback = {
+        let cancel = Arc::clone(&cancel);
+        let error = Arc::clone(&error);
+        ProgressCallback::new(move |done, total| {
+            Python::with_gil(|py| {
+                let result = py.check_signals().and_then(|_| match &progress {
+                    Some(progress) => progress.call1(py, (done, total)).map(|r| r.extract::<bool>(py).ok() != Some(false)),
+                    None => Ok(true),
+                });
+                let keep_going = match result {
+                    Ok(keep_going) => keep_going,
+                    Err(err) => {
+                        *error.lock().expect("error lock") = Some(err);
+                        false
+                    }
+                };
+                if !keep_going {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            })
+        })
+    };
+    let options = RenderOptions {
+        spp,
+        max_depth,
+        seed,
+        camera_id: camera,
+        integrator,
+        step_size,
+        sampler,
+        target_error,
+        max_spp,
+        progressive,
+        time_budget: time_budget.map(Duration::from_secs_f64),
+        cancel: Some(Arc::clone(&cancel)),
+        aovs: aovs.clone().unwrap_or_default(),
+        denoise,
+        progress: Some(callback),
+        ..RenderOptions::default()
+    };
+
+    let mut inner = scene.scene.lock().expect("scene lock");
+    let settings = &scene.settings;
+    let inner: &mut Scene = &mut inner;
+    let output = py
+        .allow_threads(|| render_loaded_scene(inner, settings, &options))
+        .map_err(PyRuntimeError::new_err)?;
+    if let Some(err) = error.lock().expect("error lock").take() {
+        return Err(err);
+    }
+
+    let image = bitmap_to_numpy(py, &output.image, 3)?;
+    if aovs.is_none() && output.noisy.is_none() {
+        return Ok(image);
+    }
+    let dict = PyDict::new(py);
+    for (aov, bitmap) in &output.aovs {
+        dict.set_item(aov.name(), bitmap_to_numpy(py, bitmap, aov.channels())?)?;
+    }
+    if let Some(noisy) = &output.noisy {
+        dict.set_item("noisy", bitmap_to_numpy(py, noisy, 3)?)?;
+    }
+    Ok((image, dict).to_object(py))
+}
+
+/// Renders `scene` and back-propagates `adjoint`, the (H, W, 3) gradient
+/// of a loss with respect to the image. Returns `(image, gradients)` where
+/// `gradients` maps every `raw_data` key to an array of the buffer's shape.
+/// Visibility discontinuities are ignored.
+#[pyfunction(
+    spp = "None",
+    max_depth = "None",
+    integrator = "None",
+    seed = "0",
+    camera = "0",
+    sampler = "None",
+    step_size = "None"
+)]
+fn render_backward(
+    py: Python<'_>,
+    scene: &PyScene,
+    adjoint: &PyAny,
+    spp: Option<u32>,
+    max_depth: Option<u32>,
+    integrator: Option<String>,
+    seed: u64,
+    camera: usize,
+    sampler: Option<String>,
+    step_size: Option<Float>,
+) -> PyResult<PyObject> {
+    let shape: Vec<usize> = adjoint.getattr("shape")?.extract()?;
+    if shape.len() != 3 || shape[2] != 3 {
+        return Err(PyValueError::new_err(format!("adjoint must have shape (H, W, 3), got {:?}", shape)));
+    }
+    let (height, width) = (shape[0], shape[1]);
+    let rows = float_rows(py, adjoint, 3)?;
+    let mut adjoint = Bitmap::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let row = y * width + x;
+            adjoint.set_pixel(x, y, Vector3f::new(rows[(row, 0)], rows[(row, 1)], rows[(row, 2)]));
+        }
+    }
+
+    let settings = &scene.settings;
+    let spp = spp.or(settings.samples_per_pixel).unwrap_or(1);
+    let max_depth = max_depth.or(settings.max_depth).unwrap_or(DEFAULT_MAX_DEPTH);
+    let step_size = step_size.or(settings.step_size);
+    let name = integrator
+        .or_else(|| settings.integrator_type.clone())
+        .unwrap_or_else(|| "path".to_string());
+    let integrator = create_differentiable_integrator(&name, max_depth, spp, step_size, settings.firefly)
+        .ok_or_else(|| PyValueError::new_err(format!("integrator {} is not differentiable", name)))?;
+    let sampler_name = sampler
+        .or_else(|| settings.sampler_type.clone())
+        .unwrap_or_else(|| "independent".to_string());
+    let sampler = create_sampler(&sampler_name, spp, seed)
+        .ok_or_else(|| PyValueError::new_err(format!("unsupported sampler: {}", sampler_name)))?;
+    let renderer = DifferentiableRenderer::new(integrator, camera, seed).with_sampler(sampler);
+
+    let mut inner = scene.scene.lock().expect("scene lock");
+    let inner: &mut Scene = &mut inner;
+    let (image, gradients) = py
+        .allow_threads(|| renderer.render_backward(inner, &adjoint))
+        .map_err(PyRuntimeError::new_err)?;
+
+    let dict = PyDict::new(py);
+    for (key, gradient) in &gradients {
+        dict.set_item(key, matrix_to_numpy(py, gradient))?;
+    }
+    Ok((bitmap_to_numpy(py, &image, 3)?, dict).to_object(py))
+}
+
+fn numpy_image(py: Python<'_>, value: &PyAny) -> PyResult<Image> {
+    let shape: Vec<usize> = value.getattr("shape")?.extract()?;
+    let (height, width, channels) = match shape.as_slice() {
+        [h, w] => (*h, *w, 1),
+        [h, w, c] if *c > 0 => (*h, *w, *c),
+        _ => return Err(PyValueError::new_err(format!("expected an (H, W) or (H, W, C) array, got {:?}", shape))),
+    };
+    let names = match channels {
+        1 => vec!["Y".to_string()],
+        3 => vec!["R".to_string(), "G".to_string(), "B".to_string()],
+        4 => vec!["R".to_string(), "G".to_string(), "B".to_string(), "A".to_string()],
+        _ => (0..channels).map(|c| format!("C{}", c)).collect(),
+    };
+    let rows = float_rows(py, value, channels)?;
+    let mut image = Image::new(width, height, names);
+    for (i, pixel) in image.data.chunks_exact_mut(channels).enumerate() {
+        for (c, v) in pixel.iter_mut().enumerate() {
+            *v = rows[(i, c)];
+        }
+    }
+    Ok(image)
+}
+
+fn tonemap_settings(exposure: Float, tonemapper: &str, transfer: &str, dither: bool) -> PyResult<TonemapSettings> {
+    Ok(TonemapSettings {
+        exposure,
+        tonemapper: Tonemapper::from_name(tonemapper)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown tonemapper: {}", tonemapper)))?,
+        transfer: TransferFunction::from_name(transfer)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown transfer function: {}", transfer)))?,
+        dither,
+    })
+}
+
+/// Maps a linear (H, W, 3) image to display-encoded uint8 values with the
+/// same pipeline as PNG and JPEG output.
+#[pyfunction(exposure = "0.0", tonemapper = "\"clamp\"", transfer = "\"srgb\"", dither = "false")]
+fn tonemap(py: Python<'_>, image: &PyAny, exposure: Float, tonemapper: &str, transfer: &str, dither: bool) -> PyResult<PyObject> {
+    let settings = tonemap_settings(exposure, tonemapper, transfer, dither)?;
+    let image = numpy_image(py, image)?;
+    let pixels = tonemap_rgb8(&image, &settings);
+    let data = OMatrix::<u8, Dynamic, Dynamic>::from_row_slice(image.height, image.width * 3, &pixels);
+    let array = matrix_to_numpy(py, &data);
+    Ok(array.call_method1(py, "reshape", (image.height, image.width, 3))?)
+}
+
+/// Writes an (H, W) or (H, W, C) array as EXR, HDR, PFM, PNG or JPEG,
+/// chosen by extension. The tonemapping arguments apply to PNG and JPEG.
+#[pyfunction(exposure = "0.0", tonemapper = "\"clamp\"", transfer = "\"srgb\"", dither = "false")]
+fn write_image(
+    py: Python<'_>,
+    path: &str,
+    image: &PyAny,
+    exposure: Float,
+    tonemapper: &str,
+    transfer: &str,
+    dither: bool,
+) -> PyResult<()> {
+    let settings = tonemap_settings(exposure, tonemapper, transfer, dither)?;
+    let image = numpy_image(py, image)?;
+    let result = match ImageFormat::from_path(path) {
+        Some(format) if format.is_ldr() => write_ldr(path, &image, &settings),
+        _ => write_image_file(path, &image),
+    };
+    result.map_err(|err| PyRuntimeError::new_err(format!("failed to write {}: {}", path, err)))
+}
+
+/// Reads an image file as a linear (H, W, C) float32 array.
+#[pyfunction]
+fn read_image(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+    let image = read_image_file(path).map_err(|err| PyRuntimeError::new_err(format!("failed to read {}: {}", path, err)))?;
+    let channels = image.channel_count();
+    let data = MatrixXF::from_row_slice(image.height, image.width * channels, &image.data);
+    let array = matrix_to_numpy(py, &data);
+    Ok(array.call_method1(py, "reshape", (image.height, image.width, channels))?)
+}
+
+#[pymodule]
+fn dacquoise(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyScene>()?;
+    m.add_class::<PyTransform>()?;
+    m.add_class::<PyPerspectiveCamera>()?;
+    m.add_class::<PyTexture>()?;
+    m.add_class::<PyBSDF>()?;
+    m.add_class::<PyEmitter>()?;
+    m.add_class::<PyVolume>()?;
+    m.add_class::<PyMedium>()?;
+    m.add_class::<PyShape>()?;
+    m.add_function(wrap_pyfunction!(render, m)?)?;
+    m.add_function(wrap_pyfunction!(render_backward, m)?)?;
+    m.add_function(wrap_pyfunction!(load_scene, m)?)?;
+    m.add_function(wrap_pyfunction!(write_scene, m)?)?;
+    m.add_function(wrap_pyfunction!(tonemap, m)?)?;
+    m.add_function(wrap_pyfunction!(write_image, m)?)?;
+    m.add_function(wrap_pyfunction!(read_image, m)?)?;
+    Ok(())
+}
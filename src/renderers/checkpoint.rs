@@ -0,0 +1,244 @@
+// Copyright @yucwang 2026
+
+use super::pixel_stats::PixelStats;
+use crate::math::constants::{Float, Vector3f};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"DQCKPT04";
+
+// Count, mean RGB, luminance mean and M2, and the done flag.
+const PIXEL_RECORD_SIZE: u64 = 4 + 5 * 4 + 1;
+const MAX_NAME: usize = 256;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(std::io::Error),
+    Format(String),
+}
+
+impl From<std::io::Error> for CheckpointError {
+    fn from(err: std::io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "io error: {}", err),
+            CheckpointError::Format(err) => write!(f, "format error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+/// Saved state of a single pixel: its running estimate and whether adaptive
+/// sampling had already stopped it. Samplers are deterministic in (seed,
+/// pixel, sample index), so the sample count doubles as the RNG state.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelCheckpoint {
+    pub stats: PixelStats,
+    pub done: bool,
+}
+
+/// Snapshot of a progressive render after `passes` passes. Values are stored
+/// bit-exact, so resuming with the same settings reproduces an uninterrupted
+/// render; the settings that change the samples are stored to check that.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    /// `Sampler::name` of the sampler that drew the samples.
+    pub sampler: String,
+    pub samples_per_pixel: u32,
+    /// `Integrator::name` and `Integrator::max_depth` of the estimator.
+    pub integrator: String,
+    pub max_depth: u32,
+    pub passes: u32,
+    pub pixels: Vec<PixelCheckpoint>,
+}
+
+impl Checkpoint {
+    /// Writes to a temporary file next to `path` and renames it into place,
+    /// so a crash mid-write never leaves a truncated checkpoint behind.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = Path::new(&tmp_name);
+
+        {
+            let mut writer = BufWriter::new(File::create(tmp_path)?);
+            writer.write_all(MAGIC)?;
+            writer.write_all(&(self.width as u32).to_le_bytes())?;
+            writer.write_all(&(self.height as u32).to_le_bytes())?;
+            writer.write_all(&self.seed.to_le_bytes())?;
+            write_name(&mut writer, &self.sampler)?;
+            writer.write_all(&self.samples_per_pixel.to_le_bytes())?;
+            write_name(&mut writer, &self.integrator)?;
+            writer.write_all(&self.max_depth.to_le_bytes())?;
+            writer.write_all(&self.passes.to_le_bytes())?;
+            for pixel in &self.pixels {
+                let (count, mean, lum_mean, lum_m2) = pixel.stats.raw_parts();
+                writer.write_all(&count.to_le_bytes())?;
+                for value in [mean[0], mean[1], mean[2], lum_mean, lum_m2] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+                writer.write_all(&[pixel.done as u8])?;
+            }
+            writer.flush()?;
+        }
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, CheckpointError> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::Format("not a dacquoise checkpoint".to_string()));
+        }
+
+        let width = read_u32(&mut reader)? as usize;
+        let height = read_u32(&mut reader)? as usize;
+        let seed = read_u64(&mut reader)?;
+        let sampler = read_name(&mut reader)?;
+        let samples_per_pixel = read_u32(&mut reader)?;
+        let integrator = read_name(&mut reader)?;
+        let max_depth = read_u32(&mut reader)?;
+        let passes = read_u32(&mut reader)?;
+
+        // The header fixes the film size; check it against what the file
+        // holds before allocating for it.
+        let header_len = (MAGIC.len() + 4 + 4 + 8 + 4 + sampler.len() + 4 + 4 + integrator.len() + 4 + 4) as u64;
+        let records_len = file_len.saturating_sub(header_len);
+        let pixel_count = width.checked_mul(height)
+            .filter(|&count| (count as u64).checked_mul(PIXEL_RECORD_SIZE) == Some(records_len))
+            .ok_or_else(|| CheckpointError::Format(format!(
+                "{}x{} pixels do not match the {} bytes of pixel records", width, height, records_len)))?;
+
+        let mut pixels = Vec::with_capacity(pixel_count);
+        for _ in 0..pixel_count {
+            let count = read_u32(&mut reader)?;
+            let mut values = [0.0 as Float; 5];
+            for value in values.iter_mut() {
+                *value = Float::from_bits(read_u32(&mut reader)?);
+            }
+            let mut done = [0u8; 1];
+            reader.read_exact(&mut done)?;
+            let mean = Vector3f::new(values[0], values[1], values[2]);
+            pixels.push(PixelCheckpoint {
+                stats: PixelStats::from_raw_parts(count, mean, values[3], values[4]),
+                done: done[0] != 0,
+            });
+        }
+
+        Ok(Self { width, height, seed, sampler, samples_per_pixel, integrator, max_depth, passes, pixels })
+    }
+}
+
+// Length-prefixed UTF-8 name of a sampler or integrator type.
+fn write_name<W: Write>(writer: &mut W, name: &str) -> Result<(), CheckpointError> {
+    writer.write_all(&(name.len() as u32).to_le_bytes())?;
+    writer.write_all(name.as_bytes())?;
+    Ok(())
+}
+
+fn read_name<R: Read>(reader: &mut R) -> Result<String, CheckpointError> {
+    let len = read_u32(reader)? as usize;
+    if len > MAX_NAME {
+        return Err(CheckpointError::Format(format!("type name of {} bytes", len)));
+    }
+    let mut name = vec![0u8; len];
+    reader.read_exact(&mut name)?;
+    String::from_utf8(name).map_err(|_| CheckpointError::Format("type name is not UTF-8".to_string()))
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, CheckpointError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, CheckpointError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Checkpoint, CheckpointError, PixelCheckpoint};
+    use crate::math::constants::Vector3f;
+    use crate::renderers::pixel_stats::PixelStats;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let mut stats = PixelStats::new();
+        stats.add_sample(Vector3f::new(0.1, 0.2, 0.3));
+        stats.add_sample(Vector3f::new(1.0 / 3.0, 0.5, 7.0));
+        let checkpoint = Checkpoint {
+            width: 2,
+            height: 1,
+            seed: 42,
+            sampler: "IndependentSampler".to_string(),
+            samples_per_pixel: 4,
+            integrator: "PathIntegrator".to_string(),
+            max_depth: 5,
+            passes: 2,
+            pixels: vec![
+                PixelCheckpoint { stats, done: false },
+                PixelCheckpoint { stats: PixelStats::new(), done: true },
+            ],
+        };
+
+        let path = std::env::temp_dir().join(format!("dacquoise_ckpt_{}.bin", std::process::id()));
+        checkpoint.write_to_file(&path).expect("write checkpoint");
+        let loaded = Checkpoint::read_from_file(&path).expect("read checkpoint");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.width, 2);
+        assert_eq!(loaded.height, 1);
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.sampler, "IndependentSampler");
+        assert_eq!(loaded.samples_per_pixel, 4);
+        assert_eq!(loaded.integrator, "PathIntegrator");
+        assert_eq!(loaded.max_depth, 5);
+        assert_eq!(loaded.passes, 2);
+        assert_eq!(loaded.pixels[0].stats.raw_parts(), stats.raw_parts());
+        assert!(loaded.pixels[1].done);
+    }
+
+    #[test]
+    fn test_dimensions_beyond_the_file_are_rejected() {
+        let checkpoint = Checkpoint {
+            width: 1,
+            height: 1,
+            seed: 0,
+            sampler: "IndependentSampler".to_string(),
+            samples_per_pixel: 4,
+            integrator: "PathIntegrator".to_string(),
+            max_depth: 5,
+            passes: 1,
+            pixels: vec![PixelCheckpoint { stats: PixelStats::new(), done: false }],
+        };
+        let path = std::env::temp_dir().join(format!("dacquoise_ckpt_dims_{}.bin", std::process::id()));
+        checkpoint.write_to_file(&path).expect("write checkpoint");
+        // Claim a huge film; reading must fail before allocating for it.
+        let mut bytes = std::fs::read(&path).expect("read back");
+        bytes[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).expect("rewrite checkpoint");
+        let result = Checkpoint::read_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+        assert!(matches!(result, Err(CheckpointError::Format(_))));
+    }
+}
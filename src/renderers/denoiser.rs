@@ -0,0 +1,194 @@
+// Copyright @yucwang 2026
+
+use crate::math::bitmap::Bitmap;
+use crate::math::constants::{Float, Vector3f};
+
+/// Guide images for `denoise`, gathered at the first non-specular hit (see
+/// `AovIntegrator::with_skip_specular`). Pixels that miss the scene have a
+/// zero normal.
+pub struct DenoiserFeatures {
+    pub albedo: Bitmap,
+    pub normal: Bitmap,
+    /// Distance in the first channel.
+    pub depth: Bitmap,
+}
+
+/// Edge-stopping parameters of the à-trous filter (Dammertz et al. 2010,
+/// "Edge-Avoiding À-Trous Wavelet Transform for fast Global Illumination
+/// Filtering").
+#[derive(Clone, Copy, Debug)]
+pub struct DenoiseSettings {
+    /// Filter passes; pass `i` uses taps `2^i` pixels apart.
+    pub iterations: u32,
+    /// Color tolerance of the first pass, halved on each later pass.
+    pub sigma_color: Float,
+    /// Exponent on the cosine between normals.
+    pub normal_power: Float,
+    /// Tolerated relative depth difference.
+    pub sigma_depth: Float,
+    pub sigma_albedo: Float,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            sigma_color: 1.0,
+            normal_power: 64.0,
+            sigma_depth: 0.1,
+            sigma_albedo: 0.1,
+        }
+    }
+}
+
+const KERNEL: [Float; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+const ALBEDO_EPSILON: Float = 1e-3;
+
+/// Filters `image` guided by `features`. The image is divided by the
+/// albedo first, so texture detail is kept by the guide rather than blurred
+/// away, and multiplied back afterwards.
+pub fn denoise(image: &Bitmap, features: &DenoiserFeatures, settings: &DenoiseSettings) -> Bitmap {
+    let (width, height) = (image.width(), image.height());
+    let albedo = |x, y| features.albedo.pixel(x, y).map(|a: Float| a.max(0.0) + ALBEDO_EPSILON);
+
+    let mut current = Bitmap::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let color = image.pixel(x, y).map(|c| if c.is_finite() { c } else { 0.0 });
+            current.set_pixel(x, y, color.component_div(&albedo(x, y)));
+        }
+    }
+
+    let mut sigma_color = settings.sigma_color;
+    for iteration in 0..settings.iterations {
+        let step = 1usize << iteration;
+        let mut next = Bitmap::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                next.set_pixel(x, y, filter_pixel(&current, features, settings, sigma_color, step, x, y));
+            }
+        }
+        current = next;
+        sigma_color *= 0.5;
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let value = current.pixel(x, y).component_mul(&albedo(x, y));
+            current.set_pixel(x, y, value);
+        }
+    }
+    current
+}
+
+fn filter_pixel(
+    image: &Bitmap,
+    features: &DenoiserFeatures,
+    settings: &DenoiseSettings,
+    sigma_color: Float,
+    step: usize,
+    x: usize,
+    y: usize,
+) -> Vector3f {
+    let (width, height) = (image.width() as isize, image.height() as isize);
+    let color_p = image.pixel(x, y);
+    let normal_p = features.normal.pixel(x, y);
+    let depth_p = features.depth.pixel(x, y).x;
+    let albedo_p = features.albedo.pixel(x, y);
+
+    let mut sum = Vector3f::zeros();
+    let mut weight_sum = 0.0;
+    for (j, kj) in KERNEL.iter().enumerate() {
+        let qy = y as isize + (j as isize - 2) * step as isize;
+        if qy < 0 || qy >= height {
+            continue;
+        }
+        for (i, ki) in KERNEL.iter().enumerate() {
+            let qx = x as isize + (i as isize - 2) * step as isize;
+            if qx < 0 || qx >= width {
+                continue;
+            }
+            let (qx, qy) = (qx as usize, qy as usize);
+            let color_q = image.pixel(qx, qy);
+            let normal_q = features.normal.pixel(qx, qy);
+
+            let w_color = (-(color_p - color_q).norm_squared() / (sigma_color * sigma_color).max(1e-8)).exp();
+            // Background pixels only mix with background.
+            let w_normal = match (normal_p.norm_squared() > 0.0, normal_q.norm_squared() > 0.0) {
+                (false, false) => 1.0,
+                (true, true) => normal_p.dot(&normal_q).max(0.0).powf(settings.normal_power),
+                _ => 0.0,
+            };
+            let depth_q = features.depth.pixel(qx, qy).x;
+            let depth_diff = (depth_p - depth_q).abs() / (settings.sigma_depth * depth_p.max(depth_q)).max(1e-6);
+            let w_depth = (-depth_diff * depth_diff).exp();
+            let albedo_diff = (albedo_p - features.albedo.pixel(qx, qy)).norm_squared();
+            let w_albedo = (-albedo_diff / (settings.sigma_albedo * settings.sigma_albedo)).exp();
+
+            let w = kj * ki * w_color * w_normal * w_depth * w_albedo;
+            sum += color_q * w;
+            weight_sum += w;
+        }
+    }
+    if weight_sum > 0.0 {
+        sum / weight_sum
+    } else {
+        color_p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant(width: usize, height: usize, value: Vector3f) -> Bitmap {
+        let mut bitmap = Bitmap::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                bitmap.set_pixel(x, y, value);
+            }
+        }
+        bitmap
+    }
+
+    #[test]
+    fn test_denoise_reduces_noise_and_keeps_feature_edges() {
+        let (width, height) = (32, 16);
+        let mut normal = constant(width, height, Vector3f::new(0.0, 0.0, 1.0));
+        let mut image = Bitmap::new(width, height);
+        let mut state = 12345u32;
+        for y in 0..height {
+            for x in 0..width {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                let noise = (state >> 8) as Float / (1u32 << 24) as Float - 0.5;
+                // Left half faces the camera, right half faces sideways and is darker.
+                let base = if x < width / 2 { 0.5 } else { 0.1 };
+                if x >= width / 2 {
+                    normal.set_pixel(x, y, Vector3f::new(1.0, 0.0, 0.0));
+                }
+                image.set_pixel(x, y, Vector3f::new(1.0, 1.0, 1.0) * (base + 0.2 * noise));
+            }
+        }
+        let features = DenoiserFeatures {
+            albedo: constant(width, height, Vector3f::new(0.5, 0.5, 0.5)),
+            normal,
+            depth: constant(width, height, Vector3f::new(2.0, 0.0, 0.0)),
+        };
+        let result = denoise(&image, &features, &DenoiseSettings::default());
+
+        let error = |bitmap: &Bitmap| -> Float {
+            let mut sum = 0.0;
+            for y in 0..height {
+                for x in 0..width {
+                    let base = if x < width / 2 { 0.5 } else { 0.1 };
+                    sum += (bitmap.pixel(x, y).x - base).powi(2);
+                }
+            }
+            sum
+        };
+        assert!(error(&result) < 0.1 * error(&image), "{} vs {}", error(&result), error(&image));
+        // The normal edge keeps the two halves apart.
+        assert!((result.pixel(width / 2 - 1, 8).x - 0.5).abs() < 0.05);
+        assert!((result.pixel(width / 2, 8).x - 0.1).abs() < 0.05);
+    }
+}
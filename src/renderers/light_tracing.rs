@@ -0,0 +1,159 @@
+// Copyright @yucwang 2026
+
+use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::integrators::light_tracer::LightTracerIntegrator;
+use crate::math::bitmap::Bitmap;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::renderers::simple::ProgressCallback;
+use crate::renderers::splat::splat_in_order;
+use crate::samplers::independent::IndependentSampler;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+pub use super::renderer::Renderer;
+
+/// Particles traced by one work unit; bounds the splats held per thread.
+const PARTICLES_PER_UNIT: usize = 4096;
+
+/// Renders with `LightTracerIntegrator`, tracing `samples_per_pixel` times
+/// as many particles as the sensor has pixels. Threads trace fixed ranges of
+/// particles and the driving thread adds their splats into one film in
+/// particle order. Progress is reported per pass of one particle per pixel; a
+/// raised cancel flag stops after the current pass.
+pub struct LightTracingRenderer {
+    id: String,
+    integrator: LightTracerIntegrator,
+    camera_id: usize,
+    seed: u64,
+    cancel: Option<Arc<AtomicBool>>,
+    progress_callback: Option<ProgressCallback>,
+}
+
+impl ComputationNode for LightTracingRenderer {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn to_string(&self) -> String {
+        format!("LightTracingRenderer [id={}]\n  camera_id: {}\n  seed: {}\n  integrator: {}", self.id, self.camera_id, self.seed, self.integrator.describe())
+    }
+}
+
+impl Renderer for LightTracingRenderer {
+    fn render(&self, scene: &mut Scene) -> Bitmap {
+        let mut sensor = match scene.take_sensor(self.camera_id) {
+            Some(sensor) => sensor,
+            None => return Bitmap::new(0, 0),
+        };
+        let (width, height) = {
+            let bmp = sensor.bitmap();
+            (bmp.width(), bmp.height())
+        };
+        if width == 0 || height == 0 {
+            scene.insert_sensor(self.camera_id, sensor);
+            return Bitmap::new(0, 0);
+        }
+
+        scene.build_bvh();
+        let spp = self.integrator.samples_per_pixel.max(1) as usize;
+        let pixel_count = width * height;
+        let units = pixel_count.div_ceil(PARTICLES_PER_UNIT);
+        let scene_ref: &Scene = scene;
+        let sensor_ref: &dyn crate::core::sensor::Sensor = sensor.as_ref();
+
+        // Particles are traced in passes of one per pixel. Every particle
+        // has its own sampler stream, keyed like a pixel sample, and splats
+        // are added in particle order, so the image only depends on the seed.
+        let mut film = vec![Vector3f::zeros(); pixel_count];
+        let mut passes = 0;
+        while passes < spp && !self.is_cancelled() {
+            let pass = passes as u32;
+            splat_in_order(
+                units,
+                |unit, splats| {
+                    let mut sampler = IndependentSampler::new(spp as u32, self.seed);
+                    let mut splat = |pixel: Vector2f, value: Vector3f| {
+                        let x = (pixel.x as usize).min(width - 1);
+                        let y = (pixel.y as usize).min(height - 1);
+                        splats.push((x + width * y, value));
+                    };
+                    for pixel in (unit * PARTICLES_PER_UNIT)..((unit + 1) * PARTICLES_PER_UNIT).min(pixel_count) {
+                        sampler.start_pixel_sample(pixel % width, pixel / width, pass);
+                        self.integrator.trace_particle(scene_ref, sensor_ref, &mut sampler, &mut splat);
+                    }
+                },
+                |_, splats| {
+                    for &(index, value) in splats {
+                        film[index] += value;
+                    }
+                },
+            );
+            passes += 1;
+            if let Some(callback) = &self.progress_callback {
+                callback.report(passes as u64, spp as u64);
+            }
+        }
+
+        let scale = 1.0 / (passes.max(1) * pixel_count) as Float;
+        let bitmap = sensor.bitmap_mut();
+        for y in 0..height {
+            for x in 0..width {
+                bitmap.set_pixel(x, y, film[x + width * y] * scale);
+            }
+        }
+        let bitmap = bitmap.clone();
+        scene.insert_sensor(self.camera_id, sensor);
+        bitmap
+    }
+}
+
+impl LightTracingRenderer {
+    pub fn new(integrator: LightTracerIntegrator, camera_id: usize, seed: u64) -> Self {
+        Self {
+            id: generate_node_id("LightTracingRenderer"),
+            integrator,
+            camera_id,
+            seed,
+            cancel: None,
+            progress_callback: None,
+        }
+    }
+
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_scene::TestScene;
+    use crate::integrators::path::PathIntegrator;
+    use crate::renderers::simple::SimpleRenderer;
+
+    // Both estimators converge to the same image, emitter seen directly
+    // included.
+    #[test]
+    fn test_light_tracing_matches_path_tracing() {
+        let reference = SimpleRenderer::new(Box::new(PathIntegrator::new(3, 512)), 0, 1).render(&mut TestScene::default().build());
+        let image = LightTracingRenderer::new(LightTracerIntegrator::new(3, 2048), 0, 1).render(&mut TestScene::default().build());
+        for y in 0..4 {
+            for x in 0..4 {
+                let (a, b) = (image.pixel(x, y), reference.pixel(x, y));
+                assert!((a - b).norm() <= 0.1 * b.norm() + 1e-4, "pixel ({}, {}): {:?} vs {:?}", x, y, a, b);
+            }
+        }
+    }
+}
@@ -1,4 +1,12 @@
 // Copyright @yucwang 2021
 
+pub mod checkpoint;
+pub mod denoiser;
+pub mod differentiable;
+pub mod light_tracing;
+pub mod pixel_stats;
+pub mod pssmlt;
 pub mod simple;
+pub mod splat;
+pub mod sppm;
 pub mod renderer;
@@ -0,0 +1,100 @@
+// Copyright @yucwang 2026
+
+use crate::math::constants::{Float, Vector3f};
+
+// Luminance below this is treated as black when computing relative error,
+// so dark pixels do not demand an unbounded number of samples.
+const RELATIVE_ERROR_FLOOR: Float = 1e-3;
+
+/// Running per-pixel estimate using Welford's online algorithm.
+/// RGB mean is tracked per channel, variance on luminance.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PixelStats {
+    count: u32,
+    mean: Vector3f,
+    lum_mean: Float,
+    lum_m2: Float,
+}
+
+impl PixelStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuilds stats from `raw_parts`, e.g. when resuming from a checkpoint.
+    pub fn from_raw_parts(count: u32, mean: Vector3f, lum_mean: Float, lum_m2: Float) -> Self {
+        Self { count, mean, lum_mean, lum_m2 }
+    }
+
+    /// Exact accumulator state as (count, mean, luminance mean, luminance M2).
+    pub fn raw_parts(&self) -> (u32, Vector3f, Float, Float) {
+        (self.count, self.mean, self.lum_mean, self.lum_m2)
+    }
+
+    pub fn add_sample(&mut self, rgb: Vector3f) {
+        self.count += 1;
+        let n = self.count as Float;
+        self.mean += (rgb - self.mean) / n;
+
+        let lum = 0.212671 * rgb[0] + 0.715160 * rgb[1] + 0.072169 * rgb[2];
+        let delta = lum - self.lum_mean;
+        self.lum_mean += delta / n;
+        self.lum_m2 += delta * (lum - self.lum_mean);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn mean(&self) -> Vector3f {
+        self.mean
+    }
+
+    /// Unbiased sample variance of the luminance.
+    pub fn variance(&self) -> Float {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.lum_m2 / ((self.count - 1) as Float)
+    }
+
+    /// Standard error of the mean divided by the mean luminance.
+    pub fn relative_error(&self) -> Float {
+        if self.count < 2 {
+            return Float::INFINITY;
+        }
+        let std_error = (self.variance() / self.count as Float).sqrt();
+        std_error / self.lum_mean.abs().max(RELATIVE_ERROR_FLOOR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PixelStats;
+    use crate::math::constants::Vector3f;
+
+    #[test]
+    fn test_pixel_stats_welford() {
+        let mut stats = PixelStats::new();
+        assert!(stats.relative_error().is_infinite());
+
+        for v in [1.0f32, 2.0, 3.0, 4.0] {
+            stats.add_sample(Vector3f::new(v, v, v));
+        }
+        assert_eq!(stats.count(), 4);
+        assert!((stats.mean()[0] - 2.5).abs() < 1e-5);
+        // Luminance weights sum to ~1, so variance matches the grey values.
+        assert!((stats.variance() - 5.0 / 3.0).abs() < 1e-3);
+        let expected = (5.0f32 / 3.0 / 4.0).sqrt() / 2.5;
+        assert!((stats.relative_error() - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_pixel_stats_constant_signal() {
+        let mut stats = PixelStats::new();
+        for _ in 0..8 {
+            stats.add_sample(Vector3f::new(0.5, 0.5, 0.5));
+        }
+        assert!(stats.relative_error() < 1e-4);
+    }
+}
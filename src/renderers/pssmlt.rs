@@ -0,0 +1,188 @@
+// Copyright @yucwang 2026
+
+use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::scene::Scene;
+use crate::integrators::pssmlt::{Bootstrap, PssmltIntegrator};
+use crate::math::bitmap::Bitmap;
+use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::renderers::simple::ProgressCallback;
+use crate::renderers::splat::splat_in_order;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+pub use super::renderer::Renderer;
+
+/// Renders with `PssmltIntegrator`: bootstraps the image brightness, then
+/// runs `mutations_per_pixel` times as many mutations as the sensor has
+/// pixels, spread over the integrator's chains. Threads own fixed ranges of
+/// bootstrap paths, and chain splats are added into one film in chain order,
+/// so the image only depends on the seed. Progress is reported per finished
+/// chain; a raised cancel flag stops before the next chain and normalizes by
+/// the mutations run so far.
+pub struct PssmltRenderer {
+    id: String,
+    integrator: PssmltIntegrator,
+    camera_id: usize,
+    seed: u64,
+    cancel: Option<Arc<AtomicBool>>,
+    progress_callback: Option<ProgressCallback>,
+}
+
+impl ComputationNode for PssmltRenderer {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn to_string(&self) -> String {
+        format!("PssmltRenderer [id={}]\n  camera_id: {}\n  seed: {}\n  integrator: {}", self.id, self.camera_id, self.seed, self.integrator.describe())
+    }
+}
+
+impl Renderer for PssmltRenderer {
+    fn render(&self, scene: &mut Scene) -> Bitmap {
+        let mut sensor = match scene.take_sensor(self.camera_id) {
+            Some(sensor) => sensor,
+            None => return Bitmap::new(0, 0),
+        };
+        let (width, height) = {
+            let bmp = sensor.bitmap();
+            (bmp.width(), bmp.height())
+        };
+        if width == 0 || height == 0 {
+            scene.insert_sensor(self.camera_id, sensor);
+            return Bitmap::new(0, 0);
+        }
+
+        scene.build_bvh();
+        let thread_count = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let scene_ref: &Scene = scene;
+        let sensor_ref: &dyn crate::core::sensor::Sensor = sensor.as_ref();
+        let integrator = &self.integrator;
+
+        let bootstrap_count = integrator.bootstrap_samples() as usize;
+        let chunk = bootstrap_count.div_ceil(thread_count);
+        let luminance: Vec<Float> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..thread_count)
+                .map(|t| {
+                    scope.spawn(move || {
+                        ((t * chunk)..((t + 1) * chunk).min(bootstrap_count))
+                            .map(|i| integrator.bootstrap_luminance(scene_ref, sensor_ref, self.seed, i))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().expect("pssmlt bootstrap thread panicked")).collect()
+        });
+        let bootstrap = Bootstrap::new(self.seed, &luminance);
+
+        let mut film = vec![Vector3f::zeros(); width * height];
+        let mut mutations_run = 0;
+        if bootstrap.brightness > 0.0 {
+            let mutations = integrator.mutations_per_pixel.max(1) as u64 * (width * height) as u64;
+            let chains = (integrator.chains() as u64).min(mutations) as usize;
+            let bootstrap_ref = &bootstrap;
+            let mut done = 0;
+            splat_in_order(
+                chains,
+                |chain, splats| {
+                    if self.is_cancelled() {
+                        return 0;
+                    }
+                    let mut splat = |raster: Vector2f, value: Vector3f| {
+                        let x = (raster.x as usize).min(width - 1);
+                        let y = (raster.y as usize).min(height - 1);
+                        let index = x + width * y;
+                        // Chains linger on a pixel; merge repeats in place.
+                        match splats.last_mut() {
+                            Some(last) if last.0 == index => last.1 += value,
+                            _ => splats.push((index, value)),
+                        }
+                    };
+                    // The first chains take the remainder.
+                    let count = mutations / chains as u64 + u64::from((chain as u64) < mutations % chains as u64);
+                    integrator.run_chain(scene_ref, sensor_ref, bootstrap_ref, chain, count, &mut splat);
+                    count
+                },
+                |count, splats| {
+                    if count == 0 {
+                        return;
+                    }
+                    for &(index, value) in splats {
+                        film[index] += value;
+                    }
+                    mutations_run += count;
+                    done += 1;
+                    if let Some(callback) = &self.progress_callback {
+                        callback.report(done, chains as u64);
+                    }
+                },
+            );
+        }
+
+        // Every mutation splats a total weight of one, so the film sums to
+        // the number of mutations run times the mean path luminance.
+        let scale = bootstrap.brightness * (width * height) as Float / mutations_run.max(1) as Float;
+        let bitmap = sensor.bitmap_mut();
+        for y in 0..height {
+            for x in 0..width {
+                bitmap.set_pixel(x, y, film[x + width * y] * scale);
+            }
+        }
+        let bitmap = bitmap.clone();
+        scene.insert_sensor(self.camera_id, sensor);
+        bitmap
+    }
+}
+
+impl PssmltRenderer {
+    pub fn new(integrator: PssmltIntegrator, camera_id: usize, seed: u64) -> Self {
+        Self {
+            id: generate_node_id("PssmltRenderer"),
+            integrator,
+            camera_id,
+            seed,
+            cancel: None,
+            progress_callback: None,
+        }
+    }
+
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_scene::TestScene;
+    use crate::integrators::path::PathIntegrator;
+    use crate::integrators::pssmlt::MltSettings;
+    use crate::renderers::simple::SimpleRenderer;
+
+    // The chains only redistribute samples, so the image converges to the
+    // path traced one.
+    #[test]
+    fn test_pssmlt_matches_path_tracing() {
+        let reference = SimpleRenderer::new(Box::new(PathIntegrator::new(3, 4096)), 0, 1).render(&mut TestScene::default().build());
+        let settings = MltSettings { bootstrap_samples: Some(20000), chains: Some(64), ..MltSettings::default() };
+        let integrator = PssmltIntegrator::new(PathIntegrator::new(3, 1), 8192).with_settings(settings);
+        let image = PssmltRenderer::new(integrator, 0, 1).render(&mut TestScene::default().build());
+        for y in 0..4 {
+            for x in 0..4 {
+                let (a, b) = (image.pixel(x, y), reference.pixel(x, y));
+                assert!((a - b).norm() <= 0.1 * b.norm() + 1e-4, "pixel ({}, {}): {:?} vs {:?}", x, y, a, b);
+            }
+        }
+    }
+}
@@ -2,22 +2,80 @@
 
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::integrator::Integrator;
-use crate::core::rng::LcgRng;
+use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
 use crate::math::bitmap::Bitmap;
 use crate::math::constants::{Float, Vector2f, Vector3f};
+use crate::samplers::independent::IndependentSampler;
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 pub use super::renderer::Renderer;
+use super::checkpoint::{Checkpoint, PixelCheckpoint};
+use super::pixel_stats::PixelStats;
+
+/// Per-pixel stopping rule: after each batch of `samples_per_pixel` samples,
+/// stop once the relative standard error drops below `target_error` or
+/// `max_spp` samples have been taken.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveSampling {
+    pub target_error: Float,
+    pub max_spp: u32,
+}
+
+/// Progressive mode renders the whole image one sample per pixel at a time
+/// and stops after `max_passes`, once `time_budget` has elapsed (after at
+/// least one pass), or when the cancel flag is raised. With neither limit set it runs `samples_per_pixel`
+/// passes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProgressiveSettings {
+    pub time_budget: Option<Duration>,
+    pub max_passes: Option<u32>,
+}
+
+/// Called on the thread driving the render with the number of finished
+/// work units and their total: image blocks, or passes in progressive mode
+/// (total 0 when only a time budget bounds the passes).
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(u64, u64) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new<F: Fn(u64, u64) + Send + Sync + 'static>(callback: F) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub fn report(&self, done: u64, total: u64) {
+        (self.0)(done, total)
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+struct PixelState {
+    stats: PixelStats,
+    done: bool,
+}
 
 pub struct SimpleRenderer {
     id: String,
     integrator: Box<dyn Integrator>,
+    sampler: Box<dyn Sampler>,
     camera_id: usize,
     seed: u64,
+    adaptive: Option<AdaptiveSampling>,
+    progressive: Option<ProgressiveSettings>,
+    cancel: Option<Arc<AtomicBool>>,
+    progress_callback: Option<ProgressCallback>,
+    checkpoint: Option<(PathBuf, u32)>,
+    resume: Option<Checkpoint>,
 }
 
 impl ComputationNode for SimpleRenderer {
@@ -26,16 +84,137 @@ impl ComputationNode for SimpleRenderer {
     }
 
     fn to_string(&self) -> String {
-        format!("SimpleRenderer [id={}]\n  camera_id: usize\n  seed: u64\n  integrator: {}",
-            self.id, self.integrator.describe())
+        let adaptive = match self.adaptive {
+            Some(a) => format!("target_error={}, max_spp={}", a.target_error, a.max_spp),
+            None => "off".to_string(),
+        };
+        let progressive = match self.progressive {
+            Some(p) => format!("time_budget={:?}, max_passes={:?}", p.time_budget, p.max_passes),
+            None => "off".to_string(),
+        };
+        format!("SimpleRenderer [id={}]\n  camera_id: usize\n  seed: u64\n  adaptive: {}\n  progressive: {}\n  sampler: {}\n  integrator: {}",
+            self.id, adaptive, progressive, self.sampler.describe(), self.integrator.describe())
     }
 }
 
 impl Renderer for SimpleRenderer {
     fn render(&self, scene: &mut Scene) -> Bitmap {
+        self.render_with_sample_count(scene).0
+    }
+}
+
+impl SimpleRenderer {
+    pub fn new(integrator: Box<dyn Integrator>, camera_id: usize, seed: u64) -> Self {
+        let sampler = Box::new(IndependentSampler::new(integrator.samples_per_pixel(), seed));
+        Self {
+            id: generate_node_id("SimpleRenderer"),
+            integrator,
+            sampler,
+            camera_id,
+            seed,
+            adaptive: None,
+            progressive: None,
+            cancel: None,
+            progress_callback: None,
+            checkpoint: None,
+            resume: None,
+        }
+    }
+
+    /// Panics if `max_spp` is below the integrator's samples per pixel:
+    /// every pixel takes at least one batch of those.
+    pub fn with_adaptive_sampling(mut self, target_error: Float, max_spp: u32) -> Self {
+        let spp = self.samples_per_pixel();
+        assert!(max_spp >= spp, "max_spp {} is below the {} samples per pixel of one batch", max_spp, spp);
+        self.adaptive = Some(AdaptiveSampling { target_error, max_spp });
+        self
+    }
+
+    pub fn with_progressive(mut self, time_budget: Option<Duration>, max_passes: Option<u32>) -> Self {
+        self.progressive = Some(ProgressiveSettings { time_budget, max_passes });
+        self
+    }
+
+    /// Flag polled between progressive passes and image blocks; setting it
+    /// stops the render and returns the estimate accumulated so far (blocks
+    /// not yet started stay black).
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// Writes a checkpoint of the progressive render to `path` every
+    /// `interval` passes and once more when the render stops.
+    pub fn with_checkpoint(mut self, path: PathBuf, interval: u32) -> Self {
+        self.checkpoint = Some((path, interval));
+        self
+    }
+
+    /// Continues a progressive render from `checkpoint`. A checkpoint for
+    /// which `checkpoint_mismatch` reports a difference is ignored with a
+    /// warning; `render_scene` checks it first and returns an error.
+    pub fn with_resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(checkpoint);
+        self
+    }
+
+    /// Why `checkpoint` cannot continue this render on a `width` x `height`
+    /// film, or `None` when it can.
+    pub fn checkpoint_mismatch(&self, checkpoint: &Checkpoint, width: usize, height: usize) -> Option<String> {
+        if checkpoint.width != width || checkpoint.height != height || checkpoint.pixels.len() != width * height {
+            return Some(format!("is {}x{}, but the sensor is {}x{}", checkpoint.width, checkpoint.height, width, height));
+        }
+        if checkpoint.seed != self.seed {
+            return Some(format!("was rendered with seed {}, not {}", checkpoint.seed, self.seed));
+        }
+        if checkpoint.sampler != self.sampler.name() {
+            return Some(format!("was rendered with {}, not {}", checkpoint.sampler, self.sampler.name()));
+        }
+        if checkpoint.samples_per_pixel != self.samples_per_pixel() {
+            return Some(format!("was rendered with {} samples per pixel, not {}",
+                checkpoint.samples_per_pixel, self.samples_per_pixel()));
+        }
+        if checkpoint.integrator != self.integrator.name() {
+            return Some(format!("was rendered with {}, not {}", checkpoint.integrator, self.integrator.name()));
+        }
+        if checkpoint.max_depth != self.integrator.max_depth() {
+            return Some(format!("was rendered with max_depth {}, not {}", checkpoint.max_depth, self.integrator.max_depth()));
+        }
+        None
+    }
+
+    /// Replaces the default independent sampler. Each render thread works
+    /// on its own `clone_box` of it.
+    pub fn with_sampler(mut self, sampler: Box<dyn Sampler>) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    fn samples_per_pixel(&self) -> u32 {
+        match self.integrator.samples_per_pixel() {
+            0 => 1,
+            v => v,
+        }
+    }
+
+    /// Renders the image and also returns the number of samples taken per
+    /// pixel, stored in every channel of the second bitmap.
+    pub fn render_with_sample_count(&self, scene: &mut Scene) -> (Bitmap, Bitmap) {
+        if self.progressive.is_some() {
+            return self.render_progressive(scene, 0, &mut |_, _| {});
+        }
         let mut sensor = match scene.take_sensor(self.camera_id) {
             Some(sensor) => sensor,
-            None => return Bitmap::new(0, 0),
+            None => return (Bitmap::new(0, 0), Bitmap::new(0, 0)),
         };
 
         let (width, height) = {
@@ -43,15 +222,14 @@ impl Renderer for SimpleRenderer {
             (bmp.width(), bmp.height())
         };
         if width == 0 || height == 0 {
-            return Bitmap::new(0, 0);
+            scene.insert_sensor(self.camera_id, sensor);
+            return (Bitmap::new(0, 0), Bitmap::new(0, 0));
         }
 
         scene.build_bvh();
-        let spp = match self.integrator.samples_per_pixel() {
-            0 => 1,
-            v => v,
-        };
-        let inv_spp = 1.0 / (spp as Float);
+        let spp = self.samples_per_pixel();
+        let adaptive = self.adaptive;
+        let max_spp = adaptive.map(|a| a.max_spp).unwrap_or(spp);
 
         let block_size = 128usize;
         let blocks_x = (width + block_size - 1) / block_size;
@@ -76,15 +254,19 @@ impl Renderer for SimpleRenderer {
         let thread_count = thread::available_parallelism()
             .map(|n| n.get())
             .unwrap_or(1);
-        let (tx, rx) = mpsc::channel::<(usize, usize, usize, usize, Vec<Vector3f>)>();
-        let mut output = vec![Vector3f::zeros(); width * height];
+        let (tx, rx) = mpsc::channel::<(usize, usize, usize, usize, Vec<PixelStats>)>();
+        let mut output = vec![PixelStats::new(); width * height];
 
         thread::scope(|scope| {
             for _ in 0..thread_count {
                 let next_block = Arc::clone(&next_block);
                 let tx = tx.clone();
                 scope.spawn(move || {
+                    let mut sampler = self.sampler.clone_box();
                     loop {
+                        if self.is_cancelled() {
+                            break;
+                        }
                         let block_index = next_block.fetch_add(1, Ordering::Relaxed);
                         if block_index >= total_blocks {
                             break;
@@ -97,22 +279,29 @@ impl Renderer for SimpleRenderer {
                         let x1 = (x0 + block_size).min(width);
                         let y1 = (y0 + block_size).min(height);
 
-                        let mut block = vec![Vector3f::zeros(); (x1 - x0) * (y1 - y0)];
+                        let mut block = vec![PixelStats::new(); (x1 - x0) * (y1 - y0)];
                         for y in y0..y1 {
                             for x in x0..x1 {
-                                let mut color = Vector3f::zeros();
+                                let mut stats = PixelStats::new();
                                 let pixel = Vector2f::new(x as Float, y as Float);
-                                let seed = ((self.seed & 0xFFF) << 32)
-                                    | (((y as u64) & 0xFFFF) << 16)
-                                    | ((x as u64) & 0xFFFF);
-                                let mut rng = LcgRng::new(seed);
-                                for _sample in 0..spp {
-                                    let rgb = integrator_ref.trace_ray_forward(scene_ref, sensor_ref, pixel, &mut rng);
-                                    color += Vector3f::new(rgb[0], rgb[1], rgb[2]);
+                                loop {
+                                    let batch = spp.min(max_spp - stats.count());
+                                    for _sample in 0..batch {
+                                        sampler.start_pixel_sample(x, y, stats.count());
+                                        let rgb = integrator_ref.trace_ray_forward(scene_ref, sensor_ref, pixel, sampler.as_mut());
+                                        stats.add_sample(Vector3f::new(rgb[0], rgb[1], rgb[2]));
+                                    }
+                                    let converged = match adaptive {
+                                        Some(a) => stats.relative_error() <= a.target_error,
+                                        None => true,
+                                    };
+                                    if converged || stats.count() >= max_spp {
+                                        break;
+                                    }
                                 }
                                 let local_x = x - x0;
                                 let local_y = y - y0;
-                                block[local_x + (x1 - x0) * local_y] = color * inv_spp;
+                                block[local_x + (x1 - x0) * local_y] = stats;
                             }
                         }
                         if tx.send((x0, y0, x1, y1, block)).is_err() {
@@ -123,7 +312,7 @@ impl Renderer for SimpleRenderer {
             }
 
             drop(tx);
-            for _ in 0..total_blocks {
+            for done in 1..=total_blocks {
                 if let Ok((x0, y0, x1, y1, block)) = rx.recv() {
                     for y in y0..y1 {
                         for x in x0..x1 {
@@ -133,29 +322,382 @@ impl Renderer for SimpleRenderer {
                         }
                     }
                     progress.inc(1);
+                    if let Some(callback) = &self.progress_callback {
+                        callback.report(done as u64, total_blocks as u64);
+                    }
                 }
             }
         });
         progress.finish_and_clear();
+        let mut sample_count = Bitmap::new(width, height);
         let bitmap = sensor.bitmap_mut();
         for y in 0..height {
             for x in 0..width {
-                bitmap.set_pixel(x, y, output[x + width * y]);
+                let stats = &output[x + width * y];
+                bitmap.set_pixel(x, y, stats.mean());
+                let n = stats.count() as Float;
+                sample_count.set_pixel(x, y, Vector3f::new(n, n, n));
             }
         }
         let bitmap = bitmap.clone();
         scene.insert_sensor(self.camera_id, sensor);
-        bitmap
+        (bitmap, sample_count)
+    }
+
+    /// Progressive rendering: every pass adds one sample to each pixel that
+    /// has not converged yet. `on_snapshot` receives the current estimate
+    /// every `snapshot_interval` passes (never when the interval is 0).
+    ///
+    /// Each pixel keeps its own RNG stream, so `n` passes produce the same
+    /// image as a block render with `n` samples per pixel.
+    pub fn render_progressive(
+        &self,
+        scene: &mut Scene,
+        snapshot_interval: u32,
+        on_snapshot: &mut dyn FnMut(u32, &Bitmap),
+    ) -> (Bitmap, Bitmap) {
+        let mut sensor = match scene.take_sensor(self.camera_id) {
+            Some(sensor) => sensor,
+            None => return (Bitmap::new(0, 0), Bitmap::new(0, 0)),
+        };
+
+        let (width, height) = {
+            let bmp = sensor.bitmap();
+            (bmp.width(), bmp.height())
+        };
+        if width == 0 || height == 0 {
+            scene.insert_sensor(self.camera_id, sensor);
+            return (Bitmap::new(0, 0), Bitmap::new(0, 0));
+        }
+
+        scene.build_bvh();
+        let spp = self.samples_per_pixel();
+        let adaptive = self.adaptive;
+        let settings = self.progressive.unwrap_or_default();
+        let max_passes = match (settings.max_passes, settings.time_budget, adaptive) {
+            (Some(passes), _, _) => passes,
+            (None, Some(_), _) => u32::MAX,
+            (None, None, Some(a)) => a.max_spp,
+            (None, None, None) => spp,
+        };
+        let max_spp = adaptive.map(|a| a.max_spp).unwrap_or(u32::MAX);
+
+        let resume = self.resume.as_ref().filter(|checkpoint| {
+            match self.checkpoint_mismatch(checkpoint, width, height) {
+                Some(mismatch) => {
+                    log::warn!("Checkpoint {}, starting from scratch.", mismatch);
+                    false
+                }
+                None => true,
+            }
+        });
+        let mut states: Vec<PixelState> = match resume {
+            Some(checkpoint) => checkpoint
+                .pixels
+                .iter()
+                .map(|pixel| PixelState {
+                    stats: pixel.stats,
+                    done: pixel.done,
+                })
+                .collect(),
+            None => (0..width * height)
+                .map(|_| PixelState {
+                    stats: PixelStats::new(),
+                    done: false,
+                })
+                .collect(),
+        };
+        let mut pass = resume.map_or(0, |checkpoint| checkpoint.passes);
+        let first_pass = pass;
+
+        let progress = if max_passes == u32::MAX {
+            ProgressBar::new_spinner()
+        } else {
+            ProgressBar::new(max_passes as u64)
+        };
+        if std::env::var_os("DACQUOISE_PROGRESS").is_some() {
+            progress.set_draw_target(ProgressDrawTarget::term_like(Box::new(
+                console::Term::buffered_stderr(),
+            )));
+        }
+        progress.set_style(
+            ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} passes")
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+        );
+
+        let rows_per_chunk = 16usize;
+        let thread_count = thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let scene_ref: &Scene = scene;
+        let sensor_ref: &dyn crate::core::sensor::Sensor = sensor.as_ref();
+        let integrator_ref: &dyn Integrator = self.integrator.as_ref();
+        let start = Instant::now();
+        progress.set_position(pass as u64);
+        let mut all_done = states.iter().all(|state| state.done);
+        let mut checkpointed_pass = pass;
+
+        while pass < max_passes && !all_done {
+            if self.is_cancelled() {
+                break;
+            }
+            // The budget is checked from the second pass on, so every run
+            // produces an image.
+            if pass > first_pass && settings.time_budget.is_some_and(|budget| start.elapsed() >= budget) {
+                break;
+            }
+
+            let active = AtomicUsize::new(0);
+            let chunks = Mutex::new(states.chunks_mut(width * rows_per_chunk).enumerate());
+            thread::scope(|scope| {
+                for _ in 0..thread_count {
+                    scope.spawn(|| {
+                        let mut sampler = self.sampler.clone_box();
+                        loop {
+                            let next = chunks.lock().expect("chunk lock").next();
+                            let (chunk_index, chunk) = match next {
+                                Some(next) => next,
+                                None => break,
+                            };
+                            let mut chunk_active = 0usize;
+                            for (offset, state) in chunk.iter_mut().enumerate() {
+                                if state.done {
+                                    continue;
+                                }
+                                let index = chunk_index * width * rows_per_chunk + offset;
+                                let (x, y) = (index % width, index / width);
+                                sampler.start_pixel_sample(x, y, state.stats.count());
+                                let pixel = Vector2f::new(x as Float, y as Float);
+                                let rgb = integrator_ref.trace_ray_forward(scene_ref, sensor_ref, pixel, sampler.as_mut());
+                                state.stats.add_sample(Vector3f::new(rgb[0], rgb[1], rgb[2]));
+
+                                let count = state.stats.count();
+                                state.done = count >= max_spp
+                                    || adaptive.is_some_and(|a| {
+                                        count >= spp && state.stats.relative_error() <= a.target_error
+                                    });
+                                if !state.done {
+                                    chunk_active += 1;
+                                }
+                            }
+                            active.fetch_add(chunk_active, Ordering::Relaxed);
+                        }
+                    });
+                }
+            });
+
+            pass += 1;
+            progress.inc(1);
+            if let Some(callback) = &self.progress_callback {
+                let total = if max_passes == u32::MAX { 0 } else { max_passes as u64 };
+                callback.report(pass as u64, total);
+            }
+            if snapshot_interval > 0 && pass.is_multiple_of(snapshot_interval) {
+                let (image, _) = Self::states_to_bitmaps(&states, width, height);
+                on_snapshot(pass, &image);
+            }
+            if let Some((path, interval)) = &self.checkpoint {
+                if *interval > 0 && pass.is_multiple_of(*interval) {
+                    self.write_checkpoint(path, &states, width, height, pass);
+                    checkpointed_pass = pass;
+                }
+            }
+            all_done = active.load(Ordering::Relaxed) == 0;
+        }
+        progress.finish_and_clear();
+        if let Some((path, _)) = &self.checkpoint {
+            if checkpointed_pass != pass {
+                self.write_checkpoint(path, &states, width, height, pass);
+            }
+        }
+        log::info!("Progressive render finished after {} passes in {:.2?}.", pass, start.elapsed());
+
+        let (image, sample_count) = Self::states_to_bitmaps(&states, width, height);
+        *sensor.bitmap_mut() = image.clone();
+        scene.insert_sensor(self.camera_id, sensor);
+        (image, sample_count)
+    }
+
+    fn write_checkpoint(&self, path: &Path, states: &[PixelState], width: usize, height: usize, passes: u32) {
+        let checkpoint = Checkpoint {
+            width,
+            height,
+            seed: self.seed,
+            sampler: self.sampler.name().to_string(),
+            samples_per_pixel: self.samples_per_pixel(),
+            integrator: self.integrator.name().to_string(),
+            max_depth: self.integrator.max_depth(),
+            passes,
+            pixels: states
+                .iter()
+                .map(|state| PixelCheckpoint {
+                    stats: state.stats,
+                    done: state.done,
+                })
+                .collect(),
+        };
+        match checkpoint.write_to_file(path) {
+            Ok(()) => log::info!("Checkpoint written to {} after {} passes.", path.display(), passes),
+            Err(err) => log::warn!("Failed to write checkpoint {}: {}", path.display(), err),
+        }
+    }
+
+    fn states_to_bitmaps(states: &[PixelState], width: usize, height: usize) -> (Bitmap, Bitmap) {
+        let mut image = Bitmap::new(width, height);
+        let mut sample_count = Bitmap::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let stats = &states[x + width * y].stats;
+                image.set_pixel(x, y, stats.mean());
+                let n = stats.count() as Float;
+                sample_count.set_pixel(x, y, Vector3f::new(n, n, n));
+            }
+        }
+        (image, sample_count)
     }
 }
 
-impl SimpleRenderer {
-    pub fn new(integrator: Box<dyn Integrator>, camera_id: usize, seed: u64) -> Self {
-        Self {
-            id: generate_node_id("SimpleRenderer"),
-            integrator,
-            camera_id,
-            seed,
+#[cfg(test)]
+mod tests {
+    use super::{ProgressCallback, SimpleRenderer};
+    use crate::renderers::checkpoint::Checkpoint;
+    use crate::core::integrator::Integrator;
+    use crate::core::sampler::Sampler;
+    use crate::core::scene::Scene;
+    use crate::core::sensor::Sensor;
+    use crate::math::constants::{Vector2f, Vector3f};
+    use crate::math::spectrum::RGBSpectrum;
+    use crate::samplers::create_sampler;
+    use crate::sensors::perspective::PerspectiveCamera;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Pixel x = 0 is flat; every other pixel returns the sampler's values,
+    // so its estimate is noisy and depends on the sample pattern.
+    struct NoiseIntegrator {
+        spp: u32,
+    }
+
+    impl Integrator for NoiseIntegrator {
+        fn trace_ray_forward(&self, _scene: &Scene, _sensor: &dyn Sensor, pixel: Vector2f, sampler: &mut dyn Sampler) -> RGBSpectrum {
+            if pixel.x < 1.0 {
+                return RGBSpectrum::new(0.5, 0.5, 0.5);
+            }
+            let p = sampler.get_pixel_2d();
+            RGBSpectrum::new(p.x, p.y, sampler.get_1d())
+        }
+
+        fn samples_per_pixel(&self) -> u32 {
+            self.spp
+        }
+    }
+
+    fn scene(width: usize) -> Scene {
+        let mut scene = Scene::new();
+        scene.add_sensor(Box::new(PerspectiveCamera::new(
+            Vector3f::new(0.0, 0.0, 1.0),
+            Vector3f::zeros(),
+            Vector3f::new(0.0, 1.0, 0.0),
+            0.5,
+            width as f32,
+            width,
+            1,
+            0.01,
+            1.0e4,
+        )));
+        scene
+    }
+
+    fn renderer(spp: u32) -> SimpleRenderer {
+        SimpleRenderer::new(Box::new(NoiseIntegrator { spp }), 0, 9)
+    }
+
+    #[test]
+    fn test_adaptive_sampling_stops_flat_pixels_early() {
+        for progressive in [false, true] {
+            let mut renderer = renderer(4).with_adaptive_sampling(0.01, 64);
+            if progressive {
+                renderer = renderer.with_progressive(None, None);
+            }
+            let (image, sample_count) = renderer.render_with_sample_count(&mut scene(2));
+            assert_eq!(sample_count.pixel(0, 0), Vector3f::new(4.0, 4.0, 4.0));
+            assert_eq!(sample_count.pixel(1, 0), Vector3f::new(64.0, 64.0, 64.0));
+            assert_eq!(image.pixel(0, 0), Vector3f::new(0.5, 0.5, 0.5));
         }
     }
+
+    #[test]
+    fn test_progressive_passes_match_block_render() {
+        for sampler in ["independent", "stratified", "sobol"] {
+            let sampler = || create_sampler(sampler, 8, 9).expect("sampler");
+            let (block, _) = renderer(8).with_sampler(sampler()).render_with_sample_count(&mut scene(3));
+            let (progressive, sample_count) = renderer(8)
+                .with_sampler(sampler())
+                .with_progressive(None, None)
+                .render_with_sample_count(&mut scene(3));
+            assert_eq!(block.raw_copy(), progressive.raw_copy());
+            assert_eq!(sample_count.pixel(2, 0), Vector3f::new(8.0, 8.0, 8.0));
+        }
+    }
+
+    #[test]
+    fn test_cancel_stops_after_the_current_pass() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        let (image, sample_count) = renderer(8)
+            .with_progressive(None, None)
+            .with_cancel_flag(Arc::clone(&cancel))
+            .with_progress_callback(ProgressCallback::new(move |pass, _| {
+                if pass == 3 {
+                    flag.store(true, Ordering::Relaxed);
+                }
+            }))
+            .render_with_sample_count(&mut scene(2));
+        assert_eq!(sample_count.pixel(1, 0), Vector3f::new(3.0, 3.0, 3.0));
+        // The estimate is the mean of the three samples taken, not of eight.
+        let (three_passes, _) = renderer(3).with_progressive(None, None).render_with_sample_count(&mut scene(2));
+        assert_eq!(image.raw_copy(), three_passes.raw_copy());
+    }
+
+    #[test]
+    fn test_zero_time_budget_renders_one_pass() {
+        let (_, sample_count) = renderer(8)
+            .with_progressive(Some(Duration::ZERO), None)
+            .render_with_sample_count(&mut scene(2));
+        assert_eq!(sample_count.pixel(0, 0), Vector3f::new(1.0, 1.0, 1.0));
+        assert_eq!(sample_count.pixel(1, 0), Vector3f::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_resumed_render_matches_uninterrupted_render() {
+        let path = std::env::temp_dir().join(format!("dacquoise_resume_{}.ckpt", std::process::id()));
+        let progressive = |passes: u32| {
+            renderer(8)
+                .with_sampler(create_sampler("stratified", 8, 9).expect("sampler"))
+                .with_progressive(None, Some(passes))
+        };
+        let _ = progressive(4).with_checkpoint(path.clone(), 0).render_with_sample_count(&mut scene(3));
+        let checkpoint = Checkpoint::read_from_file(&path).expect("read checkpoint");
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(checkpoint.passes, 4);
+
+        let resumed = progressive(8);
+        assert_eq!(resumed.checkpoint_mismatch(&checkpoint, 3, 1), None);
+        let (resumed, resumed_count) = resumed.with_resume(checkpoint.clone()).render_with_sample_count(&mut scene(3));
+        let (full, full_count) = progressive(8).render_with_sample_count(&mut scene(3));
+        assert_eq!(resumed.raw_copy(), full.raw_copy());
+        assert_eq!(resumed_count.raw_copy(), full_count.raw_copy());
+
+        // Another sample count would mix two sample patterns.
+        assert!(renderer(16).with_sampler(create_sampler("stratified", 16, 9).expect("sampler"))
+            .checkpoint_mismatch(&checkpoint, 3, 1)
+            .is_some_and(|mismatch| mismatch.contains("samples per pixel")));
+    }
+
+    #[test]
+    #[should_panic(expected = "max_spp")]
+    fn test_max_spp_below_spp_is_rejected() {
+        let _ = renderer(8).with_adaptive_sampling(0.01, 4);
+    }
 }
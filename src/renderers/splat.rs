@@ -0,0 +1,128 @@
+// Copyright @yucwang 2026
+
+use crate::math::constants::Vector3f;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+/// Splats of one work unit as (pixel index, value), in the order made.
+pub(crate) type Splats = Vec<(usize, Vector3f)>;
+
+struct Queue<R> {
+    done: BTreeMap<usize, (R, Splats)>,
+    merged: usize,
+    failed: bool,
+}
+
+/// Marks the queue failed when a worker panics, so nobody waits for its unit.
+struct PanicGuard<'a, R>(&'a (Mutex<Queue<R>>, Condvar));
+
+impl<R> Drop for PanicGuard<'_, R> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let (queue, ready) = self.0;
+            queue.lock().unwrap_or_else(|e| e.into_inner()).failed = true;
+            ready.notify_all();
+        }
+    }
+}
+
+/// Runs `work` for units `0..units` on all threads and hands each unit's
+/// result and splats to `merge` on the calling thread in unit order, so the
+/// summed image does not depend on scheduling or thread count. Workers run
+/// at most two units per thread ahead of the merge, which bounds the splats
+/// held in memory.
+pub(crate) fn splat_in_order<R, W, M>(units: usize, work: W, mut merge: M)
+where
+    R: Send,
+    W: Fn(usize, &mut Splats) -> R + Sync,
+    M: FnMut(R, &Splats),
+{
+    let thread_count = thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(units.max(1));
+    let window = 2 * thread_count;
+    let next = AtomicUsize::new(0);
+    let shared = (Mutex::new(Queue { done: BTreeMap::new(), merged: 0, failed: false }), Condvar::new());
+    let (queue, ready) = &shared;
+    let work = &work;
+    thread::scope(|scope| {
+        for _ in 0..thread_count {
+            scope.spawn(|| {
+                let _guard = PanicGuard(&shared);
+                loop {
+                    let unit = next.fetch_add(1, Ordering::Relaxed);
+                    if unit >= units {
+                        break;
+                    }
+                    {
+                        let mut state = queue.lock().expect("splat queue lock");
+                        while !state.failed && unit >= state.merged + window {
+                            state = ready.wait(state).expect("splat queue lock");
+                        }
+                        if state.failed {
+                            break;
+                        }
+                    }
+                    let mut splats = Vec::new();
+                    let result = work(unit, &mut splats);
+                    queue.lock().expect("splat queue lock").done.insert(unit, (result, splats));
+                    ready.notify_all();
+                }
+            });
+        }
+
+        for unit in 0..units {
+            let (result, splats) = {
+                let mut state = queue.lock().expect("splat queue lock");
+                loop {
+                    if let Some(entry) = state.done.remove(&unit) {
+                        break entry;
+                    }
+                    // The panic resurfaces when the scope joins the worker.
+                    if state.failed {
+                        return;
+                    }
+                    state = ready.wait(state).expect("splat queue lock");
+                }
+            };
+            merge(result, &splats);
+            queue.lock().expect("splat queue lock").merged = unit + 1;
+            ready.notify_all();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constants::Float;
+
+    // Later units finish first, yet they are merged in order.
+    #[test]
+    fn test_units_are_merged_in_order() {
+        let mut order = Vec::new();
+        let mut film = [Vector3f::zeros(); 3];
+        splat_in_order(
+            64,
+            |unit, splats| {
+                thread::sleep(std::time::Duration::from_micros(((64 - unit) * 20) as u64));
+                splats.push((unit % 3, Vector3f::repeat(unit as Float)));
+                unit
+            },
+            |unit, splats| {
+                order.push(unit);
+                for &(index, value) in splats {
+                    film[index] += value;
+                }
+            },
+        );
+        assert_eq!(order, (0..64).collect::<Vec<_>>());
+        assert_eq!(film[0].x, (0..64).filter(|u| u % 3 == 0).sum::<usize>() as Float);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_worker_panic_is_propagated() {
+        splat_in_order(32, |unit, _| assert!(unit != 5, "unit 5"), |_, _| {});
+    }
+}
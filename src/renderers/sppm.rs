@@ -0,0 +1,280 @@
+// Copyright @yucwang 2026
+
+use crate::core::computation_node::{ComputationNode, generate_node_id};
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::integrators::sppm::{PhotonGrid, SppmIntegrator, VisiblePoint};
+use crate::math::bitmap::Bitmap;
+use crate::math::constants::{Float, Vector2f, Vector3f, PI};
+use crate::renderers::simple::ProgressCallback;
+use crate::renderers::splat::splat_in_order;
+use crate::samplers::independent::IndependentSampler;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+pub use super::renderer::Renderer;
+
+/// Photons traced by one work unit; bounds the deposits held per thread.
+const PHOTONS_PER_UNIT: usize = 4096;
+
+/// Per-pixel state carried across SPPM iterations.
+#[derive(Clone, Copy)]
+struct PixelState {
+    radius: Float,
+    /// Photon count of the running estimate, shrunk by alpha each iteration.
+    photons: Float,
+    /// Accumulated flux, scaled to the current radius.
+    tau: Vector3f,
+    /// Sum of the radiance the camera paths found themselves.
+    direct: Vector3f,
+}
+
+/// Runs `SppmIntegrator` for its number of iterations. Camera and photon
+/// passes are split over threads in fixed ranges, and photon deposits are
+/// gathered into one flux buffer in photon order, so the image only depends
+/// on the seed.
+/// Progress is reported per iteration; a raised cancel flag stops after the
+/// current one.
+pub struct SppmRenderer {
+    id: String,
+    integrator: SppmIntegrator,
+    camera_id: usize,
+    seed: u64,
+    cancel: Option<Arc<AtomicBool>>,
+    progress_callback: Option<ProgressCallback>,
+}
+
+impl ComputationNode for SppmRenderer {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn to_string(&self) -> String {
+        format!("SppmRenderer [id={}]\n  camera_id: {}\n  seed: {}\n  integrator: {}", self.id, self.camera_id, self.seed, self.integrator.describe())
+    }
+}
+
+impl Renderer for SppmRenderer {
+    fn render(&self, scene: &mut Scene) -> Bitmap {
+        let mut sensor = match scene.take_sensor(self.camera_id) {
+            Some(sensor) => sensor,
+            None => return Bitmap::new(0, 0),
+        };
+        let (width, height) = {
+            let bmp = sensor.bitmap();
+            (bmp.width(), bmp.height())
+        };
+        if width == 0 || height == 0 {
+            scene.insert_sensor(self.camera_id, sensor);
+            return Bitmap::new(0, 0);
+        }
+
+        scene.build_bvh();
+        let pixel_count = width * height;
+        let iterations = self.integrator.iterations.max(1);
+        let photon_count = self.integrator.settings.photon_count.map(|n| n as usize).unwrap_or(pixel_count).max(1);
+        let initial_radius = self.integrator.settings.initial_radius.unwrap_or_else(|| {
+            let bounds = scene.scene_bounds();
+            ((bounds.p_max - bounds.p_min).norm() * 0.01).max(1e-4)
+        });
+        let alpha = self.integrator.alpha();
+        let thread_count = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let scene_ref: &Scene = scene;
+        let sensor_ref: &dyn crate::core::sensor::Sensor = sensor.as_ref();
+        let integrator = &self.integrator;
+
+        let mut pixels = vec![
+            PixelState { radius: initial_radius, photons: 0.0, tau: Vector3f::zeros(), direct: Vector3f::zeros() };
+            pixel_count
+        ];
+        let mut completed = 0;
+        while completed < iterations && !self.is_cancelled() {
+            let iteration = completed;
+            // Camera pass: one visible point per pixel.
+            let rows = height.div_ceil(thread_count);
+            let traced: Vec<(Vector3f, Option<VisiblePoint>)> = thread::scope(|scope| {
+                let handles: Vec<_> = (0..thread_count)
+                    .map(|t| {
+                        scope.spawn(move || {
+                            let mut sampler = IndependentSampler::new(iterations, self.seed);
+                            let mut traced = Vec::new();
+                            for y in (t * rows)..((t + 1) * rows).min(height) {
+                                for x in 0..width {
+                                    sampler.start_pixel_sample(x, y, iteration);
+                                    let pixel = Vector2f::new(x as Float, y as Float);
+                                    traced.push(integrator.trace_camera(scene_ref, sensor_ref, pixel, &mut sampler));
+                                }
+                            }
+                            traced
+                        })
+                    })
+                    .collect();
+                handles.into_iter().flat_map(|h| h.join().expect("sppm camera thread panicked")).collect()
+            });
+            let mut visible_points = Vec::with_capacity(pixel_count);
+            for (state, (direct, point)) in pixels.iter_mut().zip(traced) {
+                state.direct += direct;
+                visible_points.push(point);
+            }
+            let radii: Vec<Float> = pixels.iter().map(|s| s.radius).collect();
+            let grid = PhotonGrid::build(
+                &visible_points.iter().zip(&radii).map(|(p, &r)| p.as_ref().map(|p| (p.position(), r))).collect::<Vec<_>>(),
+            );
+
+            // Photon pass. Photons use a stream of their own, keyed by
+            // their index within the iteration, and their deposits are
+            // gathered in photon order.
+            let (grid_ref, points_ref, radii_ref) = (&grid, visible_points.as_slice(), radii.as_slice());
+            let mut flux = vec![Vector3f::zeros(); pixel_count];
+            let mut counts = vec![0u32; pixel_count];
+            splat_in_order(
+                photon_count.div_ceil(PHOTONS_PER_UNIT),
+                |unit, splats| {
+                    let mut sampler = IndependentSampler::new(iterations, self.seed.wrapping_add(1));
+                    let mut deposit = |index: usize, value: Vector3f| splats.push((index, value));
+                    for k in (unit * PHOTONS_PER_UNIT)..((unit + 1) * PHOTONS_PER_UNIT).min(photon_count) {
+                        sampler.start_pixel_sample(k, 0, iteration);
+                        integrator.trace_photon(scene_ref, &mut sampler, grid_ref, points_ref, radii_ref, &mut deposit);
+                    }
+                },
+                |_, splats| {
+                    for &(index, value) in splats {
+                        flux[index] += value;
+                        counts[index] += 1;
+                    }
+                },
+            );
+
+            for (index, state) in pixels.iter_mut().enumerate() {
+                let point = match &visible_points[index] {
+                    Some(point) => point,
+                    None => continue,
+                };
+                let (flux, count) = (flux[index], counts[index]);
+                if count == 0 {
+                    continue;
+                }
+                let count = count as Float;
+                let photons = state.photons + alpha * count;
+                let radius = state.radius * (photons / (state.photons + count)).sqrt();
+                let scale = (radius / state.radius) * (radius / state.radius);
+                state.tau = (state.tau + point.throughput.component_mul(&flux)) * scale;
+                state.photons = photons;
+                state.radius = radius;
+            }
+            completed += 1;
+            if let Some(callback) = &self.progress_callback {
+                callback.report(completed as u64, iterations as u64);
+            }
+        }
+
+        let completed = completed.max(1);
+        let emitted = (completed as usize * photon_count) as Float;
+        let bitmap = sensor.bitmap_mut();
+        for y in 0..height {
+            for x in 0..width {
+                let state = &pixels[x + width * y];
+                let indirect = state.tau / (emitted * PI * state.radius * state.radius);
+                bitmap.set_pixel(x, y, state.direct / completed as Float + indirect);
+            }
+        }
+        let bitmap = bitmap.clone();
+        scene.insert_sensor(self.camera_id, sensor);
+        bitmap
+    }
+}
+
+impl SppmRenderer {
+    pub fn new(integrator: SppmIntegrator, camera_id: usize, seed: u64) -> Self {
+        Self {
+            id: generate_node_id("SppmRenderer"),
+            integrator,
+            camera_id,
+            seed,
+            cancel: None,
+            progress_callback: None,
+        }
+    }
+
+    pub fn with_cancel_flag(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::SceneObject;
+    use crate::core::test_scene::{rectangle, TestScene};
+    use crate::integrators::light_tracer::LightTracerIntegrator;
+    use crate::integrators::path::PathIntegrator;
+    use crate::integrators::sppm::SppmSettings;
+    use crate::materials::microfacet::MicrofacetType;
+    use crate::materials::roughdielectric::RoughDielectricBSDF;
+    use crate::math::spectrum::RGBSpectrum;
+    use crate::renderers::light_tracing::LightTracingRenderer;
+    use crate::renderers::simple::SimpleRenderer;
+    use std::sync::Arc;
+
+    // A diffuse floor lit from above, optionally through a sheet of glass.
+    // With the glass the camera zooms in on the floor below it, which it
+    // sees past the edge of the sheet.
+    fn scene(glass: bool) -> Scene {
+        let scene = TestScene::default();
+        if !glass {
+            return scene.build();
+        }
+        let white = RGBSpectrum::new(1.0, 1.0, 1.0);
+        let bsdf = Arc::new(RoughDielectricBSDF::new(MicrofacetType::GGX, 0.01, 0.01, true, 1.5, 1.0, white, white, None));
+        scene
+            .with_object(SceneObject::new(rectangle(0.4, 1.0, false), bsdf))
+            .with_camera(Vector3f::new(0.0, -2.0, 2.0), 0.12, 4)
+            .build()
+    }
+
+    fn mean(image: &Bitmap) -> Vector3f {
+        let mut sum = Vector3f::zeros();
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                sum += image.pixel(x, y);
+            }
+        }
+        sum / (image.width() * image.height()) as Float
+    }
+
+    #[test]
+    fn test_sppm_matches_path_tracing() {
+        let reference = SimpleRenderer::new(Box::new(PathIntegrator::new(4, 512)), 0, 1).render(&mut scene(false));
+        let image = SppmRenderer::new(SppmIntegrator::new(4, 512), 0, 1).render(&mut scene(false));
+        for y in 0..4 {
+            for x in 0..4 {
+                let (a, b) = (image.pixel(x, y), reference.pixel(x, y));
+                assert!((a - b).norm() <= 0.05 * b.norm() + 1e-3, "pixel ({}, {}): {:?} vs {:?}", x, y, a, b);
+            }
+        }
+    }
+
+    // Light refracted by the glass onto the floor: the particle tracer
+    // connects it to the camera directly, SPPM has to gather it. Single
+    // pixels are still noisy at this photon count, the image mean is not.
+    #[test]
+    fn test_sppm_matches_light_tracing_caustic() {
+        let reference = LightTracingRenderer::new(LightTracerIntegrator::new(4, 4096), 0, 1).render(&mut scene(true));
+        let settings = SppmSettings { photon_count: Some(10000), initial_radius: Some(0.02), alpha: None };
+        let image = SppmRenderer::new(SppmIntegrator::new(4, 64).with_settings(settings), 0, 1).render(&mut scene(true));
+        let (a, b) = (mean(&image), mean(&reference));
+        assert!(b.x > 0.1, "no caustic in the reference: {:?}", b);
+        assert!((a - b).norm() <= 0.15 * b.norm(), "{:?} vs {:?}", a, b);
+    }
+}
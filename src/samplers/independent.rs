@@ -0,0 +1,109 @@
+// Copyright @yucwang 2026
+
+use super::scramble::hash;
+use crate::core::rng::LcgRng;
+use crate::core::sampler::Sampler;
+use crate::math::constants::{Float, Vector2f};
+
+/// Uniform random samples with no stratification. Every pixel sample gets
+/// its own LCG stream seeded from (seed, pixel, sample index).
+pub struct IndependentSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    rng: LcgRng,
+    pixel_sample: Vector2f,
+    dimension: u32,
+}
+
+impl IndependentSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        Self {
+            samples_per_pixel,
+            seed,
+            rng: LcgRng::new(seed),
+            pixel_sample: Vector2f::zeros(),
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.rng = LcgRng::new(hash(&[self.seed, x as u64, y as u64, sample_index as u64]));
+        self.pixel_sample = Vector2f::new(self.rng.next_f32(), self.rng.next_f32());
+        self.dimension = 2;
+    }
+
+    fn get_1d(&mut self) -> Float {
+        self.dimension += 1;
+        self.rng.next_f32()
+    }
+
+    fn get_2d(&mut self) -> Vector2f {
+        self.dimension += 2;
+        Vector2f::new(self.rng.next_f32(), self.rng.next_f32())
+    }
+
+    fn get_pixel_2d(&mut self) -> Vector2f {
+        self.pixel_sample
+    }
+
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(Self {
+            samples_per_pixel: self.samples_per_pixel,
+            seed: self.seed,
+            rng: LcgRng::new(self.rng.state()),
+            pixel_sample: self.pixel_sample,
+            dimension: self.dimension,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("IndependentSampler [spp={}, seed={}]", self.samples_per_pixel, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IndependentSampler;
+    use crate::core::sampler::Sampler;
+
+    #[test]
+    fn test_values_depend_only_on_pixel_sample_and_dimension() {
+        let mut a = IndependentSampler::new(4, 11);
+        let mut b = IndependentSampler::new(4, 11);
+        // Visiting another pixel sample first must not change the values.
+        b.start_pixel_sample(0, 0, 3);
+        let _ = b.get_2d();
+        a.start_pixel_sample(5, 2, 1);
+        b.start_pixel_sample(5, 2, 1);
+        assert_eq!(a.get_pixel_2d(), b.get_pixel_2d());
+        for _ in 0..4 {
+            assert_eq!(a.get_1d(), b.get_1d());
+            assert_eq!(a.get_2d(), b.get_2d());
+        }
+        assert_eq!(a.dimension(), b.dimension());
+    }
+
+    #[test]
+    fn test_clone_continues_the_stream() {
+        let mut sampler = IndependentSampler::new(4, 11);
+        sampler.start_pixel_sample(1, 1, 2);
+        let _ = sampler.get_1d();
+        let mut clone = sampler.clone_box();
+        assert_eq!(clone.dimension(), sampler.dimension());
+        assert_eq!(clone.get_pixel_2d(), sampler.get_pixel_2d());
+        for _ in 0..4 {
+            assert_eq!(clone.get_1d(), sampler.get_1d());
+            assert_eq!(clone.get_2d(), sampler.get_2d());
+        }
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright @yucwang 2026
+
+use super::scramble::ONE_MINUS_EPSILON;
+use crate::core::rng::LcgRng;
+use crate::core::sampler::Sampler;
+use crate::math::constants::{Float, Vector2f, PI};
+
+/// One primary sample space coordinate, with what is needed to undo a
+/// rejected mutation.
+#[derive(Clone, Copy)]
+struct PrimarySample {
+    value: Float,
+    /// Iteration that last changed `value`.
+    modified: u64,
+    backup: Float,
+    backup_modified: u64,
+}
+
+/// Primary sample space sampler for Metropolis light transport (Kelemen et
+/// al. 2002). Sample values are the state of a Markov chain instead of
+/// fresh random numbers: `start_iteration` proposes a mutation of every
+/// coordinate (all new values for a large step, a Gaussian perturbation for
+/// a small one), and `accept` or `reject` keeps or undoes it. Coordinates
+/// are mutated lazily, when an integrator first reads them in an iteration.
+///
+/// Dimensions 0 and 1 are the film position: `raster_position` scales them
+/// to the film and `get_pixel_2d` returns the offset within the pixel.
+pub struct MltSampler {
+    width: usize,
+    height: usize,
+    sigma: Float,
+    large_step_probability: Float,
+    rng: LcgRng,
+    samples: Vec<PrimarySample>,
+    iteration: u64,
+    large_step: bool,
+    last_large_step: u64,
+    dimension: u32,
+}
+
+impl MltSampler {
+    /// Sampler for a `width` x `height` film. Its first evaluation draws
+    /// every coordinate from `seed`'s stream, so the same seed reproduces
+    /// the same initial path.
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self {
+            width: width.max(1),
+            height: height.max(1),
+            sigma: 0.01,
+            large_step_probability: 0.3,
+            rng: LcgRng::new(seed),
+            samples: Vec::new(),
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+            dimension: 2,
+        }
+    }
+
+    /// Standard deviation of small steps and the probability of a large one.
+    pub fn with_mutation(mut self, sigma: Float, large_step_probability: Float) -> Self {
+        self.sigma = sigma;
+        self.large_step_probability = large_step_probability;
+        self
+    }
+
+    /// Continues with another random stream, keeping the current sample
+    /// values. Chains started from the same initial path use it to diverge.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = LcgRng::new(seed);
+    }
+
+    /// Proposes the next state of the chain.
+    pub fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.next_f32() < self.large_step_probability;
+        self.dimension = 2;
+    }
+
+    pub fn is_large_step(&self) -> bool {
+        self.large_step
+    }
+
+    /// Keeps the proposed state.
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    /// Restores the state before the last `start_iteration`.
+    pub fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.modified == self.iteration {
+                sample.value = sample.backup;
+                sample.modified = sample.backup_modified;
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    /// Film position of the current state, in raster coordinates.
+    pub fn raster_position(&mut self) -> Vector2f {
+        let u = self.value(0);
+        let v = self.value(1);
+        Vector2f::new(u * self.width as Float, v * self.height as Float)
+    }
+
+    fn value(&mut self, index: usize) -> Float {
+        // A coordinate the chain has not used yet is uniformly distributed,
+        // whatever the mutation.
+        while self.samples.len() <= index {
+            let value = self.uniform();
+            let iteration = self.iteration;
+            self.samples.push(PrimarySample { value, modified: iteration, backup: value, backup_modified: iteration });
+        }
+        if self.samples[index].modified == self.iteration {
+            return self.samples[index].value;
+        }
+
+        // Catch up with large steps accepted since the last read, then
+        // apply every small step missed since then as one wider step.
+        if self.samples[index].modified < self.last_large_step {
+            self.samples[index].value = self.uniform();
+            self.samples[index].modified = self.last_large_step;
+        }
+        let sample = self.samples[index];
+        let value = if self.large_step {
+            self.uniform()
+        } else {
+            let steps = (self.iteration - sample.modified) as Float;
+            let normal = {
+                let u1 = 1.0 - self.rng.next_f32();
+                let u2 = self.rng.next_f32();
+                (-2.0 * u1.max(Float::MIN_POSITIVE).ln()).sqrt() * (2.0 * PI * u2).cos()
+            };
+            let value = sample.value + normal * self.sigma * steps.sqrt();
+            (value - value.floor()).min(ONE_MINUS_EPSILON)
+        };
+        self.samples[index] = PrimarySample {
+            value,
+            modified: self.iteration,
+            backup: sample.value,
+            backup_modified: sample.modified,
+        };
+        value
+    }
+
+    fn uniform(&mut self) -> Float {
+        self.rng.next_f32().min(ONE_MINUS_EPSILON)
+    }
+}
+
+impl Sampler for MltSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        1
+    }
+
+    /// The chain alone decides the sample values: this only rewinds to the
+    /// first dimension after the film position, so the current state can be
+    /// evaluated again.
+    fn start_pixel_sample(&mut self, _x: usize, _y: usize, _sample_index: u32) {
+        self.dimension = 2;
+    }
+
+    fn get_1d(&mut self) -> Float {
+        let value = self.value(self.dimension as usize);
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> Vector2f {
+        let x = self.get_1d();
+        let y = self.get_1d();
+        Vector2f::new(x, y)
+    }
+
+    fn get_pixel_2d(&mut self) -> Vector2f {
+        let p = self.raster_position();
+        Vector2f::new(p.x - p.x.floor(), p.y - p.y.floor())
+    }
+
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+
+    /// Copy of the whole chain state, so the clone proposes and replays the
+    /// same mutations as the original.
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(Self {
+            width: self.width,
+            height: self.height,
+            sigma: self.sigma,
+            large_step_probability: self.large_step_probability,
+            rng: LcgRng::new(self.rng.state()),
+            samples: self.samples.clone(),
+            iteration: self.iteration,
+            large_step: self.large_step,
+            last_large_step: self.last_large_step,
+            dimension: self.dimension,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "MltSampler [film={}x{}, sigma={}, large_step_probability={}]",
+            self.width, self.height, self.sigma, self.large_step_probability
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(sampler: &mut MltSampler) -> Vec<Float> {
+        sampler.start_pixel_sample(0, 0, 0);
+        (0..8).map(|_| sampler.get_1d()).collect()
+    }
+
+    #[test]
+    fn test_reject_restores_and_accept_keeps_state() {
+        let mut sampler = MltSampler::new(4, 4, 7).with_mutation(0.01, 0.0);
+        let initial = read(&mut sampler);
+        assert_eq!(read(&mut sampler), initial);
+
+        sampler.start_iteration();
+        let proposed = read(&mut sampler);
+        assert_ne!(proposed, initial);
+        for (a, b) in proposed.iter().zip(&initial) {
+            let d = (a - b).abs();
+            assert!(d.min(1.0 - d) < 0.1, "small step moved {} to {}", b, a);
+        }
+        sampler.reject();
+        assert_eq!(read(&mut sampler), initial);
+
+        sampler.start_iteration();
+        let proposed = read(&mut sampler);
+        sampler.accept();
+        assert_eq!(read(&mut sampler), proposed);
+    }
+
+    #[test]
+    fn test_clone_keeps_chain_state() {
+        let mut sampler = MltSampler::new(4, 4, 3).with_mutation(0.01, 0.3);
+        read(&mut sampler);
+        sampler.start_iteration();
+        read(&mut sampler);
+        sampler.accept();
+
+        // Dimensions past the ones read so far come from the random stream,
+        // which the clone has to continue as well.
+        let mut clone = sampler.clone_box();
+        clone.start_pixel_sample(0, 0, 0);
+        sampler.start_pixel_sample(0, 0, 0);
+        let cloned: Vec<Float> = (0..16).map(|_| clone.get_1d()).collect();
+        let original: Vec<Float> = (0..16).map(|_| sampler.get_1d()).collect();
+        assert_eq!(cloned, original);
+    }
+}
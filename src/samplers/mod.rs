@@ -0,0 +1,28 @@
+// Copyright @yucwang 2026
+
+pub mod independent;
+pub mod mlt;
+pub mod pmj02;
+pub mod sobol;
+pub mod stratified;
+
+pub(crate) mod scramble;
+
+use crate::core::sampler::Sampler;
+use independent::IndependentSampler;
+use pmj02::PMJ02Sampler;
+use sobol::SobolSampler;
+use stratified::StratifiedSampler;
+
+/// Builds a sampler from a Mitsuba-style `<sampler type=...>` name.
+/// `ldsampler` maps to Sobol and `multijitter` to PMJ02.
+pub fn create_sampler(name: &str, samples_per_pixel: u32, seed: u64) -> Option<Box<dyn Sampler>> {
+    let sampler: Box<dyn Sampler> = match name {
+        "independent" => Box::new(IndependentSampler::new(samples_per_pixel, seed)),
+        "stratified" => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+        "sobol" | "ldsampler" => Box::new(SobolSampler::new(samples_per_pixel, seed)),
+        "pmj02" | "multijitter" => Box::new(PMJ02Sampler::new(samples_per_pixel, seed)),
+        _ => return None,
+    };
+    Some(sampler)
+}
@@ -0,0 +1,245 @@
+// Copyright @yucwang 2026
+
+use super::scramble::{hash, mix_bits, to_unit_float};
+use crate::core::sampler::Sampler;
+use crate::math::constants::{Float, Vector2f};
+use std::sync::OnceLock;
+
+const PMJ02_SET_COUNT: usize = 4;
+const PMJ02_LOG_SET_SIZE: u32 = 10;
+const PMJ02_SET_SIZE: usize = 1 << PMJ02_LOG_SET_SIZE;
+
+static PMJ02_SETS: OnceLock<Vec<Vec<(u32, u32)>>> = OnceLock::new();
+
+fn pmj02_sets() -> &'static Vec<Vec<(u32, u32)>> {
+    PMJ02_SETS.get_or_init(|| {
+        (0..PMJ02_SET_COUNT)
+            .map(|set| generate_pmj02(PMJ02_LOG_SET_SIZE, 0x706d6a3032 + set as u64))
+            .collect()
+    })
+}
+
+struct BitRng {
+    state: u64,
+}
+
+impl BitRng {
+    fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        (mix_bits(self.state) >> 32) as u32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        ((self.next_u32() as u64 * n as u64) >> 32) as usize
+    }
+}
+
+/// Progressive multi-jittered (0, 2) points (Christensen et al. 2018) as
+/// 32-bit fixed point. Every power-of-two prefix is stratified over all
+/// base-2 elementary intervals. `log_count` must be even.
+pub(crate) fn generate_pmj02(log_count: u32, seed: u64) -> Vec<(u32, u32)> {
+    let mut rng = BitRng { state: seed };
+    let count = 1usize << log_count;
+    let mut points = vec![(rng.next_u32(), rng.next_u32())];
+
+    // `n` is a power of four; each iteration goes n -> 2n -> 4n.
+    let mut log_n = 0u32;
+    while points.len() < count {
+        let n = points.len();
+        let cell_bits = log_n / 2;
+
+        // n -> 2n: the new point goes to the subquadrant diagonally opposite
+        // of the old point in the same cell.
+        let targets: Vec<(u32, u32)> = points
+            .iter()
+            .map(|&(x, y)| (subquadrant(x, cell_bits) ^ 1, subquadrant(y, cell_bits) ^ 1))
+            .collect();
+        extend(&mut points, &targets, cell_bits + 1, log_n + 1, &mut rng);
+
+        // 2n -> 4n: fill the two remaining subquadrants of every cell.
+        let mut first = Vec::with_capacity(n);
+        let mut second = Vec::with_capacity(n);
+        for &(x, y) in &points[..n] {
+            let (sx, sy) = (subquadrant(x, cell_bits), subquadrant(y, cell_bits));
+            if rng.next_u32() & 1 == 0 {
+                first.push((sx ^ 1, sy));
+                second.push((sx, sy ^ 1));
+            } else {
+                first.push((sx, sy ^ 1));
+                second.push((sx ^ 1, sy));
+            }
+        }
+        let targets: Vec<(u32, u32)> = first.into_iter().chain(second).collect();
+        extend(&mut points, &targets, cell_bits + 1, log_n + 2, &mut rng);
+
+        log_n += 2;
+    }
+    points
+}
+
+// Index of the subquadrant containing `v` on a 2^(cell_bits+1) grid.
+fn subquadrant(v: u32, cell_bits: u32) -> u32 {
+    v >> (31 - cell_bits)
+}
+
+// Appends one point per target subquadrant so that the first 2^log_total
+// points cover every elementary interval exactly once. A greedy placement
+// can paint itself into a corner, in which case the step is retried.
+fn extend(points: &mut Vec<(u32, u32)>, targets: &[(u32, u32)], grid_bits: u32, log_total: u32, rng: &mut BitRng) {
+    let base = points.len();
+    loop {
+        points.truncate(base);
+        if try_extend(points, targets, grid_bits, log_total, rng) {
+            return;
+        }
+    }
+}
+
+fn try_extend(points: &mut Vec<(u32, u32)>, targets: &[(u32, u32)], grid_bits: u32, log_total: u32, rng: &mut BitRng) -> bool {
+    let m = log_total;
+    let total = 1usize << m;
+    // occupied[a] covers intervals 2^-a wide and 2^-(m-a) high.
+    let mut occupied = vec![vec![false; total]; m as usize + 1];
+    let interval = |a: u32, fx: u32, fy: u32| ((fx >> (m - a)) << (m - a) | (fy >> a)) as usize;
+    let fine = |v: u32| v >> (32 - m);
+    for &(x, y) in points.iter() {
+        for a in 0..=m {
+            occupied[a as usize][interval(a, fine(x), fine(y))] = true;
+        }
+    }
+
+    let span_bits = m - grid_bits;
+    let mut candidates = Vec::new();
+    for &(sx, sy) in targets {
+        candidates.clear();
+        for fx in (sx << span_bits)..((sx + 1) << span_bits) {
+            for fy in (sy << span_bits)..((sy + 1) << span_bits) {
+                if (0..=m).all(|a| !occupied[a as usize][interval(a, fx, fy)]) {
+                    candidates.push((fx, fy));
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return false;
+        }
+        let (fx, fy) = candidates[rng.below(candidates.len())];
+        for a in 0..=m {
+            occupied[a as usize][interval(a, fx, fy)] = true;
+        }
+        let low_mask = (1u32 << (32 - m)) - 1;
+        let x = (fx << (32 - m)) | (rng.next_u32() & low_mask);
+        let y = (fy << (32 - m)) | (rng.next_u32() & low_mask);
+        points.push((x, y));
+    }
+    true
+}
+
+/// Progressive multi-jittered (0, 2) sampler. Points come from a few
+/// precomputed pmj02 sets; each pixel and dimension pair picks a set and
+/// applies a random XOR digit scramble, which preserves the elementary
+/// interval stratification. Sample indices beyond the set size move on to
+/// another set and scramble.
+pub struct PMJ02Sampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: (u64, u64),
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl PMJ02Sampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        if samples_per_pixel as usize > PMJ02_SET_SIZE {
+            log::warn!("PMJ02 sampler is only stratified up to {} samples per pixel.", PMJ02_SET_SIZE);
+        }
+        Self {
+            samples_per_pixel,
+            seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn sample_2d(&self, dimension: u32) -> Vector2f {
+        let round = (self.sample_index as usize / PMJ02_SET_SIZE) as u64;
+        let dim_hash = hash(&[self.seed, self.pixel.0, self.pixel.1, dimension as u64, round]);
+        let set = &pmj02_sets()[(dim_hash % PMJ02_SET_COUNT as u64) as usize];
+        let (x, y) = set[self.sample_index as usize % PMJ02_SET_SIZE];
+        let scramble = hash(&[dim_hash]);
+        Vector2f::new(
+            to_unit_float(x ^ scramble as u32),
+            to_unit_float(y ^ (scramble >> 32) as u32),
+        )
+    }
+}
+
+impl Sampler for PMJ02Sampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.pixel = (x as u64, y as u64);
+        self.sample_index = sample_index;
+        self.dimension = 2;
+    }
+
+    fn get_1d(&mut self) -> Float {
+        let value = self.sample_2d(self.dimension).x;
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> Vector2f {
+        let value = self.sample_2d(self.dimension);
+        self.dimension += 2;
+        value
+    }
+
+    fn get_pixel_2d(&mut self) -> Vector2f {
+        self.sample_2d(0)
+    }
+
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(Self {
+            samples_per_pixel: self.samples_per_pixel,
+            seed: self.seed,
+            pixel: self.pixel,
+            sample_index: self.sample_index,
+            dimension: self.dimension,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("PMJ02Sampler [spp={}, seed={}]", self.samples_per_pixel, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_pmj02;
+
+    #[test]
+    fn test_pmj02_elementary_intervals() {
+        let points = generate_pmj02(8, 1);
+        assert_eq!(points.len(), 256);
+        for m in 0..=8u32 {
+            let prefix = &points[..1 << m];
+            for a in 0..=m {
+                let mut hit = vec![false; 1 << m];
+                for &(x, y) in prefix {
+                    let xi = if a == 0 { 0 } else { (x >> (32 - a)) as usize };
+                    let yi = if a == m { 0 } else { (y >> (32 - (m - a))) as usize };
+                    let cell = (xi << (m - a)) | yi;
+                    assert!(!hit[cell], "prefix {} interval {}x{} hit twice", 1 << m, a, m - a);
+                    hit[cell] = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright @yucwang 2026
+
+// Hashing and scrambling helpers shared by the samplers.
+
+use crate::math::constants::Float;
+
+// Largest f32 below one.
+pub(crate) const ONE_MINUS_EPSILON: Float = 0.99999994;
+
+/// SplitMix64 finalizer.
+pub(crate) fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
+}
+
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    let mut h = 0x9e3779b97f4a7c15u64;
+    for &v in values {
+        h = mix_bits(h ^ v.wrapping_mul(0xbf58476d1ce4e5b9));
+    }
+    h
+}
+
+/// Maps 32 fixed-point bits to [0, 1).
+pub(crate) fn to_unit_float(bits: u32) -> Float {
+    ((bits as f64) * (1.0 / 4294967296.0)).min(ONE_MINUS_EPSILON as f64) as Float
+}
+
+/// Element `index` of a pseudo-random permutation of 0..len (Kensler 2013).
+pub(crate) fn permute(mut index: u32, len: u32, seed: u32) -> u32 {
+    let mut w = len.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & w) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & w) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & w) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & w) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & w) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= w;
+        index ^= index >> 5;
+        if index < len {
+            break;
+        }
+    }
+    index.wrapping_add(seed) % len
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// Owen scrambling of 32 fixed-point bits (Burley 2020): every bit is
+/// flipped depending on the bits above it.
+pub(crate) fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
@@ -0,0 +1,141 @@
+// Copyright @yucwang 2026
+
+use super::scramble::{hash, nested_uniform_scramble, to_unit_float};
+use crate::core::sampler::Sampler;
+use crate::math::constants::{Float, Vector2f};
+
+/// First two Sobol dimensions as 32-bit fixed point. Dimension 0 is the
+/// van der Corput sequence; dimension 1 uses the direction numbers of the
+/// primitive polynomial x + 1, i.e. v_k = v_{k-1} ^ (v_{k-1} >> 1).
+pub(crate) fn sobol_2d(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0u32;
+    let mut v = 1u32 << 31;
+    let mut bits = index;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            y ^= v;
+        }
+        bits >>= 1;
+        v ^= v >> 1;
+    }
+    (x, y)
+}
+
+/// Owen-scrambled Sobol (0, 2)-sequence with per-dimension padding (Burley
+/// 2020). Each pair of dimensions uses the first two Sobol dimensions with
+/// its own index shuffle and Owen scramble, which keeps every pair well
+/// stratified for power-of-two sample counts while avoiding correlation
+/// between pairs.
+pub struct SobolSampler {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: (u64, u64),
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        if !samples_per_pixel.is_power_of_two() {
+            log::warn!("Sobol sampler works best with a power-of-two sample count, got {}.", samples_per_pixel);
+        }
+        Self {
+            samples_per_pixel,
+            seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn sample_2d(&self, dimension: u32) -> Vector2f {
+        let dim_hash = hash(&[self.seed, self.pixel.0, self.pixel.1, dimension as u64]);
+        let index = nested_uniform_scramble(self.sample_index, dim_hash as u32);
+        let (x, y) = sobol_2d(index);
+        let x = nested_uniform_scramble(x, (dim_hash >> 32) as u32);
+        let y = nested_uniform_scramble(y, hash(&[dim_hash]) as u32);
+        Vector2f::new(to_unit_float(x), to_unit_float(y))
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.pixel = (x as u64, y as u64);
+        self.sample_index = sample_index;
+        self.dimension = 2;
+    }
+
+    fn get_1d(&mut self) -> Float {
+        let value = self.sample_2d(self.dimension).x;
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> Vector2f {
+        let value = self.sample_2d(self.dimension);
+        self.dimension += 2;
+        value
+    }
+
+    fn get_pixel_2d(&mut self) -> Vector2f {
+        self.sample_2d(0)
+    }
+
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(Self {
+            samples_per_pixel: self.samples_per_pixel,
+            seed: self.seed,
+            pixel: self.pixel,
+            sample_index: self.sample_index,
+            dimension: self.dimension,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("SobolSampler [spp={}, seed={}]", self.samples_per_pixel, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sobol_2d, SobolSampler};
+    use crate::core::sampler::Sampler;
+
+    #[test]
+    fn test_sobol_first_points() {
+        assert_eq!(sobol_2d(0), (0, 0));
+        assert_eq!(sobol_2d(1), (0x80000000, 0x80000000));
+        assert_eq!(sobol_2d(2), (0x40000000, 0xc0000000));
+        assert_eq!(sobol_2d(3), (0xc0000000, 0x40000000));
+    }
+
+    #[test]
+    fn test_sobol_sampler_is_02_net() {
+        // 16 scrambled points must hit every 1/16 elementary interval once.
+        let mut sampler = SobolSampler::new(16, 7);
+        let mut points = Vec::new();
+        for index in 0..16 {
+            sampler.start_pixel_sample(3, 5, index);
+            let _ = sampler.get_1d();
+            points.push(sampler.get_2d());
+        }
+        for log_x in 0..=4u32 {
+            let (nx, ny) = (1usize << log_x, 1usize << (4 - log_x));
+            let mut hit = [false; 16];
+            for p in &points {
+                let cell = (p.x * nx as f32) as usize * ny + (p.y * ny as f32) as usize;
+                assert!(!hit[cell]);
+                hit[cell] = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright @yucwang 2026
+
+use super::scramble::{hash, permute, to_unit_float, ONE_MINUS_EPSILON};
+use crate::core::sampler::Sampler;
+use crate::math::constants::{Float, Vector2f};
+
+/// Jittered stratified samples. 1D dimensions use `spp` strata, 2D
+/// dimensions a `res x res` grid with `res = ceil(sqrt(spp))`. Strata are
+/// visited in a random order that differs per pixel and dimension, so
+/// dimensions stay decorrelated.
+pub struct StratifiedSampler {
+    samples_per_pixel: u32,
+    resolution: u32,
+    seed: u64,
+    pixel: (u64, u64),
+    sample_index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let resolution = (samples_per_pixel as f64).sqrt().ceil() as u32;
+        Self {
+            samples_per_pixel,
+            resolution,
+            seed,
+            pixel: (0, 0),
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Picks the stratum for the current sample among `strata` and returns it
+    // together with a hash for the jitter. Indices past the first round of
+    // strata start a fresh permutation.
+    fn stratum(&self, dimension: u32, strata: u32) -> (u32, u64) {
+        let round = (self.sample_index / strata) as u64;
+        let dim_hash = hash(&[self.seed, self.pixel.0, self.pixel.1, dimension as u64, round]);
+        let stratum = permute(self.sample_index % strata, strata, dim_hash as u32);
+        let jitter_hash = hash(&[dim_hash, self.sample_index as u64]);
+        (stratum, jitter_hash)
+    }
+
+    fn sample_2d(&self, dimension: u32) -> Vector2f {
+        let res = self.resolution;
+        let (stratum, jitter) = self.stratum(dimension, res * res);
+        let jx = to_unit_float(jitter as u32);
+        let jy = to_unit_float((jitter >> 32) as u32);
+        Vector2f::new(
+            (((stratum % res) as Float + jx) / res as Float).min(ONE_MINUS_EPSILON),
+            (((stratum / res) as Float + jy) / res as Float).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.pixel = (x as u64, y as u64);
+        self.sample_index = sample_index;
+        self.dimension = 2;
+    }
+
+    fn get_1d(&mut self) -> Float {
+        let (stratum, jitter) = self.stratum(self.dimension, self.samples_per_pixel);
+        self.dimension += 1;
+        ((stratum as Float + to_unit_float(jitter as u32)) / self.samples_per_pixel as Float)
+            .min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> Vector2f {
+        let value = self.sample_2d(self.dimension);
+        self.dimension += 2;
+        value
+    }
+
+    fn get_pixel_2d(&mut self) -> Vector2f {
+        self.sample_2d(0)
+    }
+
+    fn dimension(&self) -> u32 {
+        self.dimension
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(Self {
+            samples_per_pixel: self.samples_per_pixel,
+            resolution: self.resolution,
+            seed: self.seed,
+            pixel: self.pixel,
+            sample_index: self.sample_index,
+            dimension: self.dimension,
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!("StratifiedSampler [spp={}, resolution={}, seed={}]",
+            self.samples_per_pixel, self.resolution, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StratifiedSampler;
+    use crate::core::sampler::Sampler;
+
+    #[test]
+    fn test_values_depend_only_on_pixel_sample_and_dimension() {
+        let mut a = StratifiedSampler::new(9, 5);
+        let mut b = StratifiedSampler::new(9, 5);
+        b.start_pixel_sample(7, 7, 8);
+        let _ = b.get_1d();
+        a.start_pixel_sample(2, 3, 4);
+        b.start_pixel_sample(2, 3, 4);
+        assert_eq!(a.get_pixel_2d(), b.get_pixel_2d());
+        for _ in 0..4 {
+            assert_eq!(a.get_1d(), b.get_1d());
+            assert_eq!(a.get_2d(), b.get_2d());
+        }
+    }
+
+    #[test]
+    fn test_clone_continues_the_stream() {
+        let mut sampler = StratifiedSampler::new(9, 5);
+        sampler.start_pixel_sample(2, 3, 4);
+        let _ = sampler.get_2d();
+        let mut clone = sampler.clone_box();
+        assert_eq!(clone.dimension(), sampler.dimension());
+        for _ in 0..4 {
+            assert_eq!(clone.get_1d(), sampler.get_1d());
+            assert_eq!(clone.get_2d(), sampler.get_2d());
+        }
+    }
+
+    #[test]
+    fn test_every_stratum_gets_one_sample() {
+        let res = 4;
+        let mut sampler = StratifiedSampler::new(res * res, 3);
+        for dimension_draws in 0..3 {
+            let mut hits = vec![0; (res * res) as usize];
+            for index in 0..res * res {
+                sampler.start_pixel_sample(6, 1, index);
+                for _ in 0..dimension_draws {
+                    let _ = sampler.get_2d();
+                }
+                let p = if dimension_draws == 0 { sampler.get_pixel_2d() } else { sampler.get_2d() };
+                let cell = (p.y * res as f32) as usize * res as usize + (p.x * res as f32) as usize;
+                hits[cell] += 1;
+            }
+            assert!(hits.iter().all(|&h| h == 1), "{:?}", hits);
+        }
+    }
+}
@@ -1,6 +1,8 @@
 // Copyright @yucwang 2026
 
-use crate::core::sensor::Sensor;
+use crate::core::scene_writer::{SceneWriteError, XmlElement};
+use crate::core::sensor::{ImportanceRecord, Sensor};
+use crate::math::animated_transform::AnimatedTransform;
 use crate::math::bitmap::Bitmap;
 use crate::math::constants::{Float, Vector2f, Vector3f};
 use crate::math::ray::Ray3f;
@@ -14,6 +16,9 @@ pub struct PerspectiveCamera {
     aspect: Float,
     near_clip: Float,
     far_clip: Float,
+    shutter_open: Float,
+    shutter_close: Float,
+    motion: Option<AnimatedTransform>,
     bitmap: Bitmap,
 }
 
@@ -40,9 +45,46 @@ impl PerspectiveCamera {
             aspect,
             near_clip,
             far_clip,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            motion: None,
             bitmap: Bitmap::new(width, height),
         }
     }
+
+    pub fn with_shutter(mut self, open: Float, close: Float) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close.max(open);
+        self
+    }
+
+    /// Animated camera-to-world transform. Columns 0..3 of the matrix at a
+    /// given time give (left, up, forward, origin), as for a static
+    /// `to_world`; it overrides the static pose when rays carry a time.
+    pub fn with_motion(mut self, motion: AnimatedTransform) -> Self {
+        self.motion = Some(motion);
+        self
+    }
+
+    fn generate_ray(&self,
+                    u: &Vector2f,
+                    origin: Vector3f,
+                    forward: Vector3f,
+                    right: Vector3f,
+                    up: Vector3f) -> Ray3f {
+        let px = (2.0 * u.x - 1.0) * self.aspect * self.tan_half_fov_y;
+        let py = (1.0 - 2.0 * u.y) * self.tan_half_fov_y;
+
+        let d_camera = Vector3f::new(px, py, 1.0).normalize();
+        let dir = (right * d_camera.x + up * d_camera.y + forward * d_camera.z).normalize();
+
+        let inv_z = if d_camera.z != 0.0 { 1.0 / d_camera.z } else { std::f32::MAX };
+        let near_t = self.near_clip * inv_z;
+        let far_t = self.far_clip * inv_z;
+        let origin = origin + dir * near_t;
+        let max_t = far_t - near_t;
+        Ray3f::new(origin, dir, Some(0.0), Some(max_t))
+    }
 }
 
 impl PerspectiveCamera {
@@ -57,18 +99,65 @@ impl PerspectiveCamera {
 
 impl Sensor for PerspectiveCamera {
     fn sample_ray(&self, u: &Vector2f) -> Ray3f {
-        let px = (2.0 * u.x - 1.0) * self.aspect * self.tan_half_fov_y;
-        let py = (1.0 - 2.0 * u.y) * self.tan_half_fov_y;
+        self.generate_ray(u, self.origin, self.forward, self.right, self.up)
+    }
 
-        let d_camera = Vector3f::new(px, py, 1.0).normalize();
-        let dir = (self.right * d_camera.x + self.up * d_camera.y + self.forward * d_camera.z).normalize();
+    fn shutter_interval(&self) -> (Float, Float) {
+        (self.shutter_open, self.shutter_close)
+    }
 
-        let inv_z = if d_camera.z != 0.0 { 1.0 / d_camera.z } else { std::f32::MAX };
-        let near_t = self.near_clip * inv_z;
-        let far_t = self.far_clip * inv_z;
-        let origin = self.origin + dir * near_t;
-        let max_t = far_t - near_t;
-        Ray3f::new(origin, dir, Some(0.0), Some(max_t))
+    fn sample_ray_at_time(&self, u: &Vector2f, time: Float) -> Ray3f {
+        let ray = match &self.motion {
+            Some(motion) => {
+                let m = motion.matrix_at(time);
+                let origin = Vector3f::new(m[(0, 3)], m[(1, 3)], m[(2, 3)]);
+                let forward = Vector3f::new(m[(0, 2)], m[(1, 2)], m[(2, 2)]).normalize();
+                let up_hint = Vector3f::new(m[(0, 1)], m[(1, 1)], m[(2, 1)]);
+                let right = forward.cross(&up_hint).normalize();
+                let up = right.cross(&forward).normalize();
+                self.generate_ray(u, origin, forward, right, up)
+            }
+            None => self.sample_ray(u),
+        };
+        ray.with_time(time)
+    }
+
+    // Inverts `generate_ray` for the static pose. A pixel covers a film
+    // area of `film_area / pixel_count` at unit distance, and film area
+    // maps to solid angle by cos³θ, which gives We.
+    fn eval_importance(&self, p: &Vector3f) -> Option<ImportanceRecord> {
+        let to_camera = self.origin - p;
+        let distance = to_camera.norm();
+        if distance <= 0.0 {
+            return None;
+        }
+        let dir = -to_camera / distance;
+        let cos_theta = dir.dot(&self.forward);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let depth = distance * cos_theta;
+        if depth < self.near_clip || depth > self.far_clip {
+            return None;
+        }
+
+        let px = dir.dot(&self.right) / cos_theta;
+        let py = dir.dot(&self.up) / cos_theta;
+        let u = 0.5 * (px / (self.aspect * self.tan_half_fov_y) + 1.0);
+        let v = 0.5 * (1.0 - py / self.tan_half_fov_y);
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+
+        let (width, height) = (self.width() as Float, self.height() as Float);
+        let film_area = 4.0 * self.aspect * self.tan_half_fov_y * self.tan_half_fov_y;
+        Some(ImportanceRecord {
+            pixel: Vector2f::new(u * width, v * height),
+            direction: -dir,
+            distance,
+            visible_distance: distance - self.near_clip / cos_theta,
+            importance: width * height / (film_area * cos_theta * cos_theta * cos_theta),
+        })
     }
 
     fn bitmap(&self) -> &Bitmap {
@@ -79,8 +168,41 @@ impl Sensor for PerspectiveCamera {
         &mut self.bitmap
     }
 
+    // The field of view is written along y, which is how it is stored.
+    fn to_xml(&self) -> Result<XmlElement, SceneWriteError> {
+        let fov = 2.0 * self.tan_half_fov_y.atan().to_degrees();
+        let mut element = XmlElement::typed("sensor", "perspective")
+            .with_child(XmlElement::float("fov", fov))
+            .with_child(XmlElement::string("fov_axis", "y"))
+            .with_child(XmlElement::float("near_clip", self.near_clip))
+            .with_child(XmlElement::float("far_clip", self.far_clip))
+            .with_child(XmlElement::float("shutter_open", self.shutter_open))
+            .with_child(XmlElement::float("shutter_close", self.shutter_close));
+        match &self.motion {
+            Some(motion) => {
+                let mut animation = XmlElement::new("animation").with_attr("name", "to_world");
+                for (time, matrix) in motion.keyframes() {
+                    animation.push(XmlElement::new("transform").with_attr("time", time).with_child(XmlElement::matrix(&matrix)));
+                }
+                element.push(animation);
+            }
+            None => {
+                let vec3 = |v: Vector3f| format!("{}, {}, {}", v.x, v.y, v.z);
+                let lookat = XmlElement::new("lookat")
+                    .with_attr("origin", vec3(self.origin))
+                    .with_attr("target", vec3(self.origin + self.forward))
+                    .with_attr("up", vec3(self.up));
+                element.push(XmlElement::new("transform").with_attr("name", "to_world").with_child(lookat));
+            }
+        }
+        let film = XmlElement::typed("film", "hdrfilm")
+            .with_child(XmlElement::integer("width", self.width()))
+            .with_child(XmlElement::integer("height", self.height()));
+        Ok(element.with_child(film))
+    }
+
     fn describe(&self) -> String {
-        String::from("PerspectiveCamera\n  origin: Vector3f\n  forward: Vector3f\n  right: Vector3f\n  up: Vector3f\n  tan_half_fov_y: Float\n  aspect: Float\n  near_clip: Float\n  far_clip: Float")
+        String::from("PerspectiveCamera\n  origin: Vector3f\n  forward: Vector3f\n  right: Vector3f\n  up: Vector3f\n  tan_half_fov_y: Float\n  aspect: Float\n  near_clip: Float\n  far_clip: Float\n  shutter: (Float, Float)\n  motion: Option<AnimatedTransform>")
     }
 }
 
@@ -104,4 +226,41 @@ mod tests {
         assert!((dir.y - 0.0).abs() < 1e-6);
         assert!((dir.z + 1.0).abs() < 1e-6);
     }
+
+    #[test]
+    fn test_importance_inverts_camera_rays() {
+        let cam = PerspectiveCamera::new(
+            Vector3f::new(1.0, 2.0, 3.0),
+            Vector3f::new(0.0, 0.0, 0.0),
+            Vector3f::new(0.0, 1.0, 0.0),
+            0.8,
+            1.5,
+            12,
+            8,
+            0.1,
+            100.0,
+        );
+        for (x, y) in [(0.5, 0.5), (0.1, 0.9), (0.95, 0.2)] {
+            let ray = cam.sample_ray(&Vector2f::new(x, y));
+            let record = cam.eval_importance(&ray.at(5.0)).expect("point in view");
+            assert!((record.pixel.x - x * 12.0).abs() < 1e-3 && (record.pixel.y - y * 8.0).abs() < 1e-3, "{:?}", record.pixel);
+            assert!((record.direction + ray.dir()).norm() < 1e-5);
+            assert!(record.visible_distance < record.distance);
+        }
+        assert!(cam.eval_importance(&Vector3f::new(2.0, 4.0, 6.0)).is_none());
+
+        // We integrates to the pixel count over the field of view.
+        let n = 200;
+        let mut total = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let ray = cam.sample_ray(&Vector2f::new((i as Float + 0.5) / n as Float, (j as Float + 0.5) / n as Float));
+                let record = cam.eval_importance(&ray.at(1.0)).unwrap();
+                let cos = ray.dir().dot(&cam.forward);
+                let solid_angle = 4.0 * 1.5 * 0.4f32.tan().powi(2) * cos * cos * cos / (n * n) as Float;
+                total += record.importance * solid_angle;
+            }
+        }
+        assert!((total - 96.0).abs() < 1e-2 * 96.0, "{}", total);
+    }
 }
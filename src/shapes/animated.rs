@@ -0,0 +1,203 @@
+// Copyright @yucwang 2026
+
+use crate::core::computation_node::{indent_string, ComputationNode};
+use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
+use crate::core::shape::Shape;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
+use crate::math::aabb::AABB;
+use crate::math::animated_transform::AnimatedTransform;
+use crate::math::constants::{Float, Vector2f};
+use crate::math::ray::Ray3f;
+use crate::math::spectrum::RGBSpectrum;
+use crate::math::transform::Transform;
+use std::sync::Arc;
+
+/// Shape moved by a keyframed `to_world`. The wrapped shape is built in
+/// object space; rays are taken into object space at their own time, and
+/// the bounding box covers the whole motion so the scene BVH stays valid.
+pub struct AnimatedShape {
+    shape: Arc<dyn Shape>,
+    motion: AnimatedTransform,
+    bbox: AABB,
+}
+
+impl AnimatedShape {
+    pub fn new(shape: Arc<dyn Shape>, motion: AnimatedTransform) -> Self {
+        let bbox = motion.motion_bounds(&shape.bounding_box());
+        Self { shape, motion, bbox }
+    }
+
+    pub fn motion(&self) -> &AnimatedTransform {
+        &self.motion
+    }
+
+    // Object-space ray for `ray` and the transform at its time. The object
+    // direction is renormalised, so distances scale by `|M^-1 d|`.
+    fn local_ray(&self, ray: &Ray3f) -> (Ray3f, Transform) {
+        let to_world = self.motion.transform_at(ray.time);
+        let d_local = to_world.inv_apply_vector(ray.dir());
+        let scale = d_local.norm();
+        let local = Ray3f::new(
+            to_world.inv_apply_point(ray.origin()),
+            d_local,
+            Some(ray.min_t * scale),
+            Some((ray.max_t * scale).min(Float::MAX)),
+        )
+        .with_time(ray.time);
+        (local, to_world)
+    }
+
+    // Area scale of the first keyframe, `|det M|^(2/3)`; exact for rigid
+    // motion and uniform scale.
+    fn area_scale(&self) -> Float {
+        let m = self.motion.matrix_at(self.motion.time_range().0);
+        m.fixed_slice::<3, 3>(0, 0).determinant().abs().powf(2.0 / 3.0)
+    }
+}
+
+impl ComputationNode for AnimatedShape {
+    fn id(&self) -> &str {
+        self.shape.id()
+    }
+
+    fn to_string(&self) -> String {
+        let (t0, t1) = self.motion.time_range();
+        format!(
+            "AnimatedShape [\n  time_range: ({}, {}),\n  shape:\n{}\n]",
+            t0,
+            t1,
+            indent_string(&self.shape.to_string(), "    ")
+        )
+    }
+}
+
+impl Shape for AnimatedShape {
+    fn bounding_box(&self) -> AABB {
+        self.bbox
+    }
+
+    fn ray_intersection(&self, ray: &Ray3f) -> Option<SurfaceIntersection> {
+        let (local, to_world) = self.local_ray(ray);
+        let hit = self.shape.ray_intersection(&local)?;
+        let p_world = to_world.apply_point(hit.p());
+        let t_world = (p_world - ray.origin()).dot(&ray.dir());
+        if !ray.test_segment(t_world) {
+            return None;
+        }
+
+        let intersection = SurfaceIntersection::new(
+            p_world,
+            to_world.apply_normal(hit.geo_normal()).normalize(),
+            to_world.apply_normal(hit.sh_normal()).normalize(),
+            hit.uv(),
+            t_world,
+            RGBSpectrum::default(),
+            None,
+            None,
+        )
+        .with_triangle_index(hit.triangle_index())
+        .with_tangent(hit.tangent().map(|t| to_world.apply_vector(t).normalize()))
+        .with_color(hit.color());
+        Some(intersection)
+    }
+
+    fn ray_intersection_t(&self, ray: &Ray3f) -> bool {
+        let (local, _) = self.local_ray(ray);
+        self.shape.ray_intersection_t(&local)
+    }
+
+    /// Samples the shape at the first keyframe, with the density of
+    /// `surface_area`. The loader rejects animated emitters, so no ray time
+    /// is needed.
+    fn sample(&self, u: &Vector2f) -> SurfaceSampleRecord {
+        let to_world = self.motion.transform_at(self.motion.time_range().0);
+        let record = self.shape.sample(u);
+        let local = record.intersection();
+        let area_scale = self.area_scale();
+
+        let intersection = SurfaceIntersection::new(
+            to_world.apply_point(local.p()),
+            to_world.apply_normal(local.geo_normal()).normalize(),
+            to_world.apply_normal(local.sh_normal()).normalize(),
+            local.uv(),
+            0.0,
+            RGBSpectrum::default(),
+            None,
+            None,
+        )
+        .with_triangle_index(local.triangle_index())
+        .with_tangent(local.tangent().map(|t| to_world.apply_vector(t).normalize()))
+        .with_color(local.color());
+        let pdf = if area_scale > 0.0 { record.pdf() / area_scale } else { 0.0 };
+        SurfaceSampleRecord::new(intersection, pdf)
+    }
+
+    /// Area at the first keyframe.
+    fn surface_area(&self) -> Float {
+        self.shape.surface_area() * self.area_scale()
+    }
+
+    // The wrapped shape's own to_world, if any, is replayed inside every
+    // keyframe before the keyframe matrix.
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        let mut element = self.shape.to_xml(ctx)?;
+        let local = element.take_child("transform", "to_world").map(|t| t.children).unwrap_or_default();
+        let mut animation = XmlElement::new("animation").with_attr("name", "to_world");
+        for (time, matrix) in self.motion.keyframes() {
+            animation.push(XmlElement::new("transform")
+                .with_attr("time", time)
+                .with_children(local.iter().cloned())
+                .with_child(XmlElement::matrix(&matrix)));
+        }
+        element.push(animation);
+        Ok(element)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnimatedShape;
+    use crate::core::shape::Shape;
+    use crate::math::animated_transform::AnimatedTransform;
+    use crate::math::constants::{Matrix4f, Vector2f, Vector3f};
+    use crate::math::ray::Ray3f;
+    use crate::math::transform::Transform;
+    use crate::shapes::cube::Cube;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_animated_shape_follows_motion() {
+        let mut end = Matrix4f::identity();
+        end[(0, 3)] = 4.0;
+        let motion = AnimatedTransform::new(vec![(0.0, Matrix4f::identity()), (1.0, end)]);
+        let cube = Arc::new(Cube::new(Transform::default(), None));
+        let shape = AnimatedShape::new(cube, motion);
+
+        let bbox = shape.bounding_box();
+        assert!(bbox.p_min.x <= -1.0 && bbox.p_max.x >= 5.0);
+
+        // A ray through x = 4 misses at t = 0 and hits the cube at t = 1.
+        let ray = Ray3f::new(Vector3f::new(4.0, 0.0, 5.0), Vector3f::new(0.0, 0.0, -1.0), None, None);
+        assert!(shape.ray_intersection(&ray).is_none());
+        let hit = shape.ray_intersection(&ray.with_time(1.0)).expect("hit at t = 1");
+        assert!((hit.t() - 4.0).abs() < 1e-4);
+        assert!((hit.geo_normal() - Vector3f::new(0.0, 0.0, 1.0)).norm() < 1e-4);
+        assert!(!shape.ray_intersection_t(&ray.with_time(0.5)));
+        assert!(shape.ray_intersection_t(&ray.with_time(1.0)));
+    }
+
+    #[test]
+    fn test_sample_density_matches_surface_area() {
+        let mut start = Matrix4f::identity();
+        for i in 0..3 {
+            start[(i, i)] = 2.0;
+        }
+        let motion = AnimatedTransform::new(vec![(0.0, start), (1.0, Matrix4f::identity())]);
+        let cube = Arc::new(Cube::new(Transform::default(), None));
+        let shape = AnimatedShape::new(cube, motion);
+
+        assert!((shape.surface_area() - 96.0).abs() < 1e-3);
+        let record = shape.sample(&Vector2f::new(0.3, 0.7));
+        assert!((record.pdf() * shape.surface_area() - 1.0).abs() < 1e-4);
+    }
+}
@@ -3,6 +3,7 @@
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
 use crate::core::shape::Shape;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants::{EPSILON, Float, Vector2f, Vector3f};
 use crate::math::ray::Ray3f;
@@ -210,6 +211,10 @@ impl Shape for Cube {
     fn surface_area(&self) -> Float {
         self.area
     }
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("shape", "cube").with_child(XmlElement::transform("to_world", self.to_world.matrix())))
+    }
 }
 
 fn cube_normal(p: Vector3f) -> Vector3f {
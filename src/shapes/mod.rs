@@ -4,3 +4,4 @@ pub mod triangle;
 pub mod triangle_mesh;
 pub mod rectangle;
 pub mod cube;
+pub mod animated;
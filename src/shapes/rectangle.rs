@@ -3,6 +3,7 @@
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
 use crate::core::shape::Shape;
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants::{EPSILON, Float, Vector2f, Vector3f};
 use crate::math::ray::Ray3f;
@@ -134,4 +135,7 @@ impl Shape for Rectangle {
         self.area
     }
 
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        Ok(XmlElement::typed("shape", "rectangle").with_child(XmlElement::transform("to_world", self.to_world.matrix())))
+    }
 }
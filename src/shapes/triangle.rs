@@ -4,11 +4,13 @@
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::shape::Shape;
 use crate::core::interaction::{ SurfaceIntersection, SurfaceSampleRecord };
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants:: { EPSILON, Float, Vector2f, Vector3f };
 use crate::math::ray::Ray3f;
 use crate::math::spectrum::RGBSpectrum;
 use crate::math::warp::square_to_triangle;
+use crate::shapes::triangle_mesh::TriangleMesh;
 
 use std::option::Option;
 
@@ -108,6 +110,11 @@ impl Shape for Triangle {
         length
     }
 
+
+    // Written as a one-face PLY mesh.
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        TriangleMesh::from_indexed(vec![self.p0, self.p1, self.p2], vec![[0, 1, 2]], Some(self.id.clone())).to_xml(ctx)
+    }
 }
 
 impl Triangle {
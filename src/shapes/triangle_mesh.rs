@@ -1,15 +1,16 @@
 // Copyright @yucwang 2023
 
-use super::triangle::Triangle;
-
 use crate::core::computation_node::{ComputationNode, generate_node_id};
 use crate::core::interaction::{SurfaceIntersection, SurfaceSampleRecord};
+use crate::core::scene_writer::{MeshExport, SceneWriteError, WriteContext, XmlElement};
 use crate::core::shape::Shape;
 use crate::core::bvh::BVH;
 use crate::io::obj_utils;
 use crate::io::obj_utils::ObjLoadError;
+use crate::io::serialized_utils;
+use crate::io::serialized_utils::SerializedLoadError;
 use crate::math::aabb::AABB;
-use crate::math::constants::{ Float, Vector2f, Vector3f };
+use crate::math::constants::{ Float, Matrix4f, Vector2f, Vector3f };
 use crate::math::ray::Ray3f;
 use crate::math::spectrum::RGBSpectrum;
 use crate::math::transform::Transform;
@@ -17,9 +18,10 @@ use crate::math::transform::Transform;
 use ply_rs_bw::parser::Parser;
 use ply_rs_bw::ply::{DefaultElement, Property};
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter, Write};
 use std::vec::Vec;
 
 #[derive(Debug)]
@@ -47,92 +49,148 @@ impl fmt::Display for PlyLoadError {
 
 impl std::error::Error for PlyLoadError {}
 
+/// File a mesh was read from, kept so a written scene can point back to it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshSource {
+    pub path: String,
+    /// Shape type in the scene file: `obj`, `ply` or `serialized`.
+    pub format: &'static str,
+    pub shape_index: usize,
+}
+
+/// Indexed triangle mesh. Positions, normals, UVs, tangents and colors are
+/// per-vertex buffers shared by all faces; each face is three `u32`
+/// indices. Attribute buffers are either empty or one entry per vertex.
 pub struct TriangleMesh {
     id: String,
-    vertices: Vec<Vector3f>,
-    normals:  Vec<Vector3f>,
-    uvs:      Vec<Vector2f>,
-    triangles:Vec<Triangle>,
-    tri_areas: Vec<Float>,
+    positions: Vec<Vector3f>,
+    normals: Vec<Vector3f>,
+    uvs: Vec<Vector2f>,
+    tangents: Vec<Vector3f>,
+    colors: Vec<RGBSpectrum>,
+    indices: Vec<[u32; 3]>,
+    area_cdf: Vec<Float>,
     total_area: Float,
-    tri_normals: Vec<Vector3f>,
-    tri_uv_indices: Vec<[Option<usize>; 3]>,
     bvh: Option<BVH>,
     use_face_normals: bool,
+    source: Option<MeshSource>,
+    // Transforms applied since the mesh was read from `source`.
+    to_world: Matrix4f,
 }
 
 impl TriangleMesh {
+    /// Builds a mesh from shared positions and triangle indices. Faces that
+    /// reference missing vertices are dropped.
+    pub fn from_indexed(positions: Vec<Vector3f>, indices: Vec<[u32; 3]>, id: Option<String>) -> Self {
+        let vertex_count = positions.len();
+        let indices = indices
+            .into_iter()
+            .filter(|f| f.iter().all(|&i| (i as usize) < vertex_count))
+            .collect();
+        let mut mesh = Self {
+            id: id.unwrap_or_else(|| generate_node_id("TriangleMesh")),
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            tangents: Vec::new(),
+            colors: Vec::new(),
+            indices,
+            area_cdf: Vec::new(),
+            total_area: 0.0,
+            bvh: None,
+            use_face_normals: false,
+            source: None,
+            to_world: Matrix4f::identity(),
+        };
+        mesh.update_geometry();
+        mesh
+    }
+
+    /// Per-vertex shading normals; ignored if the count does not match.
+    pub fn with_normals(mut self, normals: Vec<Vector3f>) -> Self {
+        if normals.len() == self.positions.len() {
+            self.normals = normals;
+        }
+        self
+    }
+
+    /// Per-vertex UVs; also derives per-vertex tangents from them.
+    pub fn with_uvs(mut self, uvs: Vec<Vector2f>) -> Self {
+        if uvs.len() == self.positions.len() {
+            self.uvs = uvs;
+            self.compute_tangents();
+        }
+        self
+    }
+
+    /// Per-vertex tangents, replacing the ones derived from UVs.
+    pub fn with_tangents(mut self, tangents: Vec<Vector3f>) -> Self {
+        if tangents.len() == self.positions.len() {
+            self.tangents = tangents;
+        }
+        self
+    }
+
+    /// Per-vertex colors, read by the `mesh_attribute` texture.
+    pub fn with_colors(mut self, colors: Vec<RGBSpectrum>) -> Self {
+        if colors.len() == self.positions.len() {
+            self.colors = colors;
+        }
+        self
+    }
+
     pub fn from_obj(path: &str) -> Result<Self, ObjLoadError> {
         Self::from_obj_with_id(path, None)
     }
 
     pub fn from_obj_with_id(path: &str, id: Option<String>) -> Result<Self, ObjLoadError> {
         let obj_set = obj_utils::load_obj_from_file(path)?;
-        let mut vertices = Vec::new();
+        let mut positions = Vec::new();
         let mut normals = Vec::new();
         let mut uvs = Vec::new();
-        let mut triangles = Vec::new();
-        let mut tri_areas = Vec::new();
-        let mut total_area = 0.0;
-        let mut tri_normals = Vec::new();
-        let mut tri_uv_indices = Vec::new();
+        let mut indices = Vec::new();
+        let mut all_normals = true;
+        let mut any_uvs = false;
 
+        // OBJ indexes positions, UVs and normals separately; each distinct
+        // (position, uv, normal) triple becomes one shared vertex.
         for object in obj_set.objects {
-            for v in object.vertices {
-                vertices.push(Vector3f::new(v.x as f32, v.y as f32, v.z as f32));
-            }
-            for vn in object.normals {
-                normals.push(Vector3f::new(vn.x as f32, vn.y as f32, vn.z as f32));
-            }
-            for vt in object.tex_vertices {
-                uvs.push(Vector2f::new(vt.u as f32, vt.v as f32));
-            }
-            for geom in object.geometry {
-                for shape in geom.shapes {
+            let mut remap: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+            for geom in &object.geometry {
+                for shape in &geom.shapes {
                     if let wavefront_obj::obj::Primitive::Triangle(a, b, c) = shape.primitive {
-                        let p0 = vertices[(a.0) as usize];
-                        let p1 = vertices[(b.0) as usize];
-                        let p2 = vertices[(c.0) as usize];
-                        let tri = Triangle::new(p0, p1, p2);
-                        let area = tri.surface_area();
-                        total_area += area;
-                        tri_areas.push(area);
-                        triangles.push(tri);
-
-                        let n0 = a.2.map(|i| i as usize).and_then(|i| normals.get(i)).cloned();
-                        let n1 = b.2.map(|i| i as usize).and_then(|i| normals.get(i)).cloned();
-                        let n2 = c.2.map(|i| i as usize).and_then(|i| normals.get(i)).cloned();
-                        if let (Some(n0), Some(n1), Some(n2)) = (n0, n1, n2) {
-                            let n = (n0 + n1 + n2).normalize();
-                            tri_normals.push(n);
-                        } else {
-                            let n = (p1 - p0).cross(&(p2 - p0)).normalize();
-                            tri_normals.push(n);
+                        let mut face = [0u32; 3];
+                        for (slot, key) in face.iter_mut().zip([a, b, c]) {
+                            *slot = *remap.entry(key).or_insert_with(|| {
+                                let v = object.vertices.get(key.0).map_or(Vector3f::zeros(), |v| {
+                                    Vector3f::new(v.x as Float, v.y as Float, v.z as Float)
+                                });
+                                let uv = key.1.and_then(|i| object.tex_vertices.get(i));
+                                let n = key.2.and_then(|i| object.normals.get(i));
+                                any_uvs |= uv.is_some();
+                                all_normals &= n.is_some();
+                                positions.push(v);
+                                uvs.push(uv.map_or(Vector2f::zeros(), |t| Vector2f::new(t.u as Float, t.v as Float)));
+                                normals.push(n.map_or(Vector3f::zeros(), |n| {
+                                    Vector3f::new(n.x as Float, n.y as Float, n.z as Float)
+                                }));
+                                (positions.len() - 1) as u32
+                            });
                         }
-
-                        let uv0 = a.1.map(|i| i as usize);
-                        let uv1 = b.1.map(|i| i as usize);
-                        let uv2 = c.1.map(|i| i as usize);
-                        tri_uv_indices.push([uv0, uv1, uv2]);
+                        indices.push(face);
                     }
                 }
             }
         }
 
-        let mut mesh = Self {
-            id: id.unwrap_or_else(|| generate_node_id("TriangleMesh")),
-            vertices,
-            normals,
-            uvs,
-            triangles,
-            tri_areas,
-            total_area,
-            tri_normals,
-            tri_uv_indices,
-            bvh: None,
-            use_face_normals: false,
-        };
-        mesh.build_bvh();
+        let mut mesh = Self::from_indexed(positions, indices, id);
+        if all_normals {
+            mesh = mesh.with_normals(normals);
+        }
+        if any_uvs {
+            mesh = mesh.with_uvs(uvs);
+        }
+        mesh.source = Some(MeshSource { path: path.to_string(), format: "obj", shape_index: 0 });
         Ok(mesh)
     }
 
@@ -152,35 +210,41 @@ impl TriangleMesh {
             .get("vertex")
             .ok_or(PlyLoadError::MissingElement("vertex"))?;
 
-        let mut vertices = Vec::with_capacity(vertices_payload.len());
+        let mut positions = Vec::with_capacity(vertices_payload.len());
         let mut normals = Vec::with_capacity(vertices_payload.len());
         let mut uvs = Vec::with_capacity(vertices_payload.len());
+        let mut colors = Vec::with_capacity(vertices_payload.len());
         let mut has_uv = true;
         let mut has_normals = true;
+        let mut has_colors = true;
 
         for v in vertices_payload {
             let x = ply_prop_f32(v, "x").ok_or(PlyLoadError::MissingElement("vertex.x"))?;
             let y = ply_prop_f32(v, "y").ok_or(PlyLoadError::MissingElement("vertex.y"))?;
             let z = ply_prop_f32(v, "z").ok_or(PlyLoadError::MissingElement("vertex.z"))?;
-            vertices.push(Vector3f::new(x, y, z));
+            positions.push(Vector3f::new(x, y, z));
+
+            if has_uv {
+                let u = ply_prop_f32(v, "u").or_else(|| ply_prop_f32(v, "s"));
+                let vv = ply_prop_f32(v, "v").or_else(|| ply_prop_f32(v, "t"));
+                match (u, vv) {
+                    (Some(u), Some(vv)) => uvs.push(Vector2f::new(u, vv)),
+                    _ => has_uv = false,
+                }
+            }
 
-            let u = ply_prop_f32(v, "u").or_else(|| ply_prop_f32(v, "s"));
-            let vv = ply_prop_f32(v, "v").or_else(|| ply_prop_f32(v, "t"));
-            if let (Some(u), Some(vv)) = (u, vv) {
-                uvs.push(Vector2f::new(u, vv));
-            } else {
-                has_uv = false;
-                uvs.push(Vector2f::new(0.0, 0.0));
+            if has_normals {
+                match (ply_prop_f32(v, "nx"), ply_prop_f32(v, "ny"), ply_prop_f32(v, "nz")) {
+                    (Some(nx), Some(ny), Some(nz)) => normals.push(Vector3f::new(nx, ny, nz)),
+                    _ => has_normals = false,
+                }
             }
 
-            let nx = ply_prop_f32(v, "nx");
-            let ny = ply_prop_f32(v, "ny");
-            let nz = ply_prop_f32(v, "nz");
-            if let (Some(nx), Some(ny), Some(nz)) = (nx, ny, nz) {
-                normals.push(Vector3f::new(nx, ny, nz));
-            } else {
-                has_normals = false;
-                normals.push(Vector3f::new(0.0, 0.0, 0.0));
+            if has_colors {
+                match (ply_prop_color(v, "red"), ply_prop_color(v, "green"), ply_prop_color(v, "blue")) {
+                    (Some(r), Some(g), Some(b)) => colors.push(RGBSpectrum::new(r, g, b)),
+                    _ => has_colors = false,
+                }
             }
         }
 
@@ -189,148 +253,307 @@ impl TriangleMesh {
             .get("face")
             .ok_or(PlyLoadError::MissingElement("face"))?;
 
-        let mut triangles = Vec::new();
-        let mut tri_areas = Vec::new();
-        let mut total_area = 0.0;
-        let mut tri_normals = Vec::new();
-        let mut tri_uv_indices = Vec::new();
-
+        let mut indices = Vec::with_capacity(faces_payload.len());
         for face in faces_payload {
-            let indices = ply_prop_indices(face, "vertex_indices")
+            let face_indices = ply_prop_indices(face, "vertex_indices")
                 .or_else(|| ply_prop_indices(face, "vertex_index"))
                 .ok_or(PlyLoadError::MissingElement("face.vertex_indices"))?;
 
-            if indices.len() < 3 {
+            if face_indices.len() < 3 {
                 continue;
             }
+            for i in 1..(face_indices.len() - 1) {
+                indices.push([face_indices[0] as u32, face_indices[i] as u32, face_indices[i + 1] as u32]);
+            }
+        }
 
-            for i in 1..(indices.len() - 1) {
-                let i0 = indices[0];
-                let i1 = indices[i];
-                let i2 = indices[i + 1];
-                if i0 >= vertices.len() || i1 >= vertices.len() || i2 >= vertices.len() {
-                    continue;
-                }
+        let mut mesh = Self::from_indexed(positions, indices, id);
+        if has_normals {
+            mesh = mesh.with_normals(normals);
+        }
+        if has_uv {
+            mesh = mesh.with_uvs(uvs);
+        }
+        if has_colors {
+            mesh = mesh.with_colors(colors);
+        }
+        mesh.source = Some(MeshSource { path: path.to_string(), format: "ply", shape_index: 0 });
+        Ok(mesh)
+    }
 
-                let p0 = vertices[i0];
-                let p1 = vertices[i1];
-                let p2 = vertices[i2];
-                let tri = Triangle::new(p0, p1, p2);
-                let area = tri.surface_area();
-                total_area += area;
-                tri_areas.push(area);
-                triangles.push(tri);
-
-                let mut n = if has_normals {
-                    normals[i0] + normals[i1] + normals[i2]
-                } else {
-                    Vector3f::new(0.0, 0.0, 0.0)
-                };
-                if n.norm() > 0.0 {
-                    n = n.normalize();
-                } else {
-                    n = (p1 - p0).cross(&(p2 - p0)).normalize();
-                }
-                tri_normals.push(n);
+    pub fn from_serialized(path: &str, shape_index: usize) -> Result<Self, SerializedLoadError> {
+        Self::from_serialized_with_id(path, shape_index, None)
+    }
 
-                if has_uv {
-                    tri_uv_indices.push([Some(i0), Some(i1), Some(i2)]);
-                } else {
-                    tri_uv_indices.push([None, None, None]);
-                }
-            }
+    /// Loads shape `shape_index` of a Mitsuba `.serialized` file. Face
+    /// normals are enabled when the file asks for them.
+    pub fn from_serialized_with_id(path: &str, shape_index: usize, id: Option<String>) -> Result<Self, SerializedLoadError> {
+        let shape = serialized_utils::load_serialized_from_file(path, shape_index)?;
+        let positions = shape.positions.iter().map(|p| Vector3f::new(p[0], p[1], p[2])).collect();
+        let mut mesh = Self::from_indexed(positions, shape.indices, id);
+        if !shape.normals.is_empty() {
+            mesh = mesh.with_normals(shape.normals.iter().map(|n| Vector3f::new(n[0], n[1], n[2])).collect());
         }
-
-        let mut mesh = Self {
-            id: id.unwrap_or_else(|| generate_node_id("TriangleMesh")),
-            vertices,
-            normals,
-            uvs,
-            triangles,
-            tri_areas,
-            total_area,
-            tri_normals,
-            tri_uv_indices,
-            bvh: None,
-            use_face_normals: false,
-        };
-        mesh.build_bvh();
+        if !shape.uvs.is_empty() {
+            // Mitsuba stores UVs with the texture origin at the top left.
+            mesh = mesh.with_uvs(shape.uvs.iter().map(|uv| Vector2f::new(uv[0], 1.0 - uv[1])).collect());
+        }
+        if !shape.colors.is_empty() {
+            mesh = mesh.with_colors(shape.colors.iter().map(|c| RGBSpectrum::new(c[0], c[1], c[2])).collect());
+        }
+        mesh.set_face_normals(shape.face_normals);
+        mesh.source = Some(MeshSource { path: path.to_string(), format: "serialized", shape_index });
         Ok(mesh)
     }
 
     pub fn apply_transform(&mut self, scale: &Vector3f, translate: &Vector3f) {
-        for v in &mut self.vertices {
+        let mut matrix = Matrix4f::new_nonuniform_scaling(scale);
+        matrix.fixed_slice_mut::<3, 1>(0, 3).copy_from(translate);
+        self.to_world = matrix * self.to_world;
+        for v in &mut self.positions {
             *v = v.component_mul(scale) + translate;
         }
-
-        for tri in &mut self.triangles {
-            tri.apply_transform(scale, translate);
+        let inv_scale = Vector3f::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z);
+        for n in &mut self.normals {
+            *n = n.component_mul(&inv_scale).normalize();
         }
-
-        self.tri_normals.clear();
-        self.tri_areas.clear();
-        self.total_area = 0.0;
-        for tri in &self.triangles {
-            let n = tri.geometric_normal();
-            self.tri_normals.push(n);
-            let area = tri.surface_area();
-            self.tri_areas.push(area);
-            self.total_area += area;
+        for t in &mut self.tangents {
+            *t = t.component_mul(scale);
         }
 
-        self.build_bvh();
+        self.update_geometry();
     }
 
     pub fn apply_transform_matrix(&mut self, transform: &Transform) {
-        for v in &mut self.vertices {
+        self.to_world = transform.matrix() * self.to_world;
+        for v in &mut self.positions {
             *v = transform.apply_point(*v);
         }
+        for n in &mut self.normals {
+            *n = transform.apply_normal(*n).normalize();
+        }
+        for t in &mut self.tangents {
+            *t = transform.apply_vector(*t);
+        }
 
-        for tri in &mut self.triangles {
-            tri.apply_transform_matrix(transform);
+        self.update_geometry();
+    }
+
+    pub fn set_face_normals(&mut self, use_face_normals: bool) {
+        self.use_face_normals = use_face_normals;
+    }
+
+    pub fn source(&self) -> Option<&MeshSource> {
+        self.source.as_ref()
+    }
+
+    /// Writes the mesh, as transformed, to a binary little-endian PLY file
+    /// that `from_ply` reads back.
+    pub fn write_ply(&self, path: &str) -> std::io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "ply\nformat binary_little_endian 1.0")?;
+        writeln!(out, "element vertex {}", self.positions.len())?;
+        writeln!(out, "property float x\nproperty float y\nproperty float z")?;
+        if !self.normals.is_empty() {
+            writeln!(out, "property float nx\nproperty float ny\nproperty float nz")?;
+        }
+        if !self.uvs.is_empty() {
+            writeln!(out, "property float u\nproperty float v")?;
+        }
+        if !self.colors.is_empty() {
+            writeln!(out, "property float red\nproperty float green\nproperty float blue")?;
         }
+        writeln!(out, "element face {}", self.indices.len())?;
+        writeln!(out, "property list uchar uint vertex_indices\nend_header")?;
 
-        self.tri_normals.clear();
-        self.tri_areas.clear();
-        self.total_area = 0.0;
-        for tri in &self.triangles {
-            let n = tri.geometric_normal();
-            self.tri_normals.push(n);
-            let area = tri.surface_area();
-            self.tri_areas.push(area);
-            self.total_area += area;
+        for i in 0..self.positions.len() {
+            let mut values = vec![self.positions[i].x, self.positions[i].y, self.positions[i].z];
+            if let Some(n) = self.normals.get(i) {
+                values.extend_from_slice(&[n.x, n.y, n.z]);
+            }
+            if let Some(uv) = self.uvs.get(i) {
+                values.extend_from_slice(&[uv.x, uv.y]);
+            }
+            if let Some(c) = self.colors.get(i) {
+                values.extend_from_slice(&[c[0], c[1], c[2]]);
+            }
+            for v in values {
+                out.write_all(&v.to_le_bytes())?;
+            }
+        }
+        for face in &self.indices {
+            out.write_all(&[3u8])?;
+            for index in face {
+                out.write_all(&index.to_le_bytes())?;
+            }
         }
+        out.flush()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
 
-        self.build_bvh();
+    pub fn positions(&self) -> &[Vector3f] {
+        &self.positions
     }
 
-    fn build_bvh(&mut self) {
-        if self.triangles.is_empty() {
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    pub fn normals(&self) -> &[Vector3f] {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &[Vector2f] {
+        &self.uvs
+    }
+
+    pub fn colors(&self) -> &[RGBSpectrum] {
+        &self.colors
+    }
+
+    fn triangle(&self, idx: usize) -> (Vector3f, Vector3f, Vector3f) {
+        let [i0, i1, i2] = self.indices[idx];
+        (self.positions[i0 as usize], self.positions[i1 as usize], self.positions[i2 as usize])
+    }
+
+    fn triangle_bounds(&self, idx: usize) -> AABB {
+        let (p0, p1, p2) = self.triangle(idx);
+        let mut bounds = AABB::new(p0, p1);
+        bounds.expand_by_point(&p2);
+        bounds
+    }
+
+    // Recomputes the area CDF and the BVH after positions change.
+    fn update_geometry(&mut self) {
+        self.area_cdf.clear();
+        self.area_cdf.reserve(self.indices.len());
+        self.total_area = 0.0;
+        for idx in 0..self.indices.len() {
+            let (p0, p1, p2) = self.triangle(idx);
+            self.total_area += 0.5 * (p1 - p0).cross(&(p2 - p0)).norm();
+            self.area_cdf.push(self.total_area);
+        }
+
+        if self.indices.is_empty() {
             self.bvh = None;
             return;
         }
-
-        let mut prim_bounds = Vec::with_capacity(self.triangles.len());
-        let mut prim_centroids = Vec::with_capacity(self.triangles.len());
-        for tri in &self.triangles {
-            let bounds = tri.bounding_box();
+        let mut prim_bounds = Vec::with_capacity(self.indices.len());
+        let mut prim_centroids = Vec::with_capacity(self.indices.len());
+        for idx in 0..self.indices.len() {
+            let bounds = self.triangle_bounds(idx);
             prim_centroids.push(bounds.center());
             prim_bounds.push(bounds);
         }
-
         self.bvh = Some(BVH::new(prim_bounds, prim_centroids));
     }
 
-    fn tri_uv(&self, idx: usize, bary: Vector3f) -> Vector2f {
-        let indices = self.tri_uv_indices.get(idx).cloned().unwrap_or([None, None, None]);
-        let uv0 = indices[0].and_then(|i| self.uvs.get(i)).cloned().unwrap_or(Vector2f::new(0.0, 0.0));
-        let uv1 = indices[1].and_then(|i| self.uvs.get(i)).cloned().unwrap_or(Vector2f::new(0.0, 0.0));
-        let uv2 = indices[2].and_then(|i| self.uvs.get(i)).cloned().unwrap_or(Vector2f::new(0.0, 0.0));
-        uv0 * bary.x + uv1 * bary.y + uv2 * bary.z
+    // Per-vertex tangents (dp/du) accumulated from the faces around each
+    // vertex; zero where the UV mapping is degenerate.
+    fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vector3f::zeros(); self.positions.len()];
+        for face in &self.indices {
+            let [i0, i1, i2] = face.map(|i| i as usize);
+            let dp1 = self.positions[i1] - self.positions[i0];
+            let dp2 = self.positions[i2] - self.positions[i0];
+            let duv1 = self.uvs[i1] - self.uvs[i0];
+            let duv2 = self.uvs[i2] - self.uvs[i0];
+            let det = duv1.x * duv2.y - duv2.x * duv1.y;
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            let tangent = (dp1 * duv2.y - dp2 * duv1.y) / det;
+            for i in [i0, i1, i2] {
+                tangents[i] += tangent;
+            }
+        }
+        for t in &mut tangents {
+            let len = t.norm();
+            if len > 0.0 && len.is_finite() {
+                *t /= len;
+            } else {
+                *t = Vector3f::zeros();
+            }
+        }
+        self.tangents = tangents;
     }
 
-    pub fn set_face_normals(&mut self, use_face_normals: bool) {
-        self.use_face_normals = use_face_normals;
+    // Möller-Trumbore; returns (t, b1, b2).
+    fn intersect_triangle(&self, idx: usize, ray: &Ray3f) -> Option<(Float, Float, Float)> {
+        let (p0, p1, p2) = self.triangle(idx);
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pvec = ray.dir().cross(&e2);
+        let det = e1.dot(&pvec);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tvec = ray.origin() - p0;
+        let b1 = tvec.dot(&pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = tvec.cross(&e1);
+        let b2 = ray.dir().dot(&qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = e2.dot(&qvec) * inv_det;
+        if !ray.test_segment(t) {
+            return None;
+        }
+        Some((t, b1, b2))
+    }
+
+    // Surface record at barycentrics (b0, b1, b2) of face `idx`, with
+    // interpolated shading normal, UV, tangent and color.
+    fn surface_at(&self, idx: usize, bary: Vector3f, t: Float) -> SurfaceIntersection {
+        let [i0, i1, i2] = self.indices[idx].map(|i| i as usize);
+        let (p0, p1, p2) = self.triangle(idx);
+        let p = p0 * bary.x + p1 * bary.y + p2 * bary.z;
+        let geo_n = (p1 - p0).cross(&(p2 - p0)).normalize();
+
+        let mut sh_n = geo_n;
+        if !self.use_face_normals && !self.normals.is_empty() {
+            let n = self.normals[i0] * bary.x + self.normals[i1] * bary.y + self.normals[i2] * bary.z;
+            let len = n.norm();
+            if len > 0.0 && len.is_finite() {
+                sh_n = n / len;
+            }
+        }
+        if sh_n.dot(&geo_n) < 0.0 {
+            sh_n = -sh_n;
+        }
+
+        let uv = if self.uvs.is_empty() {
+            Vector2f::zeros()
+        } else {
+            self.uvs[i0] * bary.x + self.uvs[i1] * bary.y + self.uvs[i2] * bary.z
+        };
+        let tangent = if self.tangents.is_empty() {
+            None
+        } else {
+            let tn = self.tangents[i0] * bary.x + self.tangents[i1] * bary.y + self.tangents[i2] * bary.z;
+            if tn.norm() > 0.0 { Some(tn) } else { None }
+        };
+        let color = if self.colors.is_empty() {
+            None
+        } else {
+            Some(self.colors[i0] * bary.x + self.colors[i1] * bary.y + self.colors[i2] * bary.z)
+        };
+
+        SurfaceIntersection::new(p, geo_n, sh_n, uv, t, RGBSpectrum::default(), None, None)
+            .with_triangle_index(Some(idx))
+            .with_tangent(tangent)
+            .with_color(color)
     }
 }
 
@@ -352,6 +575,17 @@ fn ply_property_f32(prop: &Property) -> Option<Float> {
     }
 }
 
+// Vertex colors: integer channels are normalized to [0, 1], floats are kept.
+fn ply_prop_color(elem: &DefaultElement, name: &str) -> Option<Float> {
+    match elem.get(name)? {
+        Property::UChar(v) => Some(*v as Float / 255.0),
+        Property::UShort(v) => Some(*v as Float / 65535.0),
+        Property::Float(v) => Some(*v),
+        Property::Double(v) => Some(*v as Float),
+        _ => None,
+    }
+}
+
 fn ply_prop_indices(elem: &DefaultElement, name: &str) -> Option<Vec<usize>> {
     elem.get(name).and_then(ply_property_indices)
 }
@@ -376,109 +610,45 @@ impl ComputationNode for TriangleMesh {
     }
 
     fn to_string(&self) -> String {
-        format!("TriangleMesh [id={}]\n  vertices: Vec<Vector3f>\n  normals: Vec<Vector3f>\n  uvs: Vec<Vector2f>\n  triangles: Vec<Triangle>\n  use_face_normals: bool",
-            self.id)
+        format!("TriangleMesh [id={}]\n  vertices: {}\n  triangles: {}\n  normals: {}\n  uvs: {}\n  colors: {}\n  use_face_normals: {}",
+            self.id,
+            self.positions.len(),
+            self.indices.len(),
+            !self.normals.is_empty(),
+            !self.uvs.is_empty(),
+            !self.colors.is_empty(),
+            self.use_face_normals)
     }
 }
 
 impl Shape for TriangleMesh {
     fn bounding_box(&self) -> AABB {
         let mut bound = AABB::default();
-        for tri in &self.triangles {
-            bound.expand_by_aabb(&tri.bounding_box());
+        for p in &self.positions {
+            bound.expand_by_point(p);
         }
         bound
     }
 
     fn ray_intersection(&self, ray: &Ray3f) -> Option<SurfaceIntersection> {
-        let mut closest_hit: Option<SurfaceIntersection> = None;
-        let mut closest_t = std::f32::MAX;
-
-        if let Some(bvh) = &self.bvh {
-            if let Some((idx, hit)) = bvh.ray_intersection(ray, |prim_idx, ray| {
-                self.triangles[prim_idx].ray_intersection(ray).map(|h| {
-                    let t = h.t();
-                    (h, t)
-                })
-            }) {
-                let geo_n = hit.geo_normal();
-                let mut sh_n = if self.use_face_normals {
-                    geo_n
-                } else {
-                    self.tri_normals.get(idx).cloned().unwrap_or(geo_n)
-                };
-                if sh_n.dot(&geo_n) < 0.0 {
-                    sh_n = -sh_n;
-                }
-                let bary = self.triangles[idx].barycentric(&hit.p());
-                let uv = self.tri_uv(idx, bary);
-                let intersection = SurfaceIntersection::new(
-                    hit.p(),
-                    geo_n,
-                    sh_n,
-                    uv,
-                    hit.t(),
-                    RGBSpectrum::default(),
-                    None,
-                    None,
-                )
-                .with_triangle_index(Some(idx));
-                return Some(intersection);
-            }
-        } else {
-            for (idx, tri) in self.triangles.iter().enumerate() {
-                if let Some(hit) = tri.ray_intersection(ray) {
-                    let hit_t = hit.t();
-                    if hit_t < closest_t {
-                        let geo_n = hit.geo_normal();
-                        let mut sh_n = if self.use_face_normals {
-                            geo_n
-                        } else {
-                            self.tri_normals.get(idx).cloned().unwrap_or(geo_n)
-                        };
-                        if sh_n.dot(&geo_n) < 0.0 {
-                            sh_n = -sh_n;
-                        }
-                        let bary = tri.barycentric(&hit.p());
-                        let uv = self.tri_uv(idx, bary);
-                        let hit = SurfaceIntersection::new(
-                            hit.p(),
-                            geo_n,
-                            sh_n,
-                            uv,
-                            hit_t,
-                            RGBSpectrum::default(),
-                            None,
-                            None,
-                        )
-                        .with_triangle_index(Some(idx));
-                        closest_t = hit_t;
-                        closest_hit = Some(hit);
-                    }
-                }
-            }
-        }
-
-        closest_hit
+        let bvh = self.bvh.as_ref()?;
+        let (idx, (t, b1, b2)) = bvh.ray_intersection(ray, |prim_idx, ray| {
+            self.intersect_triangle(prim_idx, ray).map(|hit| (hit, hit.0))
+        })?;
+        Some(self.surface_at(idx, Vector3f::new(1.0 - b1 - b2, b1, b2), t))
     }
 
     fn ray_intersection_t(&self, ray: &Ray3f) -> bool {
-        if let Some(bvh) = &self.bvh {
-            return bvh.ray_intersection_t(ray, |prim_idx, ray| {
-                self.triangles[prim_idx].ray_intersection_t(ray)
-            });
+        match &self.bvh {
+            Some(bvh) => bvh.ray_intersection_t(ray, |prim_idx, ray| {
+                self.intersect_triangle(prim_idx, ray).is_some()
+            }),
+            None => false,
         }
-
-        for tri in &self.triangles {
-            if tri.ray_intersection_t(ray) {
-                return true;
-            }
-        }
-        false
     }
 
     fn sample(&self, u: &Vector2f) -> SurfaceSampleRecord {
-        if self.triangles.is_empty() || self.total_area <= 0.0 {
+        if self.indices.is_empty() || self.total_area <= 0.0 {
             let p = Vector3f::zeros();
             let n = Vector3f::new(0.0, 0.0, 1.0);
             let uv = Vector2f::new(0.0, 0.0);
@@ -487,49 +657,114 @@ impl Shape for TriangleMesh {
         }
 
         let target = u.x * self.total_area;
-        let mut accum = 0.0;
-        let mut idx = 0;
-        for (i, area) in self.tri_areas.iter().enumerate() {
-            accum += *area;
-            if target <= accum {
-                idx = i;
-                break;
-            }
-        }
-
-        let tri_area = self.tri_areas[idx].max(1e-6);
-        let local_u = Vector2f::new(((target - (accum - tri_area)) / tri_area).min(0.999999), u.y);
+        let idx = self.area_cdf.partition_point(|&c| c < target).min(self.indices.len() - 1);
+        let start = if idx > 0 { self.area_cdf[idx - 1] } else { 0.0 };
+        let tri_area = (self.area_cdf[idx] - start).max(1e-6);
+        let local_u = Vector2f::new(((target - start) / tri_area).clamp(0.0, 0.999999), u.y);
 
         let bary = crate::math::warp::square_to_triangle(&local_u);
-        let (p0, p1, p2) = self.triangles[idx].vertices();
-        let p = p0 * bary.x + p1 * bary.y + p2 * bary.z;
+        let intersection = self.surface_at(idx, bary, 0.0);
+        SurfaceSampleRecord::new(intersection, 1.0 / self.total_area)
+    }
 
-        let geo_n = self.triangles[idx].geometric_normal();
-        let mut sh_n = if self.use_face_normals {
-            geo_n
-        } else {
-            self.tri_normals.get(idx).cloned().unwrap_or(geo_n)
+    fn surface_area(&self) -> Float {
+        self.total_area
+    }
+
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        let mut element = match (&self.source, ctx.mesh_export()) {
+            (Some(source), MeshExport::Reference) => {
+                let mut element = XmlElement::typed("shape", source.format)
+                    .with_child(XmlElement::string("filename", &ctx.path_value(&source.path)));
+                if source.format == "serialized" {
+                    element.push(XmlElement::integer("shape_index", source.shape_index));
+                }
+                element.with_child(XmlElement::transform("to_world", &self.to_world))
+            }
+            _ => {
+                let path = ctx.asset_path(&self.id, "ply")?;
+                self.write_ply(&path.to_string_lossy())?;
+                XmlElement::typed("shape", "ply").with_child(XmlElement::string("filename", &ctx.path_value(&path)))
+            }
         };
-        if sh_n.dot(&geo_n) < 0.0 {
-            sh_n = -sh_n;
+        if self.use_face_normals {
+            element.push(XmlElement::boolean("face_normals", true));
         }
+        Ok(element)
+    }
 
-        let uv = self.tri_uv(idx, bary);
-        let intersection = SurfaceIntersection::new(
-            p,
-            geo_n,
-            sh_n,
-            uv,
-            0.0,
-            RGBSpectrum::default(),
-            None,
-            None,
-        );
-        SurfaceSampleRecord::new(intersection, 1.0 / self.total_area)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TriangleMesh;
+    use crate::core::shape::Shape;
+    use crate::math::constants::{Vector2f, Vector3f};
+    use crate::math::ray::Ray3f;
+    use crate::math::spectrum::RGBSpectrum;
+
+    fn quad() -> TriangleMesh {
+        let positions = vec![
+            Vector3f::new(0.0, 0.0, 0.0),
+            Vector3f::new(1.0, 0.0, 0.0),
+            Vector3f::new(1.0, 1.0, 0.0),
+            Vector3f::new(0.0, 1.0, 0.0),
+        ];
+        let normals = vec![
+            Vector3f::new(-1.0, 0.0, 1.0).normalize(),
+            Vector3f::new(1.0, 0.0, 1.0).normalize(),
+            Vector3f::new(1.0, 0.0, 1.0).normalize(),
+            Vector3f::new(-1.0, 0.0, 1.0).normalize(),
+        ];
+        let uvs = vec![
+            Vector2f::new(0.0, 0.0),
+            Vector2f::new(1.0, 0.0),
+            Vector2f::new(1.0, 1.0),
+            Vector2f::new(0.0, 1.0),
+        ];
+        let colors = vec![
+            RGBSpectrum::new(1.0, 0.0, 0.0),
+            RGBSpectrum::new(0.0, 1.0, 0.0),
+            RGBSpectrum::new(0.0, 1.0, 0.0),
+            RGBSpectrum::new(1.0, 0.0, 0.0),
+        ];
+        TriangleMesh::from_indexed(positions, vec![[0, 1, 2], [0, 2, 3]], None)
+            .with_normals(normals)
+            .with_uvs(uvs)
+            .with_colors(colors)
     }
 
-    fn surface_area(&self) -> Float {
-        self.total_area
+    #[test]
+    fn test_mesh_interpolates_vertex_attributes() {
+        let mesh = quad();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.triangle_count(), 2);
+        assert!((mesh.surface_area() - 1.0).abs() < 1e-6);
+
+        let ray = Ray3f::new(Vector3f::new(0.25, 0.75, 1.0), Vector3f::new(0.0, 0.0, -1.0), None, None);
+        let hit = mesh.ray_intersection(&ray).expect("hit");
+        assert!((hit.t() - 1.0).abs() < 1e-5);
+        assert!((hit.uv() - Vector2f::new(0.25, 0.75)).norm() < 1e-5);
+        assert_eq!(hit.geo_normal(), Vector3f::new(0.0, 0.0, 1.0));
+        // Normal and color blend linearly in x across the quad.
+        let sh = hit.sh_normal();
+        assert!(sh.x < 0.0 && sh.x > -0.71);
+        let color = hit.color().expect("vertex color");
+        assert!((color[0] - 0.75).abs() < 1e-5 && (color[1] - 0.25).abs() < 1e-5);
+        let tangent = hit.tangent().expect("tangent from uvs");
+        assert!((tangent.normalize() - Vector3f::new(1.0, 0.0, 0.0)).norm() < 1e-5);
+
+        assert!(mesh.ray_intersection_t(&ray));
+        let miss = Ray3f::new(Vector3f::new(1.5, 0.5, 1.0), Vector3f::new(0.0, 0.0, -1.0), None, None);
+        assert!(mesh.ray_intersection(&miss).is_none());
     }
 
+    #[test]
+    fn test_mesh_drops_invalid_faces() {
+        let positions = vec![Vector3f::zeros(), Vector3f::new(1.0, 0.0, 0.0), Vector3f::new(0.0, 1.0, 0.0)];
+        let mesh = TriangleMesh::from_indexed(positions, vec![[0, 1, 2], [0, 1, 7]], None)
+            .with_normals(vec![Vector3f::new(0.0, 0.0, 1.0)]);
+        assert_eq!(mesh.triangle_count(), 1);
+        assert!(mesh.normals().is_empty());
+    }
 }
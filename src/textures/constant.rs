@@ -1,5 +1,6 @@
 // Copyright @yucwang 2026
 
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::texture::Texture;
 use crate::math::constants::Vector2f;
 use crate::math::spectrum::RGBSpectrum;
@@ -22,6 +23,10 @@ impl Texture for ConstantTexture {
     fn describe(&self) -> String {
         String::from("ConstantTexture")
     }
+
+    fn to_xml(&self, name: &str, _ctx: &mut WriteContext) -> Result<Vec<XmlElement>, SceneWriteError> {
+        Ok(vec![XmlElement::rgb(name, self.value)])
+    }
 }
 
 #[cfg(test)]
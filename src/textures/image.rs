@@ -1,10 +1,12 @@
 // Copyright @yucwang 2026
 
 use crate::core::texture::Texture;
-use crate::core::scene::RawDataView;
+use crate::io::image_io::{read_exr, read_image, write_exr, ExrOptions, Image};
+use crate::core::gradient::GradientBuffers;
+use crate::core::scene::{RawData, RawDataView};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::constants::{Float, Matrix3f, MatrixXF, Vector2f};
 use crate::math::spectrum::RGBSpectrum;
-use exr::prelude::*;
 use image::io::Reader as ImageReader;
 use image::GenericImageView;
 use std::path::Path;
@@ -25,9 +27,10 @@ pub enum WrapMode {
 pub struct ImageTexture {
     width: usize,
     height: usize,
-    data: MatrixXF,
+    data: RawData,
     filter_mode: FilterMode,
-    wrap_mode: WrapMode,
+    wrap_mode_u: WrapMode,
+    wrap_mode_v: WrapMode,
     to_uv: Matrix3f,
 }
 
@@ -49,42 +52,26 @@ impl ImageTexture {
         Self::from_data(width, height, data)
     }
 
+    /// Texture over linear RGB texels laid out as `height x (width * 3)`.
+    pub fn from_matrix(data: MatrixXF) -> Self {
+        let (width, height, data) = ensure_min_size(data.ncols() / 3, data.nrows(), data);
+        Self::from_data(width, height, data)
+    }
+
     pub fn from_exr(path: &str) -> std::result::Result<Self, String> {
-        let image = read()
-            .no_deep_data()
-            .largest_resolution_level()
-            .rgba_channels(
-                |resolution, _| {
-                    let width = resolution.width() as usize;
-                    let height = resolution.height() as usize;
-                    let columns = width * 3;
-                    ImageTexture {
-                        width,
-                        height,
-                        data: MatrixXF::zeros(height, columns),
-                        filter_mode: FilterMode::Bilinear,
-                        wrap_mode: WrapMode::Repeat,
-                        to_uv: Matrix3f::identity(),
-                    }
-                },
-                |image, position, (r, g, b, _a): (f32, f32, f32, f32)| {
-                    let x = position.x() as usize;
-                    let y = position.y() as usize;
-                    let base = x * 3;
-                    image.data[(y, base)] = r;
-                    image.data[(y, base + 1)] = g;
-                    image.data[(y, base + 2)] = b;
-                },
-            )
-            .first_valid_layer()
-            .all_attributes()
-            .from_file(path)
-            .map_err(|e| format!("failed to read exr {}: {}", path, e))?;
-
-        let pixels = image.layer_data.channel_data.pixels;
-        let (width, height) = pixels.dimensions();
-        let (width, height, data) = ensure_min_size(width, height, pixels.data);
-        Ok(Self::from_data(width, height, data))
+        let image = read_exr(path).map_err(|e| format!("failed to read exr {}: {}", path, e))?;
+        Ok(Self::from_float_image(&image))
+    }
+
+    /// Loads a linear EXR, Radiance HDR or PFM file.
+    pub fn from_hdr_file(path: &str) -> std::result::Result<Self, String> {
+        let image = read_image(path).map_err(|e| format!("failed to read image {}: {}", path, e))?;
+        Ok(Self::from_float_image(&image))
+    }
+
+    fn from_float_image(image: &Image) -> Self {
+        let (width, height, data) = ensure_min_size(image.width, image.height, image.to_rgb_matrix());
+        Self::from_data(width, height, data)
     }
 
     pub fn from_image(path: &str, srgb: bool) -> std::result::Result<Self, String> {
@@ -92,7 +79,18 @@ impl ImageTexture {
             .map_err(|e| format!("failed to open image {}: {}", path, e))?
             .decode()
             .map_err(|e| format!("failed to decode image {}: {}", path, e))?;
+        Ok(Self::from_dynamic_image(&img, srgb))
+    }
 
+    /// Decodes an encoded PNG or JPEG held in memory, e.g. an image
+    /// embedded in a binary glTF buffer.
+    pub fn from_memory(bytes: &[u8], srgb: bool) -> std::result::Result<Self, String> {
+        let img = image::load_from_memory(bytes)
+            .map_err(|e| format!("failed to decode image from memory: {}", e))?;
+        Ok(Self::from_dynamic_image(&img, srgb))
+    }
+
+    fn from_dynamic_image(img: &image::DynamicImage, srgb: bool) -> Self {
         let (width, height) = img.dimensions();
         let width = width as usize;
         let height = height as usize;
@@ -116,7 +114,7 @@ impl ImageTexture {
         }
 
         let (width, height, data) = ensure_min_size(width, height, data);
-        Ok(Self::from_data(width, height, data))
+        Self::from_data(width, height, data)
     }
 
     pub fn from_file(path: &str) -> std::result::Result<Self, String> {
@@ -131,7 +129,7 @@ impl ImageTexture {
             .to_ascii_lowercase();
 
         match ext.as_str() {
-            "exr" => Self::from_exr(path),
+            "exr" | "hdr" | "rgbe" | "pfm" => Self::from_hdr_file(path),
             "jpg" | "jpeg" | "png" => Self::from_image(path, srgb),
             _ => Err(format!("unsupported texture format: {}", ext)),
         }
@@ -141,9 +139,10 @@ impl ImageTexture {
         Self {
             width,
             height,
-            data,
+            data: RawData::new(data),
             filter_mode: FilterMode::Bilinear,
-            wrap_mode: WrapMode::Repeat,
+            wrap_mode_u: WrapMode::Repeat,
+            wrap_mode_v: WrapMode::Repeat,
             to_uv: Matrix3f::identity(),
         }
     }
@@ -157,90 +156,87 @@ impl ImageTexture {
     }
 
     pub fn set_wrap_mode(&mut self, wrap_mode: WrapMode) {
-        self.wrap_mode = wrap_mode;
+        self.set_wrap_modes(wrap_mode, wrap_mode);
+    }
+
+    /// Separate wrap modes along u and v.
+    pub fn set_wrap_modes(&mut self, wrap_mode_u: WrapMode, wrap_mode_v: WrapMode) {
+        self.wrap_mode_u = wrap_mode_u;
+        self.wrap_mode_v = wrap_mode_v;
     }
 
     pub fn set_uv_transform(&mut self, transform: Matrix3f) {
         self.to_uv = transform;
     }
 
-    pub fn raw_data_view(&self) -> RawDataView {
-        RawDataView::from_matrix(&self.data)
+    /// Multiplies every texel by `factor`.
+    pub fn scale(&mut self, factor: RGBSpectrum) {
+        let width = self.width;
+        self.data.update(|data| {
+            for x in 0..width {
+                for c in 0..3 {
+                    data.column_mut(x * 3 + c).scale_mut(factor[c]);
+                }
+            }
+        });
     }
 
-    pub fn raw_matrix(&self) -> &MatrixXF {
-        &self.data
+    pub fn raw_data_view(&self) -> RawDataView {
+        self.data.view()
     }
 
-    fn pixel_at(&self, x: usize, y: usize) -> (Float, Float, Float) {
-        let base = x * 3;
-        (
-            self.data[(y, base)],
-            self.data[(y, base + 1)],
-            self.data[(y, base + 2)],
-        )
+    pub fn raw_matrix(&self) -> &MatrixXF {
+        self.data.matrix()
     }
 
-    fn sample_bilinear(&self, uv: Vector2f) -> RGBSpectrum {
-        if self.width == 0 || self.height == 0 {
-            return RGBSpectrum::default();
+    /// Writes the linear texels to an RGB EXR file.
+    pub fn write_exr(&self, path: &str) -> std::result::Result<(), String> {
+        let mut image = Image::rgb(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (r, g, b) = self.pixel_at(x, y);
+                image.pixel_mut(x, y).copy_from_slice(&[r, g, b]);
+            }
         }
+        write_exr(path, &image, &ExrOptions::default())
+            .map_err(|e| format!("failed to write exr {}: {}", path, e))
+    }
 
-        let uv = self.wrap_uv(uv);
-        let u = uv.x;
-        let v = uv.y;
-
-        let x = u * (self.width as Float) - 0.5;
-        let y = (1.0 - v) * (self.height as Float) - 0.5;
-
-        let x0 = x.floor() as isize;
-        let y0 = y.floor() as isize;
-        let x1 = x0 + 1;
-        let y1 = y0 + 1;
-
-        let x0u = self.wrap_index(x0, self.width);
-        let y0u = self.wrap_index(y0, self.height);
-        let x1u = self.wrap_index(x1, self.width);
-        let y1u = self.wrap_index(y1, self.height);
-
-        let tx = x - x0 as Float;
-        let ty = y - y0 as Float;
-
-        let (r00, g00, b00) = self.pixel_at(x0u, y0u);
-        let (r10, g10, b10) = self.pixel_at(x1u, y0u);
-        let (r01, g01, b01) = self.pixel_at(x0u, y1u);
-        let (r11, g11, b11) = self.pixel_at(x1u, y1u);
-
-        let r0 = r00 * (1.0 - tx) + r10 * tx;
-        let g0 = g00 * (1.0 - tx) + g10 * tx;
-        let b0 = b00 * (1.0 - tx) + b10 * tx;
-
-        let r1 = r01 * (1.0 - tx) + r11 * tx;
-        let g1 = g01 * (1.0 - tx) + g11 * tx;
-        let b1 = b01 * (1.0 - tx) + b11 * tx;
-
-        let r = r0 * (1.0 - ty) + r1 * ty;
-        let g = g0 * (1.0 - ty) + g1 * ty;
-        let b = b0 * (1.0 - ty) + b1 * ty;
-
-        RGBSpectrum::new(r, g, b)
+    fn pixel_at(&self, x: usize, y: usize) -> (Float, Float, Float) {
+        let base = x * 3;
+        let data = self.data.matrix();
+        (data[(y, base)], data[(y, base + 1)], data[(y, base + 2)])
     }
 
-    fn sample_nearest(&self, uv: Vector2f) -> RGBSpectrum {
-        if self.width == 0 || self.height == 0 {
-            return RGBSpectrum::default();
+    /// Texels (x, y) and weights the filtered lookup at `uv` blends.
+    fn texel_weights(&self, uv: Vector2f) -> [((usize, usize), Float); 4] {
+        let uv = self.wrap_uv(self.apply_uv_transform(uv));
+        let x = uv.x * (self.width as Float) - 0.5;
+        let y = (1.0 - uv.y) * (self.height as Float) - 0.5;
+        match self.filter_mode {
+            FilterMode::Bilinear => {
+                let x0 = x.floor() as isize;
+                let y0 = y.floor() as isize;
+                let tx = x - x0 as Float;
+                let ty = y - y0 as Float;
+
+                let x0u = wrap_index(x0, self.width, self.wrap_mode_u);
+                let y0u = wrap_index(y0, self.height, self.wrap_mode_v);
+                let x1u = wrap_index(x0 + 1, self.width, self.wrap_mode_u);
+                let y1u = wrap_index(y0 + 1, self.height, self.wrap_mode_v);
+                [
+                    ((x0u, y0u), (1.0 - tx) * (1.0 - ty)),
+                    ((x1u, y0u), tx * (1.0 - ty)),
+                    ((x0u, y1u), (1.0 - tx) * ty),
+                    ((x1u, y1u), tx * ty),
+                ]
+            }
+            FilterMode::Nearest => {
+                let xi = wrap_index(x.round() as isize, self.width, self.wrap_mode_u);
+                let yi = wrap_index(y.round() as isize, self.height, self.wrap_mode_v);
+                [((xi, yi), 1.0), ((xi, yi), 0.0), ((xi, yi), 0.0), ((xi, yi), 0.0)]
+            }
         }
-
-        let uv = self.wrap_uv(uv);
-        let u = uv.x;
-        let v = uv.y;
-
-        let x = (u * (self.width as Float) - 0.5).round() as isize;
-        let y = ((1.0 - v) * (self.height as Float) - 0.5).round() as isize;
-        let xi = self.wrap_index(x, self.width);
-        let yi = self.wrap_index(y, self.height);
-        let (r, g, b) = self.pixel_at(xi, yi);
-        RGBSpectrum::new(r, g, b)
     }
 
     fn apply_uv_transform(&self, uv: Vector2f) -> Vector2f {
@@ -257,44 +253,44 @@ impl ImageTexture {
     }
 
     fn wrap_uv(&self, uv: Vector2f) -> Vector2f {
-        let u = self.wrap_coord(uv.x);
-        let v = self.wrap_coord(uv.y);
+        let u = wrap_coord(uv.x, self.wrap_mode_u);
+        let v = wrap_coord(uv.y, self.wrap_mode_v);
         Vector2f::new(u, v)
     }
+}
 
-    fn wrap_coord(&self, value: Float) -> Float {
-        match self.wrap_mode {
-            WrapMode::Repeat => value.rem_euclid(1.0),
-            WrapMode::Mirror => {
-                let mut v = value.rem_euclid(2.0);
-                if v > 1.0 {
-                    v = 2.0 - v;
-                }
-                v
+fn wrap_coord(value: Float, wrap_mode: WrapMode) -> Float {
+    match wrap_mode {
+        WrapMode::Repeat => value.rem_euclid(1.0),
+        WrapMode::Mirror => {
+            let mut v = value.rem_euclid(2.0);
+            if v > 1.0 {
+                v = 2.0 - v;
             }
-            WrapMode::Clamp => value.clamp(0.0, 1.0),
+            v
         }
+        WrapMode::Clamp => value.clamp(0.0, 1.0),
     }
+}
 
-    fn wrap_index(&self, idx: isize, size: usize) -> usize {
-        let n = size as isize;
-        if n <= 0 {
-            return 0;
-        }
-        match self.wrap_mode {
-            WrapMode::Repeat => idx.rem_euclid(n) as usize,
-            WrapMode::Clamp => idx.clamp(0, n - 1) as usize,
-            WrapMode::Mirror => {
-                if n == 1 {
-                    return 0;
-                }
-                let period = 2 * (n - 1);
-                let mut i = idx.rem_euclid(period);
-                if i >= n {
-                    i = period - i;
-                }
-                i as usize
+fn wrap_index(idx: isize, size: usize, wrap_mode: WrapMode) -> usize {
+    let n = size as isize;
+    if n <= 0 {
+        return 0;
+    }
+    match wrap_mode {
+        WrapMode::Repeat => idx.rem_euclid(n) as usize,
+        WrapMode::Clamp => idx.clamp(0, n - 1) as usize,
+        WrapMode::Mirror => {
+            if n == 1 {
+                return 0;
+            }
+            let period = 2 * (n - 1);
+            let mut i = idx.rem_euclid(period);
+            if i >= n {
+                i = period - i;
             }
+            i as usize
         }
     }
 }
@@ -305,12 +301,60 @@ impl Texture for ImageTexture {
     }
 
     fn eval(&self, uv: Vector2f) -> RGBSpectrum {
-        let uv = self.apply_uv_transform(uv);
-        match self.filter_mode {
-            FilterMode::Bilinear => self.sample_bilinear(uv),
-            FilterMode::Nearest => self.sample_nearest(uv),
+        if self.width == 0 || self.height == 0 {
+            return RGBSpectrum::default();
+        }
+        let mut value = RGBSpectrum::default();
+        for ((x, y), weight) in self.texel_weights(uv) {
+            let (r, g, b) = self.pixel_at(x, y);
+            value += RGBSpectrum::new(r, g, b) * weight;
+        }
+        value
+    }
+
+    fn backward(&self, uv: Vector2f, _vertex_color: Option<RGBSpectrum>, grad: &RGBSpectrum, grads: &mut GradientBuffers) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        let view = self.raw_data_view();
+        for ((x, y), weight) in self.texel_weights(uv) {
+            for c in 0..3 {
+                grads.add(&view, y, x * 3 + c, grad[c] * weight);
+            }
         }
     }
+
+    // Texels may have been edited through the raw data view, so they are
+    // always written out rather than pointing back to the source image.
+    fn to_xml(&self, name: &str, ctx: &mut WriteContext) -> std::result::Result<Vec<XmlElement>, SceneWriteError> {
+        let path = ctx.asset_path(name, "exr")?;
+        self.write_exr(&path.to_string_lossy())
+            .map_err(|e| SceneWriteError::Io(std::io::Error::other(e)))?;
+        let filter = match self.filter_mode {
+            FilterMode::Bilinear => "bilinear",
+            FilterMode::Nearest => "nearest",
+        };
+        let wrap = |mode| match mode {
+            WrapMode::Repeat => "repeat",
+            WrapMode::Mirror => "mirror",
+            WrapMode::Clamp => "clamp",
+        };
+        let mut texture = XmlElement::typed("texture", "bitmap")
+            .with_attr("name", name)
+            .with_child(XmlElement::string("filename", &ctx.path_value(&path)))
+            .with_child(XmlElement::boolean("raw", true))
+            .with_child(XmlElement::string("filter_type", filter));
+        texture = if self.wrap_mode_u == self.wrap_mode_v {
+            texture.with_child(XmlElement::string("wrap_mode", wrap(self.wrap_mode_u)))
+        } else {
+            texture
+                .with_child(XmlElement::string("wrap_mode_u", wrap(self.wrap_mode_u)))
+                .with_child(XmlElement::string("wrap_mode_v", wrap(self.wrap_mode_v)))
+        };
+        let texture = texture
+            .with_child(XmlElement::new("transform").with_attr("name", "to_uv").with_child(XmlElement::matrix3(&self.to_uv)));
+        Ok(vec![texture])
+    }
 }
 
 fn ensure_min_size(
@@ -2,3 +2,4 @@
 
 pub mod constant;
 pub mod image;
+pub mod vertex_color;
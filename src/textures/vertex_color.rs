@@ -0,0 +1,42 @@
+// Copyright @yucwang 2026
+
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
+use crate::core::texture::Texture;
+use crate::math::constants::Vector2f;
+use crate::math::spectrum::RGBSpectrum;
+
+/// Reads the interpolated vertex color of the hit mesh. Shapes without
+/// vertex colors fall back to a constant value.
+pub struct VertexColorTexture {
+    fallback: RGBSpectrum,
+}
+
+impl VertexColorTexture {
+    pub fn new(fallback: RGBSpectrum) -> Self {
+        Self { fallback }
+    }
+}
+
+impl Texture for VertexColorTexture {
+    fn eval(&self, _uv: Vector2f) -> RGBSpectrum {
+        self.fallback
+    }
+
+    fn eval_with_color(&self, _uv: Vector2f, vertex_color: Option<RGBSpectrum>) -> RGBSpectrum {
+        vertex_color.unwrap_or(self.fallback)
+    }
+
+    fn describe(&self) -> String {
+        String::from("VertexColorTexture")
+    }
+
+    // The loader takes the fallback from the bsdf's own `name` value.
+    fn to_xml(&self, name: &str, _ctx: &mut WriteContext) -> Result<Vec<XmlElement>, SceneWriteError> {
+        Ok(vec![
+            XmlElement::typed("texture", "mesh_attribute")
+                .with_attr("name", name)
+                .with_child(XmlElement::string("name", "vertex_color")),
+            XmlElement::rgb(name, self.fallback),
+        ])
+    }
+}
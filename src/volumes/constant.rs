@@ -1,8 +1,10 @@
 // Copyright @yucwang 2026
 
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::core::volume::Volume;
 use crate::math::aabb::AABB;
 use crate::math::constants::{Float, Vector3f};
+use crate::math::spectrum::RGBSpectrum;
 
 pub struct ConstantVolume {
     value: Vector3f,
@@ -45,6 +47,15 @@ impl Volume for ConstantVolume {
     fn eval(&self, _p_world: Vector3f) -> Vector3f {
         self.value
     }
+
+    fn to_xml(&self, _ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        let value = if self.channels == 1 {
+            XmlElement::float("value", self.value.x)
+        } else {
+            XmlElement::rgb("value", RGBSpectrum::new(self.value.x, self.value.y, self.value.z))
+        };
+        Ok(XmlElement::typed("volume", "const").with_child(value))
+    }
 }
 
 #[cfg(test)]
@@ -1,15 +1,18 @@
 // Copyright @yucwang 2026
 
+use crate::core::gradient::GradientBuffers;
 use crate::core::volume::Volume;
-use crate::core::scene::RawDataView;
+use crate::core::scene::{RawData, RawDataView};
+use crate::core::scene_writer::{SceneWriteError, WriteContext, XmlElement};
 use crate::math::aabb::AABB;
 use crate::math::constants::{Float, MatrixXF, Vector3f};
 use crate::math::transform::Transform;
 use crate::volumes::{wrap_coord3, VolumeFilterMode, VolumeWrapMode};
 use std::fs;
+use std::io::{BufWriter, Write};
 
 pub struct GridVolume {
-    data: MatrixXF,
+    data: RawData,
     xres: usize,
     yres: usize,
     zres: usize,
@@ -90,7 +93,7 @@ impl GridVolume {
         }
 
         Ok(Self {
-            data,
+            data: RawData::new(data),
             xres,
             yres,
             zres,
@@ -120,80 +123,103 @@ impl GridVolume {
     }
 
     pub fn raw_data_view(&self) -> RawDataView {
-        RawDataView::from_matrix(&self.data)
+        self.data.view()
+    }
+
+    /// Writes the grid as a version 3 float32 `.vol` file that `from_file`
+    /// reads back.
+    pub fn write_vol(&self, path: &str) -> std::io::Result<()> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        out.write_all(b"VOL")?;
+        out.write_all(&[3u8])?;
+        for v in [1, self.xres as i32, self.yres as i32, self.zres as i32, self.channels as i32] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        let (min, max) = (self.bbox.p_min, self.bbox.p_max);
+        for v in [min.x, min.y, min.z, max.x, max.y, max.z] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        for z in 0..self.zres {
+            for y in 0..self.yres {
+                for x in 0..self.xres {
+                    for c in 0..self.channels {
+                        out.write_all(&self.fetch(x, y, z, c).to_le_bytes())?;
+                    }
+                }
+            }
+        }
+        out.flush()
     }
 
     fn fetch(&self, x: usize, y: usize, z: usize, channel: usize) -> Float {
         let row = z * self.yres + y;
         let col = x * self.channels + channel;
-        self.data[(row, col)]
+        self.data.matrix()[(row, col)]
     }
 
-    fn sample_nearest(&self, p: Vector3f) -> Vector3f {
-        if self.xres == 0 || self.yres == 0 || self.zres == 0 {
-            return Vector3f::zeros();
-        }
-
+    /// Voxels (x, y, z) and weights the filtered lookup at the normalized
+    /// grid position `p` blends.
+    fn voxel_weights(&self, p: Vector3f) -> [((usize, usize, usize), Float); 8] {
         let x = p.x * (self.xres as Float - 1.0);
         let y = p.y * (self.yres as Float - 1.0);
         let z = p.z * (self.zres as Float - 1.0);
+        let clamp = |i: isize, res: usize| i.clamp(0, res as isize - 1) as usize;
 
-        let xi = (x + 0.5).floor() as isize;
-        let yi = (y + 0.5).floor() as isize;
-        let zi = (z + 0.5).floor() as isize;
-
-        let x0 = xi.clamp(0, self.xres as isize - 1) as usize;
-        let y0 = yi.clamp(0, self.yres as isize - 1) as usize;
-        let z0 = zi.clamp(0, self.zres as isize - 1) as usize;
-
-        self.sample_channels(x0, y0, z0)
+        match self.filter_mode {
+            VolumeFilterMode::Nearest => {
+                let voxel = (
+                    clamp((x + 0.5).floor() as isize, self.xres),
+                    clamp((y + 0.5).floor() as isize, self.yres),
+                    clamp((z + 0.5).floor() as isize, self.zres),
+                );
+                let mut weights = [(voxel, 0.0); 8];
+                weights[0].1 = 1.0;
+                weights
+            }
+            VolumeFilterMode::Trilinear => {
+                let x0 = x.floor() as isize;
+                let y0 = y.floor() as isize;
+                let z0 = z.floor() as isize;
+                let tx = x - x0 as Float;
+                let ty = y - y0 as Float;
+                let tz = z - z0 as Float;
+
+                let mut weights = [((0, 0, 0), 0.0); 8];
+                for (i, weight) in weights.iter_mut().enumerate() {
+                    let (dx, dy, dz) = (i & 1, (i >> 1) & 1, (i >> 2) & 1);
+                    let voxel = (
+                        clamp(x0 + dx as isize, self.xres),
+                        clamp(y0 + dy as isize, self.yres),
+                        clamp(z0 + dz as isize, self.zres),
+                    );
+                    let wx = if dx == 1 { tx } else { 1.0 - tx };
+                    let wy = if dy == 1 { ty } else { 1.0 - ty };
+                    let wz = if dz == 1 { tz } else { 1.0 - tz };
+                    *weight = (voxel, wx * wy * wz);
+                }
+                weights
+            }
+        }
     }
 
-    fn sample_trilinear(&self, p: Vector3f) -> Vector3f {
+    /// Normalized, wrapped grid position of `p_world`.
+    fn grid_position(&self, p_world: Vector3f) -> Option<Vector3f> {
         if self.xres == 0 || self.yres == 0 || self.zres == 0 {
-            return Vector3f::zeros();
+            return None;
         }
-
-        let x = p.x * (self.xres as Float - 1.0);
-        let y = p.y * (self.yres as Float - 1.0);
-        let z = p.z * (self.zres as Float - 1.0);
-
-        let x0 = x.floor() as isize;
-        let y0 = y.floor() as isize;
-        let z0 = z.floor() as isize;
-        let x1 = x0 + 1;
-        let y1 = y0 + 1;
-        let z1 = z0 + 1;
-
-        let tx = x - x0 as Float;
-        let ty = y - y0 as Float;
-        let tz = z - z0 as Float;
-
-        let x0u = x0.clamp(0, self.xres as isize - 1) as usize;
-        let y0u = y0.clamp(0, self.yres as isize - 1) as usize;
-        let z0u = z0.clamp(0, self.zres as isize - 1) as usize;
-        let x1u = x1.clamp(0, self.xres as isize - 1) as usize;
-        let y1u = y1.clamp(0, self.yres as isize - 1) as usize;
-        let z1u = z1.clamp(0, self.zres as isize - 1) as usize;
-
-        let c000 = self.sample_channels(x0u, y0u, z0u);
-        let c100 = self.sample_channels(x1u, y0u, z0u);
-        let c010 = self.sample_channels(x0u, y1u, z0u);
-        let c110 = self.sample_channels(x1u, y1u, z0u);
-        let c001 = self.sample_channels(x0u, y0u, z1u);
-        let c101 = self.sample_channels(x1u, y0u, z1u);
-        let c011 = self.sample_channels(x0u, y1u, z1u);
-        let c111 = self.sample_channels(x1u, y1u, z1u);
-
-        let c00 = c000 * (1.0 - tx) + c100 * tx;
-        let c10 = c010 * (1.0 - tx) + c110 * tx;
-        let c01 = c001 * (1.0 - tx) + c101 * tx;
-        let c11 = c011 * (1.0 - tx) + c111 * tx;
-
-        let c0 = c00 * (1.0 - ty) + c10 * ty;
-        let c1 = c01 * (1.0 - ty) + c11 * ty;
-
-        c0 * (1.0 - tz) + c1 * tz
+        let mut p = self.to_world.inv_apply_point(p_world);
+        if self.use_grid_bbox {
+            let diag = self.bbox.diagnal();
+            if diag.x.abs() < 1e-8 || diag.y.abs() < 1e-8 || diag.z.abs() < 1e-8 {
+                return None;
+            }
+            p = Vector3f::new(
+                (p.x - self.bbox.p_min.x) / diag.x,
+                (p.y - self.bbox.p_min.y) / diag.y,
+                (p.z - self.bbox.p_min.z) / diag.z,
+            );
+        }
+        Some(wrap_coord3(p, self.wrap_mode))
     }
 
     fn sample_channels(&self, x: usize, y: usize, z: usize) -> Vector3f {
@@ -244,29 +270,58 @@ impl Volume for GridVolume {
     }
 
     fn eval(&self, p_world: Vector3f) -> Vector3f {
-        if self.data.nrows() == 0 || self.data.ncols() == 0 {
-            return Vector3f::zeros();
+        let p = match self.grid_position(p_world) {
+            Some(p) => p,
+            None => return Vector3f::zeros(),
+        };
+        let mut value = Vector3f::zeros();
+        for ((x, y, z), weight) in self.voxel_weights(p) {
+            if weight != 0.0 {
+                value += self.sample_channels(x, y, z) * weight;
+            }
         }
+        value
+    }
 
-        let mut p = self.to_world.inv_apply_point(p_world);
-        if self.use_grid_bbox {
-            let diag = self.bbox.diagnal();
-            if diag.x.abs() < 1e-8 || diag.y.abs() < 1e-8 || diag.z.abs() < 1e-8 {
-                return Vector3f::zeros();
+    // Single-channel grids feed all three output channels.
+    fn backward(&self, p_world: Vector3f, grad: &Vector3f, grads: &mut GradientBuffers) {
+        let p = match self.grid_position(p_world) {
+            Some(p) => p,
+            None => return,
+        };
+        let view = self.raw_data_view();
+        for ((x, y, z), weight) in self.voxel_weights(p) {
+            let row = z * self.yres + y;
+            if self.channels == 1 {
+                grads.add(&view, row, x, (grad.x + grad.y + grad.z) * weight);
+            } else {
+                for c in 0..3 {
+                    grads.add(&view, row, x * self.channels + c, grad[c] * weight);
+                }
             }
-            p = Vector3f::new(
-                (p.x - self.bbox.p_min.x) / diag.x,
-                (p.y - self.bbox.p_min.y) / diag.y,
-                (p.z - self.bbox.p_min.z) / diag.z,
-            );
         }
+    }
 
-        let p = wrap_coord3(p, self.wrap_mode);
-
-        match self.filter_mode {
-            VolumeFilterMode::Nearest => self.sample_nearest(p),
-            VolumeFilterMode::Trilinear => self.sample_trilinear(p),
-        }
+    // Grid data can be edited through the raw data view, so it is always
+    // written out.
+    fn to_xml(&self, ctx: &mut WriteContext) -> Result<XmlElement, SceneWriteError> {
+        let path = ctx.asset_path("grid", "vol")?;
+        self.write_vol(&path.to_string_lossy())?;
+        let filter = match self.filter_mode {
+            VolumeFilterMode::Nearest => "nearest",
+            VolumeFilterMode::Trilinear => "trilinear",
+        };
+        let wrap = match self.wrap_mode {
+            VolumeWrapMode::Repeat => "repeat",
+            VolumeWrapMode::Mirror => "mirror",
+            VolumeWrapMode::Clamp => "clamp",
+        };
+        Ok(XmlElement::typed("volume", "grid")
+            .with_child(XmlElement::string("filename", &ctx.path_value(&path)))
+            .with_child(XmlElement::string("filter_type", filter))
+            .with_child(XmlElement::string("wrap_mode", wrap))
+            .with_child(XmlElement::boolean("use_grid_bbox", self.use_grid_bbox))
+            .with_child(XmlElement::transform("to_world", self.to_world.matrix())))
     }
 }
 
@@ -0,0 +1,209 @@
+//! End-to-end regression tests: renders the scenes in `tests/scenes` and
+//! compares them with the references in `tests/references`.
+//!
+//! Every scene is rendered as `BATCHES` independent images. Their spread
+//! gives a per-pixel standard error which, together with the error stored
+//! next to each reference, sets how far a pixel may be from the reference.
+//! After an intended change in the output, regenerate the references with
+//!
+//!     DACQUOISE_UPDATE_REFERENCES=1 cargo test --release --test regression
+
+use dacquoise::io::image_io::{read_image, write_image, Image};
+use dacquoise::{render_scene_with_options, RenderOptions};
+
+use std::path::PathBuf;
+
+struct Case {
+    name: &'static str,
+    /// Samples per pixel of each test batch.
+    spp: u32,
+}
+
+const CASES: &[Case] = &[
+    Case { name: "cbox", spp: 8 },
+    Case { name: "furnace", spp: 4 },
+    Case { name: "glass_sphere", spp: 4 },
+    Case { name: "medium", spp: 4 },
+];
+
+const BATCHES: u32 = 8;
+// References use this many times more samples per batch than the test.
+const REFERENCE_SPP_FACTOR: u32 = 64;
+const REFERENCE_SEED: u64 = 1 << 20;
+
+// Pass criteria. With 8 batches the per-pixel z-scores follow roughly a
+// t-distribution with 7 degrees of freedom, so E[z^2] is about 1.4.
+const MAX_MEAN_Z2: f64 = 2.0;
+const OUTLIER_Z: f64 = 6.0;
+const MAX_OUTLIER_FRACTION: f64 = 0.005;
+const MAX_IMAGE_Z: f64 = 5.0;
+
+fn data_dir(sub: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join(sub)
+}
+
+/// Per-pixel mean and standard error of the mean over a set of batches.
+struct Estimate {
+    width: usize,
+    height: usize,
+    mean: Vec<[f64; 3]>,
+    stderr: Vec<[f64; 3]>,
+}
+
+impl Estimate {
+    fn render(name: &str, spp: u32, batches: u32, seed: u64) -> Self {
+        let scene = data_dir("scenes").join(format!("{}.xml", name));
+        let mut sum: Vec<[f64; 3]> = Vec::new();
+        let mut sum_sq: Vec<[f64; 3]> = Vec::new();
+        let (mut width, mut height) = (0, 0);
+        for batch in 0..batches {
+            let options = RenderOptions { spp: Some(spp), seed: seed + batch as u64, ..RenderOptions::default() };
+            let image = render_scene_with_options(&scene.to_string_lossy(), &options)
+                .unwrap_or_else(|err| panic!("{}: {}", name, err))
+                .image;
+            width = image.width();
+            height = image.height();
+            sum.resize(width * height, [0.0; 3]);
+            sum_sq.resize(width * height, [0.0; 3]);
+            for y in 0..height {
+                for x in 0..width {
+                    let pixel = image.pixel(x, y);
+                    for c in 0..3 {
+                        let v = pixel[c] as f64;
+                        sum[y * width + x][c] += v;
+                        sum_sq[y * width + x][c] += v * v;
+                    }
+                }
+            }
+        }
+        let n = batches as f64;
+        let mean: Vec<[f64; 3]> = sum.iter().map(|s| s.map(|v| v / n)).collect();
+        let stderr = mean
+            .iter()
+            .zip(&sum_sq)
+            .map(|(m, sq)| {
+                let mut se = [0.0; 3];
+                for c in 0..3 {
+                    let variance = ((sq[c] - n * m[c] * m[c]) / (n - 1.0)).max(0.0);
+                    se[c] = (variance / n).sqrt();
+                }
+                se
+            })
+            .collect();
+        Self { width, height, mean, stderr }
+    }
+
+    fn read(name: &str) -> Self {
+        let path = data_dir("references").join(format!("{}.exr", name));
+        let image = read_image(&path).unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err));
+        let channel = |name: &str| {
+            image.channel_index(name).unwrap_or_else(|| panic!("{} has no channel {}", path.display(), name))
+        };
+        let mean_channels = [channel("R"), channel("G"), channel("B")];
+        let stderr_channels = [channel("stderr.R"), channel("stderr.G"), channel("stderr.B")];
+        let mut mean = Vec::with_capacity(image.width * image.height);
+        let mut stderr = Vec::with_capacity(image.width * image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let pixel = image.pixel(x, y);
+                mean.push(mean_channels.map(|c| pixel[c] as f64));
+                stderr.push(stderr_channels.map(|c| pixel[c] as f64));
+            }
+        }
+        Self { width: image.width, height: image.height, mean, stderr }
+    }
+
+    fn write(&self, name: &str) {
+        let channels = ["R", "G", "B", "stderr.R", "stderr.G", "stderr.B"];
+        let mut image = Image::new(self.width, self.height, channels.iter().map(|c| c.to_string()).collect());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (m, se) = (self.mean[y * self.width + x], self.stderr[y * self.width + x]);
+                let values = [m[0], m[1], m[2], se[0], se[1], se[2]].map(|v| v as f32);
+                image.pixel_mut(x, y).copy_from_slice(&values);
+            }
+        }
+        let path = data_dir("references").join(format!("{}.exr", name));
+        write_image(&path, &image).unwrap_or_else(|err| panic!("failed to write {}: {}", path.display(), err));
+    }
+}
+
+struct Report {
+    mean_z2: f64,
+    outlier_fraction: f64,
+    image_z: f64,
+}
+
+impl Report {
+    fn new(test: &Estimate, reference: &Estimate) -> Self {
+        let mut z2_sum = 0.0;
+        let mut outliers = 0usize;
+        let (mut test_sum, mut reference_sum) = (0.0, 0.0);
+        let (mut test_var, mut reference_var) = (0.0, 0.0);
+        for i in 0..test.mean.len() {
+            for c in 0..3 {
+                let (t, r) = (test.mean[i][c], reference.mean[i][c]);
+                let (se_t, se_r) = (test.stderr[i][c], reference.stderr[i][c]);
+                // The floor keeps noiseless pixels, like a constant
+                // background, from demanding exact equality.
+                let sigma = (se_t * se_t + se_r * se_r).sqrt() + 1e-3 * (1.0 + r.abs());
+                let z = if t.is_finite() { (t - r) / sigma } else { f64::INFINITY };
+                z2_sum += z * z;
+                if z.abs() > OUTLIER_Z {
+                    outliers += 1;
+                }
+                test_sum += t;
+                reference_sum += r;
+                test_var += se_t * se_t;
+                reference_var += se_r * se_r;
+            }
+        }
+        let n = (test.mean.len() * 3) as f64;
+        Self {
+            mean_z2: z2_sum / n,
+            outlier_fraction: outliers as f64 / n,
+            image_z: (test_sum - reference_sum) / (test_var + reference_var).sqrt().max(1e-9),
+        }
+    }
+
+    fn passed(&self) -> bool {
+        self.mean_z2 <= MAX_MEAN_Z2 && self.outlier_fraction <= MAX_OUTLIER_FRACTION && self.image_z.abs() <= MAX_IMAGE_Z
+    }
+}
+
+#[test]
+fn test_scenes_match_references() {
+    if std::env::var_os("DACQUOISE_UPDATE_REFERENCES").is_some() {
+        for case in CASES {
+            Estimate::render(case.name, case.spp * REFERENCE_SPP_FACTOR, BATCHES, REFERENCE_SEED).write(case.name);
+            println!("updated reference {}", case.name);
+        }
+        return;
+    }
+
+    let mut failures = Vec::new();
+    println!("{:<14} {:>10} {:>10} {:>10}  result", "scene", "mean z^2", "outliers", "image z");
+    for case in CASES {
+        let reference = Estimate::read(case.name);
+        let test = Estimate::render(case.name, case.spp, BATCHES, 0);
+        assert_eq!(
+            (test.width, test.height),
+            (reference.width, reference.height),
+            "{}: image size differs from the reference",
+            case.name
+        );
+        let report = Report::new(&test, &reference);
+        println!(
+            "{:<14} {:>10.3} {:>9.2}% {:>10.2}  {}",
+            case.name,
+            report.mean_z2,
+            report.outlier_fraction * 100.0,
+            report.image_z,
+            if report.passed() { "pass" } else { "FAIL" }
+        );
+        if !report.passed() {
+            failures.push(case.name);
+        }
+    }
+    assert!(failures.is_empty(), "scenes differ from their references: {}", failures.join(", "));
+}